@@ -1,33 +1,378 @@
-use std::sync::mpsc;
-use std::time::Duration;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::{Position, Rect};
 use ratatui::widgets::TableState;
+use serde::{Deserialize, Serialize};
+use tokio::task::AbortHandle;
 
-use crate::gcloud;
-use crate::profile::{Profile, SyncMode};
-use crate::store::Store;
+use gcloud_switch::gcloud::{self, AuthStatus, PlanStep};
+use gcloud_switch::profile::{self, GuardSettings, Profile, ProfilesFile, SshSettings, SyncMode};
+use gcloud_switch::{docker, legacy, net, providers, runtime};
+use gcloud_switch::store::{Store, TuiLayout};
+use gcloud_switch::sync::{self, Conflict, SyncTarget};
 
+use crate::clipboard;
+use crate::hooks::{self, HookEvent, Hooks};
+use crate::opener;
+use crate::theme::Theme;
+use crate::watch::Watcher;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// `[table]` in gcloud-switch.toml: which columns the table shows, how tall its rows are
+/// and where the cursor starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSettings {
+    /// Show the ADC account / quota project column.
+    #[serde(default = "default_true")]
+    pub adc_column: bool,
+    /// One line per profile, with account and project side by side.
+    #[serde(default)]
+    pub compact: bool,
+    /// Column widths in percent (profile, user, and ADC if shown); sized to the content
+    /// when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub widths: Option<Vec<u16>>,
+    /// Column selected at startup.
+    #[serde(default)]
+    pub start_column: Column,
+    /// Row the cursor starts on.
+    #[serde(default)]
+    pub start_row: StartRow,
+    /// Order of the rows when no filter is typed.
+    #[serde(default)]
+    pub order: RowOrder,
+    /// Reopen as the TUI was left: selected profile, row order, filter and panes.
+    #[serde(default)]
+    pub restore: bool,
+}
+
+/// Row the cursor starts on; falls back to the first row when that profile is gone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartRow {
+    /// The active profile.
+    #[default]
+    Active,
+    /// The profile selected when the TUI last quit.
+    Last,
+    First,
+}
+
+/// Order of the table's rows; `m` switches between them for the session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RowOrder {
+    /// Alphabetical by profile name.
+    #[default]
+    Name,
+    /// Most recently activated on this machine first, never activated ones last.
+    Recent,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for TableSettings {
+    fn default() -> Self {
+        Self {
+            adc_column: true,
+            compact: false,
+            widths: None,
+            start_column: Column::Both,
+            start_row: StartRow::Active,
+            order: RowOrder::Name,
+            restore: false,
+        }
+    }
+}
+
+impl TableSettings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Number of columns shown.
+    pub fn columns(&self) -> usize {
+        if self.adc_column { 3 } else { 2 }
+    }
+
+    /// Height of the header and of each row.
+    pub fn row_height(&self) -> u16 {
+        if self.compact { 1 } else { 2 }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(widths) = &self.widths {
+            if widths.len() != self.columns() {
+                anyhow::bail!(
+                    "widths has {} entries, but the table has {} columns",
+                    widths.len(),
+                    self.columns()
+                );
+            }
+            if widths.contains(&0) {
+                anyhow::bail!("widths must all be at least 1");
+            }
+        }
+        if self.start_column == Column::Adc && !self.adc_column {
+            anyhow::bail!("start_column = \"adc\" needs adc_column");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Column {
+    #[default]
     Both,
     User,
     Adc,
 }
 
+/// Auth triage view, cycled with `h`: profiles whose credentials need attention on top,
+/// or only those.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuthView {
+    #[default]
+    All,
+    ProblemsFirst,
+    ProblemsOnly,
+}
+
+impl AuthView {
+    pub fn label(self) -> &'static str {
+        match self {
+            AuthView::All => "all profiles",
+            AuthView::ProblemsFirst => "auth problems first",
+            AuthView::ProblemsOnly => "auth problems only",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMode {
     Normal,
-    AddProfileName,
-    AddProfileUserAccount,
-    AddProfileUserProject,
-    AddProfileAdcAccount,
-    AddProfileAdcQuotaProject,
+    Form,
+    Rename,
+    Tag,
+    AccountPicker,
+    ClusterPicker,
+    ErrorDetail,
+    SyncConflict,
     ConfirmDelete,
+    ConfirmActivate,
+    ConfirmGuarded,
     EditAccount,
     EditProject,
+    Filter,
+    Jump,
+    Compare,
+    Messages,
+    DryRun,
+}
+
+/// Fields of the add/edit form, in Tab order.
+pub const FORM_LABELS: [&str; 8] = [
+    "Name",
+    "User account",
+    "User project",
+    "ADC account",
+    "ADC quota project",
+    "Region",
+    "Zone",
+    "SSH zone",
+];
+pub const FIELD_NAME: usize = 0;
+pub const FIELD_USER_ACCOUNT: usize = 1;
+pub const FIELD_USER_PROJECT: usize = 2;
+pub const FIELD_ADC_ACCOUNT: usize = 3;
+pub const FIELD_ADC_QUOTA_PROJECT: usize = 4;
+/// compute/region in the profile's gcloud properties.
+pub const FIELD_REGION: usize = 5;
+/// compute/zone in the profile's gcloud properties.
+pub const FIELD_ZONE: usize = 6;
+/// `SshSettings::zone`.
+pub const FIELD_SSH_ZONE: usize = 7;
+
+/// What a text field holds, which decides the characters it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Name,
+    Account,
+    Project,
+    Region,
+    Zone,
+}
+
+impl FieldKind {
+    fn of(field: usize) -> Self {
+        match field {
+            FIELD_NAME => FieldKind::Name,
+            FIELD_USER_ACCOUNT | FIELD_ADC_ACCOUNT => FieldKind::Account,
+            FIELD_REGION => FieldKind::Region,
+            FIELD_ZONE | FIELD_SSH_ZONE => FieldKind::Zone,
+            _ => FieldKind::Project,
+        }
+    }
+
+    /// Region and zone fields may be left empty.
+    fn optional(self) -> bool {
+        matches!(self, FieldKind::Region | FieldKind::Zone)
+    }
+
+    fn accepts(self, c: char, at_start: bool) -> bool {
+        match self {
+            FieldKind::Name => c.is_ascii_alphanumeric() || c == '-',
+            // Email characters: letters, digits, @, ., -, _, +
+            FieldKind::Account => {
+                c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '-' | '_' | '+')
+            }
+            // GCP project: first char must be a letter, rest: letters, digits, -, _
+            FieldKind::Project => {
+                if at_start {
+                    c.is_ascii_alphabetic()
+                } else {
+                    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+                }
+            }
+            // Compute locations: europe-west1, europe-west1-b
+            FieldKind::Region | FieldKind::Zone => {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'
+            }
+        }
+    }
+}
+
+/// State of the modal add/edit profile form.
+pub struct ProfileForm {
+    pub values: [String; FORM_LABELS.len()],
+    pub errors: [Option<String>; FORM_LABELS.len()],
+    pub focus: usize,
+    pub cursor: usize,
+    /// Name of the profile being edited; None when adding a new one.
+    pub editing: Option<String>,
+    /// Profile the form started from; fields not shown in the form (tags etc.) carry over.
+    base: Profile,
+    /// Profile being cloned, whose stored ADC is copied to the new profile.
+    pub clone_of: Option<String>,
+}
+
+impl ProfileForm {
+    fn new() -> Self {
+        Self {
+            values: Default::default(),
+            errors: Default::default(),
+            focus: FIELD_NAME,
+            cursor: 0,
+            editing: None,
+            base: Profile::default(),
+            clone_of: None,
+        }
+    }
+
+    fn for_profile(name: &str, profile: &Profile) -> Self {
+        let values = [
+            name.to_string(),
+            profile.user_account.clone(),
+            profile.user_project.clone(),
+            profile.adc_account.clone(),
+            profile.adc_quota_project.clone(),
+            profile.gcloud_properties.get("compute/region").cloned().unwrap_or_default(),
+            profile.gcloud_properties.get("compute/zone").cloned().unwrap_or_default(),
+            profile.ssh.as_ref().and_then(|ssh| ssh.zone.clone()).unwrap_or_default(),
+        ];
+        Self {
+            values,
+            errors: Default::default(),
+            focus: FIELD_USER_ACCOUNT,
+            cursor: profile.user_account.chars().count(),
+            editing: Some(name.to_string()),
+            base: profile.clone(),
+            clone_of: None,
+        }
+    }
+
+    /// A new profile prefilled from `profile`, named `new_name`.
+    fn clone_of(name: &str, new_name: String, profile: &Profile) -> Self {
+        let mut form = Self::for_profile(name, profile);
+        form.values[FIELD_NAME] = new_name;
+        form.editing = None;
+        form.clone_of = Some(name.to_string());
+        form.base.updated_at = None;
+        form
+    }
+
+    /// Value an empty field falls back to: ADC fields default to the user's.
+    pub fn placeholder(&self, field: usize) -> Option<&str> {
+        match field {
+            FIELD_ADC_ACCOUNT => Some(self.values[FIELD_USER_ACCOUNT].trim()),
+            FIELD_ADC_QUOTA_PROJECT => Some(self.values[FIELD_USER_PROJECT].trim()),
+            _ => None,
+        }
+    }
+
+    /// Trimmed value of a field, or its placeholder when left empty.
+    fn effective(&self, field: usize) -> String {
+        let value = self.values[field].trim();
+        if value.is_empty() {
+            self.placeholder(field).unwrap_or("").to_string()
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// The account whose projects should be suggested for a project field.
+    fn account_for(&self, field: usize) -> String {
+        if field == FIELD_ADC_QUOTA_PROJECT {
+            self.effective(FIELD_ADC_ACCOUNT)
+        } else {
+            self.effective(FIELD_USER_ACCOUNT)
+        }
+    }
+
+    fn validate_field(&self, field: usize, existing: &[String]) -> Option<String> {
+        let value = self.effective(field);
+        if value.is_empty() {
+            return (!FieldKind::of(field).optional()).then(|| "required".to_string());
+        }
+        match FieldKind::of(field) {
+            FieldKind::Name => {
+                if let Err(e) = profile::validate_name(&value) {
+                    Some(e.to_string())
+                } else if self.editing.is_none() && existing.contains(&value) {
+                    Some("a profile with this name exists".to_string())
+                } else {
+                    None
+                }
+            }
+            FieldKind::Account => match value.split_once('@') {
+                Some((user, domain)) if !user.is_empty() && domain.contains('.') => None,
+                _ => Some("not an email address".to_string()),
+            },
+            FieldKind::Project => {
+                if !(6..=30).contains(&value.len()) {
+                    Some("project IDs are 6-30 characters".to_string())
+                } else {
+                    None
+                }
+            }
+            FieldKind::Region => {
+                (!is_region(&value)).then(|| "not a region (e.g. europe-west1)".to_string())
+            }
+            FieldKind::Zone => match value.rsplit_once('-') {
+                Some((region, suffix)) if is_region(region) && suffix.len() == 1 => None,
+                _ => Some("not a zone (e.g. europe-west1-b)".to_string()),
+            },
+        }
+    }
 }
 
 /// A shell command that requires TUI suspension (e.g. interactive gcloud auth).
@@ -35,14 +380,350 @@ pub enum PendingAction {
     None,
     Reauth,
     ReauthAndActivate,
+    /// Reauth every marked profile in turn.
+    ReauthMarked,
+    /// Reauth the expired sides of every profile in turn.
+    ReauthExpired,
+    /// `gcloud auth login` for a new account, picked into the field being edited.
+    AddAccount,
+}
+
+/// Popup listing authenticated accounts for the account field being edited.
+pub struct AccountPicker {
+    pub accounts: Vec<String>,
+    /// Highlighted entry; `accounts.len()` is the "add new account" entry.
+    pub index: usize,
+    /// Mode to go back to (Form or EditAccount).
+    return_to: InputMode,
+}
+
+/// Modal for choosing a profile's GKE clusters from the ones its account can see.
+pub struct ClusterPicker {
+    pub profile: String,
+    /// Cluster specs with whether they are chosen: the profile's current ones first, then
+    /// the discovered ones it doesn't list yet.
+    pub clusters: Vec<(String, bool)>,
+    pub index: usize,
+    /// Still waiting for `gcloud container clusters list`.
+    pub loading: bool,
+    pub error: Option<String>,
+}
+
+/// Modal with the full output of a failed gcloud command.
+pub struct ErrorDetail {
+    pub failure: gcloud::CommandFailure,
+    /// First visible line of the output.
+    pub scroll: u16,
+    /// Largest useful `scroll`, from the last draw (depends on wrapping).
+    pub max_scroll: u16,
+    /// Mode to go back to when the modal is closed.
+    return_to: InputMode,
+}
+
+/// Two profiles side by side (`=`), with the rows that differ highlighted.
+pub struct CompareView {
+    pub a: String,
+    pub b: String,
+    pub rows: Vec<DiffRow>,
+    /// Hide the rows both profiles agree on.
+    pub only_differences: bool,
+    /// First visible row.
+    pub scroll: u16,
+    /// Largest useful `scroll`, from the last draw.
+    pub max_scroll: u16,
+}
+
+impl CompareView {
+    /// The rows currently shown.
+    pub fn visible_rows(&self) -> impl Iterator<Item = &DiffRow> {
+        self.rows.iter().filter(|row| row.differ || !self.only_differences)
+    }
+}
+
+/// What Enter and `a` would run for a profile (`D`), shown without running any of it.
+pub struct DryRun {
+    pub name: String,
+    /// Heading and steps per key: the activation first, then the login.
+    pub sections: Vec<(String, Vec<PlanStep>)>,
+    /// First visible line.
+    pub scroll: u16,
+    /// Largest useful `scroll`, from the last draw.
+    pub max_scroll: u16,
+}
+
+/// Screen geometry from the last draw, used to map mouse events onto widgets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HitAreas {
+    /// Table area including its header.
+    pub table: Rect,
+    /// Left edge of each table column (Profile, User, ADC); `u16::MAX` for a hidden one.
+    pub col_x: [u16; 3],
+    /// Number of profile rows that fit in the table viewport.
+    pub visible_rows: usize,
+    /// Suggestion dropdown (including borders) when open.
+    pub suggestions: Option<Rect>,
+    /// Index of the first suggestion shown in the dropdown.
+    pub suggestion_offset: usize,
+}
+
+/// Everything shown in the detail pane for one profile. Gathered from disk once per
+/// selection rather than on every frame.
+pub struct ProfileDetail {
+    pub adc_path: std::path::PathBuf,
+    pub has_adc: bool,
+    /// Expiry (unix seconds) of gcloud's cached access token for the user account.
+    pub user_token_expiry: Option<i64>,
+    pub config_path: Option<std::path::PathBuf>,
+    /// Properties of the mapped gcloud configuration; None if it doesn't exist.
+    pub config_properties: Option<std::collections::BTreeMap<String, String>>,
+    /// Whether the mapped configuration is gcloud's active one.
+    pub is_gcloud_active: bool,
+    /// Number of the user project, once resolved.
+    pub project_number: Option<String>,
+}
+
+/// How a profile differs from the live gcloud state.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Drift {
+    /// `core/account` of the profile's gcloud configuration, if it differs from the profile.
+    pub account: Option<String>,
+    /// `core/project` of the profile's gcloud configuration, if it differs from the profile.
+    pub project: Option<String>,
+    /// The store and gcloud disagree on whether this profile is the active one.
+    pub active: bool,
+}
+
+/// What gcloud and client libraries will use right now, for the context line above the table.
+#[derive(Debug, Clone, Default)]
+pub struct LiveContext {
+    /// `core/account` and `core/project` of gcloud's active configuration.
+    pub account: String,
+    pub project: String,
+    /// The profile whose stored ADC is installed (ignoring the quota project).
+    pub adc_profile: Option<String>,
+    /// `gcloud::describe_adc` of the installed ADC; None when there is none.
+    pub adc: Option<String>,
+}
+
+impl LiveContext {
+    /// Read gcloud's active configuration and the installed ADC, matching the ADC against
+    /// the stored ADC of `candidates` in order.
+    pub fn read<'a>(store: &Store, gcloud_active: Option<&str>, candidates: impl IntoIterator<Item = &'a str>) -> Self {
+        let props = match gcloud_active {
+            Some(config) => gcloud::read_configuration(config).ok().flatten().unwrap_or_default(),
+            None => Default::default(),
+        };
+        let live = |key: &str| props.get(key).cloned().unwrap_or_default();
+        let mut context = LiveContext {
+            account: live("core/account"),
+            project: live("core/project"),
+            ..Default::default()
+        };
+        let Ok(Some(adc)) = gcloud::load_live_adc() else {
+            return context;
+        };
+        context.adc = Some(gcloud::describe_adc(&adc));
+        context.adc_profile = candidates
+            .into_iter()
+            .find(|name| {
+                store
+                    .load_adc_json(name)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|stored| gcloud::same_adc(&stored, &adc))
+            })
+            .map(str::to_string);
+        context
+    }
+}
+
+impl Drift {
+    /// Compare a profile with its `config_<name>` file and gcloud's active configuration.
+    pub fn of(name: &str, profile: &Profile, active_profile: Option<&str>, gcloud_active: Option<&str>) -> Self {
+        let mut drift = Drift::default();
+        // Profiles without a configuration (sync off) have nothing to drift from
+        if let Ok(Some(props)) = gcloud::read_configuration(name) {
+            let live = |key: &str| props.get(key).cloned().unwrap_or_default();
+            let account = live("core/account");
+            let project = live("core/project");
+            drift.account = (account != profile.user_account).then_some(account);
+            drift.project = (project != profile.user_project).then_some(project);
+        }
+        drift.active = (active_profile == Some(name)) != (gcloud_active == Some(name));
+        drift
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.account.is_none() && self.project.is_none() && !self.active
+    }
+
+    /// Short description for the status bar.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(account) = &self.account {
+            parts.push(format!("gcloud account is '{}'", account));
+        }
+        if let Some(project) = &self.project {
+            parts.push(format!("gcloud project is '{}'", project));
+        }
+        if self.active {
+            parts.push("active profile differs from gcloud".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// How many mutations `u` can step back through.
+const UNDO_LIMIT: usize = 20;
+
+/// gcloud configuration change that reverses one made alongside a store mutation.
+enum GcloudUndo {
+    Create {
+        name: String,
+        account: String,
+        project: String,
+    },
+    Delete(String),
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// Account and project an edit in mirror mode overwrote.
+    Write {
+        name: String,
+        account: String,
+        project: String,
+    },
+}
+
+/// Store state captured before a mutation, restored by undo.
+struct UndoEntry {
+    description: String,
+    /// Touched profiles as they were (None = did not exist), with their tombstone.
+    /// Only these are restored, so later pulls and outside edits of other profiles survive.
+    profiles: Vec<(String, Option<Profile>, Option<i64>)>,
+    /// Active profile at snapshot time, restored if the current one is gone after undo.
+    active_profile: Option<String>,
+    /// Stored ADC JSON of the touched profiles (None = no file).
+    adc: Vec<(String, Option<serde_json::Value>)>,
+    gcloud: Vec<GcloudUndo>,
+}
+
+/// How many lines the activity log keeps.
+const LOG_CAPACITY: usize = 200;
+
+/// One line of the activity log.
+pub struct LogEntry {
+    /// Unix seconds when the entry was recorded.
+    pub at: i64,
+    pub message: String,
+    pub severity: Severity,
+}
+
+/// How a message is colored in its toast and in the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+/// A message shown over the bottom right corner until it expires.
+pub struct Toast {
+    pub message: String,
+    pub severity: Severity,
+    /// None: shown until the next key press (`toast_duration = 0`).
+    pub expires: Option<Instant>,
+}
+
+/// Direction of a git sync run from the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDirection {
+    Push,
+    Pull,
+}
+
+/// Outcome of a background sync: conflicting profiles kept local, or the error.
+type SyncOutcome = (SyncDirection, std::result::Result<Vec<Conflict>, String>);
+
+/// One cluster's finished kubectl context refresh, tagged with the refresh it belongs to.
+struct GkeProgress {
+    generation: u64,
+    cluster: String,
+    result: std::result::Result<(), String>,
+}
+
+/// A running GKE credential refresh, shown in the status bar.
+pub struct GkeRefresh {
+    generation: u64,
+    pub profile: String,
+    pub done: usize,
+    pub total: usize,
+    failed: Vec<String>,
+}
+
+/// Two clicks on the same row within this window count as a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// A pause this long in jump mode starts a new prefix with the next key.
+const JUMP_RESET: Duration = Duration::from_millis(1000);
+
+/// `App::auth_rank` of a profile whose credentials are all fine.
+const AUTH_RANK_HEALTHY: u8 = 2;
+
+/// Quiet period after a watched file changes before reloading, so a burst of writes
+/// (gcloud touches several files, editors save via temp files) reloads once.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Token exchanges `start_auth_checks` runs at once; further accounts wait their turn.
+pub const AUTH_WORKERS: usize = 4;
+
+/// Accounts of rows off screen are only checked once no key or mouse event came in for this long.
+const AUTH_IDLE_AFTER: Duration = Duration::from_millis(500);
+
+/// Default for `auth_recheck_interval`: minutes between auth re-checks in an open TUI.
+pub const DEFAULT_AUTH_RECHECK_MINUTES: u64 = 15;
+
+/// Default for `toast_duration`: seconds a toast stays up. Errors stay twice as long.
+pub const DEFAULT_TOAST_SECONDS: u64 = 5;
+
+/// Toasts shown at once; an older one gives way to a new one.
+pub const TOAST_STACK: usize = 4;
+
+/// Accounts waiting for a token check, shared with the auth workers. `schedule_auth_checks`
+/// marks the ones on screen; workers take those first and the rest only while `idle`.
+#[derive(Default)]
+struct AuthQueue {
+    jobs: VecDeque<AuthJob>,
+    idle: bool,
+}
+
+struct AuthJob {
+    account: String,
+    /// (profile index, is user account) of every cell showing this account.
+    targets: Vec<(usize, bool)>,
+    visible: bool,
 }
 
-/// Result from a background auth check thread.
+impl AuthQueue {
+    fn take(&mut self) -> Option<AuthJob> {
+        let pos = match self.jobs.iter().position(|job| job.visible) {
+            Some(pos) => pos,
+            None if self.idle => 0,
+            None => return None,
+        };
+        self.jobs.remove(pos)
+    }
+}
+
+/// Result from a background auth check task.
 struct AuthResult {
     generation: u64,
     profile_index: usize,
     is_user: bool,
-    valid: bool,
+    status: AuthStatus,
+    /// Expiry (unix seconds) of the account's cached gcloud access token; user side only.
+    expiry: Option<i64>,
 }
 
 pub struct App {
@@ -50,17 +731,50 @@ pub struct App {
     pub profile_names: Vec<String>,
     pub profiles: Vec<Profile>,
     pub active_profile: Option<String>,
-    pub user_auth_valid: Vec<Option<bool>>,
-    pub adc_auth_valid: Vec<Option<bool>>,
+    pub user_auth_status: Vec<Option<AuthStatus>>,
+    pub adc_auth_status: Vec<Option<AuthStatus>>,
+    /// Cached access token expiry (unix seconds) of the user account per profile, filled in
+    /// with the auth checks. The ADC side has none: a stored ADC is a refresh token, and
+    /// gcloud's access token cache belongs to the account's gcloud login, not to the ADC.
+    pub user_token_expiry: Vec<Option<i64>>,
+    /// Drift against the live gcloud configuration, per profile.
+    pub drift: Vec<Drift>,
+    /// Whether ADC credentials are stored, per profile. Without them ADC activation needs a login.
+    pub adc_stored: Vec<bool>,
+    /// gcloud's active configuration as of the last drift check.
+    pub gcloud_active: Option<String>,
+    /// Account, project and ADC gcloud has live, as of the last drift check.
+    pub live: LiveContext,
+    /// Index into `profiles` of the selected profile.
     pub selected_row: usize,
     pub selected_col: Column,
+    /// Indices into `profiles` in display order, after applying the filter.
+    pub view: Vec<usize>,
+    pub filter_query: String,
+    pub auth_view: AuthView,
+    pub row_order: RowOrder,
+    /// When each profile was last activated here, from state.toml.
+    pub last_activated: std::collections::BTreeMap<String, i64>,
+    /// Prefix typed in jump mode (`f`), and where the selection was before it.
+    pub jump_buffer: String,
+    pub jump_missed: bool,
+    jump_origin: usize,
+    jump_typed_at: Instant,
     pub should_quit: bool,
     pub status_message: Option<String>,
     pub input_mode: InputMode,
-    pub input_buffer: String,
-    // Temporary storage for profile being added
-    pub new_profile_name: String,
-    pub new_profile: Profile,
+    // Add/edit form state (InputMode::Form)
+    pub form: Option<ProfileForm>,
+    // New-name input (InputMode::Rename)
+    pub rename_buffer: String,
+    pub rename_cursor: usize,
+    // Tag input for the selected or marked profiles (InputMode::Tag)
+    pub tag_buffer: String,
+    pub tag_cursor: usize,
+    // Name typed to activate a guarded profile (InputMode::ConfirmGuarded)
+    pub guard_buffer: String,
+    /// Profiles marked with Space for batch operations.
+    pub marked: BTreeSet<String>,
     // In-place editing state
     pub edit_col: Column,
     pub edit_account_buffer: String,
@@ -68,24 +782,123 @@ pub struct App {
     pub edit_cursor_pos: usize,
     pub suggestions: Vec<String>,
     pub suggestion_index: Option<usize>,
+    /// Project dropdown shown while a project field is focused, even with nothing highlighted.
+    pub picker_open: bool,
+    pub account_picker: Option<AccountPicker>,
+    pub cluster_picker: Option<ClusterPicker>,
+    cluster_tx: mpsc::Sender<(String, Result<Vec<String>, String>)>,
+    cluster_rx: mpsc::Receiver<(String, Result<Vec<String>, String>)>,
+    /// The cluster listing behind the open picker, aborted when the picker closes.
+    cluster_task: Option<AbortHandle>,
+    pub error_detail: Option<ErrorDetail>,
+    pub compare: Option<CompareView>,
+    pub dry_run: Option<DryRun>,
     // Pending action that needs TUI suspended
     pub pending_action: PendingAction,
     pub quit_after_activate: bool,
+    /// Set by Enter when no login is needed; the main loop runs the activation so it can
+    /// redraw between steps.
+    pub activation_queued: bool,
+    /// Plain Enter keeps the TUI open after activating and Alt+Enter quits, instead of
+    /// the reverse. From `stay_open` in gcloud-switch.toml, toggled with `o`.
+    pub stay_open: bool,
+    /// User hooks from gcloud-switch.toml, run in the background on lifecycle events.
+    hooks: Hooks,
+    /// Update `.boto` / `.bigqueryrc` on activation (`legacy_configs` in gcloud-switch.toml).
+    legacy_configs: bool,
     // Async auth check state
     auth_tx: mpsc::Sender<AuthResult>,
     auth_rx: mpsc::Receiver<AuthResult>,
+    /// Bumped by every `start_auth_checks`; results of an older generation are dropped.
     auth_generation: u64,
+    /// Auth results of the current generation still outstanding.
+    pub auth_pending: usize,
+    auth_queue: Arc<Mutex<AuthQueue>>,
+    /// The auth worker tasks, aborted when a new generation starts.
+    auth_tasks: Vec<AbortHandle>,
+    /// Last key or mouse event, for deferring off-screen auth checks.
+    last_input: Instant,
+    /// How often the auth checks run again while the TUI stays open (`auth_recheck_interval`).
+    pub auth_recheck: Option<Duration>,
+    /// Messages from `notify`, oldest first, at most `TOAST_STACK`.
+    pub toasts: VecDeque<Toast>,
+    /// How long a toast stays up (`toast_duration`); zero keeps it until the next key.
+    pub toast_duration: Duration,
+    /// When the current round of auth checks started.
+    last_auth_check: Instant,
+    /// Advances while background work is running; drives the spinners.
+    pub busy_tick: usize,
     // Async project list fetch state
-    project_tx: mpsc::Sender<Vec<String>>,
-    project_rx: mpsc::Receiver<Vec<String>>,
+    project_tx: mpsc::Sender<(u64, Vec<String>)>,
+    project_rx: mpsc::Receiver<(u64, Vec<String>)>,
+    /// Bumped per project fetch, so a slow fetch for an earlier account is ignored.
+    project_generation: u64,
+    /// The running project fetch, aborted when a fetch for another account supersedes it.
+    project_task: Option<AbortHandle>,
     pub fetched_projects: Vec<String>,
     pub fetching_projects: bool,
+    // Region (false) and zone (true) lists for the form, from state.toml or gcloud
+    location_tx: mpsc::Sender<(bool, Vec<String>)>,
+    location_rx: mpsc::Receiver<(bool, Vec<String>)>,
+    compute_regions: Vec<String>,
+    compute_zones: Vec<String>,
+    pub fetching_locations: bool,
+    // Background project number lookups, by project id
+    number_tx: mpsc::Sender<(String, Option<String>)>,
+    number_rx: mpsc::Receiver<(String, Option<String>)>,
+    pub numbers_pending: BTreeSet<String>,
+    /// Project whose number `#` copies once it is resolved.
+    copy_number_of: Option<String>,
+    // Background git sync state
+    sync_target: Option<SyncTarget>,
+    sync_tx: mpsc::Sender<SyncOutcome>,
+    sync_rx: mpsc::Receiver<SyncOutcome>,
+    pub sync_running: Option<SyncDirection>,
+    // Background GKE credential refresh after activation. A newer activation bumps the
+    // generation, which stops an older refresh before its next cluster.
+    gke_tx: mpsc::Sender<GkeProgress>,
+    gke_rx: mpsc::Receiver<GkeProgress>,
+    gke_generation: Arc<AtomicU64>,
+    gke_lock: Arc<Mutex<()>>,
+    pub gke_refresh: Option<GkeRefresh>,
+    /// Profiles a TUI pull couldn't merge, resolved one at a time (first is shown).
+    pub conflicts: Vec<Conflict>,
+    // Live reload on external changes; None if the watcher could not be started
+    watcher: Option<Watcher>,
+    reload_due: Option<Instant>,
     pub sync_mode: SyncMode,
     pub table_state: TableState,
+    pub hit_areas: HitAreas,
+    last_click: Option<(Instant, usize)>,
+    pub show_details: bool,
+    pub show_log: bool,
+    /// First visible line of the message history (`H`), and the largest useful value from
+    /// the last draw.
+    pub history_scroll: u16,
+    pub history_max_scroll: u16,
+    /// Recent outcomes (activations, errors, imports), oldest first.
+    pub log: VecDeque<LogEntry>,
+    pub theme: Theme,
+    undo_stack: Vec<UndoEntry>,
+    /// Detail pane data, keyed by the profile index it was loaded for.
+    detail_cache: Option<(usize, ProfileDetail)>,
+    /// Widest content per table column over all profiles; recomputed after a reload.
+    pub column_widths: Option<[usize; 3]>,
+    pub table: TableSettings,
+    /// Profiles that need their name typed to activate (`[guard]` in gcloud-switch.toml).
+    pub guard: GuardSettings,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
+    pub fn new(
+        theme: Theme,
+        sync_target: Option<SyncTarget>,
+        stay_open: bool,
+        hooks: Hooks,
+        legacy_configs: bool,
+        table: TableSettings,
+        guard: GuardSettings,
+    ) -> Result<Self> {
         let store = Store::new()?;
         let data = store.load_profiles()?;
 
@@ -94,68 +907,172 @@ impl App {
         let active_profile = data.active_profile;
         let sync_mode = data.sync_mode;
 
-        let selected_row = if let Some(ref active) = active_profile {
-            profile_names
-                .iter()
-                .position(|n| n == active)
-                .unwrap_or(0)
-        } else {
-            0
+        let state = store.load_state().unwrap_or_default();
+        let start_profile = match table.start_row {
+            _ if table.restore => state.last_selected,
+            StartRow::Active => active_profile.clone(),
+            StartRow::Last => state.last_selected,
+            StartRow::First => None,
         };
+        let selected_row = start_profile
+            .and_then(|name| profile_names.iter().position(|n| *n == name))
+            .unwrap_or(0);
+        // Without a saved layout (first launch) the settings decide, as without `restore`
+        let layout = state.layout.filter(|_| table.restore);
+        let row_order = match &layout {
+            Some(layout) if layout.recent_first => RowOrder::Recent,
+            Some(_) => RowOrder::Name,
+            None => table.order,
+        };
+        let layout = layout.unwrap_or_default();
 
+        let last_activated = state.last_activated;
+        let watcher = Watcher::new(store.dir());
         let (auth_tx, auth_rx) = mpsc::channel();
         let (project_tx, project_rx) = mpsc::channel();
+        let (location_tx, location_rx) = mpsc::channel();
+        let (number_tx, number_rx) = mpsc::channel();
+        let (cluster_tx, cluster_rx) = mpsc::channel();
+        let (sync_tx, sync_rx) = mpsc::channel();
+        let (gke_tx, gke_rx) = mpsc::channel();
 
         let mut app = Self {
             store,
             profile_names,
             profiles,
             active_profile,
-            user_auth_valid: Vec::new(),
-            adc_auth_valid: Vec::new(),
+            user_auth_status: Vec::new(),
+            adc_auth_status: Vec::new(),
+            user_token_expiry: Vec::new(),
+            drift: Vec::new(),
+            adc_stored: Vec::new(),
+            gcloud_active: None,
+            live: LiveContext::default(),
             selected_row,
-            selected_col: Column::Both,
+            selected_col: table.start_column,
+            view: Vec::new(),
+            filter_query: layout.filter,
+            auth_view: AuthView::All,
+            row_order,
+            last_activated,
+            jump_buffer: String::new(),
+            jump_missed: false,
+            jump_origin: 0,
+            jump_typed_at: Instant::now(),
             should_quit: false,
             status_message: None,
             input_mode: InputMode::Normal,
-            input_buffer: String::new(),
-            new_profile_name: String::new(),
-            new_profile: Profile {
-                user_account: String::new(),
-                user_project: String::new(),
-                adc_account: String::new(),
-                adc_quota_project: String::new(),
-                updated_at: None,
-            },
+            form: None,
+            rename_buffer: String::new(),
+            rename_cursor: 0,
+            tag_buffer: String::new(),
+            tag_cursor: 0,
+            guard_buffer: String::new(),
+            marked: BTreeSet::new(),
             edit_col: Column::User,
             edit_account_buffer: String::new(),
             edit_project_buffer: String::new(),
             edit_cursor_pos: 0,
             suggestions: Vec::new(),
             suggestion_index: None,
+            picker_open: false,
+            account_picker: None,
+            cluster_picker: None,
+            cluster_tx,
+            cluster_rx,
+            cluster_task: None,
+            error_detail: None,
+            compare: None,
+            dry_run: None,
             pending_action: PendingAction::None,
             quit_after_activate: false,
+            activation_queued: false,
+            stay_open,
+            hooks,
+            legacy_configs,
             auth_tx,
             auth_rx,
             auth_generation: 0,
+            auth_pending: 0,
+            auth_queue: Arc::default(),
+            auth_tasks: Vec::new(),
+            last_input: Instant::now(),
+            auth_recheck: None,
+            toasts: VecDeque::new(),
+            toast_duration: Duration::from_secs(DEFAULT_TOAST_SECONDS),
+            last_auth_check: Instant::now(),
+            busy_tick: 0,
             project_tx,
             project_rx,
+            project_generation: 0,
+            project_task: None,
             fetched_projects: Vec::new(),
             fetching_projects: false,
+            location_tx,
+            location_rx,
+            compute_regions: Vec::new(),
+            compute_zones: Vec::new(),
+            fetching_locations: false,
+            number_tx,
+            number_rx,
+            numbers_pending: BTreeSet::new(),
+            copy_number_of: None,
+            sync_target,
+            sync_tx,
+            sync_rx,
+            sync_running: None,
+            gke_tx,
+            gke_rx,
+            gke_generation: Arc::new(AtomicU64::new(0)),
+            gke_lock: Arc::new(Mutex::new(())),
+            gke_refresh: None,
+            conflicts: Vec::new(),
+            watcher: None,
+            reload_due: None,
             sync_mode,
-            table_state: TableState::default().with_selected(Some(selected_row)),
+            table_state: TableState::default(),
+            hit_areas: HitAreas::default(),
+            last_click: None,
+            show_details: layout.details,
+            show_log: layout.log,
+            history_scroll: 0,
+            history_max_scroll: 0,
+            log: VecDeque::new(),
+            theme,
+            undo_stack: Vec::new(),
+            detail_cache: None,
+            column_widths: None,
+            table,
+            guard,
         };
 
-        app.start_auth_checks();
+        match watcher {
+            Ok(watcher) => app.watcher = Some(watcher),
+            Err(e) => app.notify_warn(format!("Live reload disabled: {:#}", e)),
+        }
+        app.refresh_view();
+        app.adc_stored = app.profile_names.iter().map(|name| app.store.has_adc(name)).collect();
+        app.refresh_drift();
+        app.start_auth_checks(false);
         Ok(app)
     }
 
-    /// Spawn background threads to check auth for all unique accounts.
-    fn start_auth_checks(&mut self) {
+    /// Start background tasks to check auth for all unique accounts. `keep_results`
+    /// leaves the current icons up until the new results replace them (the profiles are
+    /// the same); otherwise they show as pending.
+    fn start_auth_checks(&mut self, keep_results: bool) {
         self.auth_generation += 1;
         let gen = self.auth_generation;
-        self.user_auth_valid = vec![None; self.profiles.len()];
-        self.adc_auth_valid = vec![None; self.profiles.len()];
+        // The previous round's checks are no longer wanted, even those in flight
+        for task in self.auth_tasks.drain(..) {
+            task.abort();
+        }
+        self.last_auth_check = Instant::now();
+        if !keep_results {
+            self.user_auth_status = vec![None; self.profiles.len()];
+            self.adc_auth_status = vec![None; self.profiles.len()];
+            self.user_token_expiry = vec![None; self.profiles.len()];
+        }
 
         // Deduplicate: group (profile_index, is_user) by account email
         let mut account_targets: std::collections::HashMap<String, Vec<(usize, bool)>> =
@@ -175,117 +1092,1395 @@ impl App {
             }
         }
 
-        for (account, targets) in account_targets {
-            let tx = self.auth_tx.clone();
-            std::thread::spawn(move || {
-                let valid = gcloud::check_account_auth(&account);
-                for (idx, is_user) in targets {
-                    let _ = tx.send(AuthResult {
-                        generation: gen,
-                        profile_index: idx,
-                        is_user,
-                        valid,
-                    });
+        self.auth_pending = account_targets.values().map(Vec::len).sum();
+        // Top rows first among the off-screen ones too
+        let mut jobs: Vec<AuthJob> = account_targets
+            .into_iter()
+            .map(|(account, targets)| AuthJob { account, targets, visible: false })
+            .collect();
+        jobs.sort_by_key(|job| job.targets.iter().map(|(idx, _)| *idx).min());
+        let workers = jobs.len().min(AUTH_WORKERS);
+        self.auth_queue = Arc::new(Mutex::new(AuthQueue { jobs: jobs.into(), idle: false }));
+        self.schedule_auth_checks();
+        for _ in 0..workers {
+            let (tx, queue) = (self.auth_tx.clone(), Arc::clone(&self.auth_queue));
+            let store = self.store.clone();
+            let task = runtime::spawn(async move {
+                loop {
+                    let job = {
+                        let mut queue = queue.lock().unwrap_or_else(|e| e.into_inner());
+                        if queue.jobs.is_empty() {
+                            break;
+                        }
+                        queue.take()
+                    };
+                    // Only off-screen accounts left, and the user is busy: wait
+                    let Some(AuthJob { account, targets, .. }) = job else {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        continue;
+                    };
+                    let status = gcloud::check_account_auth_cached(&store, &account).await;
+                    let expiry = gcloud::read_token_expiry(&account).ok().flatten();
+                    for (idx, is_user) in targets {
+                        let _ = tx.send(AuthResult {
+                            generation: gen,
+                            profile_index: idx,
+                            is_user,
+                            status,
+                            expiry,
+                        });
+                    }
                 }
             });
+            self.auth_tasks.push(task.abort_handle());
+        }
+    }
+
+    /// Drain completed auth results from the auth tasks.
+    /// Tell the auth workers which accounts are on screen (the selected row counts) and
+    /// whether the user has been idle long enough to check the others. Called every tick.
+    pub fn schedule_auth_checks(&mut self) {
+        let mut queue = self.auth_queue.lock().unwrap_or_else(|e| e.into_inner());
+        if queue.jobs.is_empty() {
+            return;
+        }
+        let offset = self.table_state.offset();
+        // Before the first draw the height is unknown; a screenful is a fair guess
+        let rows = match self.hit_areas.visible_rows {
+            0 => 20,
+            rows => rows,
+        };
+        let on_screen: BTreeSet<usize> = self
+            .view
+            .iter()
+            .skip(offset)
+            .take(rows)
+            .copied()
+            .chain(std::iter::once(self.selected_row))
+            .collect();
+        for job in queue.jobs.iter_mut() {
+            job.visible = job.targets.iter().any(|(idx, _)| on_screen.contains(idx));
         }
+        queue.idle = self.last_input.elapsed() >= AUTH_IDLE_AFTER;
     }
 
-    /// Drain completed auth results from background threads.
     pub fn check_auth_results(&mut self) {
+        if self.is_busy() {
+            self.busy_tick = self.busy_tick.wrapping_add(1);
+        }
         while let Ok(result) = self.auth_rx.try_recv() {
             if result.generation != self.auth_generation {
                 continue;
             }
+            self.auth_pending = self.auth_pending.saturating_sub(1);
             if result.profile_index >= self.profiles.len() {
                 continue;
             }
             if result.is_user {
-                self.user_auth_valid[result.profile_index] = Some(result.valid);
+                self.user_auth_status[result.profile_index] = Some(result.status);
+                self.user_token_expiry[result.profile_index] = result.expiry;
             } else {
-                self.adc_auth_valid[result.profile_index] = Some(result.valid);
+                self.adc_auth_status[result.profile_index] = Some(result.status);
+            }
+            if self.auth_view != AuthView::All {
+                self.refresh_view();
             }
         }
     }
 
-    /// Drain completed project list results from background thread.
-    pub fn check_project_results(&mut self) {
-        while let Ok(projects) = self.project_rx.try_recv() {
-            self.fetched_projects = projects;
-            self.fetching_projects = false;
+    /// Show an outcome as a toast and record it in the activity log.
+    pub fn notify(&mut self, message: String) {
+        self.notify_with(Severity::Info, message);
+    }
+
+    /// `notify` for something that went partly wrong.
+    pub fn notify_warn(&mut self, message: String) {
+        self.notify_with(Severity::Warn, message);
+    }
+
+    /// `notify` for a failure.
+    pub fn notify_error(&mut self, message: String) {
+        self.notify_with(Severity::Error, message);
+    }
+
+    fn notify_with(&mut self, severity: Severity, message: String) {
+        if self.log.len() == LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        self.log.push_back(LogEntry {
+            at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64,
+            message: message.clone(),
+            severity,
+        });
+        if self.toasts.len() == TOAST_STACK {
+            self.toasts.pop_front();
         }
+        let duration = if severity == Severity::Error { self.toast_duration * 2 } else { self.toast_duration };
+        self.toasts.push_back(Toast {
+            message,
+            severity,
+            expires: (!duration.is_zero()).then(|| Instant::now() + duration),
+        });
     }
 
-    /// Spawn a background thread to fetch projects for the given account.
-    fn start_project_fetch(&mut self, account: &str) {
-        if account.is_empty() {
-            self.fetched_projects.clear();
+    /// Drop the toasts whose time is up. Called every tick.
+    pub fn expire_toasts(&mut self) {
+        let now = Instant::now();
+        self.toasts.retain(|toast| toast.expires.is_none_or(|at| at > now));
+    }
+
+    /// The message to leave on the terminal when the TUI closes: the newest toast still up,
+    /// else the status line.
+    pub fn final_message(&self) -> Option<&str> {
+        self.toasts
+            .back()
+            .map(|toast| toast.message.as_str())
+            .or(self.status_message.as_deref())
+    }
+
+    /// Open the error modal if `err` came from a failed gcloud command. The one-line
+    /// message still goes through `notify`; this adds the command and its full output.
+    pub fn show_failure(&mut self, err: &anyhow::Error) {
+        let Some(failure) = err.downcast_ref::<gcloud::CommandFailure>() else {
             return;
-        }
-        self.fetching_projects = true;
-        self.fetched_projects.clear();
-        let account = account.to_string();
-        let tx = self.project_tx.clone();
-        std::thread::spawn(move || {
-            let projects = gcloud::list_projects_for_account(&account).unwrap_or_default();
-            let _ = tx.send(projects);
+        };
+        let return_to = match &self.error_detail {
+            Some(detail) => detail.return_to,
+            None => self.input_mode,
+        };
+        self.error_detail = Some(ErrorDetail {
+            failure: failure.clone(),
+            scroll: 0,
+            max_scroll: 0,
+            return_to,
         });
+        self.input_mode = InputMode::ErrorDetail;
     }
 
-    pub fn reload(&mut self) -> Result<()> {
-        let data = self.store.load_profiles()?;
-        self.profile_names = data.profiles.keys().cloned().collect();
-        self.profiles = data.profiles.values().cloned().collect();
-        self.active_profile = data.active_profile;
-        if self.selected_row >= self.profile_names.len() {
-            self.selected_row = self.profile_names.len().saturating_sub(1);
+    /// Show what Enter and `a` would run for the selected profile and column.
+    fn open_dry_run(&mut self) {
+        match self.dry_run_sections() {
+            Ok(sections) => {
+                self.dry_run = Some(DryRun {
+                    name: self.profile_names[self.selected_row].clone(),
+                    sections,
+                    scroll: 0,
+                    max_scroll: 0,
+                });
+                self.input_mode = InputMode::DryRun;
+            }
+            Err(e) => self.notify_error(format!("Dry run failed: {:#}", e)),
         }
-        self.table_state.select(Some(self.selected_row));
-        self.start_auth_checks();
-        Ok(())
     }
 
-    pub fn handle_event(&mut self) -> Result<bool> {
-        // Use poll with timeout so the UI can refresh for async auth results
-        if event::poll(Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                match self.input_mode {
-                    InputMode::Normal => self.handle_normal_key(key)?,
-                    InputMode::ConfirmDelete => self.handle_confirm_delete(key)?,
-                    InputMode::EditAccount | InputMode::EditProject => {
-                        self.handle_edit_key(key)?
-                    }
-                    _ => self.handle_input_key(key)?,
-                }
-            }
+    /// The steps of `activate_selected` (with the login it starts with when the column's
+    /// credentials need one) and of `execute_reauth`.
+    fn dry_run_sections(&self) -> Result<Vec<(String, Vec<PlanStep>)>> {
+        let i = self.selected_row;
+        let name = &self.profile_names[i];
+        let profile = &self.profiles[i];
+        let (user, adc) = match self.selected_col {
+            Column::Both => (true, true),
+            Column::User => (true, false),
+            Column::Adc => (false, true),
+        };
+        let status = |statuses: &[Option<AuthStatus>]| statuses.get(i).copied().flatten();
+        let sides = [(user, status(&self.user_auth_status)), (adc, status(&self.adc_auth_status))];
+
+        let mut login = gcloud::reauth_plan(&self.store, name, profile, user, adc)?;
+        if user {
+            login.extend(gcloud::user_activation_plan(name, &profile.user_account, &profile.user_project));
         }
-        Ok(self.should_quit)
+        let mut activate = Vec::new();
+        if sides.iter().any(|&(side, status)| side && status.is_some_and(|s| s.needs_reauth())) {
+            activate.push(PlanStep::Other("credentials need a login first:".to_string()));
+            activate.extend(login.iter().cloned());
+        } else if sides.iter().any(|&(side, status)| side && status.is_none()) {
+            activate.push(PlanStep::Other("credentials not checked yet; a login first if they need one".to_string()));
+        }
+        activate.extend(activation_plan(&self.store, name, profile, self.selected_col, self.legacy_configs, &self.hooks)?);
+        let scope = match self.selected_col {
+            Column::Both => "user config and ADC",
+            Column::User => "user config",
+            Column::Adc => "ADC",
+        };
+        Ok(vec![
+            (format!("Enter: activate {}", scope), activate),
+            (format!("a: log in again ({})", scope), login),
+        ])
     }
 
-    fn handle_normal_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Esc => {
-                self.should_quit = true;
+    /// Compare the two marked profiles, or the one marked profile with the selected one.
+    fn open_compare(&mut self) {
+        let mut names: Vec<String> = self.marked.iter().cloned().collect();
+        if names.len() == 1 && self.has_selection() {
+            let selected = &self.profile_names[self.selected_row];
+            if !self.marked.contains(selected) {
+                names.push(selected.clone());
             }
-            KeyCode::Up => {
-                if !self.profile_names.is_empty() && self.selected_row > 0 {
-                    self.selected_row -= 1;
-                    self.table_state.select(Some(self.selected_row));
-                }
+        }
+        let [a, b] = names.as_slice() else {
+            self.status_message = Some("Mark two profiles with Space to compare them.".to_string());
+            return;
+        };
+        let rows = self
+            .store
+            .load_profiles()
+            .and_then(|data| profile_diff(&self.store, &data, a, b));
+        match rows {
+            Ok(rows) => {
+                self.compare = Some(CompareView {
+                    a: a.clone(),
+                    b: b.clone(),
+                    rows,
+                    only_differences: false,
+                    scroll: 0,
+                    max_scroll: 0,
+                });
+                self.input_mode = InputMode::Compare;
                 self.status_message = None;
             }
-            KeyCode::Down => {
-                if !self.profile_names.is_empty()
-                    && self.selected_row < self.profile_names.len() - 1
-                {
-                    self.selected_row += 1;
-                    self.table_state.select(Some(self.selected_row));
-                }
-                self.status_message = None;
+            Err(e) => self.notify_error(format!("Compare failed: {:#}", e)),
+        }
+    }
+
+    fn handle_compare_key(&mut self, key: KeyEvent) {
+        let Some(view) = self.compare.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return;
+        };
+        let last = view.max_scroll;
+        match key.code {
+            KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::Down => view.scroll = (view.scroll + 1).min(last),
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(10),
+            KeyCode::PageDown => view.scroll = (view.scroll + 10).min(last),
+            KeyCode::Home => view.scroll = 0,
+            KeyCode::End => view.scroll = last,
+            KeyCode::Char('d') => {
+                view.only_differences = !view.only_differences;
+                view.scroll = 0;
             }
-            KeyCode::Left => {
-                self.selected_col = match self.selected_col {
-                    Column::Both => Column::Both,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q' | '=') => {
+                self.compare = None;
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    /// Scroll the message history, newest first; any other key closes it.
+    fn handle_history_key(&mut self, key: KeyEvent) {
+        let last = self.history_max_scroll;
+        let scroll = &mut self.history_scroll;
+        match key.code {
+            KeyCode::Up => *scroll = scroll.saturating_sub(1),
+            KeyCode::Down => *scroll = (*scroll + 1).min(last),
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+            KeyCode::PageDown => *scroll = (*scroll + 10).min(last),
+            KeyCode::Home => *scroll = 0,
+            KeyCode::End => *scroll = last,
+            _ => self.input_mode = InputMode::Normal,
+        }
+    }
+
+    /// Scroll the dry run; Enter closes it and activates as Enter in the table would.
+    fn handle_dry_run_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(view) = self.dry_run.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        let last = view.max_scroll;
+        match key.code {
+            KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::Down => view.scroll = (view.scroll + 1).min(last),
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(10),
+            KeyCode::PageDown => view.scroll = (view.scroll + 10).min(last),
+            KeyCode::Home => view.scroll = 0,
+            KeyCode::End => view.scroll = last,
+            code => {
+                self.dry_run = None;
+                self.input_mode = InputMode::Normal;
+                if code == KeyCode::Enter {
+                    self.handle_normal_key(key)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_error_detail_key(&mut self, key: KeyEvent) {
+        let Some(detail) = self.error_detail.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return;
+        };
+        let last = detail.max_scroll;
+        match key.code {
+            KeyCode::Up => detail.scroll = detail.scroll.saturating_sub(1),
+            KeyCode::Down => detail.scroll = (detail.scroll + 1).min(last),
+            KeyCode::PageUp => detail.scroll = detail.scroll.saturating_sub(10),
+            KeyCode::PageDown => detail.scroll = (detail.scroll + 10).min(last),
+            KeyCode::Home => detail.scroll = 0,
+            KeyCode::End => detail.scroll = last,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                self.input_mode = detail.return_to;
+                self.error_detail = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether auth checks, a project fetch or a sync are still running in the background.
+    pub fn is_busy(&self) -> bool {
+        self.auth_pending > 0
+            || self.fetching_projects
+            || self.fetching_locations
+            || !self.numbers_pending.is_empty()
+            || self.cluster_picker.as_ref().is_some_and(|p| p.loading)
+            || self.sync_running.is_some()
+            || self.gke_refresh.is_some()
+    }
+
+    /// Run a sync push or pull on a background thread.
+    fn start_sync(&mut self, direction: SyncDirection) {
+        let Some(target) = self.sync_target.clone() else {
+            self.notify_warn(
+                "Sync not configured. Run 'gcloud-switch sync init <remote_url>' first.".to_string(),
+            );
+            return;
+        };
+        if self.sync_running.is_some() {
+            return;
+        }
+        self.sync_running = Some(direction);
+        self.status_message = None;
+        let tx = self.sync_tx.clone();
+        std::thread::spawn(move || {
+            let result = Store::new().and_then(|store| match direction {
+                SyncDirection::Push => sync::sync_push(&store, &target).map(|()| Vec::new()),
+                // Conflicts can't be prompted for while the TUI owns the terminal
+                SyncDirection::Pull => sync::sync_pull(&store, &target, sync::MergeStrategy::Defer, None),
+            });
+            let _ = tx.send((direction, result.map_err(|e| format!("{:#}", e))));
+        });
+    }
+
+    /// Report a finished background sync; a pull reloads the table.
+    pub fn check_sync_results(&mut self) -> Result<()> {
+        while let Ok((direction, result)) = self.sync_rx.try_recv() {
+            self.sync_running = None;
+            match (direction, result) {
+                (SyncDirection::Push, Ok(_)) => self.notify("Pushed profiles to remote.".to_string()),
+                (SyncDirection::Pull, Ok(conflicts)) => {
+                    self.hooks.spawn(HookEvent::SyncPulled, hooks::pull_data(&conflicts));
+                    self.reload()?;
+                    if conflicts.is_empty() {
+                        self.notify("Pulled and merged profiles from remote.".to_string());
+                    } else {
+                        self.notify_warn(format!(
+                            "Pulled from remote; {} profile(s) changed on both sides.",
+                            conflicts.len()
+                        ));
+                    }
+                    self.conflicts.extend(conflicts);
+                }
+                (SyncDirection::Push, Err(e)) => self.notify_error(format!("Sync push failed: {}", e)),
+                (SyncDirection::Pull, Err(e)) => self.notify_error(format!("Sync pull failed: {}", e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Refresh the kubectl contexts of an activated profile's GKE clusters on a background
+    /// thread. Supersedes a refresh still running for an earlier activation, so kubectl
+    /// never ends up on the previous profile's cluster.
+    fn start_gke_refresh(&mut self, name: &str, profile: &Profile) {
+        let generation = self.gke_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.gke_refresh = None;
+        if profile.clusters.is_empty() {
+            return;
+        }
+        if let Err(e) = net::ensure_online("Refreshing GKE credentials") {
+            self.notify_warn(format!("{:#}", e));
+            return;
+        }
+        self.gke_refresh = Some(GkeRefresh {
+            generation,
+            profile: name.to_string(),
+            done: 0,
+            total: profile.clusters.len(),
+            failed: Vec::new(),
+        });
+        let (tx, current, lock) = (self.gke_tx.clone(), Arc::clone(&self.gke_generation), Arc::clone(&self.gke_lock));
+        let profile = profile.clone();
+        std::thread::spawn(move || {
+            // Wait out a superseded refresh; it stops after its current cluster
+            let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+            let is_current = || current.load(Ordering::SeqCst) == generation;
+            if !is_current() {
+                return;
+            }
+            let _ = gcloud::refresh_clusters(&profile.clusters, &profile.user_account, &profile.user_project, |cluster, result| {
+                let _ = tx.send(GkeProgress {
+                    generation,
+                    cluster: cluster.to_string(),
+                    result: result.map_err(|e| format!("{:#}", e)),
+                });
+                is_current()
+            });
+        });
+    }
+
+    /// Record finished cluster refreshes; report once the whole refresh is done.
+    pub fn check_gke_results(&mut self) {
+        while let Ok(progress) = self.gke_rx.try_recv() {
+            self.record_gke_progress(progress);
+        }
+    }
+
+    /// Keep the selected profile and the view in state.toml, for `start_row = "last"` and
+    /// `[table] restore`.
+    pub fn remember_layout(&self) -> Result<()> {
+        let layout = TuiLayout {
+            recent_first: self.row_order == RowOrder::Recent,
+            filter: self.filter_query.clone(),
+            details: self.show_details,
+            log: self.show_log,
+        };
+        let selected = self.profile_names.get(self.selected_row).cloned();
+        self.store.update_state(|state| {
+            if selected.is_some() {
+                state.last_selected = selected;
+            }
+            state.layout = Some(layout);
+        })
+    }
+
+    /// Block until a running GKE refresh finishes, for quitting right after activation.
+    pub fn wait_for_gke_refresh(&mut self) {
+        while self.gke_refresh.is_some() {
+            match self.gke_rx.recv() {
+                Ok(progress) => self.record_gke_progress(progress),
+                Err(_) => break,
+            }
+        }
+    }
+
+    fn record_gke_progress(&mut self, progress: GkeProgress) {
+        let Some(refresh) = self.gke_refresh.as_mut() else {
+            return;
+        };
+        if progress.generation != refresh.generation {
+            return;
+        }
+        refresh.done += 1;
+        if let Err(e) = progress.result {
+            refresh.failed.push(format!("{} ({})", progress.cluster, e));
+        }
+        if refresh.done < refresh.total {
+            return;
+        }
+        let Some(refresh) = self.gke_refresh.take() else {
+            return;
+        };
+        if refresh.failed.is_empty() {
+            self.notify(format!(
+                "Refreshed kubectl contexts for {} GKE cluster(s) of '{}'.",
+                refresh.total, refresh.profile
+            ));
+        } else {
+            self.notify_warn(format!("GKE credential refresh failed for: {}", refresh.failed.join(", ")));
+        }
+    }
+
+    /// Reload after profiles.toml or gcloud's configurations changed outside the TUI.
+    /// Waits for the burst of writes to settle, and for any edit in progress to finish.
+    pub fn check_file_changes(&mut self) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+        if watcher.changed() {
+            self.reload_due = Some(Instant::now() + WATCH_DEBOUNCE);
+        }
+        let idle = matches!(self.input_mode, InputMode::Normal | InputMode::Filter | InputMode::Jump);
+        if !idle || self.reload_due.is_none_or(|due| Instant::now() < due) {
+            return;
+        }
+        self.reload_due = None;
+        if let Err(e) = self.reload_external() {
+            self.notify_error(format!("Reload after external change failed: {}", e));
+        }
+    }
+
+    fn reload_external(&mut self) -> Result<()> {
+        sync_with_gcloud(&self.store)?;
+        let data = self.store.load_profiles()?;
+        // Our own saves trigger the watcher too; those only need the drift re-checked
+        let unchanged = data.active_profile == self.active_profile
+            && data.sync_mode == self.sync_mode
+            && data.profiles.keys().eq(&self.profile_names)
+            && data.profiles.values().eq(&self.profiles);
+        if unchanged {
+            self.refresh_drift();
+            return Ok(());
+        }
+        self.reload()?;
+        self.notify("Profiles changed on disk; reloaded.".to_string());
+        // Wait for any edit in progress before asking
+        if !self.conflicts.is_empty() && self.input_mode == InputMode::Normal {
+            self.input_mode = InputMode::SyncConflict;
+        }
+        Ok(())
+    }
+
+    fn handle_conflict_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.conflicts.is_empty() {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Char('l') | KeyCode::Left => {
+                let conflict = self.conflicts.remove(0);
+                self.resolve_conflict(&conflict.name, conflict.local, "local")?;
+            }
+            KeyCode::Char('r') | KeyCode::Right => {
+                let conflict = self.conflicts.remove(0);
+                self.resolve_conflict(&conflict.name, conflict.remote, "remote")?;
+            }
+            KeyCode::Char('e') => {
+                // Start from the remote side; the form saves (and re-stamps) the result.
+                // Remaining conflicts come back up once the form is closed.
+                let conflict = self.conflicts.remove(0);
+                self.select_profile(&conflict.name);
+                self.open_form(ProfileForm::for_profile(&conflict.name, &conflict.remote));
+            }
+            KeyCode::Esc => {
+                let names: Vec<String> = self.conflicts.drain(..).map(|c| c.name).collect();
+                self.input_mode = InputMode::Normal;
+                self.notify(format!("Kept local version of: {}", names.join(", ")));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Save the chosen side of a conflict with a fresh timestamp, so it wins the next sync
+    /// on every machine instead of conflicting again.
+    fn resolve_conflict(&mut self, name: &str, profile: Profile, side: &str) -> Result<()> {
+        let snapshot = self.snapshot(format!("resolve conflict '{}'", name), &[name])?;
+        self.store.add_profile(name, profile)?;
+        self.push_undo(snapshot, Vec::new());
+        self.reload()?;
+        self.notify(format!("Kept {} version of '{}'.", side, name));
+        if self.conflicts.is_empty() {
+            self.input_mode = InputMode::Normal;
+        }
+        Ok(())
+    }
+
+    /// Drain completed project list results from `start_project_fetch`.
+    pub fn check_project_results(&mut self) {
+        while let Ok((generation, projects)) = self.project_rx.try_recv() {
+            if generation != self.project_generation {
+                continue;
+            }
+            self.fetched_projects = projects;
+            self.fetching_projects = false;
+            if self.picker_open {
+                self.refresh_picker();
+            }
+        }
+    }
+
+    /// Drain region and zone lists from `start_location_fetch`.
+    pub fn check_location_results(&mut self) {
+        while let Ok((zones, locations)) = self.location_rx.try_recv() {
+            self.fetching_locations = false;
+            if zones {
+                self.compute_zones = locations;
+            } else {
+                self.compute_regions = locations;
+            }
+            if self.picker_open {
+                self.refresh_picker();
+            }
+        }
+    }
+
+    /// List regions or zones in the background (state.toml answers without asking
+    /// gcloud once they were listed), as the form's user account in its user project.
+    fn start_location_fetch(&mut self, zones: bool) {
+        let known = if zones { &self.compute_zones } else { &self.compute_regions };
+        if !known.is_empty() || self.fetching_locations {
+            return;
+        }
+        let Some(form) = self.form.as_ref() else {
+            return;
+        };
+        self.fetching_locations = true;
+        let store = self.store.clone();
+        let (account, project) = (form.effective(FIELD_USER_ACCOUNT), form.effective(FIELD_USER_PROJECT));
+        let tx = self.location_tx.clone();
+        // Not aborted with the form: the list lands in state.toml for the next one
+        runtime::spawn(async move {
+            let locations = gcloud::compute_locations(&store, zones, &account, &project).await.unwrap_or_else(|e| {
+                tracing::debug!(zones, error = %format!("{:#}", e), "compute location list failed");
+                Vec::new()
+            });
+            let _ = tx.send((zones, locations));
+        });
+    }
+
+    /// Look up a project's number in the background; `check_number_results` picks it up.
+    fn resolve_project_number(&mut self, account: &str, project: &str) {
+        if !self.numbers_pending.insert(project.to_string()) {
+            return;
+        }
+        let (store, account, project) = (self.store.clone(), account.to_string(), project.to_string());
+        let tx = self.number_tx.clone();
+        runtime::spawn(async move {
+            let number = gcloud::project_number(&store, &account, &project).await.unwrap_or_else(|e| {
+                tracing::debug!(project = %project, error = %format!("{:#}", e), "project number lookup failed");
+                None
+            });
+            let _ = tx.send((project, number));
+        });
+    }
+
+    pub fn check_number_results(&mut self) {
+        while let Ok((project, number)) = self.number_rx.try_recv() {
+            self.numbers_pending.remove(&project);
+            self.detail_cache = None;
+            if self.copy_number_of.as_deref() != Some(project.as_str()) {
+                continue;
+            }
+            self.copy_number_of = None;
+            match number {
+                Some(number) => self.copy_text(&format!("number of '{}'", project), &number),
+                None => self.notify_error(format!("Could not resolve the number of project '{}'.", project)),
+            }
+        }
+    }
+
+    /// Text of the project, region or zone field being edited, if the focus is on one.
+    fn picker_query(&self) -> Option<&str> {
+        match self.input_mode {
+            InputMode::EditProject => Some(&self.edit_project_buffer),
+            InputMode::Form => self
+                .form
+                .as_ref()
+                .filter(|f| matches!(FieldKind::of(f.focus), FieldKind::Project | FieldKind::Region | FieldKind::Zone))
+                .map(|f| f.values[f.focus].as_str()),
+            _ => None,
+        }
+    }
+
+    /// Kind of the form field with the focus, or Project while editing a project inline.
+    fn picker_kind(&self) -> FieldKind {
+        match (&self.input_mode, &self.form) {
+            (InputMode::Form, Some(form)) => FieldKind::of(form.focus),
+            _ => FieldKind::Project,
+        }
+    }
+
+    /// Whether the list behind the open picker is still being fetched.
+    pub fn fetching_suggestions(&self) -> bool {
+        match self.picker_kind() {
+            FieldKind::Region | FieldKind::Zone => self.fetching_locations,
+            _ => self.fetching_projects,
+        }
+    }
+
+    /// Whether the project dropdown should be drawn without a highlighted entry.
+    pub fn picker_visible(&self) -> bool {
+        self.picker_open && self.picker_query().is_some()
+    }
+
+    /// Open or refresh the project, region or zone picker: known and fetched values
+    /// fuzzy-filtered by the typed text, best matches first.
+    fn refresh_picker(&mut self) {
+        let Some(query) = self.picker_query().map(|q| q.trim().to_string()) else {
+            self.picker_open = false;
+            return;
+        };
+        let candidates = match self.picker_kind() {
+            kind @ (FieldKind::Region | FieldKind::Zone) => self.build_location_suggestions(kind == FieldKind::Zone),
+            _ => self.build_project_suggestions(),
+        };
+        let mut scored: Vec<(i64, String)> = candidates
+            .into_iter()
+            .filter_map(|p| fuzzy_score(&p, &query).map(|score| (score, p)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        self.suggestions = scored.into_iter().map(|(_, p)| p).collect();
+        self.picker_open = true;
+        self.suggestion_index = self
+            .suggestion_index
+            .filter(|_| !self.suggestions.is_empty())
+            .map(|idx| idx.min(self.suggestions.len() - 1));
+    }
+
+    /// Fetch the projects of the given account in the background, stopping a fetch for
+    /// another account still running.
+    fn start_project_fetch(&mut self, account: &str) {
+        self.project_generation += 1;
+        if let Some(task) = self.project_task.take() {
+            task.abort();
+        }
+        self.fetched_projects.clear();
+        if account.is_empty() {
+            self.fetching_projects = false;
+            return;
+        }
+        self.fetching_projects = true;
+        let (account, generation) = (account.to_string(), self.project_generation);
+        let tx = self.project_tx.clone();
+        let task = runtime::spawn(async move {
+            let projects = gcloud::list_projects_for_account(&account).await.unwrap_or_default();
+            let _ = tx.send((generation, projects));
+        });
+        self.project_task = Some(task.abort_handle());
+    }
+
+    pub fn reload(&mut self) -> Result<()> {
+        let data = self.store.load_profiles()?;
+        self.profile_names = data.profiles.keys().cloned().collect();
+        self.profiles = data.profiles.values().cloned().collect();
+        self.active_profile = data.active_profile;
+        self.sync_mode = data.sync_mode;
+        self.marked.retain(|name| data.profiles.contains_key(name));
+        if let Ok(state) = self.store.load_state() {
+            self.last_activated = state.last_activated;
+        }
+        if self.selected_row >= self.profile_names.len() {
+            self.selected_row = self.profile_names.len().saturating_sub(1);
+        }
+        self.refresh_view();
+        self.detail_cache = None;
+        self.column_widths = None;
+        self.adc_stored = self.profile_names.iter().map(|name| self.store.has_adc(name)).collect();
+        self.refresh_drift();
+        self.start_auth_checks(false);
+        Ok(())
+    }
+
+    /// Check every account again, keeping the current icons until the results are in.
+    /// Used after the TUI was suspended, when logins may have happened.
+    pub fn recheck_auth(&mut self) {
+        self.start_auth_checks(true);
+    }
+
+    /// Re-run the auth checks every `auth_recheck_interval`, so icons and countdowns do not
+    /// go stale while the TUI stays open. Called every tick.
+    pub fn check_auth_recheck(&mut self) {
+        let due = self.auth_recheck.is_some_and(|every| self.last_auth_check.elapsed() >= every);
+        if due && self.auth_pending == 0 {
+            tracing::debug!("periodic auth re-check");
+            self.recheck_auth();
+        }
+    }
+
+    /// Re-read everything from disk: reconcile with gcloud's configurations, reload
+    /// profiles.toml and restart the auth checks.
+    fn refresh(&mut self) -> Result<()> {
+        sync_with_gcloud(&self.store)?;
+        self.reload()?;
+        self.notify(format!("Reloaded {} profile(s).", self.profiles.len()));
+        Ok(())
+    }
+
+    /// Compare every profile with its `config_<name>` file and gcloud's active configuration.
+    fn refresh_drift(&mut self) {
+        self.gcloud_active = gcloud::read_active_config().ok().flatten();
+        self.drift = self
+            .profile_names
+            .iter()
+            .zip(&self.profiles)
+            .map(|(name, profile)| {
+                Drift::of(name, profile, self.active_profile.as_deref(), self.gcloud_active.as_deref())
+            })
+            .collect();
+        // The active profile is the likely owner of the installed ADC; only look further
+        // when it isn't
+        let active = self.active_profile.iter().filter_map(|name| self.profile_names.iter().position(|n| n == name));
+        let candidates = active
+            .chain(0..self.profile_names.len())
+            .filter(|&i| self.adc_stored.get(i).copied().unwrap_or(false))
+            .map(|i| self.profile_names[i].as_str());
+        self.live = LiveContext::read(&self.store, self.gcloud_active.as_deref(), candidates);
+    }
+
+    /// Drift of the selected profile, if it has any.
+    pub fn selected_drift(&self) -> Option<&Drift> {
+        if !self.has_selection() {
+            return None;
+        }
+        self.drift.get(self.selected_row).filter(|d| !d.is_empty())
+    }
+
+    /// Checked credentials of the selected column that need a login (user side first in
+    /// the Both column), with the account they belong to.
+    pub fn selected_auth_problem(&self) -> Option<(AuthStatus, &str)> {
+        if !self.has_selection() {
+            return None;
+        }
+        let profile = &self.profiles[self.selected_row];
+        let user = (self.user_auth_status.get(self.selected_row).copied().flatten(), profile.user_account.as_str());
+        let adc = (self.adc_auth_status.get(self.selected_row).copied().flatten(), profile.adc_account.as_str());
+        let sides = match self.selected_col {
+            Column::Both => vec![user, adc],
+            Column::User => vec![user],
+            Column::Adc => vec![adc],
+        };
+        sides
+            .into_iter()
+            .find_map(|(status, account)| status.filter(|s| s.needs_reauth()).map(|s| (s, account)))
+    }
+
+    /// Resolve the selected profile's drift: `from_gcloud` takes the live values into the
+    /// store, otherwise the stored values are written to gcloud.
+    fn reconcile_selected(&mut self, from_gcloud: bool) -> Result<()> {
+        let Some(drift) = self.selected_drift().cloned() else {
+            self.notify("Profile matches gcloud.".to_string());
+            return Ok(());
+        };
+        let name = self.profile_names[self.selected_row].clone();
+        let profile = self.profiles[self.selected_row].clone();
+        if from_gcloud {
+            let snapshot = self.snapshot(format!("take gcloud state for '{}'", name), &[&name])?;
+            let mut data = self.store.load_profiles()?;
+            if let Some(stored) = data.profiles.get_mut(&name) {
+                if let Some(account) = drift.account {
+                    stored.user_account = account;
+                }
+                if let Some(project) = drift.project {
+                    stored.user_project = project;
+                }
+                stored.touch();
+            }
+            if drift.active {
+                data.active_profile = self
+                    .gcloud_active
+                    .clone()
+                    .filter(|active| data.profiles.contains_key(active));
+            }
+            self.store.save_profiles(&data)?;
+            self.push_undo(snapshot, Vec::new());
+            self.notify(format!("Took gcloud state into '{}'.", name));
+        } else {
+            if drift.account.is_some() || drift.project.is_some() {
+                gcloud::create_configuration(&name, &profile.user_account, &profile.user_project)?;
+            }
+            if drift.active {
+                if let Some(active) = self.active_profile.clone() {
+                    if let Some(idx) = self.profile_names.iter().position(|n| *n == active) {
+                        let p = &self.profiles[idx];
+                        gcloud::activate_user(&active, &p.user_account, &p.user_project)?;
+                    }
+                }
+            }
+            self.notify(format!("Wrote '{}' to gcloud.", name));
+        }
+        self.reload()
+    }
+
+    /// Capture the store state (and the given profiles' ADC files) before a mutation.
+    fn snapshot(&self, description: String, touched: &[&str]) -> Result<UndoEntry> {
+        let data = self.store.load_profiles()?;
+        let mut profiles = Vec::new();
+        let mut adc = Vec::new();
+        for name in touched {
+            profiles.push((
+                name.to_string(),
+                data.profiles.get(*name).cloned(),
+                data.deleted.get(*name).copied(),
+            ));
+            adc.push((name.to_string(), self.store.load_adc_json(name)?));
+        }
+        Ok(UndoEntry {
+            description,
+            profiles,
+            active_profile: data.active_profile,
+            adc,
+            gcloud: Vec::new(),
+        })
+    }
+
+    /// Record a completed mutation so `u` can revert it.
+    fn push_undo(&mut self, mut entry: UndoEntry, gcloud: Vec<GcloudUndo>) {
+        entry.gcloud = gcloud;
+        self.undo_stack.push(entry);
+        if self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    fn undo(&mut self) -> Result<()> {
+        let Some(entry) = self.undo_stack.pop() else {
+            self.notify("Nothing to undo.".to_string());
+            return Ok(());
+        };
+        let mut data = self.store.load_profiles()?;
+        for (name, profile, tombstone) in entry.profiles {
+            match profile {
+                Some(profile) => data.profiles.insert(name.clone(), profile),
+                None => data.profiles.remove(&name),
+            };
+            match tombstone {
+                Some(at) => data.deleted.insert(name, at),
+                None => data.deleted.remove(&name),
+            };
+        }
+        // Later activations stay unless undo removed the active profile
+        if !data.active_profile.as_ref().is_some_and(|a| data.profiles.contains_key(a)) {
+            data.active_profile = entry.active_profile.filter(|a| data.profiles.contains_key(a));
+        }
+        self.store.save_profiles(&data)?;
+        for (name, adc) in &entry.adc {
+            match adc {
+                Some(value) => self.store.save_adc_json(name, value)?,
+                None => self.store.remove_adc(name)?,
+            }
+        }
+        let gcloud_result = entry.gcloud.into_iter().try_for_each(|change| match change {
+            GcloudUndo::Create {
+                name,
+                account,
+                project,
+            } => gcloud::create_configuration(&name, &account, &project),
+            GcloudUndo::Delete(name) => gcloud::delete_configuration(&name),
+            GcloudUndo::Rename { from, to } => gcloud::rename_configuration(&from, &to),
+            GcloudUndo::Write {
+                name,
+                account,
+                project,
+            } => gcloud::write_configuration(&name, &account, &project),
+        });
+        match gcloud_result {
+            Ok(()) => self.notify(format!("Undid: {}", entry.description)),
+            Err(e) => {
+                self.notify_warn(format!("Undid: {} (gcloud config not restored: {})", entry.description, e));
+                self.show_failure(&e);
+            }
+        }
+        self.reload()
+    }
+
+    /// Detail pane data for the selected profile, loading it if the selection changed.
+    pub fn selected_detail(&mut self) -> Option<&ProfileDetail> {
+        if !self.has_selection() {
+            return None;
+        }
+        let idx = self.selected_row;
+        if self.detail_cache.as_ref().map(|(i, _)| *i) != Some(idx) {
+            let name = &self.profile_names[idx];
+            let profile = &self.profiles[idx];
+            let project_number = gcloud::cached_project_number(&self.store, &profile.user_project);
+            let adc_path = self.store.adc_path(name);
+            let detail = ProfileDetail {
+                has_adc: adc_path.exists(),
+                adc_path,
+                user_token_expiry: gcloud::read_token_expiry(&profile.user_account)
+                    .ok()
+                    .flatten(),
+                config_path: gcloud::configuration_path(name).ok(),
+                config_properties: gcloud::read_configuration(name).ok().flatten(),
+                is_gcloud_active: gcloud::read_active_config().ok().flatten().as_deref()
+                    == Some(name.as_str()),
+                project_number,
+            };
+            if detail.project_number.is_none() && !profile.user_project.is_empty() && !net::is_offline() {
+                let (account, project) = (profile.user_account.clone(), profile.user_project.clone());
+                self.resolve_project_number(&account, &project);
+            }
+            self.detail_cache = Some((idx, detail));
+        }
+        self.detail_cache.as_ref().map(|(_, d)| d)
+    }
+
+    /// Rebuild `view` from the filter query. With a query, rows are ordered best match first.
+    pub fn refresh_view(&mut self) {
+        let query = self.filter_query.trim();
+        if query.is_empty() {
+            self.view = (0..self.profiles.len()).collect();
+            if self.row_order == RowOrder::Recent {
+                self.view.sort_by_key(|&i| std::cmp::Reverse(self.last_activated.get(&self.profile_names[i])));
+            }
+        } else {
+            let mut scored: Vec<(i64, usize)> = self
+                .profile_names
+                .iter()
+                .zip(self.profiles.iter())
+                .enumerate()
+                .filter_map(|(i, (name, p))| {
+                    [
+                        name.as_str(),
+                        &p.user_account,
+                        &p.user_project,
+                        &p.adc_account,
+                        &p.adc_quota_project,
+                    ]
+                    .iter()
+                    .filter_map(|field| fuzzy_score(field, query))
+                    .max()
+                    .map(|score| (score, i))
+                })
+                .collect();
+            scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+            self.view = scored.into_iter().map(|(_, i)| i).collect();
+        }
+        if self.auth_view != AuthView::All {
+            // Stable, so the filter's order holds within each group
+            let mut view = std::mem::take(&mut self.view);
+            view.sort_by_key(|&i| self.auth_rank(i));
+            if self.auth_view == AuthView::ProblemsOnly {
+                view.retain(|&i| self.auth_rank(i) < AUTH_RANK_HEALTHY);
+            }
+            self.view = view;
+        }
+        if !self.view.contains(&self.selected_row) {
+            if let Some(&first) = self.view.first() {
+                self.selected_row = first;
+            }
+        }
+        self.table_state.select(self.selected_view_pos());
+    }
+
+    /// How urgently profile `i`'s credentials need attention: 0 when a side needs a login,
+    /// 1 when a side is unknown, unchecked or still being checked, else `AUTH_RANK_HEALTHY`.
+    fn auth_rank(&self, i: usize) -> u8 {
+        let profile = &self.profiles[i];
+        let sides = [
+            (&profile.user_account, &self.user_auth_status),
+            (&profile.adc_account, &self.adc_auth_status),
+        ];
+        sides
+            .into_iter()
+            .filter(|(account, _)| !account.is_empty())
+            .map(|(_, statuses)| match statuses.get(i).copied().flatten() {
+                Some(status) if status.needs_reauth() => 0,
+                Some(AuthStatus::Valid | AuthStatus::NotApplicable) => AUTH_RANK_HEALTHY,
+                _ => 1,
+            })
+            .min()
+            .unwrap_or(AUTH_RANK_HEALTHY)
+    }
+
+    /// Cycle the auth triage view and say how many profiles need a login.
+    fn cycle_auth_view(&mut self) {
+        self.auth_view = match self.auth_view {
+            AuthView::All => AuthView::ProblemsFirst,
+            AuthView::ProblemsFirst => AuthView::ProblemsOnly,
+            AuthView::ProblemsOnly => AuthView::All,
+        };
+        self.refresh_view();
+        if let Some(&top) = self.view.first().filter(|_| self.auth_view != AuthView::All) {
+            self.selected_row = top;
+            self.table_state.select(Some(0));
+        }
+        let expired = (0..self.profiles.len()).filter(|&i| self.auth_rank(i) == 0).count();
+        self.notify(match self.auth_view {
+            AuthView::All => "Showing all profiles.".to_string(),
+            _ if self.auth_pending > 0 => format!("Showing {} ({} need a login so far, checks still running).", self.auth_view.label(), expired),
+            _ => format!("Showing {} ({} need a login).", self.auth_view.label(), expired),
+        });
+    }
+
+    /// Re-filter after the query changed and select the best match.
+    fn apply_filter_query(&mut self) {
+        self.refresh_view();
+        if let Some(&top) = self.view.first() {
+            self.selected_row = top;
+            self.table_state.select(Some(0));
+        }
+    }
+
+    /// Position of the selected profile within `view`, if it is visible.
+    pub fn selected_view_pos(&self) -> Option<usize> {
+        self.view.iter().position(|&i| i == self.selected_row)
+    }
+
+    /// Whether a visible profile is selected (there is something to act on).
+    fn has_selection(&self) -> bool {
+        self.selected_view_pos().is_some()
+    }
+
+    /// Move the selection by `delta` rows within `view`, clamping at the ends.
+    fn move_selection(&mut self, delta: isize) {
+        if self.view.is_empty() {
+            return;
+        }
+        let pos = self.selected_view_pos().unwrap_or(0) as isize;
+        let new_pos = (pos + delta).clamp(0, self.view.len() as isize - 1) as usize;
+        self.selected_row = self.view[new_pos];
+        self.table_state.select(Some(new_pos));
+    }
+
+    /// Move the selection and the viewport by one screenful (`pages` < 0 moves up).
+    fn move_page(&mut self, pages: isize) {
+        let page = self.hit_areas.visible_rows.max(1);
+        let max_offset = self.view.len().saturating_sub(page);
+        let offset = (self.table_state.offset() as isize + pages * page as isize)
+            .clamp(0, max_offset as isize) as usize;
+        self.move_selection(pages * page as isize);
+        *self.table_state.offset_mut() = offset;
+    }
+
+    /// Handle the row navigation keys shared by normal and filter mode.
+    fn navigate(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_page(-1),
+            KeyCode::PageDown => self.move_page(1),
+            KeyCode::Home => self.move_selection(-(self.view.len() as isize)),
+            KeyCode::End => self.move_selection(self.view.len() as isize),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn handle_event(&mut self) -> Result<bool> {
+        // Use poll with timeout so the UI can refresh for async auth results
+        if event::poll(Duration::from_millis(200))? {
+            let ev = event::read()?;
+            let moved = matches!(ev, Event::Mouse(m) if m.kind == MouseEventKind::Moved);
+            if matches!(ev, Event::Key(_) | Event::Mouse(_)) && !moved {
+                self.last_input = Instant::now();
+            }
+            if matches!(ev, Event::Key(_)) {
+                // Toasts without a deadline (`toast_duration = 0`) last until the next key
+                self.toasts.retain(|toast| toast.expires.is_some());
+            }
+            if let Event::Mouse(mouse) = ev {
+                self.handle_mouse(mouse)?;
+            } else if let Event::Key(key) = ev {
+                match self.input_mode {
+                    InputMode::Normal => self.handle_normal_key(key)?,
+                    InputMode::Filter => self.handle_filter_key(key)?,
+                    InputMode::Jump => self.handle_jump_key(key)?,
+                    InputMode::Form => self.handle_form_key(key)?,
+                    InputMode::Rename => self.handle_rename_key(key)?,
+                    InputMode::Tag => self.handle_tag_key(key)?,
+                    InputMode::AccountPicker => self.handle_account_picker_key(key),
+                    InputMode::ClusterPicker => self.handle_cluster_picker_key(key)?,
+                    InputMode::ErrorDetail => self.handle_error_detail_key(key),
+                    InputMode::Compare => self.handle_compare_key(key),
+                    InputMode::Messages => self.handle_history_key(key),
+                    InputMode::DryRun => self.handle_dry_run_key(key)?,
+                    InputMode::SyncConflict => self.handle_conflict_key(key)?,
+                    InputMode::ConfirmDelete => self.handle_confirm_delete(key)?,
+                    InputMode::ConfirmActivate => self.handle_confirm_activate(key),
+                    InputMode::ConfirmGuarded => self.handle_guard_key(key),
+                    InputMode::EditAccount | InputMode::EditProject => {
+                        self.handle_edit_key(key)?
+                    }
+                }
+            }
+        }
+        Ok(self.should_quit)
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        let pos = Position::new(mouse.column, mouse.row);
+        if matches!(
+            self.input_mode,
+            InputMode::ErrorDetail | InputMode::Compare | InputMode::Messages | InputMode::DryRun
+        ) {
+            let code = match mouse.kind {
+                MouseEventKind::ScrollDown => KeyCode::Down,
+                MouseEventKind::ScrollUp => KeyCode::Up,
+                _ => return Ok(()),
+            };
+            let key = KeyEvent::new(code, KeyModifiers::NONE);
+            match self.input_mode {
+                InputMode::Compare => self.handle_compare_key(key),
+                InputMode::Messages => self.handle_history_key(key),
+                InputMode::DryRun => self.handle_dry_run_key(key)?,
+                _ => self.handle_error_detail_key(key),
+            }
+            return Ok(());
+        }
+        let editing = matches!(
+            self.input_mode,
+            InputMode::EditAccount | InputMode::EditProject | InputMode::Form
+        );
+
+        // The suggestion dropdown sits on top of the table, so it gets first pick
+        if let Some(area) = self.hit_areas.suggestions.filter(|a| a.contains(pos)) {
+            if !editing || self.suggestions.is_empty() {
+                return Ok(());
+            }
+            let last = self.suggestions.len() - 1;
+            let idx = self.suggestion_index.unwrap_or(0);
+            match mouse.kind {
+                MouseEventKind::ScrollDown => self.suggestion_index = Some((idx + 1).min(last)),
+                MouseEventKind::ScrollUp => self.suggestion_index = Some(idx.saturating_sub(1)),
+                MouseEventKind::Down(MouseButton::Left) => {
+                    // Skip the top border
+                    let clicked = (mouse.row.saturating_sub(area.y + 1)) as usize
+                        + self.hit_areas.suggestion_offset;
+                    if mouse.row > area.y && clicked <= last {
+                        self.suggestion_index = Some(clicked);
+                        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+                        if self.input_mode == InputMode::Form {
+                            self.handle_form_key(enter)?;
+                        } else {
+                            self.handle_edit_key(enter)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if !matches!(self.input_mode, InputMode::Normal | InputMode::Filter | InputMode::Jump) {
+            return Ok(());
+        }
+        let table = self.hit_areas.table;
+        if !table.contains(pos) {
+            return Ok(());
+        }
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.move_selection(1),
+            MouseEventKind::ScrollUp => self.move_selection(-1),
+            MouseEventKind::Down(MouseButton::Left) => {
+                // The header and each row are `row_height` lines tall
+                let height = self.table.row_height();
+                if mouse.row < table.y + height {
+                    return Ok(());
+                }
+                let view_pos =
+                    self.table_state.offset() + ((mouse.row - table.y - height) / height) as usize;
+                let Some(&row) = self.view.get(view_pos) else {
+                    return Ok(());
+                };
+                self.selected_row = row;
+                self.table_state.select(Some(view_pos));
+                self.selected_col = if mouse.column >= self.hit_areas.col_x[2] {
+                    Column::Adc
+                } else if mouse.column >= self.hit_areas.col_x[1] {
+                    Column::User
+                } else {
+                    Column::Both
+                };
+                self.status_message = None;
+
+                let now = Instant::now();
+                let is_double = self
+                    .last_click
+                    .is_some_and(|(at, r)| r == row && now.duration_since(at) <= DOUBLE_CLICK);
+                if is_double {
+                    self.last_click = None;
+                    self.input_mode = InputMode::Normal;
+                    self.handle_normal_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))?;
+                } else {
+                    self.last_click = Some((now, row));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                // Esc clears marks, then an active filter, then the auth view, then quits
+                if !self.marked.is_empty() {
+                    self.marked.clear();
+                } else if !self.filter_query.is_empty() {
+                    self.filter_query.clear();
+                    self.refresh_view();
+                } else if self.auth_view != AuthView::All {
+                    self.auth_view = AuthView::All;
+                    self.refresh_view();
+                } else {
+                    self.should_quit = true;
+                }
+            }
+            code @ (KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::Home
+            | KeyCode::End) => {
+                self.navigate(code);
+                self.status_message = None;
+            }
+            KeyCode::Char('v') => {
+                self.show_details = !self.show_details;
+            }
+            KeyCode::Char('l') => {
+                self.show_log = !self.show_log;
+            }
+            KeyCode::Char('H') => {
+                self.history_scroll = 0;
+                self.input_mode = InputMode::Messages;
+            }
+            KeyCode::Char('D') if self.has_selection() => self.open_dry_run(),
+            KeyCode::Char('y') if self.has_selection() => self.copy_selected(false),
+            KeyCode::Char('Y') if self.has_selection() => self.copy_selected(true),
+            KeyCode::Char('#') if self.has_selection() => self.copy_project_number(),
+            KeyCode::Char('O') if self.has_selection() => self.open_console(),
+            KeyCode::Char('P') => self.start_sync(SyncDirection::Push),
+            KeyCode::Char('p') => self.start_sync(SyncDirection::Pull),
+            // Locked profiles refuse changes until unlocked with K
+            KeyCode::Char('e' | 'E' | 'R' | 'G' | 'S' | '<') | KeyCode::F(2)
+                if self.has_selection() && self.profiles[self.selected_row].locked =>
+            {
+                self.report_locked(&[self.selected_row]);
+            }
+            KeyCode::Char('d' | 't' | '!' | 'L') if self.any_locked(&self.batch_targets()) => {
+                self.report_locked(&self.batch_targets());
+            }
+            KeyCode::Char('G') if self.has_selection() => self.open_cluster_picker(),
+            KeyCode::Char('S') if self.has_selection() => {
+                self.snapshot_gcloud_properties()?;
+            }
+            KeyCode::Char('K') if !self.marked.is_empty() || self.has_selection() => {
+                self.toggle_locked()?;
+            }
+            KeyCode::Char('<') => {
+                self.reconcile_selected(true)?;
+            }
+            KeyCode::Char('>') => {
+                self.reconcile_selected(false)?;
+            }
+            KeyCode::Char('u') => {
+                self.undo()?;
+            }
+            KeyCode::Char('/') => {
+                self.input_mode = InputMode::Filter;
+                self.status_message = None;
+            }
+            KeyCode::Char('h') => self.cycle_auth_view(),
+            KeyCode::Char('=') => self.open_compare(),
+            KeyCode::Char('m') => {
+                self.row_order = match self.row_order {
+                    RowOrder::Name => RowOrder::Recent,
+                    RowOrder::Recent => RowOrder::Name,
+                };
+                self.refresh_view();
+                self.notify(match self.row_order {
+                    RowOrder::Name => "Profiles sorted by name.".to_string(),
+                    RowOrder::Recent => "Profiles sorted by last use.".to_string(),
+                });
+            }
+            KeyCode::Char('f') if !self.view.is_empty() => {
+                self.input_mode = InputMode::Jump;
+                self.jump_buffer.clear();
+                self.jump_missed = false;
+                self.jump_origin = self.selected_row;
+                self.status_message = None;
+            }
+            KeyCode::Left => {
+                self.selected_col = match self.selected_col {
+                    Column::Both => Column::Both,
                     Column::User => Column::Both,
                     Column::Adc => Column::User,
                 };
@@ -294,71 +2489,147 @@ impl App {
             KeyCode::Right => {
                 self.selected_col = match self.selected_col {
                     Column::Both => Column::User,
-                    Column::User => Column::Adc,
-                    Column::Adc => Column::Adc,
+                    Column::User if self.table.adc_column => Column::Adc,
+                    Column::User => Column::User,
+                    Column::Adc => Column::Adc,
+                };
+                self.status_message = None;
+            }
+            KeyCode::Enter if self.has_selection() => {
+                self.quit_after_activate = key.modifiers.contains(KeyModifiers::ALT) == self.stay_open;
+                // Quitting afterwards is up to the main loop, once the activation (and any
+                // login before it) has run
+                if self.is_guarded(self.selected_row) {
+                    self.guard_buffer.clear();
+                    self.input_mode = InputMode::ConfirmGuarded;
+                } else if self.profiles[self.selected_row].confirm_before_activate {
+                    self.status_message = Some(format!(
+                        "Activate protected profile '{}'? (y/n)",
+                        self.profile_names[self.selected_row]
+                    ));
+                    self.input_mode = InputMode::ConfirmActivate;
+                } else {
+                    self.start_activation();
+                }
+            }
+            KeyCode::Char(' ') if self.has_selection() => {
+                let name = &self.profile_names[self.selected_row];
+                if !self.marked.remove(name) {
+                    self.marked.insert(name.clone());
+                }
+                self.move_selection(1);
+                self.status_message = None;
+            }
+            KeyCode::Char('a') if !self.marked.is_empty() => {
+                self.pending_action = PendingAction::ReauthMarked;
+            }
+            KeyCode::Char('a') if self.has_selection() => {
+                self.pending_action = PendingAction::Reauth;
+            }
+            KeyCode::Char('A') => {
+                if self.expired_sides().is_empty() {
+                    self.notify(if self.auth_pending > 0 {
+                        "No expired credentials found yet; checks are still running.".to_string()
+                    } else {
+                        "No profile needs a login.".to_string()
+                    });
+                } else {
+                    self.pending_action = PendingAction::ReauthExpired;
+                }
+            }
+            KeyCode::Char('t') if !self.marked.is_empty() || self.has_selection() => {
+                self.tag_buffer.clear();
+                self.tag_cursor = 0;
+                self.input_mode = InputMode::Tag;
+                self.status_message = None;
+            }
+            KeyCode::Char('L') if !self.marked.is_empty() || self.has_selection() => {
+                self.toggle_local_only()?;
+            }
+            KeyCode::Char('!') if !self.marked.is_empty() || self.has_selection() => {
+                self.toggle_confirm_before_activate()?;
+            }
+            KeyCode::Char('n') => {
+                self.open_form(ProfileForm::new());
+            }
+            KeyCode::Char('E') if self.has_selection() => {
+                let form = ProfileForm::for_profile(
+                    &self.profile_names[self.selected_row],
+                    &self.profiles[self.selected_row],
+                );
+                self.open_form(form);
+            }
+            KeyCode::Char('e') if self.has_selection() => {
+                let edit_col = match self.selected_col {
+                    Column::Both => Column::User,
+                    col => col,
+                };
+                let profile = &self.profiles[self.selected_row];
+                self.edit_col = edit_col;
+                self.edit_account_buffer = match edit_col {
+                    Column::User => profile.user_account.clone(),
+                    Column::Adc => profile.adc_account.clone(),
+                    _ => unreachable!(),
                 };
+                self.edit_project_buffer = match edit_col {
+                    Column::User => profile.user_project.clone(),
+                    Column::Adc => profile.adc_quota_project.clone(),
+                    _ => unreachable!(),
+                };
+                self.input_mode = InputMode::EditAccount;
+                self.edit_cursor_pos = self.edit_account_buffer.chars().count();
+                self.suggestions.clear();
+                self.suggestion_index = None;
+                self.picker_open = false;
                 self.status_message = None;
             }
-            KeyCode::Enter => {
-                if !self.profile_names.is_empty() {
-                    self.quit_after_activate = !key.modifiers.contains(KeyModifiers::ALT);
-                    self.activate_selected()?;
-                    // Only quit now if no pending reauth (otherwise quit after reauth completes)
-                    if self.quit_after_activate
-                        && matches!(self.pending_action, PendingAction::None)
-                    {
-                        self.should_quit = true;
-                    }
+            KeyCode::Char('c') if self.has_selection() => {
+                let name = self.profile_names[self.selected_row].clone();
+                let mut new_name = format!("{}-copy", name);
+                let mut n = 2;
+                while self.profile_names.contains(&new_name) {
+                    new_name = format!("{}-copy-{}", name, n);
+                    n += 1;
                 }
+                let form = ProfileForm::clone_of(&name, new_name, &self.profiles[self.selected_row]);
+                self.open_form(form);
+                self.set_form_focus(FIELD_USER_PROJECT);
             }
-            KeyCode::Char('a') => {
-                if !self.profile_names.is_empty() {
-                    self.pending_action = PendingAction::Reauth;
-                }
+            KeyCode::Char('R') | KeyCode::F(2) if self.has_selection() => {
+                self.rename_buffer = self.profile_names[self.selected_row].clone();
+                self.rename_cursor = self.rename_buffer.chars().count();
+                self.input_mode = InputMode::Rename;
+                self.status_message = None;
             }
-            KeyCode::Char('n') => {
-                self.input_mode = InputMode::AddProfileName;
-                self.input_buffer.clear();
-                self.status_message = Some("Enter profile name:".to_string());
+            KeyCode::Char('d') if !self.marked.is_empty() => {
+                self.status_message = Some(format!(
+                    "Delete {} marked profile(s)? (y/n)",
+                    self.marked.len()
+                ));
+                self.input_mode = InputMode::ConfirmDelete;
             }
-            KeyCode::Char('e') => {
-                if !self.profile_names.is_empty() {
-                    let edit_col = match self.selected_col {
-                        Column::Both => Column::User,
-                        col => col,
-                    };
-                    let profile = &self.profiles[self.selected_row];
-                    self.edit_col = edit_col;
-                    self.edit_account_buffer = match edit_col {
-                        Column::User => profile.user_account.clone(),
-                        Column::Adc => profile.adc_account.clone(),
-                        _ => unreachable!(),
-                    };
-                    self.edit_project_buffer = match edit_col {
-                        Column::User => profile.user_project.clone(),
-                        Column::Adc => profile.adc_quota_project.clone(),
-                        _ => unreachable!(),
-                    };
-                    self.input_mode = InputMode::EditAccount;
-                    self.edit_cursor_pos = self.edit_account_buffer.chars().count();
-                    self.suggestions.clear();
-                    self.suggestion_index = None;
-                    self.status_message = None;
-                }
+            KeyCode::Char('d') if self.has_selection() => {
+                let name = &self.profile_names[self.selected_row];
+                self.status_message =
+                    Some(format!("Delete profile '{}'? (y/n)", name));
+                self.input_mode = InputMode::ConfirmDelete;
             }
-            KeyCode::Char('d') => {
-                if !self.profile_names.is_empty() {
-                    let name = &self.profile_names[self.selected_row];
-                    self.status_message =
-                        Some(format!("Delete profile '{}'? (y/n)", name));
-                    self.input_mode = InputMode::ConfirmDelete;
-                }
+            KeyCode::F(5) => self.refresh()?,
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => self.refresh()?,
+            KeyCode::Char('o') => {
+                self.stay_open = !self.stay_open;
+                self.notify(if self.stay_open {
+                    "Enter keeps gcloud-switch open (Alt+Enter quits).".to_string()
+                } else {
+                    "Enter quits after activating (Alt+Enter stays open).".to_string()
+                });
             }
             KeyCode::Char('s') => {
                 self.sync_mode = match self.sync_mode {
+                    SyncMode::Mirror => SyncMode::Strict,
                     SyncMode::Strict => SyncMode::Add,
                     SyncMode::Add => SyncMode::Off,
-                    SyncMode::Off => SyncMode::Strict,
+                    SyncMode::Off => SyncMode::Mirror,
                 };
                 let mut data = self.store.load_profiles()?;
                 data.sync_mode = self.sync_mode;
@@ -367,16 +2638,19 @@ impl App {
                     SyncMode::Strict => "strict",
                     SyncMode::Add => "add",
                     SyncMode::Off => "off",
+                    SyncMode::Mirror => "mirror",
                 };
-                self.status_message = Some(format!("Sync mode: {}", label));
+                self.notify(format!("Sync mode: {}", label));
             }
             KeyCode::Char('i') => {
                 let configs = gcloud::discover_existing_configs()?;
                 if configs.is_empty() {
-                    self.status_message = Some("No gcloud configurations found.".to_string());
+                    self.notify("No gcloud configurations found.".to_string());
                 } else {
                     let mut data = self.store.load_profiles()?;
                     let mut count = 0;
+                    let mut imported = Vec::new();
+                    let mut adc_accounts = Vec::new();
                     for (name, account, project) in &configs {
                         if !data.profiles.contains_key(name) {
                             let mut profile = Profile {
@@ -384,10 +2658,12 @@ impl App {
                                 user_project: project.clone(),
                                 adc_account: account.clone(),
                                 adc_quota_project: project.clone(),
-                                updated_at: None,
+                                ..Default::default()
                             };
                             profile.touch();
-                            data.profiles.insert(name.clone(), profile);
+                            imported.push(hooks::profile_data(name, &profile));
+                            adc_accounts.push((name.clone(), profile.adc_account.clone()));
+                            data.insert_profile(name, profile);
                             count += 1;
                         }
                     }
@@ -398,12 +2674,18 @@ impl App {
                             }
                         }
                         self.store.save_profiles(&data)?;
+                        for data in imported {
+                            self.hooks.spawn(HookEvent::ProfileAdded, data);
+                        }
+                        let adopted = match gcloud::adopt_live_adc(&self.store, &adc_accounts) {
+                            Ok(Some(name)) => format!("; installed ADC stored for '{}'", name),
+                            Ok(None) => String::new(),
+                            Err(e) => format!("; could not identify the installed ADC ({:#})", e),
+                        };
                         self.reload()?;
-                        self.status_message =
-                            Some(format!("Imported {} profile(s).", count));
+                        self.notify(format!("Imported {} profile(s){}.", count, adopted));
                     } else {
-                        self.status_message =
-                            Some("No new configurations to import.".to_string());
+                        self.notify("No new configurations to import.".to_string());
                     }
                 }
             }
@@ -412,125 +2694,679 @@ impl App {
         Ok(())
     }
 
-    fn handle_input_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn handle_filter_key(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
             KeyCode::Esc => {
+                self.filter_query.clear();
                 self.input_mode = InputMode::Normal;
-                self.input_buffer.clear();
-                self.status_message = None;
+                self.refresh_view();
             }
             KeyCode::Enter => {
-                let value = self.input_buffer.trim().to_string();
-                match self.input_mode {
-                    InputMode::AddProfileName => {
-                        if value.is_empty() {
-                            return Ok(());
-                        }
-                        self.new_profile_name = value;
-                        self.input_buffer.clear();
-                        self.input_mode = InputMode::AddProfileUserAccount;
-                        self.status_message = Some("Enter user account (email):".to_string());
+                self.input_mode = InputMode::Normal;
+                if self.has_selection() {
+                    self.handle_normal_key(key)?;
+                }
+            }
+            code if self.navigate(code) => {}
+            KeyCode::Backspace => {
+                self.filter_query.pop();
+                self.apply_filter_query();
+            }
+            KeyCode::Char(c) => {
+                self.filter_query.push(c);
+                self.apply_filter_query();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Jump mode: typed characters move the selection to the first visible profile whose
+    /// name starts with them, else to the best fuzzy match. Enter activates, Esc goes back,
+    /// any other key leaves jump mode and does what it does in the table.
+    fn handle_jump_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.selected_row = self.jump_origin;
+                self.table_state.select(self.selected_view_pos());
+            }
+            KeyCode::Backspace => {
+                self.jump_buffer.pop();
+                self.jump_typed_at = Instant::now();
+                self.jump();
+            }
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                if self.jump_typed_at.elapsed() >= JUMP_RESET {
+                    self.jump_buffer.clear();
+                }
+                self.jump_buffer.push(c);
+                self.jump_typed_at = Instant::now();
+                self.jump();
+            }
+            _ => {
+                self.input_mode = InputMode::Normal;
+                self.handle_normal_key(key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Select the profile `jump_buffer` points at; with an empty buffer, the one selected
+    /// before jump mode. Without a match the selection stays and `jump_missed` is set.
+    fn jump(&mut self) {
+        let target = if self.jump_buffer.is_empty() {
+            Some(self.jump_origin)
+        } else {
+            let prefix = self.jump_buffer.to_lowercase();
+            let names = self.view.iter().map(|&i| (i, self.profile_names[i].as_str()));
+            names
+                .clone()
+                .find(|(_, name)| name.to_lowercase().starts_with(&prefix))
+                .or_else(|| {
+                    names
+                        .filter_map(|(i, name)| fuzzy_score(name, &prefix).map(|score| (score, i, name)))
+                        .rev()
+                        .max_by_key(|(score, _, _)| *score)
+                        .map(|(_, i, name)| (i, name))
+                })
+                .map(|(i, _)| i)
+        };
+        self.jump_missed = target.is_none();
+        if let Some(row) = target {
+            self.selected_row = row;
+            self.table_state.select(self.selected_view_pos());
+        }
+    }
+
+    fn open_form(&mut self, form: ProfileForm) {
+        self.form = Some(form);
+        self.input_mode = InputMode::Form;
+        self.suggestions.clear();
+        self.suggestion_index = None;
+        self.picker_open = false;
+        self.status_message = None;
+    }
+
+    fn close_form(&mut self) {
+        self.form = None;
+        self.input_mode = InputMode::Normal;
+        self.suggestion_index = None;
+        self.status_message = None;
+    }
+
+    /// Move form focus, validating the field being left and prefetching projects
+    /// when entering a project field.
+    fn set_form_focus(&mut self, field: usize) {
+        let Some(form) = self.form.as_mut() else {
+            return;
+        };
+        let left = form.focus;
+        form.errors[left] = form.validate_field(left, &self.profile_names);
+        form.focus = field;
+        form.cursor = form.values[field].chars().count();
+        self.suggestion_index = None;
+        self.picker_open = false;
+        match FieldKind::of(field) {
+            FieldKind::Project => {
+                let account = form.account_for(field);
+                self.start_project_fetch(&account);
+                self.refresh_picker();
+            }
+            kind @ (FieldKind::Region | FieldKind::Zone) => {
+                self.start_location_fetch(kind == FieldKind::Zone);
+                self.refresh_picker();
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_form_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(form) = self.form.as_ref() else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        let focus = form.focus;
+        let field_count = FORM_LABELS.len();
+        if is_account_picker_key(key) && FieldKind::of(focus) == FieldKind::Account {
+            self.open_account_picker();
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Esc => {
+                if self.suggestion_index.is_some() || self.picker_visible() {
+                    self.suggestion_index = None;
+                    self.picker_open = false;
+                } else {
+                    self.close_form();
+                    self.notify("Cancelled.".to_string());
+                }
+            }
+            KeyCode::Tab => self.set_form_focus((focus + 1) % field_count),
+            KeyCode::BackTab => self.set_form_focus((focus + field_count - 1) % field_count),
+            KeyCode::Down
+                if self.picker_visible()
+                    && self.suggestion_index.is_none()
+                    && !self.suggestions.is_empty() =>
+            {
+                self.suggestion_index = Some(0);
+            }
+            KeyCode::Down => {
+                if self.suggestion_index.is_none() {
+                    self.suggestions = match FieldKind::of(focus) {
+                        FieldKind::Account => self.build_account_suggestions(),
+                        FieldKind::Project => self.build_project_suggestions(),
+                        FieldKind::Region => self.build_location_suggestions(false),
+                        FieldKind::Zone => self.build_location_suggestions(true),
+                        FieldKind::Name => Vec::new(),
+                    };
+                    if !self.suggestions.is_empty() {
+                        self.suggestion_index = Some(0);
                     }
-                    InputMode::AddProfileUserAccount => {
-                        if value.is_empty() {
-                            return Ok(());
-                        }
-                        self.new_profile.user_account = value.clone();
-                        self.new_profile.adc_account = value; // default
-                        self.input_buffer.clear();
-                        self.input_mode = InputMode::AddProfileUserProject;
-                        self.status_message = Some("Enter user project:".to_string());
+                } else if !self.suggestions.is_empty() {
+                    let idx = self.suggestion_index.unwrap_or(0);
+                    self.suggestion_index = Some((idx + 1) % self.suggestions.len());
+                }
+            }
+            KeyCode::Up => {
+                if let Some(idx) = self.suggestion_index {
+                    if !self.suggestions.is_empty() {
+                        self.suggestion_index = Some(if idx == 0 {
+                            self.suggestions.len() - 1
+                        } else {
+                            idx - 1
+                        });
+                    }
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(idx) = self.suggestion_index.take() {
+                    if let (Some(suggestion), Some(form)) =
+                        (self.suggestions.get(idx), self.form.as_mut())
+                    {
+                        form.values[focus] = suggestion.clone();
+                        form.cursor = suggestion.chars().count();
+                        form.errors[focus] = None;
                     }
-                    InputMode::AddProfileUserProject => {
-                        if value.is_empty() {
-                            return Ok(());
+                    self.picker_open = false;
+                } else {
+                    self.submit_form()?;
+                }
+            }
+            code => {
+                // The name of an existing profile isn't editable here
+                if let Some(form) = self.form.as_mut().filter(|f| {
+                    !(focus == FIELD_NAME && f.editing.is_some())
+                }) {
+                    let kind = FieldKind::of(focus);
+                    if edit_text(&mut form.values[focus], &mut form.cursor, code, |c, at| {
+                        kind.accepts(c, at == 0)
+                    }) {
+                        form.errors[focus] = None;
+                        self.suggestion_index = None;
+                        if kind != FieldKind::Name && kind != FieldKind::Account {
+                            self.refresh_picker();
                         }
-                        self.new_profile.user_project = value.clone();
-                        self.new_profile.adc_quota_project = value; // default
-                        self.input_buffer.clear();
-                        self.input_mode = InputMode::AddProfileAdcAccount;
-                        self.status_message = Some(format!(
-                            "Enter ADC account [{}]:",
-                            self.new_profile.adc_account
-                        ));
                     }
-                    InputMode::AddProfileAdcAccount => {
-                        // Empty = accept default from user profile (shown in brackets)
-                        self.new_profile.adc_account = if value.is_empty() {
-                            self.new_profile.adc_account.clone()
-                        } else {
-                            value
-                        };
-                        self.input_buffer.clear();
-                        self.input_mode = InputMode::AddProfileAdcQuotaProject;
-                        self.status_message = Some(format!(
-                            "Enter ADC quota project [{}]:",
-                            self.new_profile.adc_quota_project
-                        ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate every field and save the profile, or focus the first invalid field.
+    fn submit_form(&mut self) -> Result<()> {
+        let Some(form) = self.form.as_mut() else {
+            return Ok(());
+        };
+        for field in 0..FORM_LABELS.len() {
+            form.errors[field] = form.validate_field(field, &self.profile_names);
+        }
+        if let Some(bad) = form.errors.iter().position(|e| e.is_some()) {
+            form.focus = bad;
+            form.cursor = form.values[bad].chars().count();
+            self.status_message = Some("Fix the highlighted fields.".to_string());
+            return Ok(());
+        }
+
+        let name = form.effective(FIELD_NAME);
+        let existing = form.editing.as_ref().map(|_| form.base.clone());
+        let clone_of = form.clone_of.clone();
+        let mut profile = form.base.clone();
+        profile.user_account = form.effective(FIELD_USER_ACCOUNT);
+        profile.user_project = form.effective(FIELD_USER_PROJECT);
+        profile.adc_account = form.effective(FIELD_ADC_ACCOUNT);
+        profile.adc_quota_project = form.effective(FIELD_ADC_QUOTA_PROJECT);
+        for (key, field) in [("compute/region", FIELD_REGION), ("compute/zone", FIELD_ZONE)] {
+            match form.effective(field) {
+                value if value.is_empty() => profile.gcloud_properties.remove(key),
+                value => profile.gcloud_properties.insert(key.to_string(), value),
+            };
+        }
+        let ssh_zone = Some(form.effective(FIELD_SSH_ZONE)).filter(|z| !z.is_empty());
+        if ssh_zone.is_some() || profile.ssh.is_some() {
+            let ssh = profile.ssh.get_or_insert_with(Default::default);
+            ssh.zone = ssh_zone;
+            if *ssh == SshSettings::default() {
+                profile.ssh = None;
+            }
+        }
+
+        let snapshot = self.snapshot(
+            if existing.is_some() {
+                format!("edit '{}'", name)
+            } else {
+                format!("add '{}'", name)
+            },
+            &[&name],
+        )?;
+        let mut gcloud_undo = Vec::new();
+        match existing {
+            None => {
+                // Create gcloud configuration first (if sync requires it)
+                if self.sync_mode != SyncMode::Off {
+                    let existed = gcloud::configuration_path(&name).is_ok_and(|p| p.exists());
+                    if let Err(e) = gcloud::create_configuration(
+                        &name,
+                        &profile.user_account,
+                        &profile.user_project,
+                    ) {
+                        self.notify_error(format!("Failed to create gcloud config: {}", e));
+                        self.show_failure(&e);
+                        return Ok(());
                     }
-                    InputMode::AddProfileAdcQuotaProject => {
-                        // Empty = accept default from user profile (shown in brackets)
-                        self.new_profile.adc_quota_project = if value.is_empty() {
-                            self.new_profile.adc_quota_project.clone()
-                        } else {
-                            value
-                        };
-                        // Create gcloud configuration first (if sync requires it)
-                        if matches!(self.sync_mode, SyncMode::Strict | SyncMode::Add) {
-                            if let Err(e) = gcloud::create_configuration(
-                                &self.new_profile_name,
-                                &self.new_profile.user_account,
-                                &self.new_profile.user_project,
-                            ) {
-                                self.status_message = Some(format!(
-                                    "Failed to create gcloud config: {}",
-                                    e
-                                ));
-                                self.input_mode = InputMode::Normal;
-                                self.input_buffer.clear();
-                                return Ok(());
-                            }
-                        }
-                        // Save the profile
-                        self.store
-                            .add_profile(&self.new_profile_name, self.new_profile.clone())?;
-                        self.status_message = Some(format!(
-                            "Profile '{}' added.",
-                            self.new_profile_name
+                    if !existed {
+                        gcloud_undo.push(GcloudUndo::Delete(name.clone()));
+                    }
+                }
+                let quota_project = profile.adc_quota_project.clone();
+                let hook_data = hooks::profile_data(&name, &profile);
+                self.store.add_profile(&name, profile)?;
+                self.notify(format!("Profile '{}' added.", name));
+                self.hooks.spawn(HookEvent::ProfileAdded, hook_data);
+                // A clone starts with its source's ADC so no new login is needed
+                if let Some(source) = clone_of {
+                    if let Some(adc) = self.store.load_adc_json(&source)? {
+                        self.store.save_adc_json(&name, &adc)?;
+                        gcloud::set_adc_quota_project(&self.store, &name, &quota_project, false)?;
+                    }
+                }
+            }
+            Some(old) => {
+                let quota_changed = profile.adc_quota_project != old.adc_quota_project;
+                let quota_project = profile.adc_quota_project.clone();
+                let mirrored = self.mirror_edit(&name, &old, &profile);
+                self.store.add_profile(&name, profile)?;
+                let quota_result = if quota_changed {
+                    let is_active = self.active_profile.as_deref() == Some(name.as_str());
+                    gcloud::set_adc_quota_project(&self.store, &name, &quota_project, is_active)
+                } else {
+                    Ok(())
+                };
+                let failure = match (mirrored, quota_result) {
+                    (Ok(undo), Ok(())) => {
+                        gcloud_undo.extend(undo);
+                        None
+                    }
+                    (Ok(undo), Err(e)) => {
+                        gcloud_undo.extend(undo);
+                        Some(("set quota project", e))
+                    }
+                    (Err(e), _) => Some(("update its gcloud config", e)),
+                };
+                match failure {
+                    None => self.notify(format!("Profile '{}' updated.", name)),
+                    Some((what, e)) => {
+                        self.notify_warn(format!(
+                            "Profile '{}' updated. Failed to {}: {}",
+                            name, what, e
                         ));
-                        self.reload()?;
-                        self.input_mode = InputMode::Normal;
-                        self.input_buffer.clear();
+                        self.show_failure(&e);
                     }
-                    _ => {}
                 }
             }
-            KeyCode::Backspace => {
-                self.input_buffer.pop();
+        }
+        self.push_undo(snapshot, gcloud_undo);
+        self.close_form();
+        self.reload()?;
+        self.select_profile(&name);
+        Ok(())
+    }
+
+    fn handle_rename_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.notify("Rename cancelled.".to_string());
             }
-            KeyCode::Char(c) => {
-                if self.input_mode == InputMode::AddProfileName {
-                    if c.is_ascii_alphanumeric() || c == '-' {
-                        self.input_buffer.push(c);
+            KeyCode::Enter => {
+                let new = self.rename_buffer.trim().to_string();
+                self.input_mode = InputMode::Normal;
+                self.rename_selected(&new)?;
+            }
+            code => {
+                edit_text(&mut self.rename_buffer, &mut self.rename_cursor, code, |c, _| {
+                    FieldKind::Name.accepts(c, false)
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename the selected profile along with its ADC file and, when sync mode manages
+    /// gcloud configurations, its gcloud configuration.
+    fn rename_selected(&mut self, new: &str) -> Result<()> {
+        let old = self.profile_names[self.selected_row].clone();
+        if new == old {
+            return Ok(());
+        }
+        if let Err(e) = profile::validate_name(new) {
+            self.notify_error(format!("{}.", e));
+            return Ok(());
+        }
+        if self.profile_names.iter().any(|n| n == new) {
+            self.notify_error(format!("Profile '{}' already exists.", new));
+            return Ok(());
+        }
+
+        let snapshot =
+            self.snapshot(format!("rename '{}' to '{}'", old, new), &[&old, new])?;
+        let rename_config = self.sync_mode != SyncMode::Off
+            && gcloud::configuration_path(&old).is_ok_and(|p| p.exists());
+        if rename_config {
+            if let Err(e) = gcloud::rename_configuration(&old, new) {
+                self.notify_error(format!("Rename failed: {}", e));
+                self.show_failure(&e);
+                return Ok(());
+            }
+        }
+        if let Err(e) = self.store.rename_profile(&old, new) {
+            // Keep gcloud and the store consistent
+            if rename_config {
+                let _ = gcloud::rename_configuration(new, &old);
+            }
+            self.notify_error(format!("Rename failed: {}", e));
+            return Ok(());
+        }
+
+        self.push_undo(
+            snapshot,
+            rename_config
+                .then(|| GcloudUndo::Rename {
+                    from: new.to_string(),
+                    to: old.clone(),
+                })
+                .into_iter()
+                .collect(),
+        );
+        self.notify(format!("Renamed '{}' to '{}'.", old, new));
+        self.reload()?;
+        self.select_profile(new);
+        Ok(())
+    }
+
+    /// Copy the selected profile's project id (or account email) to the clipboard. The ADC
+    /// column copies the ADC account / quota project, otherwise the user's.
+    fn copy_selected(&mut self, account: bool) {
+        let profile = &self.profiles[self.selected_row];
+        let (what, text) = match (self.selected_col, account) {
+            (Column::Adc, true) => ("ADC account", profile.adc_account.clone()),
+            (Column::Adc, false) => ("quota project", profile.adc_quota_project.clone()),
+            (_, true) => ("account", profile.user_account.clone()),
+            (_, false) => ("project", profile.user_project.clone()),
+        };
+        if text.is_empty() {
+            self.notify_warn(format!("No {} set.", what));
+            return;
+        }
+        self.copy_text(what, &text);
+    }
+
+    /// Copy the number of the selected column's project (quota project in the ADC column),
+    /// resolving it in the background first if needed.
+    fn copy_project_number(&mut self) {
+        let profile = &self.profiles[self.selected_row];
+        let (account, project) = match self.selected_col {
+            Column::Adc => (profile.adc_account.clone(), profile.adc_quota_project.clone()),
+            _ => (profile.user_account.clone(), profile.user_project.clone()),
+        };
+        if project.is_empty() {
+            self.notify_warn("No project set.".to_string());
+        } else if let Some(number) = gcloud::cached_project_number(&self.store, &project) {
+            self.copy_text(&format!("number of '{}'", project), &number);
+        } else if net::is_offline() {
+            self.notify_warn(format!("Number of project '{}' not resolved yet (offline).", project));
+        } else {
+            self.status_message = Some(format!("Resolving the number of project '{}'\u{2026}", project));
+            self.copy_number_of = Some(project.clone());
+            self.resolve_project_number(&account, &project);
+        }
+    }
+
+    /// Open the Cloud Console for the selected column's project and account. When no browser
+    /// can be opened (e.g. over SSH) the URL goes to the clipboard instead.
+    fn open_console(&mut self) {
+        let profile = &self.profiles[self.selected_row];
+        let (project, account) = match self.selected_col {
+            Column::Adc => (&profile.adc_quota_project, &profile.adc_account),
+            _ => (&profile.user_project, &profile.user_account),
+        };
+        let url = gcloud::console_url(project, Some(account));
+        match opener::open(&url) {
+            Ok(()) if project.is_empty() => self.notify("Opened the Cloud Console (no project set).".to_string()),
+            Ok(()) => self.notify(format!("Opened the Cloud Console for '{}'.", project)),
+            Err(e) => {
+                tracing::debug!(error = %e, "could not open the Cloud Console");
+                self.copy_text("Cloud Console URL", &url);
+            }
+        }
+    }
+
+    fn copy_text(&mut self, what: &str, text: &str) {
+        match clipboard::copy(text) {
+            Ok(clipboard::Method::Command) => {
+                self.notify(format!("Copied {} '{}'.", what, text));
+            }
+            Ok(clipboard::Method::Osc52) => {
+                self.notify(format!("Copied {} '{}' (via terminal, OSC 52).", what, text));
+            }
+            Err(e) => self.notify_error(format!("Copy failed: {}", e)),
+        }
+    }
+
+    /// Profiles a batch operation applies to: the marked ones, or else the selection.
+    fn batch_targets(&self) -> Vec<usize> {
+        if self.marked.is_empty() {
+            return self.selected_view_pos().map(|_| self.selected_row).into_iter().collect();
+        }
+        self.profile_names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| self.marked.contains(*name))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn names_of(&self, targets: &[usize]) -> Vec<&str> {
+        targets.iter().map(|&i| self.profile_names[i].as_str()).collect()
+    }
+
+    fn handle_tag_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.notify("Tagging cancelled.".to_string());
+            }
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+                let input = std::mem::take(&mut self.tag_buffer);
+                self.apply_tags(&input)?;
+            }
+            code => {
+                edit_text(&mut self.tag_buffer, &mut self.tag_cursor, code, |c, _| {
+                    c == ' ' || c == '_' || FieldKind::Name.accepts(c, false)
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply space-separated tag changes to the batch targets: `tag` adds, `-tag` removes.
+    fn apply_tags(&mut self, input: &str) -> Result<()> {
+        let mut add = Vec::new();
+        let mut remove = Vec::new();
+        for word in input.split_whitespace() {
+            match word.strip_prefix('-') {
+                Some(tag) if !tag.is_empty() => remove.push(tag.to_string()),
+                Some(_) => {}
+                None => add.push(word.to_string()),
+            }
+        }
+        if add.is_empty() && remove.is_empty() {
+            return Ok(());
+        }
+
+        let targets = self.batch_targets();
+        let snapshot = self.snapshot(format!("tag {} profile(s)", targets.len()), &self.names_of(&targets))?;
+        let mut data = self.store.load_profiles()?;
+        for &i in &targets {
+            if let Some(profile) = data.profiles.get_mut(&self.profile_names[i]) {
+                profile.tags.retain(|t| !remove.contains(t));
+                for tag in &add {
+                    if !profile.tags.contains(tag) {
+                        profile.tags.push(tag.clone());
                     }
-                } else {
-                    self.input_buffer.push(c);
                 }
+                profile.touch();
+            }
+        }
+        self.store.save_profiles(&data)?;
+        self.push_undo(snapshot, Vec::new());
+        self.notify(format!("Updated tags on {} profile(s).", targets.len()));
+        self.reload()
+    }
+
+    /// Keep the marked (or selected) profiles out of sync, or put them back if they all
+    /// already are.
+    fn toggle_local_only(&mut self) -> Result<()> {
+        let targets = self.batch_targets();
+        let sync = targets.iter().all(|&i| !self.profiles[i].sync);
+        let snapshot = self.snapshot(format!("sync flag of {} profile(s)", targets.len()), &self.names_of(&targets))?;
+        let mut data = self.store.load_profiles()?;
+        for &i in &targets {
+            if let Some(profile) = data.profiles.get_mut(&self.profile_names[i]) {
+                profile.sync = sync;
+            }
+        }
+        self.store.save_profiles(&data)?;
+        self.push_undo(snapshot, Vec::new());
+        self.notify(if sync {
+            format!("{} profile(s) sync again.", targets.len())
+        } else {
+            format!("{} profile(s) kept local (not synced).", targets.len())
+        });
+        self.reload()
+    }
+
+    fn any_locked(&self, targets: &[usize]) -> bool {
+        targets.iter().any(|&i| self.profiles[i].locked)
+    }
+
+    /// Say which of `targets` are locked, in the status bar.
+    fn report_locked(&mut self, targets: &[usize]) {
+        let locked: Vec<String> = targets
+            .iter()
+            .filter(|&&i| self.profiles[i].locked)
+            .map(|&i| format!("'{}'", self.profile_names[i]))
+            .collect();
+        self.notify_warn(format!("{} locked; K unlocks.", locked.join(", ")));
+    }
+
+    /// Lock the marked (or selected) profiles against changes, or unlock them if they all
+    /// already are.
+    fn toggle_locked(&mut self) -> Result<()> {
+        let targets = self.batch_targets();
+        let locked = !targets.iter().all(|&i| self.profiles[i].locked);
+        let snapshot = self.snapshot(format!("lock of {} profile(s)", targets.len()), &self.names_of(&targets))?;
+        let mut data = self.store.load_profiles()?;
+        for &i in &targets {
+            if let Some(profile) = data.profiles.get_mut(&self.profile_names[i]) {
+                profile.locked = locked;
+                profile.touch();
+            }
+        }
+        self.store.save_profiles(&data)?;
+        self.push_undo(snapshot, Vec::new());
+        self.notify(if locked {
+            format!("{} profile(s) locked.", targets.len())
+        } else {
+            format!("{} profile(s) unlocked.", targets.len())
+        });
+        self.reload()
+    }
+
+    /// Make the marked (or selected) profiles ask before activation, or stop asking if they
+    /// all already do.
+    fn toggle_confirm_before_activate(&mut self) -> Result<()> {
+        let targets = self.batch_targets();
+        let confirm = !targets.iter().all(|&i| self.profiles[i].confirm_before_activate);
+        let snapshot = self.snapshot(format!("confirmation flag of {} profile(s)", targets.len()), &self.names_of(&targets))?;
+        let mut data = self.store.load_profiles()?;
+        for &i in &targets {
+            if let Some(profile) = data.profiles.get_mut(&self.profile_names[i]) {
+                profile.confirm_before_activate = confirm;
+                profile.touch();
+            }
+        }
+        self.store.save_profiles(&data)?;
+        self.push_undo(snapshot, Vec::new());
+        self.notify(if confirm {
+            format!("{} profile(s) ask before activation.", targets.len())
+        } else {
+            format!("{} profile(s) activate without asking.", targets.len())
+        });
+        self.reload()
+    }
+
+    /// Select a profile by name if it is visible.
+    fn select_profile(&mut self, name: &str) {
+        if let Some(idx) = self.profile_names.iter().position(|n| n == name) {
+            if self.view.contains(&idx) {
+                self.selected_row = idx;
+                self.table_state.select(self.selected_view_pos());
             }
-            _ => {}
         }
-        Ok(())
     }
 
     fn handle_confirm_delete(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
             KeyCode::Char('y') | KeyCode::Char('Y') => {
-                let name = self.profile_names[self.selected_row].clone();
-                self.store.delete_profile(&name)?;
-                if self.sync_mode == SyncMode::Strict {
-                    let _ = gcloud::delete_configuration(&name);
+                let targets = self.batch_targets();
+                let names = self.names_of(&targets);
+                let description = match names.as_slice() {
+                    [name] => format!("delete '{}'", name),
+                    _ => format!("delete {} profiles", names.len()),
+                };
+                let snapshot = self.snapshot(description, &names)?;
+                let mut gcloud_undo = Vec::new();
+                for &i in &targets {
+                    let name = &self.profile_names[i];
+                    self.store.delete_profile(name)?;
+                    if matches!(self.sync_mode, SyncMode::Strict | SyncMode::Mirror) {
+                        let _ = gcloud::delete_configuration(name);
+                        gcloud_undo.push(GcloudUndo::Create {
+                            name: name.clone(),
+                            account: self.profiles[i].user_account.clone(),
+                            project: self.profiles[i].user_project.clone(),
+                        });
+                    }
                 }
-                self.status_message = Some(format!("Deleted profile '{}'.", name));
+                self.push_undo(snapshot, gcloud_undo);
+                self.notify(match targets.as_slice() {
+                    [i] => format!("Deleted profile '{}'.", self.profile_names[*i]),
+                    _ => format!("Deleted {} profiles.", targets.len()),
+                });
+                self.marked.clear();
                 self.reload()?;
                 self.input_mode = InputMode::Normal;
             }
@@ -542,12 +3378,64 @@ impl App {
         Ok(())
     }
 
+    fn handle_confirm_activate(&mut self, key: KeyEvent) {
+        self.input_mode = InputMode::Normal;
+        self.status_message = None;
+        if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+            self.start_activation();
+        }
+    }
+
+    fn handle_guard_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+                let name = &self.profile_names[self.selected_row];
+                if self.guard_buffer == *name {
+                    self.status_message = None;
+                    self.start_activation();
+                } else {
+                    self.notify_warn(format!("'{}' does not match; not activated.", self.guard_buffer));
+                }
+            }
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.notify("Activation cancelled.".to_string());
+            }
+            KeyCode::Backspace => {
+                self.guard_buffer.pop();
+            }
+            KeyCode::Char(c) => self.guard_buffer.push(c),
+            _ => {}
+        }
+    }
+
+    /// Whether the profile at `index` is guarded by name or by one of its tags.
+    fn is_guarded(&self, index: usize) -> bool {
+        self.guard.is_guarded(&self.profile_names[index], &self.profiles[index])
+    }
+
     fn handle_edit_key(&mut self, key: KeyEvent) -> Result<()> {
+        if is_account_picker_key(key) && self.input_mode == InputMode::EditAccount {
+            self.open_account_picker();
+            return Ok(());
+        }
         match key.code {
+            KeyCode::Esc if self.picker_visible() => {
+                self.picker_open = false;
+                self.suggestion_index = None;
+            }
             KeyCode::Esc => {
                 self.input_mode = InputMode::Normal;
                 self.suggestion_index = None;
-                self.status_message = Some("Edit cancelled.".to_string());
+                self.notify("Edit cancelled.".to_string());
+            }
+            KeyCode::Down
+                if self.picker_visible()
+                    && self.suggestion_index.is_none()
+                    && !self.suggestions.is_empty() =>
+            {
+                self.suggestion_index = Some(0);
             }
             KeyCode::Down => {
                 if self.suggestion_index.is_none() {
@@ -589,6 +3477,7 @@ impl App {
                         self.edit_cursor_pos = char_count;
                     }
                     self.suggestion_index = None;
+                    self.picker_open = false;
                 } else {
                     // Save and exit edit mode
                     self.save_edit()?;
@@ -601,14 +3490,13 @@ impl App {
                     self.suggestion_index = None;
                     let account = self.edit_account_buffer.trim().to_string();
                     self.start_project_fetch(&account);
+                    self.refresh_picker();
                 } else {
                     self.save_edit()?;
                 }
             }
-            KeyCode::Left => {
-                if self.edit_cursor_pos > 0 {
-                    self.edit_cursor_pos -= 1;
-                }
+            KeyCode::Left if self.edit_cursor_pos > 0 => {
+                self.edit_cursor_pos -= 1;
             }
             KeyCode::Right => {
                 let buf = if self.input_mode == InputMode::EditAccount {
@@ -663,17 +3551,12 @@ impl App {
                 self.suggestion_index = None;
             }
             KeyCode::Char(c) => {
-                let is_valid = if self.input_mode == InputMode::EditAccount {
-                    // Email characters: letters, digits, @, ., -, _, +
-                    c.is_ascii_alphanumeric() || matches!(c, '@' | '.' | '-' | '_' | '+')
+                let kind = if self.input_mode == InputMode::EditAccount {
+                    FieldKind::Account
                 } else {
-                    // GCP project: first char must be a letter, rest: letters, digits, -, _
-                    if self.edit_cursor_pos == 0 {
-                        c.is_ascii_alphabetic()
-                    } else {
-                        c.is_ascii_alphanumeric() || c == '-' || c == '_'
-                    }
+                    FieldKind::Project
                 };
+                let is_valid = kind.accepts(c, self.edit_cursor_pos == 0);
                 if is_valid {
                     let buf = if self.input_mode == InputMode::EditAccount {
                         &mut self.edit_account_buffer
@@ -691,6 +3574,229 @@ impl App {
             }
             _ => {}
         }
+        if self.picker_open
+            && matches!(key.code, KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete)
+        {
+            self.refresh_picker();
+        }
+        Ok(())
+    }
+
+    fn open_account_picker(&mut self) {
+        let mut accounts = gcloud::list_authenticated_accounts().unwrap_or_default();
+        accounts.sort();
+        accounts.dedup();
+        self.account_picker = Some(AccountPicker {
+            accounts,
+            index: 0,
+            return_to: self.input_mode,
+        });
+        self.suggestion_index = None;
+        self.input_mode = InputMode::AccountPicker;
+    }
+
+    fn handle_account_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = self.account_picker.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return;
+        };
+        // One extra entry at the end: "add new account"
+        let entries = picker.accounts.len() + 1;
+        match key.code {
+            KeyCode::Up => picker.index = (picker.index + entries - 1) % entries,
+            KeyCode::Down => picker.index = (picker.index + 1) % entries,
+            KeyCode::Esc => {
+                self.input_mode = picker.return_to;
+                self.account_picker = None;
+            }
+            KeyCode::Enter => {
+                self.input_mode = picker.return_to;
+                match picker.accounts.get(picker.index).cloned() {
+                    Some(account) => self.fill_account(account),
+                    None => self.pending_action = PendingAction::AddAccount,
+                }
+                self.account_picker = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the cluster picker for the selected profile and list its project's clusters
+    /// in the background.
+    fn open_cluster_picker(&mut self) {
+        let name = self.profile_names[self.selected_row].clone();
+        let profile = &self.profiles[self.selected_row];
+        if profile.user_project.is_empty() {
+            self.notify_warn(format!("'{}' has no project to list GKE clusters in.", name));
+            return;
+        }
+        let (account, project) = (profile.user_account.clone(), profile.user_project.clone());
+        self.cluster_picker = Some(ClusterPicker {
+            profile: name.clone(),
+            clusters: profile.clusters.iter().map(|c| (c.clone(), true)).collect(),
+            index: 0,
+            loading: true,
+            error: None,
+        });
+        self.input_mode = InputMode::ClusterPicker;
+        let tx = self.cluster_tx.clone();
+        if let Some(task) = self.cluster_task.take() {
+            task.abort();
+        }
+        let task = runtime::spawn(async move {
+            let clusters = gcloud::list_clusters(&account, &project)
+                .await
+                .map(|clusters| clusters.iter().map(ToString::to_string).collect())
+                .map_err(|e| match e.downcast_ref::<gcloud::CommandFailure>() {
+                    // Just gcloud's own error line; the command is the same every time
+                    Some(failure) => failure
+                        .output
+                        .lines()
+                        .rev()
+                        .map(str::trim)
+                        .find(|l| !l.is_empty())
+                        .unwrap_or_default()
+                        .to_string(),
+                    None => format!("{:#}", e),
+                });
+            let _ = tx.send((name, clusters));
+        });
+        self.cluster_task = Some(task.abort_handle());
+    }
+
+    /// Close the cluster picker, stopping a listing it still waits for.
+    fn close_cluster_picker(&mut self) {
+        self.cluster_picker = None;
+        if let Some(task) = self.cluster_task.take() {
+            task.abort();
+        }
+    }
+
+    pub fn check_cluster_results(&mut self) {
+        while let Ok((name, result)) = self.cluster_rx.try_recv() {
+            let Some(picker) = self.cluster_picker.as_mut().filter(|p| p.profile == name) else {
+                continue;
+            };
+            picker.loading = false;
+            match result {
+                Ok(found) => {
+                    for spec in found {
+                        if !picker.clusters.iter().any(|(c, _)| *c == spec) {
+                            picker.clusters.push((spec, false));
+                        }
+                    }
+                }
+                Err(e) => picker.error = Some(e),
+            }
+        }
+    }
+
+    fn handle_cluster_picker_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(picker) = self.cluster_picker.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        let entries = picker.clusters.len().max(1);
+        match key.code {
+            KeyCode::Up => picker.index = (picker.index + entries - 1) % entries,
+            KeyCode::Down => picker.index = (picker.index + 1) % entries,
+            KeyCode::Char(' ') => {
+                if let Some((_, chosen)) = picker.clusters.get_mut(picker.index) {
+                    *chosen = !*chosen;
+                }
+            }
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.close_cluster_picker();
+            }
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+                let picker = self.cluster_picker.take();
+                self.close_cluster_picker();
+                if let Some(picker) = picker {
+                    let chosen: Vec<String> =
+                        picker.clusters.into_iter().filter(|(_, chosen)| *chosen).map(|(c, _)| c).collect();
+                    self.set_clusters(&picker.profile, chosen)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Save every property of gcloud's active configuration into the selected profile, to be
+    /// written back to its configuration whenever it is activated.
+    fn snapshot_gcloud_properties(&mut self) -> Result<()> {
+        let name = self.profile_names[self.selected_row].clone();
+        let Some(source) = gcloud::read_active_config()? else {
+            self.notify_warn("gcloud has no active configuration.".to_string());
+            return Ok(());
+        };
+        let properties = gcloud::snapshot_properties(&source)?;
+        let snapshot = self.snapshot(format!("gcloud property snapshot of '{}'", name), &[&name])?;
+        let mut data = self.store.load_profiles()?;
+        let Some(profile) = data.profiles.get_mut(&name) else {
+            return Ok(());
+        };
+        let count = properties.len();
+        profile.gcloud_properties = properties;
+        profile.touch();
+        self.store.save_profiles(&data)?;
+        self.push_undo(snapshot, Vec::new());
+        self.notify(format!("Saved {} propert{} of '{}' in '{}'.", count, if count == 1 { "y" } else { "ies" }, source, name));
+        self.reload()
+    }
+
+    fn set_clusters(&mut self, name: &str, clusters: Vec<String>) -> Result<()> {
+        let mut data = self.store.load_profiles()?;
+        let Some(profile) = data.profiles.get_mut(name) else {
+            return Ok(());
+        };
+        if profile.clusters == clusters {
+            self.notify("GKE clusters unchanged.".to_string());
+            return Ok(());
+        }
+        let snapshot = self.snapshot(format!("GKE clusters of '{}'", name), &[name])?;
+        let count = clusters.len();
+        profile.clusters = clusters;
+        profile.touch();
+        self.store.save_profiles(&data)?;
+        self.push_undo(snapshot, Vec::new());
+        self.notify(format!("Set {} GKE cluster(s) for '{}'.", count, name));
+        self.reload()
+    }
+
+    /// Put an account into the account field being edited (form or in-place).
+    fn fill_account(&mut self, account: String) {
+        let cursor = account.chars().count();
+        match self.input_mode {
+            InputMode::Form => {
+                if let Some(form) = self.form.as_mut() {
+                    form.errors[form.focus] = None;
+                    form.values[form.focus] = account;
+                    form.cursor = cursor;
+                }
+            }
+            InputMode::EditAccount => {
+                self.edit_account_buffer = account;
+                self.edit_cursor_pos = cursor;
+            }
+            _ => {}
+        }
+    }
+
+    /// Log in to a new account (TUI suspended) and pick it into the field being edited.
+    pub fn execute_add_account(&mut self) -> Result<()> {
+        let before = gcloud::list_authenticated_accounts().unwrap_or_default();
+        gcloud::login_new_account()?;
+        let after = gcloud::list_authenticated_accounts()?;
+        match after.into_iter().find(|a| !before.contains(a)) {
+            Some(account) => {
+                self.notify(format!("Logged in as {}.", account));
+                self.fill_account(account);
+            }
+            None => self.notify("Login finished; no new account was added.".to_string()),
+        }
         Ok(())
     }
 
@@ -728,6 +3834,20 @@ impl App {
         seen.into_iter().collect()
     }
 
+    /// Regions or zones for the form: the listed ones plus those in existing profiles.
+    fn build_location_suggestions(&self, zones: bool) -> Vec<String> {
+        let mut seen = std::collections::BTreeSet::new();
+        seen.extend(if zones { &self.compute_zones } else { &self.compute_regions }.iter().cloned());
+        for profile in &self.profiles {
+            let key = if zones { "compute/zone" } else { "compute/region" };
+            seen.extend(profile.gcloud_properties.get(key).cloned());
+            if zones {
+                seen.extend(profile.ssh.as_ref().and_then(|ssh| ssh.zone.clone()));
+            }
+        }
+        seen.into_iter().collect()
+    }
+
     fn save_edit(&mut self) -> Result<()> {
         let name = self.profile_names[self.selected_row].clone();
         let old_profile = self.profiles[self.selected_row].clone();
@@ -743,26 +3863,39 @@ impl App {
             }
             _ => {}
         }
+        let snapshot = self.snapshot(format!("edit '{}'", name), &[&name])?;
         self.store.add_profile(&name, profile.clone())?;
+        let (gcloud_undo, mirror_error) = match self.mirror_edit(&name, &old_profile, &profile) {
+            Ok(undo) => (undo.into_iter().collect(), None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        self.push_undo(snapshot, gcloud_undo);
 
         // If ADC account changed, clear auth status (needs re-check)
         if self.edit_col == Column::Adc && profile.adc_account != old_profile.adc_account {
-            if let Some(slot) = self.adc_auth_valid.get_mut(self.selected_row) {
+            if let Some(slot) = self.adc_auth_status.get_mut(self.selected_row) {
                 *slot = None;
             }
         }
 
-        // If ADC quota project changed, apply it via gcloud
+        // If ADC quota project changed, write it into the stored ADC (and the installed
+        // one when this profile is active)
         if self.edit_col == Column::Adc
             && profile.adc_quota_project != old_profile.adc_quota_project
             && !profile.adc_quota_project.is_empty()
         {
-            match gcloud::set_adc_quota_project(&profile.adc_quota_project) {
+            let is_active = self.active_profile.as_deref() == Some(name.as_str());
+            match gcloud::set_adc_quota_project(
+                &self.store,
+                &name,
+                &profile.adc_quota_project,
+                is_active,
+            ) {
                 Ok(()) => {
                     self.reload()?;
                     self.input_mode = InputMode::Normal;
                     self.suggestion_index = None;
-                    self.status_message = Some(format!(
+                    self.notify(format!(
                         "Profile '{}' updated. ADC quota project set to '{}'.",
                         name, profile.adc_quota_project
                     ));
@@ -772,10 +3905,11 @@ impl App {
                     self.reload()?;
                     self.input_mode = InputMode::Normal;
                     self.suggestion_index = None;
-                    self.status_message = Some(format!(
+                    self.notify_warn(format!(
                         "Profile '{}' updated. Failed to set quota project: {}",
                         name, e
                     ));
+                    self.show_failure(&e);
                     return Ok(());
                 }
             }
@@ -784,75 +3918,249 @@ impl App {
         self.reload()?;
         self.input_mode = InputMode::Normal;
         self.suggestion_index = None;
-        self.status_message = Some(format!("Profile '{}' updated.", name));
+        match mirror_error {
+            None => self.notify(format!("Profile '{}' updated.", name)),
+            Some(e) => {
+                self.notify_warn(format!("Profile '{}' updated. Failed to update its gcloud config: {}", name, e));
+                self.show_failure(&e);
+            }
+        }
         Ok(())
     }
 
+    /// In mirror sync mode, write a changed account or project to the profile's gcloud
+    /// configuration. Returns the step that undoes it, if anything was written.
+    fn mirror_edit(&self, name: &str, old: &Profile, new: &Profile) -> Result<Option<GcloudUndo>> {
+        if self.sync_mode != SyncMode::Mirror
+            || (new.user_account == old.user_account && new.user_project == old.user_project)
+        {
+            return Ok(None);
+        }
+        let previous = gcloud::read_configuration(name)?;
+        gcloud::write_configuration(name, &new.user_account, &new.user_project)?;
+        Ok(Some(match previous {
+            Some(props) => GcloudUndo::Write {
+                name: name.to_string(),
+                account: props.get("core/account").cloned().unwrap_or_default(),
+                project: props.get("core/project").cloned().unwrap_or_default(),
+            },
+            None => GcloudUndo::Delete(name.to_string()),
+        }))
+    }
+
+    /// Activate the selected profile, reporting a failure in the status bar.
+    fn start_activation(&mut self) {
+        if let Err(e) = self.activate_selected() {
+            self.notify_error(format!("Activation failed: {}", e));
+            self.show_failure(&e);
+        }
+    }
+
     fn activate_selected(&mut self) -> Result<()> {
         // If auth check is still pending, do a synchronous check now
-        let user_valid = match self.user_auth_valid.get(self.selected_row).copied() {
+        let user_status = match self.user_auth_status.get(self.selected_row).copied() {
             Some(Some(v)) => v,
             _ => {
                 let account = &self.profiles[self.selected_row].user_account;
-                gcloud::check_account_auth(account)
+                gcloud::check_account_auth_cached_blocking(&self.store, account)
             }
         };
-        let adc_valid = match self.adc_auth_valid.get(self.selected_row).copied() {
+        let adc_status = match self.adc_auth_status.get(self.selected_row).copied() {
             Some(Some(v)) => v,
             _ => {
                 let account = &self.profiles[self.selected_row].adc_account;
-                gcloud::check_account_auth(account)
+                gcloud::check_account_auth_cached_blocking(&self.store, account)
             }
         };
 
         // Defer to main loop if interactive reauth is needed
         let needs_reauth = match self.selected_col {
-            Column::Both => !user_valid || !adc_valid,
-            Column::User => !user_valid,
-            Column::Adc => !adc_valid,
+            Column::Both => user_status.needs_reauth() || adc_status.needs_reauth(),
+            Column::User => user_status.needs_reauth(),
+            Column::Adc => adc_status.needs_reauth(),
         };
         if needs_reauth {
             self.pending_action = PendingAction::ReauthAndActivate;
             return Ok(());
         }
 
-        self.do_activate()?;
+        self.activation_queued = true;
         Ok(())
     }
 
-    /// Execute activation (called directly or after reauth completes).
-    pub fn do_activate(&mut self) -> Result<()> {
+    /// Hook event data for a profile, with the column acted on as `scope`.
+    fn hook_data(&self, name: &str, profile: &Profile) -> serde_json::Value {
+        let mut data = hooks::profile_data(name, profile);
+        let scope = match self.selected_col {
+            Column::Both => "both",
+            Column::User => "user",
+            Column::Adc => "adc",
+        };
+        data["scope"] = scope.into();
+        data
+    }
+
+    /// Execute activation (queued by Enter, or after reauth completes). The current step
+    /// goes to the status bar, and `redraw` is called to show it.
+    pub fn do_activate(&mut self, redraw: &mut dyn FnMut(&mut App)) -> Result<()> {
         let name = self.profile_names[self.selected_row].clone();
         let profile = self.profiles[self.selected_row].clone();
+        let store = self.store.clone();
+        let column = self.selected_col;
+        let mut progress = |step: gcloud::ActivationStep| {
+            self.status_message = Some(format!("Activating '{}': {}\u{2026}", name, step));
+            redraw(self);
+        };
 
-        match self.selected_col {
-            Column::Both => {
-                gcloud::activate_both(
-                    &self.store,
-                    &name,
-                    &profile.user_account,
-                    &profile.user_project,
-                )?;
-                self.status_message = Some(format!("Activated profile '{}'.", name));
-            }
+        let switched = match column {
+            Column::Both => gcloud::activate_both_with_progress(
+                &store,
+                &name,
+                &profile.user_account,
+                &profile.user_project,
+                &mut progress,
+            ),
             Column::User => {
-                gcloud::activate_user(&name, &profile.user_account, &profile.user_project)?;
-                self.status_message = Some(format!("Activated user config for '{}'.", name));
+                gcloud::activate_user_with_progress(&name, &profile.user_account, &profile.user_project, &mut progress)
             }
             Column::Adc => {
-                gcloud::activate_adc(&self.store, &name)?;
-                self.status_message = Some(format!("Activated ADC for '{}'.", name));
+                progress(gcloud::ActivationStep::CopyAdc);
+                gcloud::activate_adc(&store, &name)
             }
-        }
+        };
+        // The last step must not outlive the activation, whichever way it went
+        self.status_message = None;
+        switched?;
+        self.notify(match column {
+            Column::Both => format!("Activated profile '{}'.", name),
+            Column::User => format!("Activated user config for '{}'.", name),
+            Column::Adc => format!("Activated ADC for '{}'.", name),
+        });
 
         self.active_profile = Some(name.clone());
         let mut data = self.store.load_profiles()?;
         data.active_profile = Some(name.clone());
         self.store.save_profiles(&data)?;
+        self.store.record_activation(&name);
+        self.last_activated.insert(name.clone(), gcloud_switch::profile::now());
+        if self.row_order == RowOrder::Recent {
+            self.refresh_view();
+        }
+        self.refresh_drift();
+        // The other clouds and kubectl follow the user side; an ADC-only switch leaves them alone
+        if self.selected_col != Column::Adc {
+            if let Err(e) = gcloud::apply_properties(&name, &profile.gcloud_properties) {
+                self.notify_warn(format!("gcloud property snapshot not applied: {:#}", e));
+            }
+            if let Err(e) = providers::activate(&profile) {
+                self.notify_warn(format!("{:#}", e));
+            }
+            if let Err(e) = docker::configure_registries(&profile.registries) {
+                self.notify_warn(format!("Docker credential helpers not configured: {:#}", e));
+            }
+            if self.legacy_configs {
+                if let Err(e) = legacy::update(&self.store, &name, &profile) {
+                    self.notify_warn(format!("Legacy SDK configs not updated: {:#}", e));
+                }
+            }
+            self.start_gke_refresh(&name, &profile);
+        }
+        self.hooks.spawn(HookEvent::ProfileActivated, self.hook_data(&name, &profile));
 
         Ok(())
     }
 
+    /// Reauth every marked profile (per the selected column), continuing past failures.
+    pub fn execute_batch_reauth(&mut self) -> Result<()> {
+        let selected = self.selected_row;
+        let mut failed = Vec::new();
+        let mut first_error = None;
+        let targets = self.batch_targets();
+        for &i in &targets {
+            self.selected_row = i;
+            if let Err(e) = self.execute_reauth() {
+                failed.push(format!("{} ({})", self.profile_names[i], e));
+                first_error.get_or_insert(e);
+            }
+        }
+        self.selected_row = selected.min(self.profile_names.len().saturating_sub(1));
+        if failed.is_empty() {
+            self.notify(format!("Re-authenticated {} profile(s).", targets.len()));
+        } else {
+            self.notify_error(format!("Reauth failed for: {}", failed.join(", ")));
+        }
+        if let Some(e) = first_error {
+            self.show_failure(&e);
+        }
+        self.reload()
+    }
+
+    /// Profiles whose checked user or ADC credentials need a login, with which sides do.
+    fn expired_sides(&self) -> Vec<(usize, bool, bool)> {
+        let expired = |statuses: &[Option<AuthStatus>], i: usize| {
+            statuses.get(i).copied().flatten().is_some_and(|s| s.needs_reauth())
+        };
+        (0..self.profiles.len())
+            .map(|i| (i, expired(&self.user_auth_status, i), expired(&self.adc_auth_status, i)))
+            .filter(|&(_, user, adc)| user || adc)
+            .collect()
+    }
+
+    /// Log in again for every expired side of every profile, one flow after the other. An
+    /// account shared by several profiles logs in once; the active profile's ADC is put back
+    /// afterwards, since ADC logins overwrite it.
+    pub fn execute_expired_reauth(&mut self) -> Result<()> {
+        let mut logged_in = std::collections::HashSet::new();
+        let mut failed = Vec::new();
+        let mut first_error = None;
+        let mut count = 0;
+        let mut adc_changed = false;
+        for (i, user, adc) in self.expired_sides() {
+            let name = self.profile_names[i].clone();
+            let profile = self.profiles[i].clone();
+            let user = user && !logged_in.contains(&profile.user_account);
+            if !user && !adc {
+                continue;
+            }
+            println!("Re-authenticating '{}'...", name);
+            match gcloud::reauth_sides(&self.store, &name, &profile, user, adc) {
+                Ok(()) => {
+                    if user {
+                        logged_in.insert(profile.user_account.clone());
+                    }
+                    adc_changed |= adc;
+                    count += 1;
+                    let mut data = hooks::profile_data(&name, &profile);
+                    data["scope"] = match (user, adc) {
+                        (true, true) => "both",
+                        (true, false) => "user",
+                        _ => "adc",
+                    }
+                    .into();
+                    self.hooks.spawn(HookEvent::ReauthCompleted, data);
+                }
+                Err(e) => {
+                    failed.push(format!("{} ({})", name, e));
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if adc_changed {
+            if let Some(active) = self.active_profile.clone().filter(|name| self.store.has_adc(name)) {
+                gcloud::activate_adc(&self.store, &active)?;
+            }
+        }
+        if failed.is_empty() {
+            self.notify(format!("Re-authenticated {} profile(s).", count));
+        } else {
+            self.notify_error(format!("Reauth failed for: {}", failed.join(", ")));
+        }
+        if let Some(e) = first_error {
+            self.show_failure(&e);
+        }
+        self.reload()
+    }
+
     /// Execute a reauth that was deferred for TUI suspension.
     pub fn execute_reauth(&mut self) -> Result<()> {
         let name = self.profile_names[self.selected_row].clone();
@@ -860,25 +4168,270 @@ impl App {
 
         match self.selected_col {
             Column::Both => {
-                gcloud::reauth_user(&profile.user_account)?;
+                gcloud::reauth_both(
+                    &self.store,
+                    &name,
+                    &profile.user_account,
+                    &profile.adc_account,
+                    &profile.adc_quota_project,
+                )?;
                 gcloud::activate_user(&name, &profile.user_account, &profile.user_project)?;
-                gcloud::reauth_adc(&self.store, &name, &profile.adc_quota_project)?;
-                self.status_message =
-                    Some(format!("Re-authenticated user and ADC for '{}'.", name));
+                self.notify(format!("Re-authenticated user and ADC for '{}'.", name));
             }
             Column::User => {
                 gcloud::reauth_user(&profile.user_account)?;
                 gcloud::activate_user(&name, &profile.user_account, &profile.user_project)?;
-                self.status_message =
-                    Some(format!("User re-authenticated for '{}'.", name));
+                self.notify(format!("User re-authenticated for '{}'.", name));
             }
             Column::Adc => {
                 gcloud::reauth_adc(&self.store, &name, &profile.adc_quota_project)?;
-                self.status_message = Some(format!("ADC re-authenticated for '{}'.", name));
+                self.notify(format!("ADC re-authenticated for '{}'.", name));
             }
         }
+        self.hooks.spawn(HookEvent::ReauthCompleted, self.hook_data(&name, &profile));
 
         self.reload()?;
         Ok(())
     }
 }
+
+/// One row of a two-profile comparison.
+pub struct DiffRow {
+    pub label: String,
+    pub a: String,
+    pub b: String,
+    pub differ: bool,
+}
+
+/// What activating `name` for `column` runs, in order, without running it: the gcloud calls
+/// and ADC copy of `App::do_activate`, then the other tools that follow the user side, the
+/// GKE refresh and the `profile-activated` hooks.
+pub fn activation_plan(
+    store: &Store,
+    name: &str,
+    profile: &Profile,
+    column: Column,
+    legacy_configs: bool,
+    hooks: &Hooks,
+) -> Result<Vec<PlanStep>> {
+    let mut steps = Vec::new();
+    if column != Column::Adc {
+        steps.extend(gcloud::user_activation_plan(name, &profile.user_account, &profile.user_project));
+    }
+    if column != Column::User {
+        steps.extend(gcloud::adc_activation_plan(store, name, column == Column::Both)?);
+    }
+    if column != Column::Adc {
+        steps.extend(gcloud::properties_plan(name, &profile.gcloud_properties)?);
+        for (provider, context) in &profile.contexts {
+            steps.push(PlanStep::Other(format!("switch {} to '{}'", provider, context)));
+        }
+        if !profile.registries.is_empty() {
+            let hosts: Vec<String> = profile.registries.iter().map(|entry| docker::registry_host(entry)).collect();
+            steps.push(PlanStep::Other(format!("make gcloud the Docker credential helper for {}", hosts.join(", "))));
+        }
+        if legacy_configs && !profile.user_project.is_empty() {
+            steps.push(PlanStep::Other(format!("set project {} in the existing gsutil and bq configs", profile.user_project)));
+        }
+        steps.extend(gcloud::clusters_plan(&profile.clusters, &profile.user_account, &profile.user_project));
+    }
+    steps.extend(hooks.commands(HookEvent::ProfileActivated).iter().cloned().map(PlanStep::Command));
+    Ok(steps)
+}
+
+/// Compare profiles `a` and `b` field by field, then their stored ADC and the properties of
+/// their gcloud configurations. Backs `diff` and the TUI's compare view (`=`). Rows empty
+/// on both sides are left out.
+pub fn profile_diff(store: &Store, data: &ProfilesFile, a: &str, b: &str) -> Result<Vec<DiffRow>> {
+    let profile = |name: &str| {
+        data.profiles
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))
+    };
+    let (pa, pb) = (profile(a)?, profile(b)?);
+    let row = |label: &str, a: String, b: String| {
+        let differ = a != b;
+        DiffRow { label: label.to_string(), a, b, differ }
+    };
+
+    let mut rows: Vec<DiffRow> = pa
+        .fields()
+        .into_iter()
+        .zip(pb.fields())
+        .map(|((label, va), (_, vb))| row(label, va, vb))
+        .collect();
+    let sync_label = |p: &Profile| if p.sync { "yes" } else { "local only" }.to_string();
+    rows.push(row("Sync", sync_label(pa), sync_label(pb)));
+
+    // Compared by content (ignoring the quota project), since the summary hides the login
+    let (adc_a, adc_b) = (store.load_adc_json(a)?, store.load_adc_json(b)?);
+    let describe = |adc: &Option<serde_json::Value>| adc.as_ref().map_or("none".to_string(), gcloud::describe_adc);
+    let adc_differ = match (&adc_a, &adc_b) {
+        (Some(x), Some(y)) => !gcloud::same_adc(x, y),
+        (x, y) => x.is_some() != y.is_some(),
+    };
+    rows.push(DiffRow {
+        label: "Stored ADC".to_string(),
+        a: describe(&adc_a),
+        b: describe(&adc_b),
+        differ: adc_differ,
+    });
+
+    let (config_a, config_b) = (gcloud::read_configuration(a)?, gcloud::read_configuration(b)?);
+    let present = |config: &Option<std::collections::BTreeMap<String, String>>| {
+        if config.is_some() { "present" } else { "missing" }.to_string()
+    };
+    rows.push(row("gcloud config", present(&config_a), present(&config_b)));
+    let (props_a, props_b) = (config_a.unwrap_or_default(), config_b.unwrap_or_default());
+    let keys: BTreeSet<&String> = props_a.keys().chain(props_b.keys()).collect();
+    for key in keys {
+        let va = props_a.get(key).cloned().unwrap_or_default();
+        let vb = props_b.get(key).cloned().unwrap_or_default();
+        rows.push(row(key, va, vb));
+    }
+    rows.retain(|row| !row.a.is_empty() || !row.b.is_empty());
+    Ok(rows)
+}
+
+/// Bring profiles.toml in line with gcloud's configurations per the sync mode (add new
+/// ones; in strict mode also drop profiles whose configuration is gone) and take over
+/// gcloud's active configuration. Returns whether anything changed.
+pub fn sync_with_gcloud(store: &Store) -> Result<bool> {
+    let mut data = store.load_profiles()?;
+
+    let mut changed = false;
+
+    match data.sync_mode {
+        SyncMode::Off => {}
+        SyncMode::Add | SyncMode::Strict | SyncMode::Mirror => {
+            let configs = gcloud::discover_existing_configs()?;
+            let config_names: std::collections::HashSet<String> =
+                configs.iter().map(|(n, _, _)| n.clone()).collect();
+
+            // Add new gcloud configs as profiles, unless the profile was deleted (possibly on
+            // another machine, arriving through sync)
+            for (name, account, project) in &configs {
+                if !data.profiles.contains_key(name) && !data.deleted.contains_key(name) {
+                    let mut profile = Profile {
+                        user_account: account.clone(),
+                        user_project: project.clone(),
+                        adc_account: account.clone(),
+                        adc_quota_project: project.clone(),
+                        ..Default::default()
+                    };
+                    profile.touch();
+                    data.profiles.insert(name.clone(), profile);
+                    changed = true;
+                }
+            }
+
+            // In strict mode, delete profiles whose gcloud configs no longer exist (locked
+            // ones stay)
+            if matches!(data.sync_mode, SyncMode::Strict | SyncMode::Mirror) {
+                let to_delete: Vec<String> = data
+                    .profiles
+                    .iter()
+                    .filter(|(name, profile)| !config_names.contains(*name) && !profile.locked)
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                for name in &to_delete {
+                    data.remove_profile(name);
+                    // Remove ADC file if it exists
+                    let adc_path = store.adc_path(name);
+                    if adc_path.exists() {
+                        let _ = std::fs::remove_file(adc_path);
+                    }
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    // Always sync active config from gcloud
+    if let Ok(Some(active)) = gcloud::read_active_config() {
+        if data.profiles.contains_key(&active) && data.active_profile.as_deref() != Some(&active) {
+            data.active_profile = Some(active);
+            changed = true;
+        }
+    }
+
+    if changed {
+        store.save_profiles(&data)?;
+    }
+
+    Ok(changed)
+}
+
+/// Ctrl+A opens the account picker from an account field.
+fn is_account_picker_key(key: KeyEvent) -> bool {
+    key.code == KeyCode::Char('a') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// Apply a cursor-movement or editing key to a single-line text buffer. `accepts` decides
+/// whether a typed char may be inserted at a given cursor position. Returns true if the
+/// buffer content changed.
+fn edit_text(
+    buf: &mut String,
+    cursor: &mut usize,
+    code: KeyCode,
+    accepts: impl Fn(char, usize) -> bool,
+) -> bool {
+    let len = buf.chars().count();
+    let byte_idx = |buf: &String, pos: usize| {
+        buf.char_indices().nth(pos).map(|(i, _)| i).unwrap_or(buf.len())
+    };
+    match code {
+        KeyCode::Left => *cursor = cursor.saturating_sub(1),
+        KeyCode::Right => *cursor = (*cursor + 1).min(len),
+        KeyCode::Home => *cursor = 0,
+        KeyCode::End => *cursor = len,
+        KeyCode::Backspace if *cursor > 0 => {
+            let idx = byte_idx(buf, *cursor - 1);
+            buf.remove(idx);
+            *cursor -= 1;
+            return true;
+        }
+        KeyCode::Delete if *cursor < len => {
+            let idx = byte_idx(buf, *cursor);
+            buf.remove(idx);
+            return true;
+        }
+        KeyCode::Char(c) if accepts(c, *cursor) => {
+            let idx = byte_idx(buf, *cursor);
+            buf.insert(idx, c);
+            *cursor += 1;
+            return true;
+        }
+        _ => {}
+    }
+    false
+}
+
+/// A Compute Engine region name: "europe-west1", "us-central1".
+fn is_region(name: &str) -> bool {
+    name.split_once('-').is_some_and(|(area, rest)| {
+        !area.is_empty() && rest.ends_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+/// Case-insensitive subsequence match of `needle` in `haystack`. Higher scores are better:
+/// consecutive characters and matches at word starts earn bonuses. None if no match.
+fn fuzzy_score(haystack: &str, needle: &str) -> Option<i64> {
+    let hay: Vec<char> = haystack.to_lowercase().chars().collect();
+    let mut score = 0i64;
+    let mut start = 0usize;
+    let mut prev: Option<usize> = None;
+    for nc in needle.to_lowercase().chars() {
+        let found = start + hay[start..].iter().position(|&c| c == nc)?;
+        score += 1;
+        if prev.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !hay[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        prev = Some(found);
+        start = found + 1;
+    }
+    Some(score)
+}
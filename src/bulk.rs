@@ -0,0 +1,225 @@
+//! Profile sets in a file for `gcloud-switch import --file`, so a platform team can hand
+//! out its starter profiles. Two formats, picked by the extension: CSV with a header row,
+//! or a small YAML subset (a list of flat mappings, optionally under `profiles:`). Anything
+//! outside that subset is rejected with its line number rather than guessed at.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// One profile from the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub account: String,
+    pub project: String,
+    pub tags: Vec<String>,
+}
+
+/// Read `path` as CSV (`.csv`) or YAML (anything else).
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let entries = if is_csv { parse_csv(&text) } else { parse_yaml(&text) };
+    entries.with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// CSV with a header naming the columns (`name`, `account`, `project`, `tags`) in any
+/// order. Tags within their field are separated by spaces or semicolons.
+pub fn parse_csv(text: &str) -> Result<Vec<Entry>> {
+    let mut rows = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+    let Some((_, header)) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<String> = split_csv_line(header)
+        .map_err(|e| anyhow::anyhow!("line 1: {}", e))?
+        .iter()
+        .map(|c| c.trim().to_ascii_lowercase())
+        .collect();
+    let mut entries = Vec::new();
+    for (i, line) in rows {
+        let line_no = i + 1;
+        let fields = split_csv_line(line).map_err(|e| anyhow::anyhow!("line {}: {}", line_no, e))?;
+        if fields.len() > columns.len() {
+            anyhow::bail!("line {}: {} fields, but the header has {}", line_no, fields.len(), columns.len());
+        }
+        let mut entry = Entry::default();
+        for (column, value) in columns.iter().zip(fields) {
+            let value = value.trim();
+            if column == "tags" {
+                entry.tags = split_tags(value, &[';']);
+            } else {
+                set_field(&mut entry, column, value.to_string()).map_err(|e| anyhow::anyhow!("line {}: {}", line_no, e))?;
+            }
+        }
+        entries.push(check(entry).map_err(|e| anyhow::anyhow!("line {}: {}", line_no, e))?);
+    }
+    Ok(entries)
+}
+
+/// Fields of one CSV line; double quotes enclose fields with commas, `""` is a quote.
+fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("unterminated quote");
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// A YAML list of mappings with scalar values, tags as `[a, b]` or a nested `- a` list:
+///
+/// ```yaml
+/// profiles:
+///   - name: team-dev
+///     account: alice@example.com
+///     project: team-dev-123
+///     tags: [dev, team-a]
+/// ```
+pub fn parse_yaml(text: &str) -> Result<Vec<Entry>> {
+    let mut entries: Vec<(usize, Entry)> = Vec::new();
+    // Indent of the dashes starting entries, fixed by the first one
+    let mut entry_indent = None;
+    // A key whose value is a block list, with its indent
+    let mut list: Option<usize> = None;
+    for (i, raw) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = strip_comment(raw);
+        if line.trim().is_empty() || line.trim() == "---" {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let content = line.trim();
+        let err = |msg: String| anyhow::anyhow!("line {}: {}", line_no, msg);
+
+        if let Some(key_indent) = list {
+            if indent > key_indent {
+                if let Some(item) = content.strip_prefix('-') {
+                    let (_, entry) = entries.last_mut().expect("a list belongs to an entry");
+                    entry.tags.push(unquote(item.trim()).to_string());
+                    continue;
+                }
+            }
+            list = None;
+        }
+
+        let rest = match content.strip_prefix('-') {
+            Some(rest) if rest.is_empty() || rest.starts_with(' ') => {
+                if *entry_indent.get_or_insert(indent) != indent {
+                    return Err(err("list item at an unexpected indent".to_string()));
+                }
+                entries.push((line_no, Entry::default()));
+                rest.trim_start()
+            }
+            _ if entry_indent.is_none() && indent == 0 && content == "profiles:" => continue,
+            _ if entry_indent.is_some_and(|dash| indent > dash) => content,
+            _ => return Err(err(format!("expected a list of profiles, found '{}'", content))),
+        };
+        if rest.is_empty() {
+            continue;
+        }
+        let (key, value) = rest
+            .split_once(':')
+            .ok_or_else(|| err(format!("expected 'key: value', found '{}'", rest)))?;
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let (_, entry) = entries.last_mut().expect("keys follow a list item");
+        if key == "tags" {
+            if value.is_empty() {
+                // Block list on the following lines; the key's indent is where `- ` sits
+                // when it shares the line with the entry's dash
+                list = Some(indent + (content.len() - rest.len()));
+            } else if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                entry.tags = split_tags(inner, &[',']);
+            } else {
+                entry.tags = split_tags(unquote(value), &[',']);
+            }
+        } else {
+            set_field(entry, &key, unquote(value).to_string()).map_err(|e| err(e.to_string()))?;
+        }
+    }
+    entries
+        .into_iter()
+        .map(|(line_no, entry)| check(entry).map_err(|e| anyhow::anyhow!("entry at line {}: {}", line_no, e)))
+        .collect()
+}
+
+/// `line` without a trailing `# comment` (a `#` at the start or after whitespace, outside quotes).
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if prev.is_whitespace() => return &line[..i],
+            _ => {}
+        }
+        prev = c;
+    }
+    line
+}
+
+fn unquote(value: &str) -> &str {
+    for q in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(q).and_then(|v| v.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    value
+}
+
+fn split_tags(value: &str, separators: &[char]) -> Vec<String> {
+    value
+        .split(|c: char| c.is_whitespace() || separators.contains(&c))
+        .map(|tag| unquote(tag.trim()))
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn set_field(entry: &mut Entry, key: &str, value: String) -> Result<()> {
+    match key {
+        "name" => entry.name = value,
+        "account" => entry.account = value,
+        "project" => entry.project = value,
+        _ => anyhow::bail!("unknown field '{}' (expected name, account, project, tags)", key),
+    }
+    Ok(())
+}
+
+fn check(entry: Entry) -> Result<Entry> {
+    if entry.name.is_empty() {
+        anyhow::bail!("missing name");
+    }
+    crate::profile::validate_name(&entry.name)
+        .with_context(|| format!("invalid profile name '{}'", entry.name))?;
+    if entry.account.is_empty() {
+        anyhow::bail!("'{}' has no account", entry.name);
+    }
+    Ok(entry)
+}
@@ -0,0 +1,143 @@
+//! `gcloud-switch ci apply`: profile activation for pipelines. Never prompts or opens a
+//! browser; anything that would need a person fails with an error instead. ADC can come
+//! from a secret in an environment variable, and the resulting environment is written
+//! where the next pipeline steps pick it up.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde_json::Value;
+
+use gcloud_switch::{docker, gcloud};
+use gcloud_switch::profile::Profile;
+use gcloud_switch::providers::export_line;
+use gcloud_switch::store::Store;
+
+use crate::hooks::{self, HookEvent, Hooks};
+
+/// Default environment variable holding the ADC secret.
+pub const DEFAULT_ADC_ENV: &str = "GCLOUD_SWITCH_ADC";
+
+pub fn apply(name: &str, adc_env: &str, env_file: Option<&Path>, hooks: &Hooks) -> Result<()> {
+    let store = Store::new()?;
+    let mut data = store.load_profiles()?;
+    let profile = data
+        .profiles
+        .get(name)
+        .with_context(|| format!("Profile '{}' not found", name))?
+        .clone();
+
+    if let Some(secret) = std::env::var(adc_env).ok().filter(|s| !s.trim().is_empty()) {
+        install_adc_secret(&store, name, &profile, adc_env, &secret)?;
+    }
+
+    let status = gcloud::check_account_auth_blocking(&profile.user_account);
+    if let Some((reason, _)) = status.explain() {
+        anyhow::bail!(
+            "No valid credentials for '{}' ({}), and ci apply never logs in interactively. \
+             Supply a service account key for it in {}, or run `gcloud auth` in an earlier step.",
+            profile.user_account,
+            reason,
+            adc_env
+        );
+    }
+    gcloud::activate_both(&store, name, &profile.user_account, &profile.user_project)?;
+    data.active_profile = Some(name.to_string());
+    store.save_profiles(&data)?;
+    store.record_activation(name);
+    // Unlike an interactive switch, a failure here fails the pipeline step
+    gcloud::apply_properties(name, &profile.gcloud_properties)?;
+    docker::configure_registries(&profile.registries)?;
+    let mut failed = Vec::new();
+    gcloud::refresh_clusters(&profile.clusters, &profile.user_account, &profile.user_project, |cluster, result| {
+        if let Err(e) = result {
+            failed.push(format!("{}: {:#}", cluster, e));
+        }
+        true
+    })?;
+    if !failed.is_empty() {
+        anyhow::bail!("Refreshing GKE credentials failed for {}", failed.join("; "));
+    }
+    let mut hook_data = hooks::profile_data(name, &profile);
+    hook_data["scope"] = "both".into();
+    hooks.run(HookEvent::ProfileActivated, hook_data);
+
+    let vars = environment(&store, name, &profile);
+    match env_file
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::var_os("GITHUB_ENV").map(PathBuf::from))
+    {
+        Some(path) => {
+            append_env_file(&path, &vars)?;
+            eprintln!("Activated profile '{}'; environment written to {}.", name, path.display());
+        }
+        None => {
+            for (key, value) in &vars {
+                println!("{}", export_line(key, Some(value)));
+            }
+            eprintln!("Activated profile '{}'.", name);
+        }
+    }
+    Ok(())
+}
+
+/// Decode the secret (JSON, or base64 of it, as CI secret stores often require), store it
+/// as the profile's ADC and, for the profile's own service account, as its gcloud login.
+fn install_adc_secret(store: &Store, name: &str, profile: &Profile, adc_env: &str, secret: &str) -> Result<()> {
+    let secret = secret.trim();
+    let json = if secret.starts_with('{') {
+        secret.to_string()
+    } else {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(secret)
+            .with_context(|| format!("{} is neither JSON nor base64-encoded JSON", adc_env))?;
+        String::from_utf8(bytes).with_context(|| format!("{} does not decode to text", adc_env))?
+    };
+    let value: Value =
+        serde_json::from_str(&json).with_context(|| format!("{} does not hold valid JSON credentials", adc_env))?;
+    store.save_adc_json(name, &value)?;
+    let path = store.adc_path(name);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    let is_own_key = value.get("type").and_then(Value::as_str) == Some("service_account")
+        && value.get("client_email").and_then(Value::as_str) == Some(profile.user_account.as_str());
+    if is_own_key {
+        gcloud::activate_service_account(&profile.user_account, &path)?;
+    }
+    Ok(())
+}
+
+/// What later steps need to use the profile without depending on gcloud's global state.
+fn environment(store: &Store, name: &str, profile: &Profile) -> Vec<(&'static str, String)> {
+    let mut vars = vec![("CLOUDSDK_ACTIVE_CONFIG_NAME", name.to_string())];
+    if !profile.user_account.is_empty() {
+        vars.push(("CLOUDSDK_CORE_ACCOUNT", profile.user_account.clone()));
+    }
+    if !profile.user_project.is_empty() {
+        vars.push(("CLOUDSDK_CORE_PROJECT", profile.user_project.clone()));
+        vars.push(("GOOGLE_CLOUD_PROJECT", profile.user_project.clone()));
+    }
+    if store.has_adc(name) {
+        vars.push(("GOOGLE_APPLICATION_CREDENTIALS", store.adc_path(name).display().to_string()));
+    }
+    vars
+}
+
+/// `KEY=value` lines, the format of `$GITHUB_ENV` and dotenv files.
+fn append_env_file(path: &Path, vars: &[(&str, String)]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    for (key, value) in vars {
+        writeln!(file, "{}={}", key, value)?;
+    }
+    Ok(())
+}
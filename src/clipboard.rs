@@ -0,0 +1,77 @@
+//! Copy text to the system clipboard.
+//! Locally this uses the platform's clipboard command; over SSH (or when no command is
+//! available) it falls back to the OSC 52 escape sequence, which the terminal emulator
+//! on the user's machine applies to its own clipboard.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+
+/// How the text was copied, for the status message.
+pub enum Method {
+    /// A local clipboard command (pbcopy, wl-copy, ...).
+    Command,
+    /// OSC 52 escape sequence; whether it worked depends on the terminal.
+    Osc52,
+}
+
+/// Clipboard commands to try, in order, with their arguments.
+fn candidates() -> &'static [(&'static str, &'static [&'static str])] {
+    if cfg!(target_os = "macos") {
+        &[("pbcopy", &[])]
+    } else if cfg!(windows) {
+        &[("clip", &[])]
+    } else {
+        &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    }
+}
+
+fn is_ssh_session() -> bool {
+    std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some()
+}
+
+fn copy_with_command(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    child
+        .stdin
+        .take()
+        .context("clipboard command has no stdin")?
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("{} failed with status {}", program, status);
+    }
+    Ok(())
+}
+
+fn copy_with_osc52(text: &str) -> Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+pub fn copy(text: &str) -> Result<Method> {
+    if !is_ssh_session() {
+        for (program, args) in candidates() {
+            if copy_with_command(program, args, text).is_ok() {
+                return Ok(Method::Command);
+            }
+        }
+    }
+    copy_with_osc52(text)?;
+    Ok(Method::Osc52)
+}
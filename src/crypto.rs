@@ -0,0 +1,205 @@
+//! Optional age encryption of synced files.
+//! Every machine shares one age X25519 identity, kept in a local key file or the OS
+//! keyring; files are encrypted to its public key and ASCII-armored so they stay text.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use age::secrecy::ExposeSecret;
+use age::x25519::Identity;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Where the sync key is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStore {
+    /// ~/.config/gcloud-switch/sync.key, readable only by the user.
+    File,
+    /// macOS Keychain (`security`) or the Secret Service (`secret-tool`) on Linux.
+    Keyring,
+}
+
+#[cfg(unix)]
+const KEYRING_SERVICE: &str = "gcloud-switch";
+#[cfg(unix)]
+const KEYRING_ACCOUNT: &str = "sync-key";
+
+fn key_file_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not determine home directory")?;
+    Ok(home.join(".config").join("gcloud-switch").join("sync.key"))
+}
+
+pub fn generate_identity() -> Identity {
+    Identity::generate()
+}
+
+pub fn parse_identity(secret: &str) -> Result<Identity> {
+    Identity::from_str(secret.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid age key (expected AGE-SECRET-KEY-1...): {}", e))
+}
+
+/// The identity's secret, for copying the key to another machine.
+pub fn identity_secret(identity: &Identity) -> String {
+    identity.to_string().expose_secret().to_string()
+}
+
+/// Load the sync key, or None if none has been stored yet.
+pub fn load_identity(store: KeyStore) -> Result<Option<Identity>> {
+    let secret = match store {
+        KeyStore::File => {
+            let path = key_file_path()?;
+            if !path.exists() {
+                return Ok(None);
+            }
+            fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?
+        }
+        KeyStore::Keyring => match keyring_lookup()? {
+            Some(secret) => secret,
+            None => return Ok(None),
+        },
+    };
+    parse_identity(&secret).map(Some)
+}
+
+pub fn save_identity(store: KeyStore, identity: &Identity) -> Result<()> {
+    let secret = identity_secret(identity);
+    match store {
+        KeyStore::File => {
+            let path = key_file_path()?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_private(&path, &format!("{}\n", secret))
+                .with_context(|| format!("Failed to write {}", path.display()))
+        }
+        KeyStore::Keyring => keyring_store(&secret),
+    }
+}
+
+/// Write a file that only the user can read, without a window where it has the umask's mode.
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // mode() only applies to new files; tighten an existing one before writing the secret
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    fs::write(path, contents)
+}
+
+pub fn encrypt(identity: &Identity, plaintext: &str) -> Result<String> {
+    age::encrypt_and_armor(&identity.to_public(), plaintext.as_bytes())
+        .context("Failed to encrypt sync file")
+}
+
+pub fn decrypt(identity: &Identity, armored: &str) -> Result<String> {
+    let plaintext = age::decrypt(identity, armored.as_bytes())
+        .context("Failed to decrypt sync file (was it encrypted with a different key?)")?;
+    String::from_utf8(plaintext).context("Decrypted sync file is not UTF-8")
+}
+
+#[cfg(target_os = "macos")]
+fn keyring_lookup() -> Result<Option<String>> {
+    let output = Command::new("security")
+        .args(["find-generic-password", "-s", KEYRING_SERVICE, "-a", KEYRING_ACCOUNT, "-w"])
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run security")?;
+    // Exit status 44: item not found
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
+
+#[cfg(target_os = "macos")]
+fn keyring_store(secret: &str) -> Result<()> {
+    use std::io::Write;
+
+    // -U updates an existing item instead of failing. A trailing -w without a value makes
+    // security prompt for the password (and its confirmation) on stdin, which keeps the
+    // secret off the command line.
+    let mut child = Command::new("security")
+        .args(["add-generic-password", "-U", "-s", KEYRING_SERVICE, "-a", KEYRING_ACCOUNT, "-w"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context("Failed to run security")?;
+    child
+        .stdin
+        .take()
+        .context("security has no stdin")?
+        .write_all(format!("{0}\n{0}\n", secret).as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("security add-generic-password failed with status {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn keyring_lookup() -> Result<Option<String>> {
+    let output = Command::new("secret-tool")
+        .args(["lookup", "service", KEYRING_SERVICE, "account", KEYRING_ACCOUNT])
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run secret-tool (install libsecret-tools)")?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn keyring_store(secret: &str) -> Result<()> {
+    use std::io::Write;
+
+    // secret-tool reads the secret from stdin, which keeps it off the command line
+    let mut child = Command::new("secret-tool")
+        .args([
+            "store",
+            "--label=gcloud-switch sync key",
+            "service",
+            KEYRING_SERVICE,
+            "account",
+            KEYRING_ACCOUNT,
+        ])
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to run secret-tool (install libsecret-tools)")?;
+    child
+        .stdin
+        .take()
+        .context("secret-tool has no stdin")?
+        .write_all(secret.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("secret-tool store failed with status {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn keyring_lookup() -> Result<Option<String>> {
+    anyhow::bail!("Keyring storage of the sync key is not supported on Windows; use --key-store file")
+}
+
+#[cfg(windows)]
+fn keyring_store(_secret: &str) -> Result<()> {
+    anyhow::bail!("Keyring storage of the sync key is not supported on Windows; use --key-store file")
+}
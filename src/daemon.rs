@@ -0,0 +1,290 @@
+//! `gcloud-switch daemon`: a long-running process answering a small JSON protocol on a
+//! unix socket, so prompts, editors and status bars can query and switch profiles without
+//! spawning the binary (and re-checking credentials) each time.
+//!
+//! One request per line, one response line each:
+//!   {"cmd": "list"}                       -> profiles with their fields and the active flag
+//!   {"cmd": "current"}                    -> the active profile, or null
+//!   {"cmd": "switch", "profile": "work"}  -> activate a profile (user and ADC) and refresh
+//!                                            its GKE clusters' kubectl contexts; profiles
+//!                                            that ask first also need "force": true,
+//!                                            guarded ones "i_know": true
+//!   {"cmd": "auth-status", "profile": "work"}  -> credential status; all profiles if omitted
+//! Responses are {"ok": true, "result": ...} or {"ok": false, "error": "..."}.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use gcloud_switch::gcloud::{self, AuthStatus};
+use gcloud_switch::{docker, legacy, providers};
+use gcloud_switch::profile::GuardSettings;
+use gcloud_switch::store::Store;
+
+use crate::hooks::{self, HookEvent, Hooks};
+
+/// How long a credential check result is reused before asking Google again.
+const AUTH_CACHE_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Deserialize)]
+struct Request {
+    cmd: String,
+    #[serde(default)]
+    profile: Option<String>,
+    /// Switch to a profile that asks for confirmation first
+    #[serde(default)]
+    force: bool,
+    /// Switch to a guarded profile
+    #[serde(default)]
+    i_know: bool,
+}
+
+struct Daemon {
+    store: Store,
+    auth_cache: Mutex<HashMap<String, (AuthStatus, Instant)>>,
+    // Activations change gcloud's global state; run them one at a time
+    switch_lock: Mutex<()>,
+    hooks: Hooks,
+    legacy_configs: bool,
+    guard: GuardSettings,
+}
+
+pub fn default_socket_path(store: &Store) -> PathBuf {
+    store.dir().join("daemon.sock")
+}
+
+#[cfg(unix)]
+pub fn run(socket: &Path, hooks: Hooks, legacy_configs: bool, guard: GuardSettings) -> Result<()> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            anyhow::bail!("A daemon is already listening on {}", socket.display());
+        }
+        // Left behind by a daemon that did not shut down cleanly
+        std::fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+    }
+    println!("Listening on {}", socket.display());
+
+    let daemon = Arc::new(Daemon {
+        store: Store::new()?,
+        auth_cache: Mutex::new(HashMap::new()),
+        switch_lock: Mutex::new(()),
+        hooks,
+        legacy_configs,
+        guard,
+    });
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let daemon = Arc::clone(&daemon);
+        std::thread::spawn(move || {
+            let Ok(reader) = stream.try_clone() else {
+                return;
+            };
+            let _ = daemon.serve(BufReader::new(reader), stream);
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run(_socket: &Path, _hooks: Hooks, _legacy_configs: bool, _guard: GuardSettings) -> Result<()> {
+    anyhow::bail!("The daemon needs unix sockets and is not available on this platform")
+}
+
+impl Daemon {
+    fn serve(&self, reader: impl BufRead, mut writer: impl Write) -> Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => match self.handle(&request) {
+                    Ok(result) => json!({ "ok": true, "result": result }),
+                    Err(e) => json!({ "ok": false, "error": format!("{:#}", e) }),
+                },
+                Err(e) => json!({ "ok": false, "error": format!("Invalid request: {}", e) }),
+            };
+            writeln!(writer, "{}", response)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn handle(&self, request: &Request) -> Result<Value> {
+        match request.cmd.as_str() {
+            "list" => self.list(),
+            "current" => self.current(),
+            "switch" => self.switch(request.profile.as_deref().context("switch needs \"profile\"")?, request.force, request.i_know),
+            "auth-status" => self.auth_status(request.profile.as_deref()),
+            other => anyhow::bail!("Unknown command '{}'", other),
+        }
+    }
+
+    fn list(&self) -> Result<Value> {
+        let data = self.store.load_profiles()?;
+        let profiles: Vec<Value> = data
+            .profiles
+            .iter()
+            .map(|(name, p)| {
+                json!({
+                    "name": name,
+                    "active": data.active_profile.as_deref() == Some(name.as_str()),
+                    "user_account": p.user_account,
+                    "user_project": p.user_project,
+                    "adc_account": p.adc_account,
+                    "adc_quota_project": p.adc_quota_project,
+                    "tags": p.tags,
+                    "contexts": p.contexts,
+                    "clusters": p.clusters,
+                    "registries": p.registries,
+                    "confirm_before_activate": p.confirm_before_activate,
+                })
+            })
+            .collect();
+        Ok(Value::Array(profiles))
+    }
+
+    fn current(&self) -> Result<Value> {
+        let data = self.store.load_profiles()?;
+        let Some(name) = data.active_profile else {
+            return Ok(Value::Null);
+        };
+        let Some(p) = data.profiles.get(&name) else {
+            return Ok(Value::Null);
+        };
+        Ok(json!({
+            "name": name,
+            "user_account": p.user_account,
+            "user_project": p.user_project,
+            "adc_account": p.adc_account,
+            "adc_quota_project": p.adc_quota_project,
+            "contexts": p.contexts,
+            "clusters": p.clusters,
+            "registries": p.registries,
+        }))
+    }
+
+    fn switch(&self, name: &str, force: bool, i_know: bool) -> Result<Value> {
+        let _guard = self.switch_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut data = self.store.load_profiles()?;
+        let profile = data
+            .profiles
+            .get(name)
+            .with_context(|| format!("Profile '{}' not found", name))?
+            .clone();
+        if profile.confirm_before_activate && !force {
+            anyhow::bail!("Profile '{}' asks for confirmation before switching; send \"force\": true", name);
+        }
+        if self.guard.is_guarded(name, &profile) && !i_know {
+            anyhow::bail!("Profile '{}' is guarded; send \"i_know\": true", name);
+        }
+        // Re-authentication opens a browser and prompts; the daemon has no terminal for that
+        if let Some((reason, _)) = self.check_auth(&profile.user_account).explain() {
+            anyhow::bail!(
+                "Credentials for '{}': {}; run `gcloud-switch switch {}` to re-authenticate",
+                profile.user_account,
+                reason,
+                name
+            );
+        }
+        gcloud::activate_both(&self.store, name, &profile.user_account, &profile.user_project)?;
+        data.active_profile = Some(name.to_string());
+        self.store.save_profiles(&data)?;
+        self.store.record_activation(name);
+        gcloud::apply_properties(name, &profile.gcloud_properties)?;
+        providers::activate(&profile)?;
+        docker::configure_registries(&profile.registries)?;
+        if self.legacy_configs {
+            legacy::update(&self.store, name, &profile)?;
+        }
+        // Still under the switch lock, so kubectl's current context matches the last switch
+        let mut clusters = Vec::new();
+        let refreshed = gcloud::refresh_clusters(&profile.clusters, &profile.user_account, &profile.user_project, |cluster, result| {
+            clusters.push(json!({
+                "cluster": cluster,
+                "error": result.err().map(|e| format!("{:#}", e)),
+            }));
+            true
+        });
+        if let Err(e) = refreshed {
+            clusters.push(json!({ "cluster": null, "error": format!("{:#}", e) }));
+        }
+        let mut hook_data = hooks::profile_data(name, &profile);
+        hook_data["scope"] = "both".into();
+        self.hooks.spawn(HookEvent::ProfileActivated, hook_data);
+        Ok(json!({ "name": name, "clusters": clusters }))
+    }
+
+    fn auth_status(&self, name: Option<&str>) -> Result<Value> {
+        let data = self.store.load_profiles()?;
+        let names: Vec<&String> = match name {
+            Some(name) => vec![data
+                .profiles
+                .get_key_value(name)
+                .with_context(|| format!("Profile '{}' not found", name))?
+                .0],
+            None => data.profiles.keys().collect(),
+        };
+        let statuses: Vec<Value> = names
+            .into_iter()
+            .map(|name| {
+                let p = &data.profiles[name];
+                json!({
+                    "name": name,
+                    "user": self.account_status(&p.user_account),
+                    "adc": self.account_status(&p.adc_account),
+                })
+            })
+            .collect();
+        Ok(match name {
+            Some(_) => statuses.into_iter().next().unwrap_or(Value::Null),
+            None => Value::Array(statuses),
+        })
+    }
+
+    fn account_status(&self, account: &str) -> Value {
+        if account.is_empty() {
+            return Value::Null;
+        }
+        json!({
+            "account": account,
+            "status": self.check_auth(account).label(),
+            "token_expires_at": gcloud::read_token_expiry(account).ok().flatten(),
+        })
+    }
+
+    /// Credential check through the cache, so repeated queries cost no token exchange.
+    fn check_auth(&self, account: &str) -> AuthStatus {
+        if let Some((status, at)) = self.lock_cache().get(account) {
+            if at.elapsed() < AUTH_CACHE_TTL {
+                return *status;
+            }
+        }
+        let status = gcloud::check_account_auth_blocking(account);
+        // A failed check says nothing about the credentials; try again next time
+        if status != AuthStatus::Unknown {
+            self.lock_cache().insert(account.to_string(), (status, Instant::now()));
+        }
+        status
+    }
+
+    fn lock_cache(&self) -> std::sync::MutexGuard<'_, HashMap<String, (AuthStatus, Instant)>> {
+        self.auth_cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
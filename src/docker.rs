@@ -0,0 +1,73 @@
+//! Docker credential helpers for a profile's registries. Pointing a registry's
+//! `credHelpers` entry at gcloud makes `docker push` / `pull` authenticate as gcloud's
+//! active account, so once the entries exist every switch carries Docker along. This is
+//! what `gcloud auth configure-docker` writes, without spawning gcloud on each switch.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+/// Registry host for a profile entry: a host as given (`gcr.io`, `europe-docker.pkg.dev`),
+/// or an Artifact Registry location (`europe-west1`) expanded to its Docker host.
+pub fn registry_host(entry: &str) -> String {
+    let entry = entry.trim();
+    if entry.contains('.') {
+        entry.to_string()
+    } else {
+        format!("{}-docker.pkg.dev", entry)
+    }
+}
+
+/// Docker's client config: `$DOCKER_CONFIG/config.json`, else `~/.docker/config.json`.
+pub fn config_path() -> Result<PathBuf> {
+    let dir = match std::env::var_os("DOCKER_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir()
+            .context("Could not determine home directory")?
+            .join(".docker"),
+    };
+    Ok(dir.join("config.json"))
+}
+
+/// Make gcloud the credential helper for each registry, leaving the rest of the config
+/// alone. Returns the hosts that were added or changed; the file is only rewritten then.
+pub fn configure_registries(registries: &[String]) -> Result<Vec<String>> {
+    if registries.is_empty() {
+        return Ok(Vec::new());
+    }
+    let path = config_path()?;
+    let mut config = match fs::read_to_string(&path) {
+        Ok(text) if !text.trim().is_empty() => serde_json::from_str::<Value>(&text)
+            .with_context(|| format!("Failed to parse {}", path.display()))?,
+        Ok(_) => Value::Object(Map::new()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Value::Object(Map::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let helpers = config
+        .as_object_mut()
+        .with_context(|| format!("{} is not a JSON object", path.display()))?
+        .entry("credHelpers")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .with_context(|| format!("credHelpers in {} is not a JSON object", path.display()))?;
+    let mut changed = Vec::new();
+    for entry in registries {
+        let host = registry_host(entry);
+        if helpers.get(&host).and_then(Value::as_str) != Some("gcloud") {
+            helpers.insert(host.clone(), Value::String("gcloud".to_string()));
+            changed.push(host);
+        }
+    }
+    if changed.is_empty() {
+        return Ok(changed);
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(&config)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    tracing::info!(path = %path.display(), hosts = ?changed, "configured docker credential helpers");
+    Ok(changed)
+}
@@ -1,18 +1,391 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
 use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt};
 
+use crate::net;
+use crate::profile::Profile;
+use crate::runtime;
 use crate::store::Store;
 
-fn gcloud_config_dir() -> Result<PathBuf> {
-    // gcloud always uses ~/.config/gcloud on all platforms, ignoring XDG/macOS conventions,
-    // unless CLOUDSDK_CONFIG is set.
+/// Name of the gcloud executable. On Windows the SDK ships `gcloud.cmd`, which
+/// `CreateProcess` does not resolve from a bare `gcloud`.
+#[cfg(windows)]
+const GCLOUD_BIN: &str = "gcloud.cmd";
+#[cfg(not(windows))]
+const GCLOUD_BIN: &str = "gcloud";
+
+/// Default for `gcloud_timeout`: how long a non-interactive gcloud command may run.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+static TIMEOUT: OnceLock<Option<Duration>> = OnceLock::new();
+
+/// Token checks that get no answer are retried this many times in all, waiting
+/// `AUTH_RETRY_DELAY` and then twice as long each time.
+const AUTH_ATTEMPTS: u32 = 3;
+const AUTH_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// A cached access token counts as proof of a working login only while it has at least
+/// this many seconds left.
+const FRESH_TOKEN_MARGIN_SECS: i64 = 60;
+
+/// Reports the account an access token belongs to.
+const TOKENINFO_URL: &str = "https://oauth2.googleapis.com/tokeninfo";
+
+/// Default for `auth_cache_ttl`: how long a token check result is reused.
+pub const DEFAULT_AUTH_CACHE_TTL_SECS: u64 = 300;
+
+static AUTH_CACHE_TTL: OnceLock<Duration> = OnceLock::new();
+
+/// How activation hands a profile's ADC to client libraries (`adc_strategy`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdcStrategy {
+    /// Copy the stored ADC over the global application_default_credentials.json.
+    #[default]
+    Copy,
+    /// Leave the global file alone; `gcloud-switch env` points GOOGLE_APPLICATION_CREDENTIALS
+    /// at the stored ADC, so only shells that evaluate it switch.
+    Env,
+}
+
+impl AdcStrategy {
+    pub fn is_copy(&self) -> bool {
+        *self == AdcStrategy::Copy
+    }
+}
+
+static ADC_STRATEGY: OnceLock<AdcStrategy> = OnceLock::new();
+
+/// Whether logins open a browser on this machine (`login_browser`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginBrowser {
+    /// Open one unless this is an SSH session or a Linux session without a display.
+    #[default]
+    Auto,
+    Always,
+    /// Print the sign-in URL and ask for the code instead (`--no-launch-browser`).
+    Never,
+}
+
+impl LoginBrowser {
+    pub fn is_auto(&self) -> bool {
+        *self == LoginBrowser::Auto
+    }
+}
+
+static LOGIN_BROWSER: OnceLock<LoginBrowser> = OnceLock::new();
+
+static COMBINED_REAUTH: OnceLock<bool> = OnceLock::new();
+
+/// Set once at startup from `combined_reauth`.
+pub fn configure_combined_reauth(combined: bool) {
+    let _ = COMBINED_REAUTH.set(combined);
+}
+
+/// Set once at startup from `login_browser`.
+pub fn configure_login_browser(mode: LoginBrowser) {
+    let _ = LOGIN_BROWSER.set(mode);
+}
+
+/// Whether a gcloud login can open a browser here.
+fn can_launch_browser() -> bool {
+    match LOGIN_BROWSER.get().copied().unwrap_or_default() {
+        LoginBrowser::Always => true,
+        LoginBrowser::Never => false,
+        LoginBrowser::Auto => {
+            let var = |name: &str| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+            if var("SSH_CONNECTION") || var("SSH_TTY") {
+                false
+            } else if cfg!(any(target_os = "macos", windows)) {
+                true
+            } else {
+                var("DISPLAY") || var("WAYLAND_DISPLAY")
+            }
+        }
+    }
+}
+
+/// Set once at startup from `adc_strategy`.
+pub fn configure_adc_strategy(strategy: AdcStrategy) {
+    let _ = ADC_STRATEGY.set(strategy);
+}
+
+pub fn adc_strategy() -> AdcStrategy {
+    ADC_STRATEGY.get().copied().unwrap_or_default()
+}
+
+/// Set once at startup from `auth_cache_ttl`; 0 checks every time.
+pub fn configure_auth_cache(secs: u64) {
+    let _ = AUTH_CACHE_TTL.set(Duration::from_secs(secs));
+}
+
+/// Set once at startup from `gcloud_timeout`; 0 lets commands run as long as they take.
+pub fn configure_timeout(secs: u64) {
+    let _ = TIMEOUT.set((secs > 0).then(|| Duration::from_secs(secs)));
+}
+
+fn timeout() -> Option<Duration> {
+    *TIMEOUT.get_or_init(|| Some(Duration::from_secs(DEFAULT_TIMEOUT_SECS)))
+}
+
+/// Start building a gcloud invocation using the platform's executable name.
+fn gcloud() -> Command {
+    Command::new(GCLOUD_BIN)
+}
+
+/// A gcloud command that exited unsuccessfully: the command line and what it printed to
+/// stderr, so the TUI can show the full failure instead of a one-line summary.
+#[derive(Debug, Clone)]
+pub struct CommandFailure {
+    pub command: String,
+    pub status: ExitStatus,
+    pub output: String,
+}
+
+impl CommandFailure {
+    fn new(args: &[&str], status: ExitStatus, stderr: &[u8]) -> Self {
+        Self {
+            command: command_line(args),
+            status,
+            output: String::from_utf8_lossy(stderr).trim_end().to_string(),
+        }
+    }
+}
+
+/// One step of a dry run: what an activation or login would do, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanStep {
+    /// A command line, as it would be run.
+    Command(String),
+    /// Something done without running a command, e.g. copying the ADC file.
+    Other(String),
+}
+
+/// Shell-like: commands as they are, the rest as comments.
+impl fmt::Display for PlanStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanStep::Command(command) => f.write_str(command),
+            PlanStep::Other(what) => write!(f, "# {}", what),
+        }
+    }
+}
+
+fn plan_command(args: &[String]) -> PlanStep {
+    PlanStep::Command(command_line(&as_strs(args)))
+}
+
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn as_strs(args: &[String]) -> Vec<&str> {
+    args.iter().map(String::as_str).collect()
+}
+
+/// The gcloud command line for messages, quoting arguments that contain whitespace.
+fn command_line(args: &[&str]) -> String {
+    let mut command = GCLOUD_BIN.to_string();
+    for arg in args {
+        command.push(' ');
+        if arg.contains(char::is_whitespace) {
+            command.push_str(&format!("'{}'", arg));
+        } else {
+            command.push_str(arg);
+        }
+    }
+    command
+}
+
+/// Log a finished gcloud command; failures at warn level with what it printed to stderr.
+fn log_command(args: &[&str], status: ExitStatus, stderr: &[u8], elapsed: Duration) {
+    let command = command_line(args);
+    if status.success() {
+        tracing::debug!(%command, %status, ?elapsed, "gcloud");
+    } else {
+        let stderr = String::from_utf8_lossy(stderr);
+        tracing::warn!(%command, %status, ?elapsed, stderr = ?stderr.trim_end(), "gcloud failed");
+    }
+}
+
+impl fmt::Display for CommandFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // gcloud prints the actual error last, after any progress output
+        match self.output.lines().rev().map(str::trim).find(|l| !l.is_empty()) {
+            Some(line) => write!(f, "{} failed: {}", self.command, line),
+            None => write!(f, "{} failed ({})", self.command, self.status),
+        }
+    }
+}
+
+impl std::error::Error for CommandFailure {}
+
+/// Run a non-interactive gcloud command, capturing and logging its output. A non-zero
+/// exit is left to the caller; running past `gcloud_timeout` kills it and fails with a
+/// [`CommandFailure`]. Dropping the future (an aborted task) kills it as well.
+async fn output(args: &[&str]) -> Result<std::process::Output> {
+    let start = Instant::now();
+    let mut command = tokio::process::Command::new(GCLOUD_BIN);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Its own process group, so a timeout also stops the Python process the launcher
+    // script starts
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .spawn()
+        .context("Failed to run gcloud (is it installed and in PATH?)")?;
+    let group = ProcessGroup(child.id());
+    let (mut stdout, mut stderr) = (child.stdout.take(), child.stderr.take());
+    // Read while waiting, so a chatty command cannot block on a full pipe
+    let finished = async {
+        tokio::join!(read_all(stdout.as_mut()), read_all(stderr.as_mut()), child.wait())
+    };
+    let finished = match timeout() {
+        Some(limit) => tokio::time::timeout(limit, finished).await.ok(),
+        None => Some(finished.await),
+    };
+    let Some((stdout, stderr, status)) = finished else {
+        drop(group);
+        let _ = child.start_kill();
+        let status = child.wait().await?;
+        let limit = timeout().unwrap_or_default().as_secs();
+        let failure = CommandFailure {
+            command: command_line(args),
+            status,
+            output: format!(
+                "Timed out after {}s and was stopped (gcloud_timeout in gcloud-switch.toml)",
+                limit
+            ),
+        };
+        tracing::warn!(command = %failure.command, elapsed = ?start.elapsed(), "gcloud timed out");
+        return Err(failure.into());
+    };
+    group.finished();
+    let output = std::process::Output { status: status?, stdout, stderr };
+    log_command(args, output.status, &output.stderr, start.elapsed());
+    Ok(output)
+}
+
+async fn read_all(pipe: Option<&mut (impl AsyncRead + Unpin)>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    if let Some(pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buf).await;
+    }
+    buf
+}
+
+/// A running command's process group, killed when this is dropped before the command
+/// finished: on timeout, or when the task waiting for it is aborted.
+struct ProcessGroup(Option<u32>);
+
+impl ProcessGroup {
+    fn finished(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            let _ = Command::new("kill")
+                .args(["-KILL", "--", &format!("-{}", pid)])
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+}
+
+/// `output`, with a non-zero exit turned into a [`CommandFailure`].
+async fn checked_output(args: &[&str]) -> Result<std::process::Output> {
+    let output = output(args).await?;
+    if !output.status.success() {
+        return Err(CommandFailure::new(args, output.status, &output.stderr).into());
+    }
+    Ok(output)
+}
+
+/// `output` for synchronous callers.
+fn run_unchecked(args: &[&str]) -> Result<std::process::Output> {
+    runtime::block_on(output(args))
+}
+
+/// `checked_output` for synchronous callers.
+fn run(args: &[&str]) -> Result<std::process::Output> {
+    runtime::block_on(checked_output(args))
+}
+
+/// Run an interactive gcloud command on the terminal. stderr is passed through as it
+/// arrives (gcloud prompts there) and also kept for the [`CommandFailure`] on error.
+fn run_interactive(args: &[&str]) -> Result<()> {
+    let start = Instant::now();
+    let mut child = gcloud()
+        .args(args)
+        .stdin(std::process::Stdio::inherit())
+        .stdout(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("Failed to run gcloud (is it installed and in PATH?)")?;
+    let mut pipe = child.stderr.take().context("gcloud stderr was not captured")?;
+    let mut captured = Vec::new();
+    let mut buf = [0u8; 1024];
+    let mut terminal = io::stderr();
+    loop {
+        let n = match pipe.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        terminal.write_all(&buf[..n])?;
+        terminal.flush()?;
+        captured.extend_from_slice(&buf[..n]);
+    }
+    let status = child.wait()?;
+    log_command(args, status, &captured, start.elapsed());
+    if !status.success() {
+        return Err(CommandFailure::new(args, status, &captured).into());
+    }
+    Ok(())
+}
+
+/// gcloud's config directory. Respects CLOUDSDK_CONFIG, just like gcloud itself.
+pub fn gcloud_config_dir() -> Result<PathBuf> {
     if let Ok(custom) = std::env::var("CLOUDSDK_CONFIG") {
         return Ok(PathBuf::from(custom));
     }
+    default_gcloud_config_dir()
+}
+
+/// On Windows gcloud keeps its state under %APPDATA%\gcloud.
+#[cfg(windows)]
+fn default_gcloud_config_dir() -> Result<PathBuf> {
+    let appdata = std::env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .or_else(dirs::config_dir)
+        .context("Could not determine %APPDATA% directory")?;
+    Ok(appdata.join("gcloud"))
+}
+
+/// Elsewhere gcloud always uses ~/.config/gcloud, ignoring XDG/macOS conventions.
+#[cfg(not(windows))]
+fn default_gcloud_config_dir() -> Result<PathBuf> {
     let home = dirs::home_dir().context("Could not determine home directory")?;
     Ok(home.join(".config").join("gcloud"))
 }
@@ -34,15 +407,10 @@ pub fn read_active_config() -> Result<Option<String>> {
 /// Create a gcloud configuration without activating it.
 pub fn create_configuration(name: &str, account: &str, project: &str) -> Result<()> {
     // Create config — ignore failure if it already exists
-    let status = Command::new("gcloud")
-        .args(["config", "configurations", "create", name, "--no-activate"])
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status()
-        .context("Failed to run gcloud (is it installed and in PATH?)")?;
+    let status = run_unchecked(&["config", "configurations", "create", name, "--no-activate"])?.status;
 
     // Verify the configuration file actually exists (covers both fresh-create and already-exists)
-    let config_file = configurations_dir()?.join(format!("config_{}", name));
+    let config_file = configuration_path(name)?;
     if !config_file.exists() && !status.success() {
         anyhow::bail!(
             "Failed to create gcloud configuration '{}'. Check that gcloud CLI is working.",
@@ -50,40 +418,105 @@ pub fn create_configuration(name: &str, account: &str, project: &str) -> Result<
         );
     }
 
+    let configuration = format!("--configuration={}", name);
     if !account.is_empty() {
-        let status = Command::new("gcloud")
-            .args(["config", "set", "account", account, &format!("--configuration={}", name)])
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .status()
-            .context("Failed to run gcloud config set account")?;
-        if !status.success() {
-            anyhow::bail!("Failed to set account on gcloud configuration '{}'", name);
-        }
+        run(&["config", "set", "account", account, &configuration])?;
     }
 
     if !project.is_empty() {
-        let status = Command::new("gcloud")
-            .args(["config", "set", "project", project, &format!("--configuration={}", name)])
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .status()
-            .context("Failed to run gcloud config set project")?;
-        if !status.success() {
-            anyhow::bail!("Failed to set project on gcloud configuration '{}'", name);
-        }
+        run(&["config", "set", "project", project, &configuration])?;
     }
 
     Ok(())
 }
 
+/// Make a configuration's account and project match, whether or not it is the active one;
+/// empty values are unset. Only properties that differ are written. Creates the
+/// configuration when it does not exist.
+pub fn write_configuration(name: &str, account: &str, project: &str) -> Result<()> {
+    let Some(current) = read_configuration(name)? else {
+        return create_configuration(name, account, project);
+    };
+    let configuration = format!("--configuration={}", name);
+    for (key, value) in [("account", account), ("project", project)] {
+        let existing = current.get(&format!("core/{}", key)).map(String::as_str).unwrap_or("");
+        if existing == value {
+            continue;
+        }
+        if value.is_empty() {
+            run(&["config", "unset", key, &configuration])?;
+        } else {
+            run(&["config", "set", key, value, &configuration])?;
+        }
+    }
+    Ok(())
+}
+
+/// Properties a snapshot leaves out, since the profile has its own fields for them.
+const PROFILE_PROPERTIES: [&str; 2] = ["core/account", "core/project"];
+
+/// Every property of a configuration except account and project, for a profile snapshot.
+pub fn snapshot_properties(name: &str) -> Result<BTreeMap<String, String>> {
+    let props = read_configuration(name)?
+        .with_context(|| format!("gcloud configuration '{}' does not exist", name))?;
+    Ok(props
+        .into_iter()
+        .filter(|(key, _)| !PROFILE_PROPERTIES.contains(&key.as_str()))
+        .collect())
+}
+
+/// Make configuration `name` hold exactly `snapshot` besides account and project: set
+/// what differs and unset what the snapshot lacks. An empty snapshot (none taken) changes
+/// nothing. Returns the number of properties changed.
+pub fn apply_properties(name: &str, snapshot: &BTreeMap<String, String>) -> Result<usize> {
+    let commands = property_commands(name, snapshot)?;
+    for args in &commands {
+        run(&as_strs(args))?;
+    }
+    Ok(commands.len())
+}
+
+/// Dry run of [`apply_properties`].
+pub fn properties_plan(name: &str, snapshot: &BTreeMap<String, String>) -> Result<Vec<PlanStep>> {
+    Ok(property_commands(name, snapshot)?.iter().map(|args| plan_command(args)).collect())
+}
+
+/// The `config set` / `config unset` calls that bring configuration `name` to `snapshot`.
+fn property_commands(name: &str, snapshot: &BTreeMap<String, String>) -> Result<Vec<Vec<String>>> {
+    if snapshot.is_empty() {
+        return Ok(Vec::new());
+    }
+    let current = read_configuration(name)?.unwrap_or_default();
+    let configuration = format!("--configuration={}", name);
+    let mut commands = Vec::new();
+    for (key, value) in snapshot {
+        if current.get(key) != Some(value) {
+            commands.push(to_args(&["config", "set", key, value, &configuration]));
+        }
+    }
+    for key in current.keys() {
+        if !snapshot.contains_key(key) && !PROFILE_PROPERTIES.contains(&key.as_str()) {
+            commands.push(to_args(&["config", "unset", key, &configuration]));
+        }
+    }
+    Ok(commands)
+}
+
 /// Delete a gcloud configuration.
 pub fn delete_configuration(name: &str) -> Result<()> {
-    let _ = Command::new("gcloud")
-        .args(["config", "configurations", "delete", name, "--quiet"])
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status();
+    let _ = run_unchecked(&["config", "configurations", "delete", name, "--quiet"]);
+    Ok(())
+}
+
+/// Rename a gcloud configuration via `gcloud config configurations rename`.
+pub fn rename_configuration(old: &str, new: &str) -> Result<()> {
+    run(&[
+        "config",
+        "configurations",
+        "rename",
+        old,
+        &format!("--new-name={}", new),
+    ])?;
     Ok(())
 }
 
@@ -95,66 +528,96 @@ fn configurations_dir() -> Result<PathBuf> {
 
 /// Activate a profile's user credentials via gcloud CLI.
 pub fn activate_user(profile_name: &str, account: &str, project: &str) -> Result<()> {
-    // Create configuration if it doesn't exist (ignore error if already exists)
-    let _ = Command::new("gcloud")
-        .args(["config", "configurations", "create", profile_name, "--no-activate"])
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status();
-
-    // Activate the configuration
-    let output = Command::new("gcloud")
-        .args(["config", "configurations", "activate", profile_name])
-        .stdout(std::process::Stdio::null())
-        .output()
-        .context("Failed to activate gcloud configuration")?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!(
-            "gcloud config configurations activate failed for '{}': {}",
-            profile_name,
-            stderr.trim()
-        );
+    activate_user_with_progress(profile_name, account, project, &mut |_| {})
+}
+
+/// A step of an activation, reported to the progress callback as it starts. Each gcloud
+/// call takes a second or more, so a switch can run for several seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationStep {
+    CreateConfig,
+    ActivateConfig,
+    SetAccount,
+    SetProject,
+    CopyAdc,
+}
+
+impl fmt::Display for ActivationStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ActivationStep::CreateConfig => "creating configuration",
+            ActivationStep::ActivateConfig => "activating configuration",
+            ActivationStep::SetAccount => "setting account",
+            ActivationStep::SetProject => "setting project",
+            ActivationStep::CopyAdc => "installing ADC",
+        })
     }
+}
 
-    // Set account and project on the active configuration
-    if !account.is_empty() {
-        let output = Command::new("gcloud")
-            .args(["config", "set", "account", account])
-            .stdout(std::process::Stdio::null())
-            .output()
-            .context("Failed to set gcloud account")?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!(
-                "gcloud config set account failed for '{}': {}",
-                account,
-                stderr.trim()
-            );
+/// [`activate_user`], calling `progress` before each step.
+pub fn activate_user_with_progress(
+    profile_name: &str,
+    account: &str,
+    project: &str,
+    progress: &mut dyn FnMut(ActivationStep),
+) -> Result<()> {
+    for (step, args) in user_activation_commands(profile_name, account, project) {
+        progress(step);
+        if step == ActivationStep::CreateConfig {
+            // Ignore the error if it already exists
+            let _ = run_unchecked(&as_strs(&args));
+        } else {
+            run(&as_strs(&args))?;
         }
     }
+    Ok(())
+}
 
+/// The gcloud calls of [`activate_user`], each with the step it reports: create the
+/// configuration if it doesn't exist, activate it, then set account and project on it.
+fn user_activation_commands(profile_name: &str, account: &str, project: &str) -> Vec<(ActivationStep, Vec<String>)> {
+    let mut commands = Vec::new();
+    if !configuration_path(profile_name).is_ok_and(|p| p.exists()) {
+        commands.push((
+            ActivationStep::CreateConfig,
+            to_args(&["config", "configurations", "create", profile_name, "--no-activate"]),
+        ));
+    }
+    commands.push((ActivationStep::ActivateConfig, to_args(&["config", "configurations", "activate", profile_name])));
+    if !account.is_empty() {
+        commands.push((ActivationStep::SetAccount, to_args(&["config", "set", "account", account])));
+    }
     if !project.is_empty() {
-        let output = Command::new("gcloud")
-            .args(["config", "set", "project", project])
-            .stdout(std::process::Stdio::null())
-            .output()
-            .context("Failed to set gcloud project")?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!(
-                "gcloud config set project failed for '{}': {}",
-                project,
-                stderr.trim()
-            );
-        }
+        commands.push((ActivationStep::SetProject, to_args(&["config", "set", "project", project])));
     }
+    commands
+}
 
-    Ok(())
+/// Dry run of [`activate_user`].
+pub fn user_activation_plan(profile_name: &str, account: &str, project: &str) -> Vec<PlanStep> {
+    user_activation_commands(profile_name, account, project)
+        .iter()
+        .map(|(_, args)| plan_command(args))
+        .collect()
+}
+
+/// Dry run of [`activate_adc`], or with `optional` of the ADC part of [`activate_both`],
+/// which skips a profile without stored ADC instead of failing.
+pub fn adc_activation_plan(store: &Store, profile_name: &str, optional: bool) -> Result<Vec<PlanStep>> {
+    let src = store.adc_path(profile_name);
+    let step = if !src.exists() {
+        (!optional).then(|| PlanStep::Other(format!("fails: no ADC credentials stored for profile '{}'", profile_name)))
+    } else if adc_strategy() == AdcStrategy::Env {
+        (!optional).then(|| PlanStep::Other("nothing: adc_strategy = \"env\" leaves the ADC to `gcloud-switch env`".to_string()))
+    } else {
+        Some(PlanStep::Other(format!("copy {} to {}", src.display(), live_adc_path()?.display())))
+    };
+    Ok(step.into_iter().collect())
 }
 
 /// Activate a profile's ADC credentials.
-/// No gcloud CLI equivalent exists, so this copies the stored ADC JSON directly.
+/// No gcloud CLI equivalent exists, so this copies the stored ADC JSON directly. With the
+/// `env` strategy nothing is copied: shells pick the stored file up through `gcloud-switch env`.
 pub fn activate_adc(store: &Store, profile_name: &str) -> Result<()> {
     let src = store.adc_path(profile_name);
     if !src.exists() {
@@ -163,8 +626,10 @@ pub fn activate_adc(store: &Store, profile_name: &str) -> Result<()> {
             profile_name
         );
     }
-    let config_dir = gcloud_config_dir()?;
-    let dest = config_dir.join("application_default_credentials.json");
+    if adc_strategy() == AdcStrategy::Env {
+        return Ok(());
+    }
+    let dest = live_adc_path()?;
     fs::copy(&src, &dest).with_context(|| {
         format!(
             "Failed to copy ADC from {} to {}",
@@ -182,100 +647,501 @@ pub fn activate_both(
     account: &str,
     project: &str,
 ) -> Result<()> {
-    activate_user(profile_name, account, project)?;
+    activate_both_with_progress(store, profile_name, account, project, &mut |_| {})
+}
+
+/// [`activate_both`], calling `progress` before each step.
+pub fn activate_both_with_progress(
+    store: &Store,
+    profile_name: &str,
+    account: &str,
+    project: &str,
+    progress: &mut dyn FnMut(ActivationStep),
+) -> Result<()> {
+    activate_user_with_progress(profile_name, account, project, progress)?;
     // ADC activation is best-effort if no ADC file exists yet
-    if store.has_adc(profile_name) {
+    if store.has_adc(profile_name) && adc_strategy().is_copy() {
+        progress(ActivationStep::CopyAdc);
         activate_adc(store, profile_name)?;
     }
     Ok(())
 }
 
+/// A GKE cluster from a profile's `clusters` list: `location/name`, or
+/// `project/location/name` when it lives outside the profile's project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GkeCluster {
+    pub project: Option<String>,
+    pub location: String,
+    pub name: String,
+}
+
+impl GkeCluster {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let parts: Vec<&str> = spec.trim().split('/').collect();
+        if parts.iter().any(|p| p.is_empty()) {
+            anyhow::bail!("Invalid cluster '{}': expected location/name or project/location/name", spec);
+        }
+        match parts.as_slice() {
+            [location, name] => Ok(Self {
+                project: None,
+                location: location.to_string(),
+                name: name.to_string(),
+            }),
+            [project, location, name] => Ok(Self {
+                project: Some(project.to_string()),
+                location: location.to_string(),
+                name: name.to_string(),
+            }),
+            _ => anyhow::bail!("Invalid cluster '{}': expected location/name or project/location/name", spec),
+        }
+    }
+}
+
+impl fmt::Display for GkeCluster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.project {
+            Some(project) => write!(f, "{}/{}/{}", project, self.location, self.name),
+            None => write!(f, "{}/{}", self.location, self.name),
+        }
+    }
+}
+
+/// GKE clusters `account` can see in `project`, via `gcloud container clusters list`.
+/// Their specs have no project, since they live in the one asked about.
+pub async fn list_clusters(account: &str, project: &str) -> Result<Vec<GkeCluster>> {
+    net::ensure_online("Listing GKE clusters")?;
+    let output = checked_output(&[
+        "container",
+        "clusters",
+        "list",
+        &format!("--account={}", account),
+        &format!("--project={}", project),
+        "--format=value(location,name)",
+        "--sort-by=location,name",
+    ])
+    .await?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (location, name) = line.trim().split_once(char::is_whitespace)?;
+            Some(GkeCluster {
+                project: None,
+                location: location.to_string(),
+                name: name.trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+pub fn list_clusters_blocking(account: &str, project: &str) -> Result<Vec<GkeCluster>> {
+    runtime::block_on(list_clusters(account, project))
+}
+
+/// Write a cluster's kubectl context via `gcloud container clusters get-credentials`,
+/// which also makes it kubectl's current context. `project` is used when the cluster
+/// names none.
+pub fn get_cluster_credentials(cluster: &GkeCluster, account: &str, project: &str) -> Result<()> {
+    net::ensure_online("Refreshing GKE credentials")?;
+    run(&as_strs(&cluster_credentials_args(cluster, account, project)))?;
+    Ok(())
+}
+
+fn cluster_credentials_args(cluster: &GkeCluster, account: &str, project: &str) -> Vec<String> {
+    let project = cluster.project.as_deref().unwrap_or(project);
+    let mut args = to_args(&[
+        "container",
+        "clusters",
+        "get-credentials",
+        &cluster.name,
+        "--location",
+        &cluster.location,
+        "--quiet",
+    ]);
+    if !project.is_empty() {
+        args.push(format!("--project={}", project));
+    }
+    if !account.is_empty() {
+        args.push(format!("--account={}", account));
+    }
+    args
+}
+
+/// Dry run of [`refresh_clusters`], in the order it goes through them.
+pub fn clusters_plan(clusters: &[String], account: &str, project: &str) -> Vec<PlanStep> {
+    clusters
+        .iter()
+        .rev()
+        .map(|spec| match GkeCluster::parse(spec) {
+            Ok(cluster) => plan_command(&cluster_credentials_args(&cluster, account, project)),
+            Err(e) => PlanStep::Other(format!("skipped: {:#}", e)),
+        })
+        .collect()
+}
+
+/// Refresh the kubectl contexts of a profile's clusters, calling `progress` after each
+/// with the cluster and its result. Runs in reverse so the first listed cluster ends up
+/// as kubectl's current context. Stops early when `progress` returns false. Fails up
+/// front, without touching any cluster, in offline mode.
+pub fn refresh_clusters(
+    clusters: &[String],
+    account: &str,
+    project: &str,
+    mut progress: impl FnMut(&str, Result<()>) -> bool,
+) -> Result<()> {
+    if !clusters.is_empty() {
+        net::ensure_online("Refreshing GKE credentials")?;
+    }
+    for spec in clusters.iter().rev() {
+        let result = GkeCluster::parse(spec)
+            .and_then(|cluster| get_cluster_credentials(&cluster, account, project));
+        if !progress(spec, result) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `gcloud compute ssh` with the terminal fully handed over (ssh needs it for prompts and
+/// the session). Returns ssh's exit status for the caller to pass on.
+pub fn compute_ssh(args: &[&str]) -> Result<ExitStatus> {
+    let start = Instant::now();
+    let status = gcloud()
+        .args(args)
+        .status()
+        .context("Failed to run gcloud (is it installed and in PATH?)")?;
+    tracing::debug!(command = %command_line(args), %status, elapsed = ?start.elapsed(), "gcloud");
+    Ok(status)
+}
+
 /// Re-authenticate user credentials via `gcloud auth login`.
 pub fn reauth_user(account: &str) -> Result<()> {
-    let status = Command::new("gcloud")
-        .args(["auth", "login", &format!("--account={}", account)])
-        .stdin(std::process::Stdio::inherit())
-        .stdout(std::process::Stdio::inherit())
-        .stderr(std::process::Stdio::inherit())
-        .status()
-        .context("Failed to run gcloud auth login")?;
-    if !status.success() {
-        anyhow::bail!("gcloud auth login failed with status {}", status);
+    run_login(&["auth", "login", &format!("--account={}", account)])
+}
+
+/// What to tell the user before an interactive login on a machine without a browser, where
+/// gcloud prints a sign-in URL and waits for a code. `None` when a browser will open.
+pub fn login_hint() -> Option<&'static str> {
+    if can_launch_browser() {
+        return None;
+    }
+    Some("No browser on this machine: open the URL below in a browser anywhere, sign in,\nthen paste the verification code it shows back here.")
+}
+
+/// Run an interactive gcloud login. Where no browser can be opened gcloud prints the
+/// sign-in URL and waits for the code instead of hanging on a browser that never appears.
+fn run_login(args: &[&str]) -> Result<()> {
+    run_interactive(&login_args(args))
+}
+
+/// A login's arguments as [`run_login`] passes them: without a browser, `--no-launch-browser`
+/// instead of `--quiet` (which would answer the code prompt with nothing).
+fn login_args<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    if can_launch_browser() {
+        return args.to_vec();
     }
+    let mut args: Vec<&str> = args.iter().copied().filter(|arg| *arg != "--quiet").collect();
+    args.push("--no-launch-browser");
+    args
+}
+
+/// Have gcloud renew an account's access token from its refresh token (or key) and cache
+/// it, via `gcloud auth print-access-token`. The token itself is discarded.
+pub fn refresh_access_token(account: &str) -> Result<()> {
+    net::ensure_online("Refreshing access tokens")?;
+    run(&["auth", "print-access-token", account, "--quiet"])?;
+    Ok(())
+}
+
+/// An access token for `account` from `gcloud auth print-access-token`.
+pub fn print_access_token(account: &str) -> Result<String> {
+    net::ensure_online("Fetching access tokens")?;
+    let output = run(&["auth", "print-access-token", account, "--quiet"])?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// An access token for the installed ADC from `gcloud auth application-default print-access-token`.
+pub fn print_adc_access_token() -> Result<String> {
+    net::ensure_online("Fetching access tokens")?;
+    let output = run(&["auth", "application-default", "print-access-token", "--quiet"])?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Store a service account key as gcloud credentials for `account`, non-interactively.
+pub fn activate_service_account(account: &str, key_file: &Path) -> Result<()> {
+    let key_file = format!("--key-file={}", key_file.display());
+    run(&["auth", "activate-service-account", account, &key_file, "--quiet"])?;
     Ok(())
 }
 
+/// Log in to a new account via `gcloud auth login` without making it gcloud's active
+/// account, so the current configuration stays untouched.
+pub fn login_new_account() -> Result<()> {
+    run_login(&["auth", "login", "--no-activate"])
+}
+
 /// Re-authenticate ADC via `gcloud auth application-default login`, then store the result.
 pub fn reauth_adc(store: &Store, profile_name: &str, quota_project: &str) -> Result<()> {
-    let status = Command::new("gcloud")
-        .args([
-            "auth",
-            "application-default",
-            "login",
-            "--quiet",
-        ])
-        .status()
-        .context("Failed to run gcloud auth application-default login")?;
-    if !status.success() {
-        anyhow::bail!(
-            "gcloud auth application-default login failed with status {}",
-            status
-        );
+    run_login(&["auth", "application-default", "login", "--quiet"])?;
+    store_fresh_adc(store, profile_name, quota_project)
+}
+
+/// Re-authenticate user and ADC. With `combined_reauth` and one account on both sides, a
+/// single `gcloud auth login --update-adc` covers both, so there is one consent screen.
+pub fn reauth_both(store: &Store, profile_name: &str, account: &str, adc_account: &str, quota_project: &str) -> Result<()> {
+    if COMBINED_REAUTH.get().copied().unwrap_or(false) && account == adc_account {
+        run_login(&["auth", "login", &format!("--account={}", account), "--update-adc"])?;
+        return store_fresh_adc(store, profile_name, quota_project);
     }
+    reauth_user(account)?;
+    reauth_adc(store, profile_name, quota_project)
+}
 
-    // Set quota project
-    let _ = Command::new("gcloud")
-        .args([
-            "auth",
-            "application-default",
-            "set-quota-project",
-            quota_project,
-        ])
-        .status();
+/// Log in again for a profile's user side, ADC side or both (through `reauth_both`).
+pub fn reauth_sides(store: &Store, profile_name: &str, profile: &Profile, user: bool, adc: bool) -> Result<()> {
+    match (user, adc) {
+        (true, true) => reauth_both(
+            store,
+            profile_name,
+            &profile.user_account,
+            &profile.adc_account,
+            &profile.adc_quota_project,
+        ),
+        (true, false) => reauth_user(&profile.user_account),
+        (false, true) => reauth_adc(store, profile_name, &profile.adc_quota_project),
+        (false, false) => Ok(()),
+    }
+}
+
+/// Dry run of [`reauth_sides`]: the interactive logins, and what happens to the ADC after one.
+pub fn reauth_plan(store: &Store, profile_name: &str, profile: &Profile, user: bool, adc: bool) -> Result<Vec<PlanStep>> {
+    let login = |args: &[&str]| PlanStep::Command(command_line(&login_args(args)));
+    let user_login = |account: &str| login(&["auth", "login", &format!("--account={}", account)]);
+    let adc_login = || login(&["auth", "application-default", "login", "--quiet"]);
+    let mut steps = Vec::new();
+    match (user, adc) {
+        (true, true) if COMBINED_REAUTH.get().copied().unwrap_or(false) && profile.user_account == profile.adc_account => {
+            steps.push(login(&["auth", "login", &format!("--account={}", profile.user_account), "--update-adc"]));
+        }
+        (true, true) => steps.extend([user_login(&profile.user_account), adc_login()]),
+        (true, false) => steps.push(user_login(&profile.user_account)),
+        (false, true) => steps.push(adc_login()),
+        (false, false) => {}
+    }
+    if adc {
+        let live = live_adc_path()?;
+        if !profile.adc_quota_project.is_empty() {
+            steps.push(PlanStep::Other(format!(
+                "set quota_project_id = {} in {}",
+                profile.adc_quota_project,
+                live.display()
+            )));
+        }
+        steps.push(PlanStep::Other(format!(
+            "copy {} to {}",
+            live.display(),
+            store.adc_path(profile_name).display()
+        )));
+    }
+    Ok(steps)
+}
+
+/// After a login wrote the live ADC: give it the quota project and keep it for the profile.
+fn store_fresh_adc(store: &Store, profile_name: &str, quota_project: &str) -> Result<()> {
+    // Set quota project: edit the fresh ADC file in place, falling back to gcloud
+    let adc_src = live_adc_path()?;
+    if !quota_project.is_empty() && write_quota_project_id(&adc_src, quota_project).is_err() {
+        let _ = set_adc_quota_project_gcloud(quota_project);
+    }
 
     // Copy the newly created ADC to our store
-    let config_dir = gcloud_config_dir()?;
-    let adc_src = config_dir.join("application_default_credentials.json");
-    if adc_src.exists() {
-        let content = fs::read_to_string(&adc_src)?;
-        let value: serde_json::Value = serde_json::from_str(&content)?;
+    if let Some(value) = load_live_adc()? {
         store.save_adc_json(profile_name, &value)?;
     }
 
     Ok(())
 }
 
-/// Set the ADC quota project via `gcloud auth application-default set-quota-project`.
-pub fn set_adc_quota_project(quota_project: &str) -> Result<()> {
-    let status = Command::new("gcloud")
-        .args([
-            "auth",
-            "application-default",
-            "set-quota-project",
-            quota_project,
-        ])
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .status()
-        .context("Failed to run gcloud auth application-default set-quota-project")?;
-    if !status.success() {
-        anyhow::bail!("gcloud auth application-default set-quota-project failed");
+/// Path of the ADC file gcloud and the client libraries read.
+pub fn live_adc_path() -> Result<PathBuf> {
+    Ok(gcloud_config_dir()?.join("application_default_credentials.json"))
+}
+
+/// The installed ADC, if there is one.
+pub fn load_live_adc() -> Result<Option<serde_json::Value>> {
+    let path = live_adc_path()?;
+    if !path.exists() {
+        return Ok(None);
     }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(value))
+}
+
+/// Store the installed ADC as `profile_name`'s, replacing any stored one.
+pub fn import_live_adc(store: &Store, profile_name: &str) -> Result<()> {
+    let value = load_live_adc()?.with_context(|| {
+        format!(
+            "No ADC installed at {}",
+            live_adc_path().map(|p| p.display().to_string()).unwrap_or_default()
+        )
+    })?;
+    store.save_adc_json(profile_name, &value)
+}
+
+/// The account an ADC blob acts as: a service account's `client_email`, the `account`
+/// gcloud records in newer logins, else the email Google reports for a token minted from
+/// its refresh token. None offline or when there is no way to tell.
+pub fn adc_identity(adc: &serde_json::Value) -> Result<Option<String>> {
+    for key in ["client_email", "account"] {
+        if let Some(email) = adc.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty()) {
+            return Ok(Some(email.to_string()));
+        }
+    }
+    if net::is_offline() || !has_refresh_token(adc) {
+        return Ok(None);
+    }
+    let token = mint_access_token_blocking(adc)?;
+    let info: serde_json::Value = net::client("Identifying the installed ADC")?
+        .get(format!("{}?access_token={}", TOKENINFO_URL, token))
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(info.get("email").and_then(|v| v.as_str()).map(str::to_string))
+}
+
+/// After an import, store the installed ADC for the imported profile whose ADC account is
+/// the one it acts as (preferring gcloud's active configuration), so switching away and
+/// back restores it. `imported` holds (name, ADC account) pairs. Skipped when a profile
+/// already stores this ADC. Returns the profile it was stored for.
+pub fn adopt_live_adc(store: &Store, imported: &[(String, String)]) -> Result<Option<String>> {
+    let Some(live) = load_live_adc()? else {
+        return Ok(None);
+    };
+    let data = store.load_profiles()?;
+    let owned = data
+        .profiles
+        .keys()
+        .any(|name| store.load_adc_json(name).ok().flatten().is_some_and(|stored| same_adc(&stored, &live)));
+    if owned {
+        return Ok(None);
+    }
+    let Some(identity) = adc_identity(&live)? else {
+        return Ok(None);
+    };
+    let matching: Vec<&String> = imported
+        .iter()
+        .filter(|(name, account)| *account == identity && !store.adc_path(name).exists())
+        .map(|(name, _)| name)
+        .collect();
+    let active = read_active_config().ok().flatten();
+    let Some(name) = matching
+        .iter()
+        .find(|name| active.as_deref() == Some(name.as_str()))
+        .or(matching.first())
+    else {
+        return Ok(None);
+    };
+    store.save_adc_json(name, &live)?;
+    Ok(Some(name.to_string()))
+}
+
+/// Whether two ADC blobs hold the same credentials. The quota project is a setting on
+/// top of them, so a differing `quota_project_id` does not count.
+pub fn same_adc(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    let strip = |v: &serde_json::Value| {
+        let mut v = v.clone();
+        if let Some(obj) = v.as_object_mut() {
+            obj.remove("quota_project_id");
+        }
+        v
+    };
+    strip(a) == strip(b)
+}
+
+/// One-line summary of an ADC blob: its type, the service account if it names one, and
+/// the quota project.
+pub fn describe_adc(adc: &serde_json::Value) -> String {
+    let mut parts = vec![credential_type(adc).to_string()];
+    if let Some(email) = adc.get("client_email").and_then(|v| v.as_str()) {
+        parts.push(email.to_string());
+    }
+    if let Some(url) = adc.get("service_account_impersonation_url").and_then(|v| v.as_str()) {
+        // .../serviceAccounts/<email>:generateAccessToken
+        let target = url.rsplit('/').next().unwrap_or(url);
+        parts.push(format!("impersonating {}", target.trim_end_matches(":generateAccessToken")));
+    }
+    if let Some(quota) = adc.get("quota_project_id").and_then(|v| v.as_str()) {
+        parts.push(format!("quota project {}", quota));
+    }
+    parts.join(", ")
+}
+
+/// Set `quota_project_id` in an ADC JSON file, which is all `set-quota-project` does
+/// once it has verified the project.
+fn write_quota_project_id(path: &Path, quota_project: &str) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut value: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    value
+        .as_object_mut()
+        .context("ADC file is not a JSON object")?
+        .insert(
+            "quota_project_id".to_string(),
+            serde_json::Value::String(quota_project.to_string()),
+        );
+    fs::write(path, serde_json::to_string_pretty(&value)?)?;
+    Ok(())
+}
+
+/// Set the ADC quota project for a profile by editing the JSON directly: the stored copy,
+/// and the installed ADC too when `update_live` is set. If the installed file can't be
+/// edited in place, falls back to `gcloud auth application-default set-quota-project`.
+pub fn set_adc_quota_project(
+    store: &Store,
+    profile_name: &str,
+    quota_project: &str,
+    update_live: bool,
+) -> Result<()> {
+    if let Some(mut value) = store.load_adc_json(profile_name)? {
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(
+                "quota_project_id".to_string(),
+                serde_json::Value::String(quota_project.to_string()),
+            );
+            store.save_adc_json(profile_name, &value)?;
+        }
+    }
+    if update_live && write_quota_project_id(&live_adc_path()?, quota_project).is_err() {
+        set_adc_quota_project_gcloud(quota_project)?;
+    }
+    Ok(())
+}
+
+/// Set the ADC quota project via `gcloud auth application-default set-quota-project`.
+fn set_adc_quota_project_gcloud(quota_project: &str) -> Result<()> {
+    run(&[
+        "auth",
+        "application-default",
+        "set-quota-project",
+        quota_project,
+    ])?;
     Ok(())
 }
 
 /// List projects accessible by a given account via `gcloud projects list`.
-pub fn list_projects_for_account(account: &str) -> Result<Vec<String>> {
-    let output = Command::new("gcloud")
-        .args([
-            "projects",
-            "list",
-            &format!("--account={}", account),
-            "--format=value(projectId)",
-            "--sort-by=projectId",
-        ])
-        .output()
-        .context("Failed to run gcloud projects list")?;
+pub async fn list_projects_for_account(account: &str) -> Result<Vec<String>> {
+    let output = output(&[
+        "projects",
+        "list",
+        &format!("--account={}", account),
+        "--format=value(projectId)",
+        "--sort-by=projectId",
+    ])
+    .await?;
     if !output.status.success() {
         return Ok(Vec::new());
     }
@@ -287,6 +1153,87 @@ pub fn list_projects_for_account(account: &str) -> Result<Vec<String>> {
         .collect())
 }
 
+/// Compute Engine regions or zones (`gcloud compute regions|zones list`), from state.toml
+/// or else listed as `account` in `project` and remembered. They are the same for every
+/// project, so one list serves all profiles. Empty if gcloud can't list them, or offline.
+pub async fn compute_locations(store: &Store, zones: bool, account: &str, project: &str) -> Result<Vec<String>> {
+    let cached = store
+        .load_state()
+        .map(|state| if zones { state.compute_zones } else { state.compute_regions })
+        .unwrap_or_default();
+    if !cached.is_empty() {
+        return Ok(cached);
+    }
+    if net::is_offline() {
+        return Ok(Vec::new());
+    }
+    let mut args = vec![
+        "compute",
+        if zones { "zones" } else { "regions" },
+        "list",
+        "--format=value(name)",
+        "--sort-by=name",
+    ];
+    let account_arg = format!("--account={}", account);
+    let project_arg = format!("--project={}", project);
+    if !account.is_empty() {
+        args.push(&account_arg);
+    }
+    if !project.is_empty() {
+        args.push(&project_arg);
+    }
+    let output = output(&args).await?;
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+    let locations: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+    if !locations.is_empty() {
+        store.update_state(|state| {
+            *if zones { &mut state.compute_zones } else { &mut state.compute_regions } = locations.clone();
+        })?;
+    }
+    Ok(locations)
+}
+
+/// A project's number as resolved before, from state.toml.
+pub fn cached_project_number(store: &Store, project: &str) -> Option<String> {
+    store.load_state().ok()?.project_numbers.remove(project)
+}
+
+/// A project's number (which many APIs and IAM bindings want instead of its id), from
+/// state.toml or else `gcloud projects describe` as `account`, and remembered. None if
+/// the project is not visible to the account, or offline.
+pub async fn project_number(store: &Store, account: &str, project: &str) -> Result<Option<String>> {
+    if let Some(number) = cached_project_number(store, project) {
+        return Ok(Some(number));
+    }
+    if net::is_offline() {
+        return Ok(None);
+    }
+    let mut args = vec!["projects", "describe", project, "--format=value(projectNumber)"];
+    let account_arg = format!("--account={}", account);
+    if !account.is_empty() {
+        args.push(&account_arg);
+    }
+    let output = output(&args).await?;
+    let number = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || number.is_empty() {
+        return Ok(None);
+    }
+    store.update_state(|state| {
+        state.project_numbers.insert(project.to_string(), number.clone());
+    })?;
+    Ok(Some(number))
+}
+
+pub fn project_number_blocking(store: &Store, account: &str, project: &str) -> Result<Option<String>> {
+    runtime::block_on(project_number(store, account, project))
+}
+
 /// Read credentials for an account from gcloud's credentials.db.
 pub fn read_gcloud_credentials(account: &str) -> Result<Option<serde_json::Value>> {
     let db_path = gcloud_config_dir()?.join("credentials.db");
@@ -309,8 +1256,146 @@ pub fn read_gcloud_credentials(account: &str) -> Result<Option<serde_json::Value
     }
 }
 
-/// Validate a refresh token by attempting a token exchange.
-pub fn validate_token_blocking(credentials: &serde_json::Value) -> Result<bool> {
+/// Outcome of checking an account's stored gcloud credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthStatus {
+    Valid,
+    /// Google rejected the refresh token: expired, revoked by the user, or signed out by a
+    /// password change or session policy.
+    Invalid,
+    /// Google refused the account or its OAuth client outright (account deleted or
+    /// suspended, client blocked by the organization's admin).
+    Revoked,
+    /// No usable credentials in credentials.db: never logged in, or the entry lacks the
+    /// client id, secret or refresh token.
+    Missing,
+    /// Credential type without a refresh token to validate (service account, workload identity).
+    NotApplicable,
+    /// Not checked because offline mode is on and gcloud has no fresh access token cached.
+    Unchecked,
+    /// The check itself failed (network error, token endpoint unavailable), so the
+    /// credentials may well be fine.
+    Unknown,
+}
+
+impl AuthStatus {
+    /// Whether activation should trigger an interactive `gcloud auth login` first.
+    pub fn needs_reauth(self) -> bool {
+        matches!(self, AuthStatus::Invalid | AuthStatus::Revoked | AuthStatus::Missing)
+    }
+
+    /// Why credentials that need a login are unusable, and what to do about it.
+    pub fn explain(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            AuthStatus::Invalid => Some(("login expired", "activate to log in again")),
+            AuthStatus::Revoked => Some((
+                "access revoked by admin",
+                "ask your Google Workspace admin to restore access, then log in again",
+            )),
+            AuthStatus::Missing => Some(("never logged in", "activate to log in")),
+            _ => None,
+        }
+    }
+
+    /// Machine-readable name, as used by the daemon protocol.
+    pub fn label(self) -> &'static str {
+        match self {
+            AuthStatus::Valid => "valid",
+            AuthStatus::Invalid => "invalid",
+            AuthStatus::Revoked => "revoked",
+            AuthStatus::Missing => "missing",
+            AuthStatus::NotApplicable => "not-applicable",
+            AuthStatus::Unchecked => "unchecked",
+            AuthStatus::Unknown => "unknown",
+        }
+    }
+}
+
+/// The `type` field of a credentials blob. User logins predating the field are `authorized_user`.
+fn credential_type(credentials: &serde_json::Value) -> &str {
+    credentials
+        .get("type")
+        .and_then(|v| v.as_str())
+        .unwrap_or("authorized_user")
+}
+
+/// Validate a refresh token by attempting a token exchange: Valid, or Invalid / Revoked
+/// from the endpoint's OAuth error. Errors mean no answer (network, timeout, 5xx, rate limit).
+pub async fn validate_token(credentials: &serde_json::Value) -> Result<AuthStatus> {
+    let resp = refresh_grant(credentials, "Token validation").await?;
+    let status = resp.status();
+    if status.is_success() {
+        Ok(AuthStatus::Valid)
+    } else if status.is_server_error()
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+    {
+        anyhow::bail!("Token endpoint answered {}", status)
+    } else {
+        let body: serde_json::Value = resp.json().await.unwrap_or_default();
+        Ok(classify_rejection(&body))
+    }
+}
+
+/// Tell an OAuth error response that locks the account or client out (`invalid_client`,
+/// `unauthorized_client`, `access_denied`, `admin_policy_enforced`, or an `invalid_grant`
+/// naming a deleted, disabled or suspended account) from a plain expired token.
+fn classify_rejection(body: &serde_json::Value) -> AuthStatus {
+    let field = |key: &str| body.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_lowercase();
+    let (error, description) = (field("error"), field("error_description"));
+    tracing::debug!(error, description, subtype = field("error_subtype"), "token rejected");
+    let locked_out = matches!(
+        error.as_str(),
+        "invalid_client" | "unauthorized_client" | "access_denied" | "admin_policy_enforced"
+    ) || ["deleted", "disabled", "suspended"].iter().any(|word| description.contains(word));
+    if locked_out {
+        AuthStatus::Revoked
+    } else {
+        AuthStatus::Invalid
+    }
+}
+
+/// Whether a credentials blob holds a refresh token this tool can exchange itself.
+pub fn has_refresh_token(credentials: &serde_json::Value) -> bool {
+    matches!(
+        credential_type(credentials),
+        "authorized_user" | "external_account_authorized_user"
+    )
+}
+
+/// Mint an access token from a refresh token in-process, without gcloud's start-up time.
+/// Only for blobs where `has_refresh_token` holds.
+pub async fn mint_access_token(credentials: &serde_json::Value) -> Result<String> {
+    if !has_refresh_token(credentials) {
+        anyhow::bail!(
+            "{} credentials have no refresh token to exchange",
+            credential_type(credentials)
+        );
+    }
+    let resp = refresh_grant(credentials, "Fetching access tokens").await?;
+    let status = resp.status();
+    let body: serde_json::Value = resp.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let reason = body
+            .get("error_description")
+            .or_else(|| body.get("error"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("no details");
+        anyhow::bail!("Token refresh failed ({}): {}", status, reason);
+    }
+    body.get("access_token")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .context("Token response has no access_token")
+}
+
+pub fn mint_access_token_blocking(credentials: &serde_json::Value) -> Result<String> {
+    runtime::block_on(mint_access_token(credentials))
+}
+
+/// POST a refresh token grant for `credentials` to their token endpoint.
+async fn refresh_grant(credentials: &serde_json::Value, what: &str) -> Result<reqwest::Response> {
     let client_id = credentials
         .get("client_id")
         .and_then(|v| v.as_str())
@@ -323,12 +1408,15 @@ pub fn validate_token_blocking(credentials: &serde_json::Value) -> Result<bool>
         .get("refresh_token")
         .and_then(|v| v.as_str())
         .context("credentials missing refresh_token")?;
+    // external_account_authorized_user blobs name the endpoint token_url
     let token_uri = credentials
         .get("token_uri")
+        .or_else(|| credentials.get("token_url"))
         .and_then(|v| v.as_str())
         .unwrap_or("https://oauth2.googleapis.com/token");
 
-    let client = reqwest::blocking::Client::new();
+    let client = net::async_client(what)?;
+    let start = Instant::now();
     let resp = client
         .post(token_uri)
         .form(&[
@@ -337,22 +1425,121 @@ pub fn validate_token_blocking(credentials: &serde_json::Value) -> Result<bool>
             ("refresh_token", refresh_token),
             ("grant_type", "refresh_token"),
         ])
-        .send()?;
+        .send()
+        .await?;
+    tracing::debug!(token_uri, status = %resp.status(), elapsed = ?start.elapsed(), "token exchange");
 
-    Ok(resp.status().is_success())
+    Ok(resp)
 }
 
 /// Check whether an account's gcloud credentials are valid.
-/// Returns false on any error (missing from DB, invalid token, network issue).
-/// Runs the blocking HTTP call on a dedicated thread to keep the main thread free.
-pub fn check_account_auth(account: &str) -> bool {
+/// Returns Missing when they are not in the DB (or lack a client id, secret or refresh
+/// token), Invalid or Revoked when the token endpoint rejects them, Unknown when the DB can't be read or the endpoint can't be reached after
+/// `AUTH_ATTEMPTS` tries, NotApplicable for credential types that have no refresh token to
+/// exchange, and Unchecked in offline mode. A fresh access token in gcloud's cache makes
+/// them Valid without a network call, offline too.
+/// Each token exchange is bounded by `network_timeout`; the TUI runs these as tasks.
+pub async fn check_account_auth(account: &str) -> AuthStatus {
+    if net::is_offline() {
+        return if has_fresh_token(account) { AuthStatus::Valid } else { AuthStatus::Unchecked };
+    }
+    match read_gcloud_credentials(account) {
+        Ok(Some(creds)) => check_credentials(account, &creds).await,
+        Ok(None) => AuthStatus::Missing,
+        Err(e) => {
+            tracing::debug!(account, error = %format!("{:#}", e), "credentials unreadable");
+            AuthStatus::Unknown
+        }
+    }
+}
+
+/// `check_account_auth`, answered from state.toml when the same refresh token was found
+/// valid or invalid less than `auth_cache_ttl` ago. For quick launches; the daemon and the
+/// notifier, which poll, keep checking for real.
+pub async fn check_account_auth_cached(store: &Store, account: &str) -> AuthStatus {
+    let ttl = AUTH_CACHE_TTL.get().copied().unwrap_or(Duration::from_secs(DEFAULT_AUTH_CACHE_TTL_SECS));
+    if net::is_offline() || ttl.is_zero() {
+        return check_account_auth(account).await;
+    }
     let creds = match read_gcloud_credentials(account) {
-        Ok(Some(c)) => c,
-        _ => return false,
+        Ok(Some(creds)) if has_refresh_token(&creds) => creds,
+        _ => return check_account_auth(account).await,
+    };
+    let fingerprint = {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        creds.get("refresh_token").and_then(|v| v.as_str()).hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
     };
-    std::thread::spawn(move || validate_token_blocking(&creds).unwrap_or(false))
-        .join()
-        .unwrap_or(false)
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let cached = store.load_state().ok().and_then(|mut state| state.auth.remove(account));
+    if let Some(record) = cached {
+        if record.fingerprint == fingerprint && now.saturating_sub(record.checked_at) < ttl.as_secs() {
+            tracing::debug!(account, status = record.status.label(), age = now.saturating_sub(record.checked_at), "auth from state.toml");
+            return record.status;
+        }
+    }
+
+    let status = check_credentials(account, &creds).await;
+    // Failed checks are not worth remembering
+    if matches!(status, AuthStatus::Valid | AuthStatus::Invalid | AuthStatus::Revoked | AuthStatus::Missing) {
+        let saved = store.update_state(|state| {
+            state.auth.insert(
+                account.to_string(),
+                crate::store::AuthRecord { status, checked_at: now, fingerprint },
+            );
+        });
+        if let Err(e) = saved {
+            tracing::warn!(error = %format!("{:#}", e), "could not save state.toml");
+        }
+    }
+    status
+}
+
+pub fn check_account_auth_blocking(account: &str) -> AuthStatus {
+    runtime::block_on(check_account_auth(account))
+}
+
+pub fn check_account_auth_cached_blocking(store: &Store, account: &str) -> AuthStatus {
+    runtime::block_on(check_account_auth_cached(store, account))
+}
+
+/// Validate credentials read from credentials.db, retrying when there is no answer.
+async fn check_credentials(account: &str, creds: &serde_json::Value) -> AuthStatus {
+    // Service accounts sign their own tokens from a private key and external accounts
+    // exchange a subject token at STS; neither can be checked with a refresh grant.
+    if !has_refresh_token(creds) {
+        return AuthStatus::NotApplicable;
+    }
+    if has_fresh_token(account) {
+        tracing::debug!(account, "cached access token still fresh");
+        return AuthStatus::Valid;
+    }
+    // A blob without these cannot be exchanged however often it is retried
+    let incomplete = ["client_id", "client_secret", "refresh_token"]
+        .iter()
+        .any(|key| creds.get(key).and_then(|v| v.as_str()).is_none_or(str::is_empty));
+    if incomplete {
+        return AuthStatus::Missing;
+    }
+    let mut delay = AUTH_RETRY_DELAY;
+    for attempt in 1..=AUTH_ATTEMPTS {
+        match validate_token(creds).await {
+            Ok(status) => return status,
+            Err(e) => {
+                tracing::debug!(account, attempt, error = %format!("{:#}", e), "token check failed");
+                if attempt < AUTH_ATTEMPTS {
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        }
+    }
+    AuthStatus::Unknown
 }
 
 /// List all account emails that have stored credentials in credentials.db.
@@ -374,6 +1561,127 @@ pub fn list_authenticated_accounts() -> Result<Vec<String>> {
     Ok(accounts)
 }
 
+/// The Cloud Console dashboard scoped to `project`. With `account` the URL carries it as
+/// the `authuser` hint, so a browser signed in to several Google accounts picks that one.
+pub fn console_url(project: &str, account: Option<&str>) -> String {
+    let mut url = reqwest::Url::parse("https://console.cloud.google.com/home/dashboard").expect("valid console URL");
+    if !project.is_empty() {
+        url.query_pairs_mut().append_pair("project", project);
+    }
+    if let Some(account) = account.filter(|a| !a.is_empty()) {
+        url.query_pairs_mut().append_pair("authuser", account);
+    }
+    url.into()
+}
+
+/// Path of the file backing a named gcloud configuration (`configurations/config_<name>`).
+pub fn configuration_path(name: &str) -> Result<PathBuf> {
+    Ok(configurations_dir()?.join(format!("config_{}", name)))
+}
+
+/// Read all properties of a gcloud configuration as `section/key` (e.g. `compute/region`).
+/// Returns None if the configuration file doesn't exist.
+pub fn read_configuration(name: &str) -> Result<Option<BTreeMap<String, String>>> {
+    let path = configuration_path(name)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(Some(parse_properties(&content)))
+}
+
+/// Parse gcloud's INI-style properties file into `section/key` → value.
+fn parse_properties(content: &str) -> BTreeMap<String, String> {
+    let mut props = BTreeMap::new();
+    let mut section = String::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            props.insert(
+                format!("{}/{}", section, key.trim()),
+                value.trim().to_string(),
+            );
+        }
+    }
+    props
+}
+
+/// Expiry (unix seconds) of the cached access token for an account, from gcloud's
+/// access_tokens.db. None if no token is cached. Columns are looked up by name, since
+/// gcloud added `rapt_token` and `id_token` over time; when `token_expiry` is empty (as
+/// some layouts leave it for ID-token-only rows) the ID token's `exp` claim is used.
+pub fn read_token_expiry(account: &str) -> Result<Option<i64>> {
+    let db_path = gcloud_config_dir()?.join("access_tokens.db");
+    if !db_path.exists() {
+        return Ok(None);
+    }
+    let conn = Connection::open_with_flags(&db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open access_tokens.db at {}", db_path.display()))?;
+    let mut stmt = conn.prepare("SELECT * FROM access_tokens WHERE account_id = ?1")?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+    let text = |row: &rusqlite::Row, name: &str| -> Option<String> {
+        let index = columns.iter().position(|c| c == name)?;
+        row.get::<_, Option<String>>(index).ok().flatten()
+    };
+    let expiry = stmt
+        .query_row(rusqlite::params![account], |row| {
+            Ok(text(row, "token_expiry")
+                .as_deref()
+                .and_then(parse_utc_timestamp)
+                .or_else(|| text(row, "id_token").as_deref().and_then(jwt_expiry)))
+        })
+        .ok()
+        .flatten();
+    Ok(expiry)
+}
+
+/// Whether gcloud has an access token for `account` that is good for a while yet.
+fn has_fresh_token(account: &str) -> bool {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    read_token_expiry(account)
+        .ok()
+        .flatten()
+        .is_some_and(|expiry| expiry - now > FRESH_TOKEN_MARGIN_SECS)
+}
+
+/// The `exp` claim of a JWT, without verifying its signature.
+fn jwt_expiry(token: &str) -> Option<i64> {
+    use base64::Engine;
+    let payload = token.split('.').nth(1)?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    claims.get("exp")?.as_i64()
+}
+
+/// Parse a naive UTC timestamp as written by gcloud (`YYYY-MM-DD HH:MM:SS[.ffffff]`)
+/// into unix seconds.
+fn parse_utc_timestamp(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let num = |range: std::ops::Range<usize>| s.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hour, min, sec) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    // Days from civil date (Howard Hinnant's algorithm)
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    Some(days * 86400 + hour * 3600 + min * 60 + sec)
+}
+
 /// Import existing gcloud configurations as profiles.
 pub fn discover_existing_configs() -> Result<Vec<(String, String, String)>> {
     let dir = match configurations_dir() {
@@ -387,17 +1695,9 @@ pub fn discover_existing_configs() -> Result<Vec<(String, String, String)>> {
             let file_name = entry.file_name().to_string_lossy().to_string();
             if let Some(name) = file_name.strip_prefix("config_") {
                 if let Ok(content) = fs::read_to_string(entry.path()) {
-                    let mut account = String::new();
-                    let mut project = String::new();
-                    for line in content.lines() {
-                        let line = line.trim();
-                        if let Some(val) = line.strip_prefix("account = ") {
-                            account = val.trim().to_string();
-                        }
-                        if let Some(val) = line.strip_prefix("project = ") {
-                            project = val.trim().to_string();
-                        }
-                    }
+                    let props = parse_properties(&content);
+                    let account = props.get("core/account").cloned().unwrap_or_default();
+                    let project = props.get("core/project").cloned().unwrap_or_default();
                     results.push((name.to_string(), account, project));
                 }
             }
@@ -0,0 +1,149 @@
+//! User hooks: external commands run on lifecycle events, configured per event in the
+//! `[hooks]` section of gcloud-switch.toml. Each command runs through the shell with the
+//! event as a JSON object on stdin and its name in GCLOUD_SWITCH_EVENT.
+
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use gcloud_switch::profile::{self, Profile};
+use gcloud_switch::sync::Conflict;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// A profile (or its user or ADC half) was activated.
+    ProfileActivated,
+    /// A profile was created, by `add`, the TUI form or an import.
+    ProfileAdded,
+    /// `gcloud auth login` / `application-default login` finished for a profile.
+    ReauthCompleted,
+    /// A sync pull merged the remote's profiles.
+    SyncPulled,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::ProfileActivated => "profile-activated",
+            HookEvent::ProfileAdded => "profile-added",
+            HookEvent::ReauthCompleted => "reauth-completed",
+            HookEvent::SyncPulled => "sync-pulled",
+        }
+    }
+}
+
+/// Commands per event name, e.g. `profile-activated = ["~/bin/refresh-kubeconfig"]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Hooks(BTreeMap<String, Vec<String>>);
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn commands(&self, event: HookEvent) -> &[String] {
+        self.0.get(event.name()).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Run the event's hooks to completion, their output going to the terminal. A failing
+    /// hook is reported but does not fail the command that triggered it.
+    pub fn run(&self, event: HookEvent, data: Value) {
+        let payload = payload(event, data);
+        for command in self.commands(event) {
+            if let Err(e) = run_hook(command, event, &payload, false) {
+                eprintln!("⚠️  Hook for {} failed: {:#}", event.name(), e);
+            }
+        }
+    }
+
+    /// Run the event's hooks on a background thread, for the TUI and the daemon. Their
+    /// output would draw over the screen, so it is discarded; failures go to the log.
+    pub fn spawn(&self, event: HookEvent, data: Value) {
+        let commands = self.commands(event).to_vec();
+        if commands.is_empty() {
+            return;
+        }
+        let payload = payload(event, data);
+        std::thread::spawn(move || {
+            for command in &commands {
+                if let Err(e) = run_hook(command, event, &payload, true) {
+                    tracing::warn!(event = event.name(), command, error = %format!("{:#}", e), "hook failed");
+                }
+            }
+        });
+    }
+}
+
+/// Event data for a profile: its name and fields.
+pub fn profile_data(name: &str, profile: &Profile) -> Value {
+    json!({
+        "profile": name,
+        "user_account": profile.user_account,
+        "user_project": profile.user_project,
+        "adc_account": profile.adc_account,
+        "adc_quota_project": profile.adc_quota_project,
+        "tags": profile.tags,
+        "contexts": profile.contexts,
+        "clusters": profile.clusters,
+        "registries": profile.registries,
+    })
+}
+
+/// Event data for a sync pull: the profiles left in conflict (changed on both sides).
+pub fn pull_data(conflicts: &[Conflict]) -> Value {
+    json!({ "conflicts": conflicts.iter().map(|c| c.name.as_str()).collect::<Vec<_>>() })
+}
+
+/// `data` plus the event name and time.
+fn payload(event: HookEvent, data: Value) -> String {
+    let mut object = json!({ "event": event.name(), "timestamp": profile::now() });
+    if let (Some(object), Value::Object(data)) = (object.as_object_mut(), data) {
+        object.extend(data);
+    }
+    object.to_string()
+}
+
+fn run_hook(command: &str, event: HookEvent, payload: &str, quiet: bool) -> Result<()> {
+    let start = Instant::now();
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell
+        .arg(command)
+        .env("GCLOUD_SWITCH_EVENT", event.name())
+        .stdin(Stdio::piped());
+    if quiet {
+        shell.stdout(Stdio::null()).stderr(Stdio::piped());
+    }
+    let mut child = shell.spawn().with_context(|| format!("Failed to run '{}'", command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores its input may exit before reading it
+        if let Err(e) = stdin.write_all(payload.as_bytes()) {
+            if e.kind() != ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+    }
+    let output = child.wait_with_output()?;
+    tracing::debug!(event = event.name(), command, status = %output.status, elapsed = ?start.elapsed(), "hook");
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.lines().rev().map(str::trim).find(|l| !l.is_empty()) {
+            Some(line) => anyhow::bail!("'{}' exited with {}: {}", command, output.status, line),
+            None => anyhow::bail!("'{}' exited with {}", command, output.status),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! `gcloud-switch run`: a throwaway gcloud config directory holding one profile, for
+//! running a single command against it without touching the real gcloud home. The
+//! directory has the profile's configuration (as the active one), the user account's
+//! credentials and the profile's stored ADC, and is removed again when dropped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+
+use crate::gcloud;
+use crate::profile::Profile;
+use crate::store::Store;
+
+/// Tables copied from gcloud's credential databases, keyed by `account_id`.
+const CREDENTIAL_DBS: [(&str, &str); 2] = [("credentials.db", "credentials"), ("access_tokens.db", "access_tokens")];
+
+/// A temporary CLOUDSDK_CONFIG for one profile. Dropping it deletes the directory.
+pub struct IsolatedConfig {
+    dir: PathBuf,
+    adc: Option<PathBuf>,
+}
+
+impl IsolatedConfig {
+    /// Build the directory for `name` under the system temp dir, readable only by the user.
+    /// Its name is random and it must not exist yet, so nobody can prepare it beforehand.
+    pub fn create(store: &Store, name: &str, profile: &Profile) -> Result<Self> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("gcloud-switch-run-");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(fs::Permissions::from_mode(0o700));
+        }
+        let dir = builder
+            .tempdir()
+            .context("Failed to create a temporary gcloud config directory")?
+            .keep();
+        let mut config = Self { dir, adc: None };
+        config.populate(store, name, profile)?;
+        Ok(config)
+    }
+
+    /// The directory to pass as CLOUDSDK_CONFIG.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The profile's ADC inside the directory, if it has one stored.
+    pub fn adc_path(&self) -> Option<&Path> {
+        self.adc.as_deref()
+    }
+
+    fn populate(&mut self, store: &Store, name: &str, profile: &Profile) -> Result<()> {
+        // The profile's gcloud configuration with its extra properties, else one from its fields
+        let configurations = self.dir.join("configurations");
+        fs::create_dir(&configurations)?;
+        let target = configurations.join(format!("config_{}", name));
+        let source = gcloud::configuration_path(name)?;
+        if source.exists() {
+            fs::copy(&source, &target).with_context(|| format!("Failed to copy {}", source.display()))?;
+        } else {
+            let mut content = format!("[core]\naccount = {}\n", profile.user_account);
+            if !profile.user_project.is_empty() {
+                content.push_str(&format!("project = {}\n", profile.user_project));
+            }
+            fs::write(&target, content)?;
+        }
+        fs::write(self.dir.join("active_config"), name)?;
+
+        // Only the user account's rows, so the command cannot reach other logins
+        let source_dir = gcloud::gcloud_config_dir()?;
+        for (file, table) in CREDENTIAL_DBS {
+            let source = source_dir.join(file);
+            if source.exists() && !profile.user_account.is_empty() {
+                copy_account_rows(&source, &self.dir.join(file), table, &profile.user_account)
+                    .with_context(|| format!("Failed to copy {} credentials for {}", file, profile.user_account))?;
+            }
+        }
+
+        if let Some(adc) = store.load_adc_json(name)? {
+            let path = self.dir.join("application_default_credentials.json");
+            fs::write(&path, serde_json::to_string_pretty(&adc)?)?;
+            self.adc = Some(path);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for IsolatedConfig {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            tracing::warn!(path = %self.dir.display(), error = %e, "failed to remove isolated gcloud config");
+        }
+    }
+}
+
+/// Create `target` with `table`'s schema from `source` and copy over `account`'s row.
+fn copy_account_rows(source: &Path, target: &Path, table: &str, account: &str) -> Result<()> {
+    let conn = Connection::open(target)?;
+    conn.execute("ATTACH DATABASE ?1 AS source", [source.to_string_lossy()])?;
+    let schema: Option<String> = conn
+        .query_row(
+            "SELECT sql FROM source.sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |row| row.get(0),
+        )
+        .ok();
+    if let Some(schema) = schema {
+        conn.execute(&schema, [])?;
+        conn.execute(
+            &format!("INSERT INTO main.{0} SELECT * FROM source.{0} WHERE account_id = ?1", table),
+            [account],
+        )?;
+    }
+    conn.execute("DETACH DATABASE source", [])?;
+    Ok(())
+}
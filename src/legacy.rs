@@ -0,0 +1,143 @@
+//! Legacy SDK config files that do not follow gcloud's configurations: standalone gsutil's
+//! `.boto` and bq's `.bigqueryrc`. With `legacy_configs` on, activation points them at the
+//! profile's project (and, for a service account ADC, its key file). Files that do not
+//! exist are left alone; only the managed keys are changed, everything else is kept.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::profile::Profile;
+use crate::store::Store;
+
+/// gsutil's config: the first path in `BOTO_CONFIG`, else `~/.boto`.
+pub fn boto_path() -> Option<PathBuf> {
+    if let Some(paths) = std::env::var_os("BOTO_CONFIG") {
+        return std::env::split_paths(&paths).next();
+    }
+    dirs::home_dir().map(|home| home.join(".boto"))
+}
+
+/// bq's flag file: `BIGQUERYRC`, else `~/.bigqueryrc`.
+pub fn bigqueryrc_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("BIGQUERYRC") {
+        return Some(PathBuf::from(path));
+    }
+    dirs::home_dir().map(|home| home.join(".bigqueryrc"))
+}
+
+/// Update the existing legacy config files for `name`. Returns the files changed.
+pub fn update(store: &Store, name: &str, profile: &Profile) -> Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    if profile.user_project.is_empty() {
+        return Ok(changed);
+    }
+    // gsutil only takes a key file for service accounts; user credentials stay as they are
+    let service_key = store
+        .load_adc_json(name)?
+        .filter(|adc| adc.get("type").and_then(|t| t.as_str()) == Some("service_account"))
+        .map(|_| store.adc_path(name).display().to_string());
+
+    if let Some(path) = boto_path() {
+        let mut values = vec![(Some("GSUtil"), "default_project_id", profile.user_project.as_str())];
+        if let Some(key) = &service_key {
+            values.push((Some("Credentials"), "gs_service_key_file", key));
+        }
+        if update_file(&path, &values)? {
+            changed.push(path);
+        }
+    }
+    if let Some(path) = bigqueryrc_path() {
+        // Flags before any [command] section apply to every bq command
+        if update_file(&path, &[(None, "project_id", profile.user_project.as_str())])? {
+            changed.push(path);
+        }
+    }
+    if !changed.is_empty() {
+        tracing::info!(profile = name, files = ?changed, "updated legacy SDK configs");
+    }
+    Ok(changed)
+}
+
+/// Set `values` (section, key, value) in an existing INI-style file. False when the file
+/// does not exist or already had them.
+fn update_file(path: &PathBuf, values: &[(Option<&str>, &str, &str)]) -> Result<bool> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let mut updated = text.clone();
+    for (section, key, value) in values {
+        updated = set_value(&updated, *section, key, value);
+    }
+    if updated == text {
+        return Ok(false);
+    }
+    fs::write(path, updated).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+/// `text` with `key = value` set in `section` (None: before the first section). Replaces
+/// the key where it is, else appends it to the section, else adds the section.
+fn set_value(text: &str, section: Option<&str>, key: &str, value: &str) -> String {
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut current: Option<String> = None;
+    let mut section_end = match section {
+        None => Some(0),
+        Some(_) => None,
+    };
+    let mut found = None;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            current = Some(trimmed[1..trimmed.len() - 1].trim().to_string());
+            continue;
+        }
+        if current.as_deref() != section {
+            continue;
+        }
+        // bq also accepts its flags written as `--key=value`
+        let entry = trimmed.trim_start_matches("--");
+        let Some((k, _)) = entry.split_once('=') else {
+            if !trimmed.is_empty() && !trimmed.starts_with('#') && !trimmed.starts_with(';') {
+                section_end = Some(i + 1);
+            }
+            continue;
+        };
+        if k.trim() == key {
+            found = Some(i);
+            break;
+        }
+        section_end = Some(i + 1);
+    }
+    let entry = format!("{} = {}", key, value);
+    if let Some(at) = found {
+        lines[at] = entry;
+    } else if let Some(at) = section_end {
+        lines.insert(at, entry);
+    } else if let Some(section) = section {
+        // The header may exist with no entries yet
+        let header = format!("[{}]", section);
+        match lines.iter().position(|l| l.trim() == header) {
+            Some(at) => lines.insert(at + 1, entry),
+            None => {
+                if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push(header);
+                lines.push(entry);
+            }
+        }
+    }
+    join_lines(lines, text)
+}
+
+fn join_lines(lines: Vec<String>, original: &str) -> String {
+    let mut joined = lines.join("\n");
+    if original.ends_with('\n') || original.is_empty() {
+        joined.push('\n');
+    }
+    joined
+}
@@ -0,0 +1,44 @@
+//! Profile management behind the gcloud-switch CLI and TUI, for reuse by other tools.
+//!
+//! - [`store::Store`] loads and saves `profiles.toml` and the per-profile ADC files.
+//! - [`profile`] holds the data types ([`Profile`], [`ProfilesFile`], [`SyncMode`]).
+//! - [`gcloud`] activates profiles, manages gcloud configurations and checks credentials.
+//! - [`providers`] switches other clouds (AWS, Azure) along with a profile.
+//! - [`docker`] points Docker's credential helpers for a profile's registries at gcloud.
+//! - [`legacy`] points standalone gsutil's `.boto` and bq's `.bigqueryrc` at a profile.
+//! - [`bulk`] reads profile sets from YAML or CSV files for `import --file`.
+//! - [`isolate`] builds a throwaway gcloud config directory holding one profile for `run`.
+//! - [`terraform`] builds the Google provider's environment and backend config for a profile.
+//! - [`sync`] pushes and pulls profiles through git, a gist or an HTTPS endpoint, with
+//!   optional [`crypto`] encryption; [`net`] holds the shared HTTP client and offline switch.
+//! - [`runtime`] runs token checks and non-interactive gcloud commands as async tasks.
+//!
+//! ```no_run
+//! use gcloud_switch::{gcloud, Store};
+//!
+//! let store = Store::new()?;
+//! let mut data = store.load_profiles()?;
+//! if let Some(profile) = data.profiles.get("work").cloned() {
+//!     gcloud::activate_both(&store, "work", &profile.user_account, &profile.user_project)?;
+//!     data.active_profile = Some("work".to_string());
+//!     store.save_profiles(&data)?;
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod bulk;
+pub mod crypto;
+pub mod docker;
+pub mod gcloud;
+pub mod isolate;
+pub mod legacy;
+pub mod net;
+pub mod profile;
+pub mod providers;
+pub mod runtime;
+pub mod store;
+pub mod sync;
+pub mod terraform;
+
+pub use profile::{Profile, ProfilesFile, SyncMode};
+pub use store::Store;
@@ -0,0 +1,156 @@
+//! Diagnostic log. The library emits `tracing` events for every gcloud invocation (with
+//! its stderr on failure), git and HTTP sync operation and store write, each with its
+//! duration; this subscriber prints them to stderr (`--verbose` on subcommands) and/or
+//! appends them to a size-rotated `gcloud-switch.log` in the store directory (`log_file`).
+
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use crate::ui::{format_clock, format_date};
+
+pub const LOG_FILE: &str = "gcloud-switch.log";
+/// Rotate once the log reaches this size, keeping `.1` to `.3`.
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const KEEP_ROTATED: usize = 3;
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+}
+
+impl LogFile {
+    fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let len = file.metadata()?.len();
+        Ok(Self { path: path.to_path_buf(), file, len })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.len + line.len() as u64 > MAX_LOG_BYTES {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.len += line.len() as u64;
+        Ok(())
+    }
+
+    /// gcloud-switch.log -> .1 -> .2 -> .3, dropping the oldest.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        for n in (1..KEEP_ROTATED).rev() {
+            let _ = fs::rename(rotated(n), rotated(n + 1));
+        }
+        fs::rename(&self.path, rotated(1))?;
+        self.file = File::create(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+struct Logger {
+    stderr: bool,
+    file: Option<Mutex<LogFile>>,
+    next_span: AtomicU64,
+}
+
+/// Install the logger. Does nothing when neither output is wanted, so events cost nothing.
+pub fn init(stderr: bool, file: Option<&Path>) -> Result<()> {
+    if !stderr && file.is_none() {
+        return Ok(());
+    }
+    let file = file.map(LogFile::open).transpose()?.map(Mutex::new);
+    let logger = Logger {
+        stderr,
+        file,
+        next_span: AtomicU64::new(1),
+    };
+    tracing::subscriber::set_global_default(logger).context("Failed to install the logger")
+}
+
+/// Collects an event's message and fields into `message key=value ...`.
+struct LineVisitor(String);
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, " {:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" || !value.contains(char::is_whitespace) {
+            let _ = write!(self.0, " {}{}", prefix(field), value);
+        } else {
+            let _ = write!(self.0, " {}{:?}", prefix(field), value);
+        }
+    }
+}
+
+fn prefix(field: &Field) -> String {
+    match field.name() {
+        "message" => String::new(),
+        name => format!("{}=", name),
+    }
+}
+
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = now.as_secs() as i64;
+    format!("{} {}.{:03}", format_date(secs), format_clock(secs), now.subsec_millis())
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= Level::DEBUG
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut visitor = LineVisitor(String::new());
+        event.record(&mut visitor);
+        let line = format!(
+            "{} {:5} {}:{}\n",
+            timestamp(),
+            metadata.level(),
+            metadata.target(),
+            visitor.0
+        );
+        if self.stderr {
+            eprint!("{}", line);
+        }
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = file.lock() {
+                let _ = file.write_line(&line);
+            }
+        }
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
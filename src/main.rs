@@ -1,11 +1,19 @@
 mod app;
-mod gcloud;
-mod profile;
-mod store;
-mod sync;
+mod ci;
+mod clipboard;
+mod daemon;
+mod hooks;
+mod logging;
+mod notifier;
+mod opener;
+mod theme;
 mod ui;
+mod watch;
+mod wizard;
 
-use std::io;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
@@ -18,15 +26,32 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 
-use crate::app::{App, PendingAction};
-use crate::profile::{Profile, SyncMode};
-use crate::store::Store;
+use gcloud_switch::profile::{self, GuardSettings, Profile, SyncMode};
+use gcloud_switch::store::Store;
+use gcloud_switch::{bulk, crypto, docker, gcloud, isolate, legacy, net, providers, sync, terraform};
+
+use crate::app::{activation_plan, profile_diff, App, Column, Drift, LiveContext, PendingAction, TableSettings, DEFAULT_AUTH_RECHECK_MINUTES, DEFAULT_TOAST_SECONDS};
+use crate::hooks::{HookEvent, Hooks};
+use crate::notifier::NotifySettings;
+use crate::theme::{Theme, ThemeSettings};
 
 #[derive(Parser)]
 #[command(name = "gcloud-switch", version, about = "TUI Google Cloud profile switcher")]
 pub struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Print the TUI's activity log on exit; with a subcommand, log every gcloud call,
+    /// git or HTTP sync operation and profile write to stderr
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// No network access: skip update checks and token validation, refuse sync and
+    /// self-update. Also enabled by GCLOUD_SWITCH_OFFLINE=1.
+    #[arg(long, global = true)]
+    offline: bool,
+    /// Print the gcloud commands and file copies instead of running them (switch, reauth,
+    /// sync pull)
+    #[arg(long, global = true)]
+    dry_run: bool,
 }
 
 #[derive(Subcommand)]
@@ -34,29 +59,267 @@ enum Commands {
     /// Add a new profile
     Add {
         /// Profile name
-        name: String,
+        #[arg(required_unless_present = "interactive")]
+        name: Option<String>,
         /// User account email
-        #[arg(long)]
-        account: String,
+        #[arg(long, required_unless_present = "interactive")]
+        account: Option<String>,
         /// User project
-        #[arg(long)]
-        project: String,
+        #[arg(long, required_unless_present = "interactive")]
+        project: Option<String>,
+        /// Prompt for the fields on the command line, with completion of known accounts and
+        /// projects; values given as arguments become the defaults
+        #[arg(long, short)]
+        interactive: bool,
         /// ADC account email (defaults to user account)
         #[arg(long)]
         adc_account: Option<String>,
         /// ADC quota project (defaults to user project)
         #[arg(long)]
         adc_quota_project: Option<String>,
+        /// AWS CLI profile to select with this profile (via `gcloud-switch env`)
+        #[arg(long)]
+        aws_profile: Option<String>,
+        /// Azure subscription (name or id) to `az account set` on switch
+        #[arg(long)]
+        azure_subscription: Option<String>,
+        /// GKE cluster whose kubectl context is refreshed on switch, as location/name or
+        /// project/location/name (repeatable; the first becomes the current context)
+        #[arg(long = "cluster")]
+        clusters: Vec<String>,
+        /// Docker registry (host like gcr.io, or an Artifact Registry location like
+        /// europe-west1) to authenticate through gcloud (repeatable)
+        #[arg(long = "registry")]
+        registries: Vec<String>,
+        /// Ask before switching to this profile (`switch` then needs --force)
+        #[arg(long)]
+        confirm_before_activate: bool,
     },
     /// List all profiles
-    List,
+    List {
+        /// Also check each profile's user and ADC credentials with Google, like the TUI;
+        /// exits with status 1 if any are invalid
+        #[arg(long)]
+        check_auth: bool,
+        /// Print the profiles as a JSON array
+        #[arg(long)]
+        json: bool,
+        /// Also show when each profile was last activated on this machine
+        #[arg(long, short)]
+        long: bool,
+        /// Most recently activated profiles first
+        #[arg(long)]
+        recent: bool,
+    },
+    /// Compare two profiles field by field: accounts, projects, stored ADC and the
+    /// properties of their gcloud configurations
+    Diff {
+        /// First profile
+        a: String,
+        /// Second profile
+        b: String,
+    },
+    /// Show a profile's fields, project numbers, stored ADC and gcloud configuration
+    Show {
+        /// Profile name (default: the active profile)
+        name: Option<String>,
+    },
+    /// Rename a profile (and its gcloud configuration, unless sync mode is off)
+    Rename {
+        /// Current profile name
+        old: String,
+        /// New profile name
+        new: String,
+    },
+    /// What gcloud and client libraries point at right now: active profile and gcloud
+    /// configuration, installed ADC, sync setup and drift
+    Status,
     /// Switch to a profile
     Switch {
         /// Profile name
         name: String,
+        /// Switch even if the profile asks for confirmation first
+        #[arg(long, short)]
+        force: bool,
+        /// Switch to a guarded profile without typing its name
+        #[arg(long = "i-know")]
+        i_know: bool,
+    },
+    /// Lock a profile against edits, renames and deletion (in the CLI and TUI) until unlocked
+    Lock {
+        /// Profile name
+        name: String,
+    },
+    /// Allow changes to a locked profile again
+    Unlock {
+        /// Profile name
+        name: String,
+    },
+    /// Make a profile ask for confirmation before it is activated (Enter in the TUI asks,
+    /// `switch` needs --force), or stop asking with --off
+    Protect {
+        /// Profile name
+        name: String,
+        /// Stop asking before activating the profile
+        #[arg(long)]
+        off: bool,
+    },
+    /// Show, set or clear a profile's context for another cloud (aws, azure)
+    Context {
+        /// Profile name
+        name: String,
+        /// Provider: aws (AWS CLI profile) or azure (subscription name or id)
+        provider: String,
+        /// New context. Omit to show the current one.
+        value: Option<String>,
+        /// Remove the profile's context for this provider
+        #[arg(long, conflicts_with = "value")]
+        clear: bool,
+    },
+    /// Renew cached access tokens from refresh tokens, so credentials stay fresh (e.g. during
+    /// a demo). Default: the active profile
+    Refresh {
+        /// Profiles to refresh
+        profiles: Vec<String>,
+        /// Refresh every profile
+        #[arg(long, conflicts_with = "profiles")]
+        all: bool,
+        /// Keep running and refresh again every --interval minutes
+        #[arg(long)]
+        watch: bool,
+        /// Minutes between refreshes with --watch
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+    },
+    /// Log in again, one login flow after the other. Default: the active profile's user and ADC
+    Reauth {
+        /// Profiles to re-authenticate (user and ADC)
+        profiles: Vec<String>,
+        /// Every profile whose user or ADC credentials need a login, only the sides that do
+        #[arg(long, conflicts_with = "profiles")]
+        all_expired: bool,
+    },
+    /// Print a fresh access token for a profile's account, like `gcloud auth print-access-token`
+    /// but minted in-process where the credentials allow
+    Token {
+        /// Profile name (default: the active profile)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Use the profile's stored ADC instead of its user account
+        #[arg(long)]
+        adc: bool,
+    },
+    /// Open the Google Cloud Console for a profile's project in the browser
+    Console {
+        /// Profile name (default: the active profile)
+        name: Option<String>,
+        /// Use the profile's ADC quota project and account instead of its user side
+        #[arg(long)]
+        adc: bool,
+        /// Leave the account hint (authuser) out of the URL
+        #[arg(long)]
+        no_account: bool,
+        /// Only print the URL, e.g. to open it on another machine
+        #[arg(long)]
+        print: bool,
+    },
+    /// Show or set the GKE clusters whose kubectl contexts a profile refreshes on switch
+    Clusters {
+        /// Profile name
+        name: String,
+        /// Clusters as location/name or project/location/name, replacing the current list.
+        /// Omit to show the list.
+        clusters: Vec<String>,
+        /// Remove all clusters from the profile
+        #[arg(long, conflicts_with = "clusters")]
+        clear: bool,
+        /// List the clusters the profile's account can see in its project, marking linked
+        /// ones with *
+        #[arg(long, conflicts_with_all = ["clusters", "clear"])]
+        available: bool,
+    },
+    /// Show or set the Docker registries a profile points at gcloud's credential helper
+    Registries {
+        /// Profile name
+        name: String,
+        /// Registry hosts or Artifact Registry locations, replacing the current list.
+        /// Omit to show the list.
+        registries: Vec<String>,
+        /// Remove all registries from the profile
+        #[arg(long, conflicts_with = "registries")]
+        clear: bool,
+    },
+    /// Snapshot every property of gcloud's active configuration into a profile; switching
+    /// to the profile writes them back to its configuration
+    Snapshot {
+        /// Profile name
+        name: String,
+        /// Configuration to snapshot, instead of gcloud's active one
+        #[arg(long)]
+        from: Option<String>,
+        /// Drop the profile's snapshot
+        #[arg(long, conflicts_with = "from")]
+        clear: bool,
+    },
+    /// SSH into a VM with `gcloud compute ssh` as a profile's account, in its project and zone
+    Ssh {
+        /// VM instance name
+        instance: String,
+        /// Profile to connect as (default: the active profile)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// Zone of the instance, instead of the profile's
+        #[arg(long)]
+        zone: Option<String>,
+        /// Tunnel through Identity-Aware Proxy, whatever the profile says
+        #[arg(long)]
+        iap: bool,
+        /// Remember --zone and --iap as the profile's SSH settings
+        #[arg(long)]
+        save: bool,
+        /// Further arguments for gcloud compute ssh, after `--`
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Run a command against a profile in a temporary gcloud config directory holding only
+    /// that profile's configuration, credentials and ADC; the real gcloud home is untouched
+    Run {
+        /// Profile to run as (default: the active profile)
+        #[arg(long, short)]
+        profile: Option<String>,
+        /// The command and its arguments, after `--`
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Print exports pointing Terraform's Google provider at a profile (GOOGLE_PROJECT,
+    /// GOOGLE_IMPERSONATE_SERVICE_ACCOUNT, GOOGLE_APPLICATION_CREDENTIALS); use as
+    /// `eval "$(gcloud-switch terraform <profile>)"`
+    Terraform {
+        /// Profile name (default: the active profile)
+        name: Option<String>,
+        /// Service account to impersonate, instead of the profile's
+        #[arg(long)]
+        impersonate: Option<String>,
+        /// Also write the profile's backend config template, rendered, to this file
+        /// (for `terraform init -backend-config=<file>`); "-" prints it instead of the exports
+        #[arg(long)]
+        backend_config: Option<PathBuf>,
+    },
+    /// Print shell commands setting other clouds' environment (AWS_PROFILE), and with
+    /// `adc_strategy = "env"` GOOGLE_APPLICATION_CREDENTIALS, for the active profile; use as
+    /// `eval "$(gcloud-switch env)"`
+    Env,
+    /// Inspect and manage stored Application Default Credentials
+    Adc {
+        #[command(subcommand)]
+        sub: AdcSub,
+    },
+    /// Import existing gcloud configurations, or the profiles listed in a YAML or CSV file
+    Import {
+        /// YAML or CSV (.csv) file with name, account, project and tags per profile
+        #[arg(long)]
+        file: Option<PathBuf>,
     },
-    /// Import existing gcloud configurations
-    Import,
     /// Check for and install new releases from GitHub
     SelfUpdate {
         /// Do not download README.md after installing
@@ -71,12 +334,29 @@ enum Commands {
         /// Skip SHA-256 checksum verification (use only if the release predates sidecar support)
         #[arg(long)]
         skip_checksum: bool,
+        /// Consider prereleases (release candidates) for this run, whatever `channel` says
+        #[arg(long)]
+        pre: bool,
+        /// Install without asking for confirmation after the changelog
+        #[arg(long, short)]
+        yes: bool,
     },
     /// Sync profile metadata (profiles.toml only) via a Git remote
     Sync {
         #[command(subcommand)]
         sub: SyncSub,
     },
+    /// Non-interactive setup for CI pipelines
+    Ci {
+        #[command(subcommand)]
+        sub: CiSub,
+    },
+    /// Serve list/current/switch/auth-status as JSON over a unix socket for prompts and editors
+    Daemon {
+        /// Socket path (default: daemon.sock in the profile store directory)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
     /// Download and open the latest README from the repository
     OpenReadme,
     /// Generate shell completion script
@@ -101,20 +381,131 @@ enum Commands {
     EditConfig,
 }
 
+#[derive(Subcommand)]
+enum AdcSub {
+    /// Show the installed ADC and which profile it belongs to, or a profile's stored ADC
+    Show {
+        /// Profile name (default: the installed ADC)
+        name: Option<String>,
+    },
+    /// Print the path of a profile's stored ADC, e.g. for GOOGLE_APPLICATION_CREDENTIALS
+    Path {
+        /// Profile name (default: the active profile)
+        name: Option<String>,
+    },
+    /// Delete a profile's stored ADC (the installed one is left in place)
+    Clear {
+        /// Profile name
+        name: String,
+    },
+    /// Store the currently installed ADC as a profile's
+    Import {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CiSub {
+    /// Activate a profile without prompting (fails if a login would be needed) and export
+    /// its environment to --env-file, else $GITHUB_ENV, else stdout
+    Apply {
+        /// Profile name
+        name: String,
+        /// Append KEY=value lines to this file
+        #[arg(long)]
+        env_file: Option<PathBuf>,
+        /// Environment variable holding ADC JSON (or base64 of it) to install for the profile;
+        /// a service account key for the profile's account also logs gcloud in
+        #[arg(long, default_value = ci::DEFAULT_ADC_ENV)]
+        adc_env: String,
+    },
+}
+
+/// `sync::SyncBackend` as a command-line value, so the library does not depend on clap.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum BackendArg {
+    /// A git repository; the remote URL is its clone URL.
+    Git,
+    /// A (secret) GitHub Gist; the remote URL is the gist URL or id.
+    Gist,
+    /// Any HTTPS endpoint; each file is GET/PUT at `<remote_url>/<file>`.
+    Http,
+}
+
+impl From<BackendArg> for sync::SyncBackend {
+    fn from(arg: BackendArg) -> Self {
+        match arg {
+            BackendArg::Git => sync::SyncBackend::Git,
+            BackendArg::Gist => sync::SyncBackend::Gist,
+            BackendArg::Http => sync::SyncBackend::Http,
+        }
+    }
+}
+
+/// `crypto::KeyStore` as a command-line value.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum KeyStoreArg {
+    /// ~/.config/gcloud-switch/sync.key, readable only by the user.
+    File,
+    /// macOS Keychain (`security`) or the Secret Service (`secret-tool`) on Linux.
+    Keyring,
+}
+
+impl From<KeyStoreArg> for crypto::KeyStore {
+    fn from(arg: KeyStoreArg) -> Self {
+        match arg {
+            KeyStoreArg::File => crypto::KeyStore::File,
+            KeyStoreArg::Keyring => crypto::KeyStore::Keyring,
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum SyncSub {
     /// Set remote URL and optionally clone (run first before push/pull)
     Init {
-        /// Git remote URL (e.g. https://github.com/user/repo.git or git@github.com:user/repo.git)
+        /// Git remote URL (e.g. https://github.com/user/repo.git or git@github.com:user/repo.git),
+        /// gist URL or id (--backend gist), or base URL of an HTTPS endpoint (--backend http)
         remote_url: String,
-        /// Branch name (default: main)
+        /// Branch name (default: main; git only)
         #[arg(long, default_value = "main")]
         branch: String,
+        /// Where to sync to
+        #[arg(long, value_enum, default_value_t = BackendArg::Git)]
+        backend: BackendArg,
+        /// Name of this machine in the sync history (default: the host name)
+        #[arg(long)]
+        device: Option<String>,
     },
     /// Push current profiles to the remote
     Push,
     /// Pull and merge profiles from the remote (newer wins per profile)
-    Pull,
+    Pull {
+        /// Keep local values for everything changed on both sides (no prompt)
+        #[arg(long, conflicts_with_all = ["theirs", "newer"])]
+        ours: bool,
+        /// Take remote values for everything changed on both sides (no prompt)
+        #[arg(long, conflicts_with = "newer")]
+        theirs: bool,
+        /// The newer side wins; keep local when neither is newer (no prompt)
+        #[arg(long)]
+        newer: bool,
+    },
+    /// Show when each device last pushed and pulled
+    Status,
+    /// Encrypt synced files with age. Without --key, reuses the stored key or generates one
+    Encrypt {
+        /// Where to keep the key: a local file or the OS keyring
+        #[arg(long, value_enum, default_value_t = KeyStoreArg::File)]
+        key_store: KeyStoreArg,
+        /// Existing key (AGE-SECRET-KEY-1...) from another machine
+        #[arg(long, conflicts_with = "off")]
+        key: Option<String>,
+        /// Turn encryption off (the key is kept)
+        #[arg(long)]
+        off: bool,
+    },
 }
 
 /// User-level parameters in ~/.config/gcloud-switch/gcloud-switch.toml. Profile data stays in profiles.toml.
@@ -126,19 +517,103 @@ struct GlobalSettings {
     self_update_frequency: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     last_update_check: Option<String>,
+    /// Which releases to update to: "stable" (default) or "prerelease".
+    #[serde(default, skip_serializing_if = "UpdateChannel::is_stable")]
+    channel: UpdateChannel,
     /// Git remote URL for syncing profiles (e.g. https://github.com/user/repo.git)
     #[serde(skip_serializing_if = "Option::is_none")]
     remote_url: Option<String>,
     /// Branch name for sync (default: main)
     #[serde(skip_serializing_if = "Option::is_none")]
     branch: Option<String>,
+    /// Sync backend: "git" (default), "gist" or "http".
+    #[serde(default, skip_serializing_if = "sync::SyncBackend::is_git")]
+    sync_backend: sync::SyncBackend,
+    /// Bearer token for the gist and http backends. GCLOUD_SWITCH_SYNC_TOKEN takes precedence.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync_token: Option<String>,
+    /// Encrypt synced files with age, keeping the key in a "file" or the "keyring". Off when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync_encryption: Option<crypto::KeyStore>,
+    /// This machine's name in the sync history. Defaults to the host name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_name: Option<String>,
     /// List of filenames to sync (default: ["profiles.toml"])
     #[serde(default = "default_sync_files")]
     sync_files: Vec<String>,
+    /// Timeout in seconds for HTTP requests (update checks, token validation, sync). Default 30.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network_timeout: Option<u64>,
+    /// Timeout in seconds for non-interactive gcloud commands; 0 for none. Default 60.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gcloud_timeout: Option<u64>,
+    /// Seconds a token check result in state.toml is reused; 0 checks every launch. Default 300.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth_cache_ttl: Option<u64>,
+    /// Minutes between auth re-checks while the TUI stays open; 0 for none. Default 15.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth_recheck_interval: Option<u64>,
     /// Editor command for opening files (e.g. "code", "zed", "vim").
     /// Falls back to $EDITOR env var, then the OS default app.
     #[serde(skip_serializing_if = "Option::is_none")]
     editor: Option<String>,
+    /// Seconds a message toast stays up (errors twice as long); 0 keeps it until a key is pressed. Default 5.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    toast_duration: Option<u64>,
+    /// Append a diagnostic log to gcloud-switch.log in the profile store directory.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    log_file: bool,
+    /// TUI colors: built-in theme name plus per-role overrides.
+    #[serde(default, skip_serializing_if = "ThemeSettings::is_empty")]
+    theme: ThemeSettings,
+    /// TUI table layout: ADC column, compact rows, column widths.
+    #[serde(default, skip_serializing_if = "TableSettings::is_default")]
+    table: TableSettings,
+    /// External commands per lifecycle event, receiving the event as JSON on stdin.
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    hooks: Hooks,
+    /// Keep the TUI open after Enter activates a profile; Alt+Enter then quits.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stay_open: bool,
+    /// On activation, also set the project (and service account key) in an existing
+    /// `.boto` and `.bigqueryrc`, for standalone gsutil and bq.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    legacy_configs: bool,
+    /// Desktop notifications from the daemon when the active profile's credentials expire.
+    #[serde(default, skip_serializing_if = "NotifySettings::is_default")]
+    notifications: NotifySettings,
+    /// Profiles (by name or tag) that need their name typed, or --i-know, to switch to.
+    #[serde(default, skip_serializing_if = "GuardSettings::is_empty")]
+    guard: GuardSettings,
+    /// How activation installs ADC: "copy" (default) over the global file, or "env" for
+    /// GOOGLE_APPLICATION_CREDENTIALS from `gcloud-switch env`.
+    #[serde(default, skip_serializing_if = "gcloud::AdcStrategy::is_copy")]
+    adc_strategy: gcloud::AdcStrategy,
+    /// Whether logins open a browser: "auto" (default: not over SSH or without a display),
+    /// "always" or "never" (print the URL and ask for the code).
+    #[serde(default, skip_serializing_if = "gcloud::LoginBrowser::is_auto")]
+    login_browser: gcloud::LoginBrowser,
+    /// Re-authenticate user and ADC of a profile with one account through a single
+    /// `gcloud auth login --update-adc` instead of two logins.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    combined_reauth: bool,
+}
+
+/// Release channel for updates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum UpdateChannel {
+    /// Only releases not marked as prerelease on GitHub.
+    #[default]
+    Stable,
+    /// Stable releases and prereleases, whichever is newest.
+    Prerelease,
+}
+
+impl UpdateChannel {
+    fn is_stable(&self) -> bool {
+        *self == UpdateChannel::Stable
+    }
 }
 
 fn default_sync_files() -> Vec<String> {
@@ -150,10 +625,31 @@ impl Default for GlobalSettings {
         Self {
             self_update_frequency: default_self_update_frequency(),
             last_update_check: None,
+            channel: UpdateChannel::default(),
             remote_url: None,
             branch: None,
+            sync_backend: sync::SyncBackend::default(),
+            sync_token: None,
+            sync_encryption: None,
+            device_name: None,
             sync_files: default_sync_files(),
+            network_timeout: None,
+            gcloud_timeout: None,
+            auth_cache_ttl: None,
+            auth_recheck_interval: None,
             editor: None,
+            toast_duration: None,
+            log_file: false,
+            theme: ThemeSettings::default(),
+            table: TableSettings::default(),
+            hooks: Hooks::default(),
+            stay_open: false,
+            legacy_configs: false,
+            notifications: NotifySettings::default(),
+            guard: GuardSettings::default(),
+            adc_strategy: gcloud::AdcStrategy::default(),
+            login_browser: gcloud::LoginBrowser::default(),
+            combined_reauth: false,
         }
     }
 }
@@ -163,11 +659,52 @@ fn default_self_update_frequency() -> String {
 }
 
 fn global_settings_path() -> Option<PathBuf> {
-    std::env::var("HOME").ok().map(|home| {
-        PathBuf::from(home).join(".config").join("gcloud-switch").join("gcloud-switch.toml")
+    dirs::home_dir().map(|home| {
+        home.join(".config").join("gcloud-switch").join("gcloud-switch.toml")
     })
 }
 
+/// Sync remote from the settings, or None if `sync init` hasn't been run.
+/// Fails if encryption is on but the key can't be loaded.
+fn sync_target(settings: &GlobalSettings) -> Result<Option<sync::SyncTarget>> {
+    let Some(remote_url) = settings.remote_url.clone() else {
+        return Ok(None);
+    };
+    let identity = match settings.sync_encryption {
+        Some(key_store) => Some(crypto::load_identity(key_store)?.ok_or_else(|| {
+            anyhow::anyhow!("Sync encryption is on but no key is stored. Run 'gcloud-switch sync encrypt --key <key>'.")
+        })?),
+        None => None,
+    };
+    Ok(Some(sync::SyncTarget {
+        backend: settings.sync_backend,
+        remote_url,
+        branch: settings.branch.clone().unwrap_or_else(|| "main".to_string()),
+        files: settings.sync_files.clone(),
+        token: std::env::var("GCLOUD_SWITCH_SYNC_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty())
+            .or_else(|| settings.sync_token.clone()),
+        identity,
+        device: settings.device_name.clone().unwrap_or_else(default_device_name),
+    }))
+}
+
+/// Host name without the domain (e.g. "laptop" for "laptop.local"), or "unknown".
+fn default_device_name() -> String {
+    let name = std::env::var("COMPUTERNAME").ok().or_else(|| {
+        let output = std::process::Command::new("hostname").output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    });
+    match name {
+        Some(name) if !name.is_empty() => name.split('.').next().unwrap_or(&name).to_string(),
+        _ => "unknown".to_string(),
+    }
+}
+
 /// Load global settings. If the file does not exist, create ~/.config/gcloud-switch/gcloud-switch.toml with default values.
 fn load_global_settings() -> GlobalSettings {
     let path = match global_settings_path() {
@@ -197,18 +734,10 @@ fn save_global_settings(settings: &GlobalSettings) -> Result<()> {
     Ok(())
 }
 
-fn check_update_available(client: &reqwest::blocking::Client) -> Result<Option<(String, String)>> {
-    let url = format!("{}/{}/releases/latest", API_URL, REPO);
-    let response = client.get(&url).send()?;
-    if !response.status().is_success() {
+fn check_update_available(client: &reqwest::blocking::Client, channel: UpdateChannel) -> Result<Option<(String, String)>> {
+    let Some(release) = channel_releases(client, channel)?.into_iter().next() else {
         return Ok(None);
-    }
-    #[derive(Deserialize)]
-    struct Release {
-        tag_name: String,
-        html_url: String,
-    }
-    let release: Release = response.json()?;
+    };
     let latest_version = release.tag_name.trim_start_matches('v').to_string();
     let current = env!("CARGO_PKG_VERSION");
     if compare_versions(current, &latest_version) < 0 {
@@ -220,7 +749,7 @@ fn check_update_available(client: &reqwest::blocking::Client) -> Result<Option<(
 
 fn maybe_check_for_updates(settings: &mut GlobalSettings) -> Result<()> {
     let freq = settings.self_update_frequency.as_str();
-    if freq == "never" {
+    if freq == "never" || net::is_offline() {
         return Ok(());
     }
     if freq == "daily" {
@@ -235,10 +764,8 @@ fn maybe_check_for_updates(settings: &mut GlobalSettings) -> Result<()> {
             }
         }
     }
-    let client = reqwest::blocking::Client::builder()
-        .user_agent("gcloud-switch-update-checker")
-        .build()?;
-    let update = check_update_available(&client)?;
+    let client = net::client("Update check")?;
+    let update = check_update_available(&client, settings.channel)?;
     if freq == "daily" {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -260,11 +787,38 @@ fn maybe_check_for_updates(settings: &mut GlobalSettings) -> Result<()> {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let dry_run = cli.dry_run;
+    if dry_run
+        && !matches!(
+            cli.command,
+            Some(Commands::Switch { .. }) | Some(Commands::Reauth { .. }) | Some(Commands::Sync { sub: SyncSub::Pull { .. } })
+        )
+    {
+        anyhow::bail!("--dry-run works with switch, reauth and sync pull");
+    }
 
     // Load/create global settings on first run (creates ~/.config/gcloud-switch/gcloud-switch.toml with defaults)
     let mut global_settings = load_global_settings();
+    net::configure(
+        cli.offline || net::offline_from_env(),
+        global_settings.network_timeout.unwrap_or(net::DEFAULT_TIMEOUT_SECS),
+    );
+    gcloud::configure_timeout(global_settings.gcloud_timeout.unwrap_or(gcloud::DEFAULT_TIMEOUT_SECS));
+    gcloud::configure_auth_cache(global_settings.auth_cache_ttl.unwrap_or(gcloud::DEFAULT_AUTH_CACHE_TTL_SECS));
+    gcloud::configure_adc_strategy(global_settings.adc_strategy);
+    gcloud::configure_login_browser(global_settings.login_browser);
+    gcloud::configure_combined_reauth(global_settings.combined_reauth);
+    // In the TUI, stderr would draw over the screen; --verbose prints the activity log instead
+    let log_path = if global_settings.log_file {
+        Some(Store::new()?.dir().join(logging::LOG_FILE))
+    } else {
+        None
+    };
+    if let Err(e) = logging::init(cli.verbose && cli.command.is_some(), log_path.as_deref()) {
+        eprintln!("⚠️  Warning: {:#}", e);
+    }
     // Optional: check for updates per global settings
-    if !matches!(cli.command, Some(Commands::SelfUpdate { .. }) | Some(Commands::OpenReadme) | Some(Commands::Completion { .. }) | Some(Commands::SetEditor { .. }) | Some(Commands::ShowConfig) | Some(Commands::EditConfig)) {
+    if !dry_run && !matches!(cli.command, Some(Commands::SelfUpdate { .. }) | Some(Commands::OpenReadme) | Some(Commands::Completion { .. }) | Some(Commands::SetEditor { .. }) | Some(Commands::ShowConfig) | Some(Commands::EditConfig) | Some(Commands::Env) | Some(Commands::Adc { .. }) | Some(Commands::Token { .. }) | Some(Commands::Console { .. }) | Some(Commands::Terraform { .. }) | Some(Commands::Run { .. }) | Some(Commands::Ci { .. }) | Some(Commands::List { json: true, .. })) {
         let _ = maybe_check_for_updates(&mut global_settings);
     }
 
@@ -275,48 +829,214 @@ fn main() -> Result<()> {
             project,
             adc_account,
             adc_quota_project,
+            aws_profile,
+            azure_subscription,
+            clusters,
+            registries,
+            interactive,
+            confirm_before_activate,
         }) => {
+            for cluster in &clusters {
+                gcloud::GkeCluster::parse(cluster)?;
+            }
             let store = Store::new()?;
             let data = store.load_profiles()?;
-            let profile = Profile {
-                user_account: account.clone(),
-                user_project: project.clone(),
-                adc_account: adc_account.unwrap_or_else(|| account.clone()),
-                adc_quota_project: adc_quota_project.unwrap_or_else(|| project.clone()),
-                updated_at: None,
+            let (name, mut profile) = if interactive {
+                let answers = wizard::add(
+                    &data,
+                    wizard::Answers {
+                        name: name.unwrap_or_default(),
+                        account: account.unwrap_or_default(),
+                        project: project.unwrap_or_default(),
+                        adc_account: adc_account.unwrap_or_default(),
+                        adc_quota_project: adc_quota_project.unwrap_or_default(),
+                        tags: Vec::new(),
+                    },
+                )?;
+                let profile = Profile {
+                    user_account: answers.account,
+                    user_project: answers.project,
+                    adc_account: answers.adc_account,
+                    adc_quota_project: answers.adc_quota_project,
+                    tags: answers.tags,
+                    ..Default::default()
+                };
+                (answers.name, profile)
+            } else {
+                // clap requires these without --interactive
+                let (name, account, project) = (name.unwrap_or_default(), account.unwrap_or_default(), project.unwrap_or_default());
+                let profile = Profile {
+                    adc_account: adc_account.unwrap_or_else(|| account.clone()),
+                    adc_quota_project: adc_quota_project.unwrap_or_else(|| project.clone()),
+                    user_account: account,
+                    user_project: project,
+                    ..Default::default()
+                };
+                (name, profile)
             };
+            if let Some(aws) = aws_profile {
+                profile.contexts.insert("aws".to_string(), aws);
+            }
+            if let Some(azure) = azure_subscription {
+                profile.contexts.insert("azure".to_string(), azure);
+            }
+            profile.clusters = clusters;
+            profile.registries = registries;
+            profile.confirm_before_activate = confirm_before_activate;
             // Create gcloud configuration first so the profile won't be orphaned
-            if matches!(data.sync_mode, SyncMode::Strict | SyncMode::Add) {
+            if data.sync_mode != SyncMode::Off {
                 gcloud::create_configuration(&name, &profile.user_account, &profile.user_project)?;
             }
             store.add_profile(&name, profile.clone())?;
             println!("Profile '{}' added.", name);
+            global_settings.hooks.run(HookEvent::ProfileAdded, hooks::profile_data(&name, &profile));
         }
-        Some(Commands::List) => {
+        Some(Commands::List { check_auth, json, long, recent }) => {
             let store = Store::new()?;
             let data = store.load_profiles()?;
-            if data.profiles.is_empty() {
+            let last_activated = store.load_state()?.last_activated;
+            let mut profiles: Vec<(&String, &Profile)> = data.profiles.iter().collect();
+            if recent {
+                profiles.sort_by_key(|(name, _)| std::cmp::Reverse(last_activated.get(*name)));
+            }
+            let auth = if check_auth {
+                net::ensure_online("Checking credentials")?;
+                let accounts: BTreeSet<&str> = data
+                    .profiles
+                    .values()
+                    .flat_map(|p| [p.user_account.as_str(), p.adc_account.as_str()])
+                    .filter(|account| !account.is_empty())
+                    .collect();
+                Some(check_accounts(&store, accounts.into_iter().collect()))
+            } else {
+                None
+            };
+            let status_of = |account: &str| {
+                auth.as_ref()
+                    .and_then(|auth| auth.get(account).copied())
+                    .unwrap_or(gcloud::AuthStatus::NotApplicable)
+            };
+            if json {
+                // Only the user side has an access token in gcloud's cache; a stored ADC is
+                // a refresh token without an expiry
+                let account_json = |account: &str, user: bool| {
+                    if account.is_empty() {
+                        return serde_json::Value::Null;
+                    }
+                    let mut entry = serde_json::json!({
+                        "account": account,
+                        "status": status_of(account).label(),
+                    });
+                    if user {
+                        entry["token_expires_at"] = gcloud::read_token_expiry(account).ok().flatten().into();
+                    }
+                    entry
+                };
+                let profiles: Vec<serde_json::Value> = profiles
+                    .iter()
+                    .map(|&(name, p)| {
+                        let mut entry = serde_json::json!({
+                            "name": name,
+                            "active": data.active_profile.as_deref() == Some(name.as_str()),
+                            "user_account": p.user_account,
+                            "user_project": p.user_project,
+                            "adc_account": p.adc_account,
+                            "adc_quota_project": p.adc_quota_project,
+                            "adc_stored": store.has_adc(name),
+                            "tags": p.tags,
+                            "contexts": p.contexts,
+                            "last_activated": last_activated.get(name),
+                        });
+                        if check_auth {
+                            entry["user"] = account_json(&p.user_account, true);
+                            entry["adc"] = account_json(&p.adc_account, false);
+                        }
+                        entry
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&profiles)?);
+            } else if data.profiles.is_empty() {
                 println!("No profiles configured. Use 'gcloud-switch add' or press 'a' in the TUI.");
             } else {
-                for (name, profile) in &data.profiles {
+                for (name, profile) in profiles {
                     let active = if data.active_profile.as_deref() == Some(name.as_str()) {
                         " (active)"
                     } else {
                         ""
                     };
+                    let contexts = if profile.contexts.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {}", profile.contexts_label())
+                    };
+                    let auth_label = if check_auth {
+                        let user_status = status_of(&profile.user_account).label();
+                        let user = match gcloud::read_token_expiry(&profile.user_account).ok().flatten() {
+                            Some(expiry) => format!("{} (token {})", user_status, ui::format_expiry(expiry)),
+                            None => user_status.to_string(),
+                        };
+                        format!(" | user: {}, adc: {}", user, status_of(&profile.adc_account).label())
+                    } else {
+                        String::new()
+                    };
                     println!(
-                        "{}{}: user={}@{} adc={}@{}",
+                        "{}{}: user={}@{} adc={}@{}{}{}",
                         name,
                         active,
                         profile.user_account,
                         profile.user_project,
                         profile.adc_account,
                         profile.adc_quota_project,
+                        contexts,
+                        auth_label,
                     );
+                    if long {
+                        let when = last_activated.get(name).map_or_else(|| "never".to_string(), |&ts| format_age(ts));
+                        println!("    last used: {}", when);
+                    }
+                }
+            }
+            if auth.is_some_and(|auth| auth.values().any(|status| status.needs_reauth())) {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Status) => {
+            let store = Store::new()?;
+            print_status(&store, &global_settings)?;
+        }
+        Some(Commands::Diff { a, b }) => {
+            let store = Store::new()?;
+            print_profile_diff(&store, &a, &b)?;
+        }
+        Some(Commands::Show { name }) => {
+            let store = Store::new()?;
+            print_profile(&store, name)?;
+        }
+        Some(Commands::Rename { old, new }) => {
+            let store = Store::new()?;
+            let data = store.load_profiles()?;
+            data.profiles
+                .get(&old)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", old))?
+                .ensure_unlocked(&old)?;
+            profile::validate_name(&new)?;
+            if data.profiles.contains_key(&new) {
+                anyhow::bail!("Profile '{}' already exists", new);
+            }
+            let rename_config = data.sync_mode != SyncMode::Off
+                && gcloud::configuration_path(&old)?.exists();
+            if rename_config {
+                gcloud::rename_configuration(&old, &new)?;
+            }
+            if let Err(e) = store.rename_profile(&old, &new) {
+                if rename_config {
+                    let _ = gcloud::rename_configuration(&new, &old);
                 }
+                return Err(e);
             }
+            println!("Renamed '{}' to '{}'.", old, new);
         }
-        Some(Commands::Switch { name }) => {
+        Some(Commands::Switch { name, force, i_know }) => {
             let store = Store::new()?;
             let mut data = store.load_profiles()?;
             let profile = data
@@ -324,24 +1044,445 @@ fn main() -> Result<()> {
                 .get(&name)
                 .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?
                 .clone();
+            if profile.confirm_before_activate && !force {
+                anyhow::bail!("Profile '{}' asks for confirmation before switching; pass --force to switch anyway", name);
+            }
+            if global_settings.guard.is_guarded(&name, &profile) && !i_know {
+                if dry_run {
+                    println!("# asks to type '{}' first (guarded profile)", name);
+                } else {
+                    confirm_guarded(&name)?;
+                }
+            }
 
             // Check auth before activation (matches TUI behavior)
-            if !gcloud::check_account_auth(&profile.user_account) {
+            let status = gcloud::check_account_auth_cached_blocking(&store, &profile.user_account);
+            if dry_run {
+                if let Some((reason, _)) = status.explain() {
+                    println!("# credentials for '{}': {}", profile.user_account, reason);
+                    print_plan(&gcloud::reauth_plan(&store, &name, &profile, true, false)?);
+                    print_hook_plan(&global_settings.hooks, HookEvent::ReauthCompleted);
+                }
+                print_plan(&activation_plan(
+                    &store,
+                    &name,
+                    &profile,
+                    Column::Both,
+                    global_settings.legacy_configs,
+                    &global_settings.hooks,
+                )?);
+                return Ok(());
+            }
+            if let Some((reason, _)) = status.explain() {
                 println!(
-                    "Credentials expired for '{}'. Re-authenticating...",
-                    profile.user_account
+                    "Credentials for '{}': {}. Re-authenticating...",
+                    profile.user_account, reason
                 );
+                print_login_hint();
                 gcloud::reauth_user(&profile.user_account)?;
+                global_settings.hooks.run(HookEvent::ReauthCompleted, hooks::profile_data(&name, &profile));
+            } else if status == gcloud::AuthStatus::Unknown {
+                eprintln!(
+                    "⚠️  Could not reach Google to check the credentials for '{}'; switching without signing in again.",
+                    profile.user_account
+                );
             }
 
-            gcloud::activate_both(&store, &name, &profile.user_account, &profile.user_project)?;
+            // One updating line on a terminal; scripts only see the result
+            let interactive = io::stderr().is_terminal();
+            let activated = gcloud::activate_both_with_progress(
+                &store,
+                &name,
+                &profile.user_account,
+                &profile.user_project,
+                &mut |step| {
+                    if interactive {
+                        eprint!("\r\x1b[2KActivating '{}': {}\u{2026}", name, step);
+                    }
+                },
+            );
+            if interactive {
+                eprint!("\r\x1b[2K");
+            }
+            activated?;
             data.active_profile = Some(name.clone());
             store.save_profiles(&data)?;
+            store.record_activation(&name);
             println!("Switched to profile '{}'.", name);
+            if gcloud::adc_strategy() == gcloud::AdcStrategy::Env && store.has_adc(&name) {
+                let adc = store.adc_path(&name);
+                if std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS").is_none_or(|current| adc != current) {
+                    eprintln!("ADC is not copied (adc_strategy = \"env\"); run eval \"$(gcloud-switch env)\" to use it here.");
+                }
+            }
+            if let Err(e) = gcloud::apply_properties(&name, &profile.gcloud_properties) {
+                eprintln!("⚠️  gcloud property snapshot not applied: {:#}", e);
+            }
+            if let Err(e) = providers::activate(&profile) {
+                eprintln!("⚠️  {:#}", e);
+            }
+            match docker::configure_registries(&profile.registries) {
+                Ok(added) if !added.is_empty() => {
+                    println!("Docker now authenticates {} through gcloud.", added.join(", "));
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("⚠️  Docker credential helpers not configured: {:#}", e),
+            }
+            if global_settings.legacy_configs {
+                match legacy::update(&store, &name, &profile) {
+                    Ok(files) => {
+                        for file in files {
+                            println!("Updated {}.", file.display());
+                        }
+                    }
+                    Err(e) => eprintln!("⚠️  Legacy SDK configs not updated: {:#}", e),
+                }
+            }
+            let refreshed = gcloud::refresh_clusters(&profile.clusters, &profile.user_account, &profile.user_project, |cluster, result| {
+                match result {
+                    Ok(()) => println!("Refreshed kubectl context for GKE cluster {}.", cluster),
+                    Err(e) => eprintln!("⚠️  GKE cluster {}: {:#}", cluster, e),
+                }
+                true
+            });
+            if let Err(e) = refreshed {
+                eprintln!("⚠️  {:#}", e);
+            }
+            let mut hook_data = hooks::profile_data(&name, &profile);
+            hook_data["scope"] = "both".into();
+            global_settings.hooks.run(HookEvent::ProfileActivated, hook_data);
+        }
+        Some(Commands::Lock { name }) => {
+            set_locked(&Store::new()?, &name, true)?;
+            println!("Locked '{}'; 'gcloud-switch unlock {}' allows changes again.", name, name);
         }
-        Some(Commands::Import) => {
+        Some(Commands::Unlock { name }) => {
+            set_locked(&Store::new()?, &name, false)?;
+            println!("Unlocked '{}'.", name);
+        }
+        Some(Commands::Protect { name, off }) => {
             let store = Store::new()?;
-            let count = import_profiles(&store)?;
+            let mut data = store.load_profiles()?;
+            let profile = data
+                .profiles
+                .get_mut(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            profile.ensure_unlocked(&name)?;
+            if profile.confirm_before_activate == off {
+                profile.confirm_before_activate = !off;
+                profile.touch();
+                store.save_profiles(&data)?;
+            }
+            if off {
+                println!("'{}' no longer asks before switching.", name);
+            } else {
+                println!("'{}' now asks before switching (switch --force skips the question).", name);
+            }
+        }
+        Some(Commands::Context { name, provider, value, clear }) => {
+            if providers::find(&provider).is_none() {
+                let known: Vec<_> = providers::PROVIDERS.iter().map(|p| p.name()).collect();
+                anyhow::bail!("Unknown provider '{}' (known: {})", provider, known.join(", "));
+            }
+            let store = Store::new()?;
+            let mut data = store.load_profiles()?;
+            let profile = data
+                .profiles
+                .get_mut(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            if clear || value.is_some() {
+                profile.ensure_unlocked(&name)?;
+            }
+            if clear {
+                if profile.contexts.remove(&provider).is_some() {
+                    profile.touch();
+                    store.save_profiles(&data)?;
+                }
+                println!("Cleared {} context for '{}'.", provider, name);
+            } else if let Some(value) = value {
+                profile.contexts.insert(provider.clone(), value.clone());
+                profile.touch();
+                store.save_profiles(&data)?;
+                println!("Set {} context for '{}' to '{}'.", provider, name, value);
+            } else {
+                match profile.contexts.get(&provider) {
+                    Some(context) => println!("{}", context),
+                    None => println!("No {} context set for '{}'.", provider, name),
+                }
+            }
+        }
+        Some(Commands::Refresh { profiles, all, watch, interval }) => {
+            net::ensure_online("Refreshing access tokens")?;
+            let store = Store::new()?;
+            loop {
+                refresh_tokens(&store, &profiles, all)?;
+                if !watch {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_secs(interval.max(1) * 60));
+            }
+        }
+        Some(Commands::Reauth { profiles, all_expired }) => {
+            let store = Store::new()?;
+            reauth_profiles(&store, &profiles, all_expired, &global_settings.hooks, dry_run)?;
+        }
+        Some(Commands::Token { profile, adc }) => {
+            let store = Store::new()?;
+            println!("{}", access_token(&store, profile, adc)?);
+        }
+        Some(Commands::Console { name, adc, no_account, print }) => {
+            let store = Store::new()?;
+            let data = store.load_profiles()?;
+            let name = name
+                .or(data.active_profile.clone())
+                .context("No profile given and no active profile")?;
+            let profile = data
+                .profiles
+                .get(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            let (project, account) = if adc {
+                (&profile.adc_quota_project, &profile.adc_account)
+            } else {
+                (&profile.user_project, &profile.user_account)
+            };
+            let url = gcloud::console_url(project, (!no_account).then_some(account.as_str()));
+            if print {
+                println!("{}", url);
+            } else {
+                println!("🌐 Opening {}", url);
+                opener::open(&url)?;
+            }
+        }
+        Some(Commands::Clusters { name, clusters, clear, available }) => {
+            for cluster in &clusters {
+                gcloud::GkeCluster::parse(cluster)?;
+            }
+            let store = Store::new()?;
+            let mut data = store.load_profiles()?;
+            let profile = data
+                .profiles
+                .get_mut(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            if available {
+                let found = gcloud::list_clusters_blocking(&profile.user_account, &profile.user_project)?;
+                if found.is_empty() {
+                    println!("No GKE clusters in project '{}'.", profile.user_project);
+                }
+                for cluster in found {
+                    let spec = cluster.to_string();
+                    let linked = profile.clusters.contains(&spec);
+                    println!("{} {}", if linked { '*' } else { ' ' }, spec);
+                }
+            } else if clear || !clusters.is_empty() {
+                profile.ensure_unlocked(&name)?;
+                let count = clusters.len();
+                profile.clusters = clusters;
+                profile.touch();
+                store.save_profiles(&data)?;
+                if clear {
+                    println!("Cleared GKE clusters for '{}'.", name);
+                } else {
+                    println!("Set {} GKE cluster(s) for '{}'.", count, name);
+                }
+            } else if profile.clusters.is_empty() {
+                println!("No GKE clusters set for '{}'.", name);
+            } else {
+                for cluster in &profile.clusters {
+                    println!("{}", cluster);
+                }
+            }
+        }
+        Some(Commands::Registries { name, registries, clear }) => {
+            let store = Store::new()?;
+            let mut data = store.load_profiles()?;
+            let profile = data
+                .profiles
+                .get_mut(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            if clear || !registries.is_empty() {
+                profile.ensure_unlocked(&name)?;
+                let hosts: Vec<String> = registries.iter().map(|r| docker::registry_host(r)).collect();
+                profile.registries = registries;
+                profile.touch();
+                store.save_profiles(&data)?;
+                if clear {
+                    println!("Cleared Docker registries for '{}'.", name);
+                } else {
+                    println!("Docker registries for '{}': {}. They apply on the next switch.", name, hosts.join(", "));
+                }
+            } else if profile.registries.is_empty() {
+                println!("No Docker registries set for '{}'.", name);
+            } else {
+                for registry in &profile.registries {
+                    println!("{}", docker::registry_host(registry));
+                }
+            }
+        }
+        Some(Commands::Snapshot { name, from, clear }) => {
+            let store = Store::new()?;
+            let mut data = store.load_profiles()?;
+            let profile = data
+                .profiles
+                .get_mut(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            profile.ensure_unlocked(&name)?;
+            if clear {
+                profile.gcloud_properties.clear();
+                println!("Dropped the gcloud property snapshot of '{}'.", name);
+            } else {
+                let source = match from {
+                    Some(source) => source,
+                    None => gcloud::read_active_config()?.context("gcloud has no active configuration")?,
+                };
+                profile.gcloud_properties = gcloud::snapshot_properties(&source)?;
+                println!(
+                    "Saved {} propert{} of configuration '{}' in '{}'.",
+                    profile.gcloud_properties.len(),
+                    if profile.gcloud_properties.len() == 1 { "y" } else { "ies" },
+                    source,
+                    name
+                );
+            }
+            profile.touch();
+            store.save_profiles(&data)?;
+        }
+        Some(Commands::Ssh { instance, profile, zone, iap, save, args }) => {
+            let store = Store::new()?;
+            let mut data = store.load_profiles()?;
+            let name = profile
+                .or(data.active_profile.clone())
+                .context("No --profile given and no active profile")?;
+            let profile = data
+                .profiles
+                .get_mut(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            let settings = profile.ssh.clone().unwrap_or_default();
+            if save {
+                profile.ensure_unlocked(&name)?;
+                profile.ssh = Some(profile::SshSettings {
+                    zone: zone.clone().or(settings.zone.clone()),
+                    iap: iap || settings.iap,
+                });
+                profile.touch();
+            }
+            let profile = profile.clone();
+            if save {
+                store.save_profiles(&data)?;
+            }
+            let zone = zone.or(settings.zone).or_else(|| {
+                gcloud::read_configuration(&name)
+                    .ok()
+                    .flatten()
+                    .and_then(|props| props.get("compute/zone").cloned())
+            });
+
+            // Same check as `switch`, so the session never runs on stale credentials
+            let status = gcloud::check_account_auth_cached_blocking(&store, &profile.user_account);
+            if let Some((reason, _)) = status.explain() {
+                println!(
+                    "Credentials for '{}': {}. Re-authenticating...",
+                    profile.user_account, reason
+                );
+                print_login_hint();
+                gcloud::reauth_user(&profile.user_account)?;
+                global_settings.hooks.run(HookEvent::ReauthCompleted, hooks::profile_data(&name, &profile));
+            }
+
+            let mut ssh_args = vec!["compute".to_string(), "ssh".to_string(), instance];
+            if !profile.user_account.is_empty() {
+                ssh_args.push(format!("--account={}", profile.user_account));
+            }
+            if !profile.user_project.is_empty() {
+                ssh_args.push(format!("--project={}", profile.user_project));
+            }
+            if let Some(zone) = zone {
+                ssh_args.push(format!("--zone={}", zone));
+            }
+            if iap || settings.iap {
+                ssh_args.push("--tunnel-through-iap".to_string());
+            }
+            if !args.is_empty() {
+                ssh_args.push("--".to_string());
+                ssh_args.extend(args);
+            }
+            eprintln!("Connecting as {} (profile '{}')...", profile.user_account, name);
+            let ssh_args: Vec<&str> = ssh_args.iter().map(String::as_str).collect();
+            let status = gcloud::compute_ssh(&ssh_args)?;
+            if !status.success() {
+                std::process::exit(status.code().unwrap_or(1));
+            }
+        }
+        Some(Commands::Run { profile, command }) => {
+            let store = Store::new()?;
+            let data = store.load_profiles()?;
+            let name = profile
+                .or(data.active_profile.clone())
+                .context("No --profile given and no active profile")?;
+            let profile = data
+                .profiles
+                .get(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            let code = run_isolated(&store, &name, profile, &command)?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+        Some(Commands::Terraform { name, impersonate, backend_config }) => {
+            let store = Store::new()?;
+            let data = store.load_profiles()?;
+            let name = name
+                .or(data.active_profile.clone())
+                .context("No profile given and no active profile")?;
+            let profile = data
+                .profiles
+                .get(&name)
+                .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+            match backend_config {
+                Some(path) if path.as_os_str() == "-" => {
+                    print!("{}", terraform::render_backend(&name, profile)?);
+                    return Ok(());
+                }
+                Some(path) => {
+                    let rendered = terraform::render_backend(&name, profile)?;
+                    std::fs::write(&path, rendered)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    // stderr, so the exports on stdout can still be evaluated
+                    eprintln!("Wrote {}; run `terraform init -backend-config={}`.", path.display(), path.display());
+                }
+                None => {}
+            }
+            for line in terraform::exports(&store, &name, profile, impersonate.as_deref()) {
+                println!("{}", line);
+            }
+        }
+        Some(Commands::Env) => {
+            let store = Store::new()?;
+            let data = store.load_profiles()?;
+            let active = data.active_profile.as_ref().and_then(|name| data.profiles.get(name));
+            for line in providers::env_exports(active) {
+                println!("{}", line);
+            }
+            if gcloud::adc_strategy() == gcloud::AdcStrategy::Env {
+                let adc = data
+                    .active_profile
+                    .as_ref()
+                    .map(|name| store.adc_path(name))
+                    .filter(|path| path.exists());
+                let adc = adc.as_ref().map(|path| path.to_string_lossy());
+                println!("{}", providers::export_line("GOOGLE_APPLICATION_CREDENTIALS", adc.as_deref()));
+            }
+        }
+        Some(Commands::Adc { sub }) => run_adc(sub)?,
+        Some(Commands::Import { file: Some(path) }) => {
+            let store = Store::new()?;
+            let count = import_file(&store, &path, &global_settings.hooks)?;
+            if count == 0 {
+                println!("No new profiles in {}.", path.display());
+            }
+        }
+        Some(Commands::Import { file: None }) => {
+            let store = Store::new()?;
+            let count = import_profiles(&store, &global_settings.hooks)?;
             if count == 0 {
                 println!("No new gcloud configurations found to import.");
             }
@@ -351,8 +1492,29 @@ fn main() -> Result<()> {
             no_open_readme,
             check_only,
             skip_checksum,
+            pre,
+            yes,
         }) => {
-            run_self_update(!no_download_readme, !no_open_readme, check_only, skip_checksum, global_settings.editor.as_deref())?;
+            let channel = if pre { UpdateChannel::Prerelease } else { global_settings.channel };
+            run_self_update(!no_download_readme, !no_open_readme, check_only, skip_checksum, yes, channel, global_settings.editor.as_deref())?;
+        }
+        Some(Commands::Ci { sub: CiSub::Apply { name, env_file, adc_env } }) => {
+            ci::apply(&name, &adc_env, env_file.as_deref(), &global_settings.hooks)?;
+        }
+        Some(Commands::Daemon { socket }) => {
+            let socket = match socket {
+                Some(path) => path,
+                None => daemon::default_socket_path(&Store::new()?),
+            };
+            if global_settings.notifications.enabled {
+                notifier::spawn(global_settings.notifications.clone());
+            }
+            daemon::run(
+                &socket,
+                global_settings.hooks.clone(),
+                global_settings.legacy_configs,
+                global_settings.guard.clone(),
+            )?;
         }
         Some(Commands::OpenReadme) => {
             run_open_readme(global_settings.editor.as_deref())?;
@@ -376,66 +1538,714 @@ fn main() -> Result<()> {
                 save_global_settings(&global_settings)?;
                 println!("✅ editor set to \"{}\".", e);
             } else {
-                match &global_settings.editor {
-                    Some(e) => println!("editor = \"{}\"", e),
-                    None => println!("editor is not set (using $EDITOR / OS default)."),
-                }
+                match &global_settings.editor {
+                    Some(e) => println!("editor = \"{}\"", e),
+                    None => println!("editor is not set (using $EDITOR / OS default)."),
+                }
+            }
+        }
+        Some(Commands::ShowConfig) => {
+            let path = global_settings_path().context("Could not determine config directory")?;
+            if path.exists() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                print!("{}", content);
+            } else {
+                println!("Config file does not exist yet: {}", path.display());
+            }
+        }
+        Some(Commands::EditConfig) => {
+            let path = global_settings_path().context("Could not determine config directory")?;
+            if !path.exists() {
+                // Ensure the file exists before opening
+                save_global_settings(&global_settings)?;
+            }
+            open_file(&path, global_settings.editor.as_deref())?;
+        }
+        Some(Commands::Sync { sub }) => {
+            let store = Store::new()?;
+            match sub {
+                SyncSub::Init { remote_url, branch, backend, device } => {
+                    let backend = sync::SyncBackend::from(backend);
+                    // The merge base describes the old remote; the first pull from a new one
+                    // merges whole profiles by timestamp
+                    if global_settings.remote_url.as_deref() != Some(remote_url.as_str())
+                        || global_settings.sync_backend != backend
+                    {
+                        store.clear_sync_base()?;
+                    }
+                    global_settings.remote_url = Some(remote_url.clone());
+                    global_settings.branch = Some(branch.clone());
+                    global_settings.sync_backend = backend;
+                    if device.is_some() || global_settings.device_name.is_none() {
+                        global_settings.device_name = Some(device.unwrap_or_else(default_device_name));
+                    }
+                    save_global_settings(&global_settings)?;
+                    println!("Sync config saved. Run 'gcloud-switch sync push' to push, or 'sync pull' to pull.");
+                    if backend.is_git() {
+                        sync::ensure_cloned(&store, &remote_url, &branch)?;
+                        println!("Remote cloned to {}.", store.sync_repo_path().display());
+                    }
+                }
+                SyncSub::Push => {
+                    let target = sync_target(&global_settings)?
+                        .ok_or_else(|| anyhow::anyhow!("Sync not configured. Run 'gcloud-switch sync init <remote_url>' first."))?;
+                    sync::sync_push(&store, &target)?;
+                    println!("Pushed profiles to remote.");
+                }
+                SyncSub::Pull { ours, theirs, newer } => {
+                    let target = sync_target(&global_settings)?
+                        .ok_or_else(|| anyhow::anyhow!("Sync not configured. Run 'gcloud-switch sync init <remote_url>' first."))?;
+                    let strategy = if ours {
+                        sync::MergeStrategy::Ours
+                    } else if theirs {
+                        sync::MergeStrategy::Theirs
+                    } else if newer {
+                        sync::MergeStrategy::Newer
+                    } else {
+                        sync::MergeStrategy::Defer
+                    };
+                    if dry_run {
+                        print_pull_preview(&sync::preview_pull(&store, &target, strategy)?);
+                        return Ok(());
+                    }
+                    // Without a flag, ask about what the timestamps leave open
+                    let mut prompt = prompt_which_to_keep;
+                    let choose: Option<&mut sync::ChooseSide> =
+                        if strategy == sync::MergeStrategy::Defer { Some(&mut prompt) } else { None };
+                    let conflicts = sync::sync_pull(&store, &target, strategy, choose)?;
+                    println!("Pulled and merged profiles from remote.");
+                    global_settings.hooks.run(HookEvent::SyncPulled, hooks::pull_data(&conflicts));
+                }
+                SyncSub::Status => {
+                    let target = sync_target(&global_settings)?
+                        .ok_or_else(|| anyhow::anyhow!("Sync not configured. Run 'gcloud-switch sync init <remote_url>' first."))?;
+                    print_sync_status(&target, &sync::sync_status(&store, &target)?);
+                }
+                SyncSub::Encrypt { key_store, key, off } => {
+                    let key_store = crypto::KeyStore::from(key_store);
+                    if off {
+                        global_settings.sync_encryption = None;
+                        save_global_settings(&global_settings)?;
+                        println!("Sync encryption turned off. The next push uploads plain files.");
+                        return Ok(());
+                    }
+                    let identity = match key {
+                        Some(key) => crypto::parse_identity(&key)?,
+                        None => match crypto::load_identity(key_store)? {
+                            Some(identity) => identity,
+                            None => crypto::generate_identity(),
+                        },
+                    };
+                    crypto::save_identity(key_store, &identity)?;
+                    global_settings.sync_encryption = Some(key_store);
+                    save_global_settings(&global_settings)?;
+                    println!("Sync encryption turned on. The next push uploads encrypted files.");
+                    println!("On your other machines, run:");
+                    println!("  gcloud-switch sync encrypt --key {}", crypto::identity_secret(&identity));
+                }
+            }
+        }
+        None => {
+            run_tui(&global_settings, sync_target(&global_settings)?, cli.verbose)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Renew the access tokens of the given profiles' user accounts (all with `all`, else the
+/// active profile), reporting each. Reloads profiles.toml so a long --watch sees changes.
+fn refresh_tokens(store: &Store, names: &[String], all: bool) -> Result<()> {
+    let data = store.load_profiles()?;
+    let names: Vec<String> = if all {
+        data.profiles.keys().cloned().collect()
+    } else if names.is_empty() {
+        vec![data.active_profile.clone().context("No profile given and no active profile")?]
+    } else {
+        names.to_vec()
+    };
+    let mut done = std::collections::HashSet::new();
+    for name in &names {
+        let profile = data
+            .profiles
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+        let account = &profile.user_account;
+        // Profiles often share an account; one refresh covers them all
+        if account.is_empty() || !done.insert(account.clone()) {
+            continue;
+        }
+        match gcloud::refresh_access_token(account) {
+            Ok(()) => match gcloud::read_token_expiry(account).ok().flatten() {
+                Some(expiry) => println!("{} ({}): token valid until {} UTC", name, account, ui::format_clock(expiry)),
+                None => println!("{} ({}): token refreshed", name, account),
+            },
+            Err(e) => eprintln!(
+                "⚠️  {} ({}): {:#}; run `gcloud-switch switch {}` to sign in again",
+                name, account, e, name
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Log in again for the named profiles (or the active one), or with `all_expired` for the
+/// sides of every profile whose credentials need it. An account shared by several profiles
+/// logs in once. Continues past failures and fails at the end if any login did.
+fn reauth_profiles(store: &Store, names: &[String], all_expired: bool, hooks: &Hooks, dry_run: bool) -> Result<()> {
+    if !dry_run {
+        net::ensure_online("Re-authenticating")?;
+    }
+    let data = store.load_profiles()?;
+    let names: Vec<String> = if all_expired {
+        data.profiles.keys().cloned().collect()
+    } else if names.is_empty() {
+        vec![data.active_profile.clone().context("No profile given and no active profile")?]
+    } else {
+        names.to_vec()
+    };
+    let mut plan = Vec::new();
+    for name in &names {
+        let profile = data
+            .profiles
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+        let expired = |account: &str| {
+            !account.is_empty() && gcloud::check_account_auth_cached_blocking(store, account).needs_reauth()
+        };
+        let (user, adc) = if all_expired {
+            (expired(&profile.user_account), expired(&profile.adc_account))
+        } else {
+            (true, true)
+        };
+        if user || adc {
+            plan.push((name, profile, user, adc));
+        }
+    }
+    if plan.is_empty() {
+        println!("No profile needs a login.");
+        return Ok(());
+    }
+
+    let mut logged_in = std::collections::HashSet::new();
+    let mut failed = Vec::new();
+    let mut adc_changed = false;
+    for (name, profile, user, adc) in plan {
+        // Profiles often share an account; one login covers them all
+        let user = user && !logged_in.contains(&profile.user_account);
+        if !user && !adc {
+            continue;
+        }
+        let sides = match (user, adc) {
+            (true, true) => "user and ADC",
+            (true, false) => "user",
+            _ => "ADC",
+        };
+        if dry_run {
+            println!("# {} for '{}'", sides, name);
+            print_plan(&gcloud::reauth_plan(store, name, profile, user, adc)?);
+            print_hook_plan(hooks, HookEvent::ReauthCompleted);
+            if user {
+                logged_in.insert(profile.user_account.clone());
+            }
+            adc_changed |= adc;
+            continue;
+        }
+        println!("Re-authenticating {} for '{}'...", sides, name);
+        print_login_hint();
+        match gcloud::reauth_sides(store, name, profile, user, adc) {
+            Ok(()) => {
+                if user {
+                    logged_in.insert(profile.user_account.clone());
+                }
+                adc_changed |= adc;
+                hooks.run(HookEvent::ReauthCompleted, hooks::profile_data(name, profile));
+            }
+            Err(e) => {
+                eprintln!("⚠️  {}: {:#}", name, e);
+                failed.push(name.clone());
+            }
+        }
+    }
+    // ADC logins overwrite the installed ADC; give it back to the active profile
+    if adc_changed {
+        if let Some(active) = data.active_profile.as_ref().filter(|name| store.has_adc(name)) {
+            if dry_run {
+                println!("# give the ADC back to '{}'", active);
+                print_plan(&gcloud::adc_activation_plan(store, active, false)?);
+            } else {
+                gcloud::activate_adc(store, active)?;
+            }
+        }
+    }
+    if !failed.is_empty() {
+        anyhow::bail!("Re-authentication failed for {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+/// Before an interactive login: where no browser can open, say how the sign-in URL works.
+fn print_login_hint() {
+    if let Some(hint) = gcloud::login_hint() {
+        eprintln!();
+        eprintln!("{}", hint);
+        eprintln!();
+    }
+}
+
+/// Print a `--dry-run` plan, one step per line: commands as they would run, the rest as
+/// `#` comments.
+fn print_plan(steps: &[gcloud::PlanStep]) {
+    for step in steps {
+        println!("{}", step);
+    }
+}
+
+/// The commands `event`'s hooks would run, for `--dry-run`.
+fn print_hook_plan(hooks: &Hooks, event: HookEvent) {
+    for command in hooks.commands(event) {
+        println!("{}", command);
+    }
+}
+
+/// Print the device history, most recently synced first, with this device marked.
+/// The access token for `gcloud-switch token`. Refresh tokens are exchanged directly;
+/// service accounts and other credential types go through gcloud.
+fn access_token(store: &Store, name: Option<String>, adc: bool) -> Result<String> {
+    let data = store.load_profiles()?;
+    let name = name
+        .or(data.active_profile.clone())
+        .context("No --profile given and no active profile")?;
+    let profile = data
+        .profiles
+        .get(&name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+    if adc {
+        let stored = store
+            .load_adc_json(&name)?
+            .with_context(|| format!("No ADC stored for '{}'", name))?;
+        if gcloud::has_refresh_token(&stored) {
+            return gcloud::mint_access_token_blocking(&stored);
+        }
+        // gcloud only prints tokens for the installed ADC
+        if gcloud::load_live_adc()?.is_some_and(|live| gcloud::same_adc(&stored, &live)) {
+            return gcloud::print_adc_access_token();
+        }
+        anyhow::bail!(
+            "The ADC of '{}' ({}) has no refresh token; activate its ADC first to get a token through gcloud",
+            name,
+            gcloud::describe_adc(&stored)
+        );
+    }
+    let account = &profile.user_account;
+    if account.is_empty() {
+        anyhow::bail!("Profile '{}' has no user account", name);
+    }
+    match gcloud::read_gcloud_credentials(account)? {
+        Some(credentials) if gcloud::has_refresh_token(&credentials) => gcloud::mint_access_token_blocking(&credentials)
+            .map_err(|e| anyhow::anyhow!("{:#}. Run `gcloud-switch switch {}` to sign in to {} again.", e, name, account)),
+        Some(_) => gcloud::print_access_token(account),
+        None => anyhow::bail!("No gcloud credentials for {}; run `gcloud-switch switch {}` to sign in", account, name),
+    }
+}
+
+fn run_adc(sub: AdcSub) -> Result<()> {
+    let store = Store::new()?;
+    let data = store.load_profiles()?;
+    let require = |name: &str| {
+        data.profiles
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))
+    };
+    let live = gcloud::load_live_adc()?;
+    match sub {
+        AdcSub::Show { name: None } => {
+            let path = gcloud::live_adc_path()?;
+            let Some(live) = live else {
+                println!("No ADC installed at {}.", path.display());
+                return Ok(());
+            };
+            println!("Installed: {}", path.display());
+            println!("  {}", gcloud::describe_adc(&live));
+            let owners: Vec<&String> = data
+                .profiles
+                .keys()
+                .filter(|name| {
+                    store
+                        .load_adc_json(name)
+                        .ok()
+                        .flatten()
+                        .is_some_and(|stored| gcloud::same_adc(&stored, &live))
+                })
+                .collect();
+            if owners.is_empty() {
+                println!("  Not stored for any profile; `gcloud-switch adc import <profile>` adopts it.");
+            }
+            for name in owners {
+                let account = &data.profiles[name].adc_account;
+                if account.is_empty() {
+                    println!("  Profile '{}'", name);
+                } else {
+                    println!("  Profile '{}' ({})", name, account);
+                }
+            }
+        }
+        AdcSub::Show { name: Some(name) } => {
+            let profile = require(&name)?;
+            let Some(stored) = store.load_adc_json(&name)? else {
+                println!("No ADC stored for '{}'.", name);
+                return Ok(());
+            };
+            println!("{}: {}", name, store.adc_path(&name).display());
+            println!("  {}", gcloud::describe_adc(&stored));
+            if !profile.adc_account.is_empty() {
+                println!("  Account: {}", profile.adc_account);
+            }
+            let installed = live.is_some_and(|live| gcloud::same_adc(&stored, &live));
+            println!("  {}", if installed { "Installed" } else { "Not installed" });
+        }
+        AdcSub::Path { name } => {
+            let name = name
+                .or(data.active_profile.clone())
+                .context("No profile given and no active profile")?;
+            require(&name)?;
+            if !store.has_adc(&name) {
+                anyhow::bail!("No ADC stored for '{}'", name);
+            }
+            println!("{}", store.adc_path(&name).display());
+        }
+        AdcSub::Clear { name } => {
+            require(&name)?;
+            let Some(stored) = store.load_adc_json(&name)? else {
+                println!("No ADC stored for '{}'.", name);
+                return Ok(());
+            };
+            store.remove_adc(&name)?;
+            println!("Deleted the stored ADC of '{}'.", name);
+            if live.is_some_and(|live| gcloud::same_adc(&stored, &live)) {
+                println!("It is still installed; switching to another profile's ADC replaces it.");
+            }
+        }
+        AdcSub::Import { name } => {
+            require(&name)?;
+            let replaced = store.has_adc(&name);
+            gcloud::import_live_adc(&store, &name)?;
+            if replaced {
+                println!("Replaced the stored ADC of '{}' with the installed one.", name);
+            } else {
+                println!("Stored the installed ADC for '{}'.", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_sync_status(target: &sync::SyncTarget, devices: &sync::DevicesFile) {
+    let backend = match target.backend {
+        sync::SyncBackend::Git => "git",
+        sync::SyncBackend::Gist => "gist",
+        sync::SyncBackend::Http => "http",
+    };
+    println!("Remote: {} ({})", target.remote_url, backend);
+    println!("This device: {}", target.device);
+    if devices.devices.is_empty() {
+        println!("No device has pushed yet.");
+        return;
+    }
+    let mut rows: Vec<_> = devices.devices.iter().collect();
+    rows.sort_by_key(|(_, info)| std::cmp::Reverse(info.last_push.max(info.last_pull)));
+    let name_w = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("Device".len());
+    let when = |ts: Option<i64>| ts.map(format_age).unwrap_or_else(|| "never".to_string());
+    println!();
+    println!("{:<name_w$}  {:<16}  Last pull", "Device", "Last push");
+    for (name, info) in rows {
+        let marker = if *name == target.device { "(this device)" } else { "" };
+        let line = format!(
+            "{:<name_w$}  {:<16}  {:<16}{}",
+            name,
+            when(info.last_push),
+            when(info.last_pull),
+            marker
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Coarse age of a Unix timestamp, e.g. "5 minutes ago" or "3 days ago".
+fn format_age(ts: i64) -> String {
+    let secs = (profile::now() - ts).max(0);
+    let (n, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86_399 => (secs / 3600, "hour"),
+        _ => (secs / 86_400, "day"),
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
+
+/// Ask on the terminal which side of a sync conflict to keep, after a table of the fields
+/// that differ.
+fn prompt_which_to_keep(conflict: &sync::Conflict) -> Result<sync::MergeChoice> {
+    use std::io::Write;
+    eprintln!("Profile '{}' changed on both sides.", conflict.name);
+    let fields = conflict.local.diff(&conflict.remote);
+    let label_w = fields.iter().map(|(label, _, _)| label.len()).max().unwrap_or(0).max(5);
+    let local_w = fields.iter().map(|(_, l, _)| l.chars().count()).max().unwrap_or(0).max(6);
+    eprintln!("  {:label_w$}  {:local_w$}  Remote", "", "Local");
+    for (label, l, r) in &fields {
+        let or_dash = |v: &str| if v.is_empty() { "-".to_string() } else { v.to_string() };
+        eprintln!("  {:label_w$}  {:local_w$}  {}", label, or_dash(l), or_dash(r));
+    }
+    eprint!("Keep (L)ocal or (R)emote? [L/r]: ");
+    io::stderr().flush()?;
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf)?;
+    if buf.trim().to_lowercase().starts_with('r') {
+        Ok(sync::MergeChoice::Remote)
+    } else {
+        Ok(sync::MergeChoice::Local)
+    }
+}
+
+/// Print a pull preview like `git diff --stat`: one line per profile, then its changed fields.
+fn print_pull_preview(preview: &sync::PullPreview) {
+    use sync::ChangeKind;
+
+    if preview.profiles.is_empty() && preview.files.is_empty() {
+        println!("Already up to date.");
+        return;
+    }
+    let count = |kind| preview.profiles.iter().filter(|c| c.kind == kind).count();
+    for change in &preview.profiles {
+        let marker = match change.kind {
+            ChangeKind::Added => '+',
+            ChangeKind::Updated => '~',
+            ChangeKind::Deleted => '-',
+            ChangeKind::Conflict => '!',
+        };
+        println!("{} {}", marker, change.name);
+        for (label, local, incoming) in &change.fields {
+            match change.kind {
+                ChangeKind::Added => println!("    {:<14} {}", label, incoming),
+                ChangeKind::Conflict => {
+                    println!("    {:<14} local: {}  remote: {}", label, local, incoming)
+                }
+                _ => println!("    {:<14} {} -> {}", label, local, incoming),
+            }
+        }
+    }
+    for file in &preview.files {
+        println!("~ {} (replaced)", file);
+    }
+    println!(
+        "{} added, {} updated, {} deleted, {} conflicting. Nothing was changed; run 'gcloud-switch sync pull' to apply.",
+        count(ChangeKind::Added),
+        count(ChangeKind::Updated),
+        count(ChangeKind::Deleted),
+        count(ChangeKind::Conflict),
+    );
+}
+
+fn set_locked(store: &Store, name: &str, locked: bool) -> Result<()> {
+    let mut data = store.load_profiles()?;
+    let profile = data
+        .profiles
+        .get_mut(name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+    if profile.locked != locked {
+        profile.locked = locked;
+        profile.touch();
+        store.save_profiles(&data)?;
+    }
+    Ok(())
+}
+
+/// The answer to "what am I pointed at": the active profile against gcloud's active
+/// configuration, the installed ADC and whose it is, sync setup and drifted profiles.
+fn print_status(store: &Store, settings: &GlobalSettings) -> Result<()> {
+    let data = store.load_profiles()?;
+    let active = data.active_profile.as_deref();
+    let gcloud_active = gcloud::read_active_config()?;
+
+    match active {
+        Some(name) => println!("Profile:  {}", name),
+        None => println!("Profile:  none active"),
+    }
+    // The active profile's stored ADC is the likely match, so try it first
+    let candidates = active
+        .into_iter()
+        .chain(data.profiles.keys().map(String::as_str))
+        .filter(|name| store.has_adc(name));
+    let live = LiveContext::read(store, gcloud_active.as_deref(), candidates);
+    let config = match (gcloud_active.as_deref(), active) {
+        (Some(config), Some(name)) if config == name => format!("'{}'", config),
+        (Some(config), Some(name)) => format!("'{}' (not the active profile '{}')", config, name),
+        (Some(config), None) => format!("'{}'", config),
+        (None, _) => "none active".to_string(),
+    };
+    println!("gcloud:   configuration {}", config);
+    if !live.account.is_empty() || !live.project.is_empty() {
+        println!("          account {}, project {}", or_dash(&live.account), or_dash(&live.project));
+    }
+    match (&live.adc, &live.adc_profile) {
+        (None, _) => println!("ADC:      none installed"),
+        (Some(adc), Some(owner)) => {
+            let account = &data.profiles[owner].adc_account;
+            println!("ADC:      {}", adc);
+            if account.is_empty() {
+                println!("          stored for '{}'", owner);
+            } else {
+                println!("          stored for '{}' ({})", owner, account);
             }
         }
-        Some(Commands::ShowConfig) => {
-            let path = global_settings_path().context("Could not determine config directory")?;
-            if path.exists() {
-                let content = std::fs::read_to_string(&path)
-                    .with_context(|| format!("Failed to read {}", path.display()))?;
-                print!("{}", content);
-            } else {
-                println!("Config file does not exist yet: {}", path.display());
-            }
+        (Some(adc), None) => {
+            println!("ADC:      {}", adc);
+            println!("          not stored for any profile; `gcloud-switch adc import <profile>` adopts it");
         }
-        Some(Commands::EditConfig) => {
-            let path = global_settings_path().context("Could not determine config directory")?;
-            if !path.exists() {
-                // Ensure the file exists before opening
-                save_global_settings(&global_settings)?;
+    }
+
+    let sync_mode = match data.sync_mode {
+        SyncMode::Strict => "strict",
+        SyncMode::Add => "add",
+        SyncMode::Off => "off",
+        SyncMode::Mirror => "mirror",
+    };
+    println!("Mode:     {} (gcloud configurations)", sync_mode);
+    match &settings.remote_url {
+        Some(remote) => {
+            let backend = match settings.sync_backend {
+                sync::SyncBackend::Git => "git",
+                sync::SyncBackend::Gist => "gist",
+                sync::SyncBackend::Http => "http",
+            };
+            let encrypted = if settings.sync_encryption.is_some() { ", encrypted" } else { "" };
+            println!("Sync:     {} ({}{})", remote, backend, encrypted);
+            match sync::local_changes(store)? {
+                None => println!("          not pushed or pulled yet"),
+                Some(changed) if changed.is_empty() => println!("          no local changes since the last sync"),
+                Some(changed) => println!("          changed since the last sync: {}", changed.join(", ")),
             }
-            open_file(&path, global_settings.editor.as_deref())?;
         }
-        Some(Commands::Sync { sub }) => {
-            let store = Store::new()?;
-            match sub {
-                SyncSub::Init { remote_url, branch } => {
-                    global_settings.remote_url = Some(remote_url.clone());
-                    global_settings.branch = Some(branch.clone());
-                    save_global_settings(&global_settings)?;
-                    println!("Sync config saved. Run 'gcloud-switch sync push' to push, or 'sync pull' to pull.");
-                    sync::ensure_cloned(&store, &remote_url, &branch)?;
-                    println!("Remote cloned to {}.", store.sync_repo_path().display());
-                }
-                SyncSub::Push => {
-                    let remote_url = global_settings.remote_url.as_ref()
-                        .ok_or_else(|| anyhow::anyhow!("Sync not configured. Run 'gcloud-switch sync init <remote_url>' first."))?;
-                    let branch = global_settings.branch.as_deref().unwrap_or("main");
-                    sync::sync_push(&store, remote_url, branch, &global_settings.sync_files)?;
-                    println!("Pushed profiles to remote.");
-                }
-                SyncSub::Pull => {
-                    let remote_url = global_settings.remote_url.as_ref()
-                        .ok_or_else(|| anyhow::anyhow!("Sync not configured. Run 'gcloud-switch sync init <remote_url>' first."))?;
-                    let branch = global_settings.branch.as_deref().unwrap_or("main");
-                    sync::sync_pull(&store, remote_url, branch, &global_settings.sync_files)?;
-                    println!("Pulled and merged profiles from remote.");
-                }
-            }
+        None => println!("Sync:     not configured"),
+    }
+
+    let drifted: Vec<(&String, Drift)> = data
+        .profiles
+        .iter()
+        .map(|(name, profile)| (name, Drift::of(name, profile, active, gcloud_active.as_deref())))
+        .filter(|(_, drift)| !drift.is_empty())
+        .collect();
+    if drifted.is_empty() {
+        println!("Drift:    none");
+    }
+    for (i, (name, drift)) in drifted.iter().enumerate() {
+        let label = if i == 0 { "Drift:" } else { "" };
+        println!("{:<10}'{}': {}", label, name, drift.describe());
+    }
+    Ok(())
+}
+
+fn or_dash(value: &str) -> &str {
+    if value.is_empty() {
+        "-"
+    } else {
+        value
+    }
+}
+
+/// Check credentials for each account, a few at a time, through the state.toml cache.
+fn check_accounts(store: &Store, accounts: Vec<&str>) -> BTreeMap<String, gcloud::AuthStatus> {
+    let queue = std::sync::Mutex::new(accounts);
+    let results = std::sync::Mutex::new(BTreeMap::new());
+    std::thread::scope(|scope| {
+        for _ in 0..app::AUTH_WORKERS {
+            scope.spawn(|| loop {
+                let Some(account) = queue.lock().unwrap_or_else(|e| e.into_inner()).pop() else {
+                    break;
+                };
+                let status = gcloud::check_account_auth_cached_blocking(store, account);
+                results.lock().unwrap_or_else(|e| e.into_inner()).insert(account.to_string(), status);
+            });
         }
-        None => {
-            run_tui()?;
+    });
+    results.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Side-by-side comparison of two profiles, differing rows marked with `*`.
+fn print_profile_diff(store: &Store, a: &str, b: &str) -> Result<()> {
+    let rows = profile_diff(store, &store.load_profiles()?, a, b)?;
+
+    let label_w = rows.iter().map(|row| row.label.len()).max().unwrap_or(0);
+    let value_w = rows.iter().map(|row| row.a.chars().count()).max().unwrap_or(0).max(a.len());
+    println!("  {:<label_w$}  {:<value_w$}  {}", "", a, b);
+    for row in &rows {
+        let marker = if row.differ { '*' } else { ' ' };
+        let va = if row.a.is_empty() { "-" } else { &row.a };
+        let vb = if row.b.is_empty() { "-" } else { &row.b };
+        println!("{} {:<label_w$}  {:<value_w$}  {}", marker, row.label, va, vb);
+    }
+    let differing = rows.iter().filter(|row| row.differ).count();
+    if differing == 0 {
+        println!("'{}' and '{}' are identical.", a, b);
+    } else {
+        println!("{} of {} fields differ.", differing, rows.len());
+    }
+    Ok(())
+}
+
+/// `show`: one profile's fields, with the numbers of its projects resolved (and cached).
+fn print_profile(store: &Store, name: Option<String>) -> Result<()> {
+    let data = store.load_profiles()?;
+    let name = name
+        .or(data.active_profile.clone())
+        .context("No profile given and no active profile")?;
+    let profile = data
+        .profiles
+        .get(&name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))?;
+
+    let number = |account: &str, project: &str| -> String {
+        if project.is_empty() {
+            return String::new();
         }
+        match gcloud::project_number_blocking(store, account, project) {
+            Ok(Some(number)) => number,
+            Ok(None) if net::is_offline() => "(offline)".to_string(),
+            Ok(None) => "(not visible to the account)".to_string(),
+            Err(e) => format!("(lookup failed: {})", e),
+        }
+    };
+    let mut rows: Vec<(String, String)> = Vec::new();
+    for (label, value) in profile.fields() {
+        rows.push((label.to_string(), value));
+        if label == "User project" {
+            rows.push(("Project number".to_string(), number(&profile.user_account, &profile.user_project)));
+        } else if label == "Quota project" && profile.adc_quota_project != profile.user_project {
+            rows.push((
+                "Quota project number".to_string(),
+                number(&profile.adc_account, &profile.adc_quota_project),
+            ));
+        }
+    }
+    rows.push(("Sync".to_string(), if profile.sync { "yes" } else { "local only" }.to_string()));
+    let adc = store.load_adc_json(&name)?;
+    rows.push(("Stored ADC".to_string(), adc.as_ref().map_or("none".to_string(), gcloud::describe_adc)));
+    match gcloud::read_configuration(&name)? {
+        Some(props) => rows.extend(props),
+        None => rows.push(("gcloud config".to_string(), "missing".to_string())),
     }
+    rows.retain(|(_, value)| !value.is_empty());
 
+    let label_w = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    println!("{}", name);
+    for (label, value) in &rows {
+        println!("  {:<label_w$}  {}", label, value);
+    }
     Ok(())
 }
 
-fn import_profiles(store: &Store) -> Result<usize> {
+fn import_profiles(store: &Store, hooks: &Hooks) -> Result<usize> {
     let configs = gcloud::discover_existing_configs()?;
     if configs.is_empty() {
         return Ok(0);
@@ -443,6 +2253,7 @@ fn import_profiles(store: &Store) -> Result<usize> {
 
     let mut data = store.load_profiles()?;
     let mut count = 0;
+    let mut imported = Vec::new();
 
     for (name, account, project) in &configs {
         if data.profiles.contains_key(name) {
@@ -455,10 +2266,11 @@ fn import_profiles(store: &Store) -> Result<usize> {
             user_project: project.clone(),
             adc_account: account.clone(),
             adc_quota_project: project.clone(),
-            updated_at: None,
+            ..Default::default()
         };
         profile.touch();
-        data.profiles.insert(name.clone(), profile);
+        imported.push((name.clone(), profile.clone()));
+        data.insert_profile(name, profile);
         println!("Imported '{}'.", name);
         count += 1;
     }
@@ -473,86 +2285,73 @@ fn import_profiles(store: &Store) -> Result<usize> {
         }
         store.save_profiles(&data)?;
     }
+    let adc_accounts: Vec<(String, String)> =
+        imported.iter().map(|(name, profile)| (name.clone(), profile.adc_account.clone())).collect();
+    match gcloud::adopt_live_adc(store, &adc_accounts) {
+        Ok(Some(name)) => println!("Stored the installed ADC for '{}'.", name),
+        Ok(None) => {}
+        Err(e) => eprintln!("⚠️  Could not identify the installed ADC: {:#}", e),
+    }
+    for (name, profile) in &imported {
+        hooks.run(HookEvent::ProfileAdded, hooks::profile_data(name, profile));
+    }
 
     Ok(count)
 }
 
-fn sync_on_startup(store: &Store) -> Result<()> {
+/// Add the profiles listed in a YAML or CSV file, skipping names that exist. Like `add`,
+/// each gets a gcloud configuration unless sync mode is off.
+fn import_file(store: &Store, path: &Path, hooks: &Hooks) -> Result<usize> {
+    let entries = bulk::read(path)?;
     let mut data = store.load_profiles()?;
-
-    // First run: import if no profiles exist
-    if data.profiles.is_empty() {
-        import_profiles(store)?;
-        return Ok(());
-    }
-
-    let mut changed = false;
-
-    match data.sync_mode {
-        SyncMode::Off => {}
-        SyncMode::Add | SyncMode::Strict => {
-            let configs = gcloud::discover_existing_configs()?;
-            let config_names: std::collections::HashSet<String> =
-                configs.iter().map(|(n, _, _)| n.clone()).collect();
-
-            // Add new gcloud configs as profiles
-            for (name, account, project) in &configs {
-                if !data.profiles.contains_key(name) {
-                    let mut profile = Profile {
-                        user_account: account.clone(),
-                        user_project: project.clone(),
-                        adc_account: account.clone(),
-                        adc_quota_project: project.clone(),
-                        updated_at: None,
-                    };
-                    profile.touch();
-                    data.profiles.insert(name.clone(), profile);
-                    changed = true;
-                }
-            }
-
-            // In strict mode, delete profiles whose gcloud configs no longer exist
-            if data.sync_mode == SyncMode::Strict {
-                let to_delete: Vec<String> = data
-                    .profiles
-                    .keys()
-                    .filter(|name| !config_names.contains(*name))
-                    .cloned()
-                    .collect();
-                for name in &to_delete {
-                    data.profiles.remove(name);
-                    if data.active_profile.as_deref() == Some(name) {
-                        data.active_profile = None;
-                    }
-                    // Remove ADC file if it exists
-                    let adc_path = store.adc_path(name);
-                    if adc_path.exists() {
-                        let _ = std::fs::remove_file(adc_path);
-                    }
-                    changed = true;
-                }
-            }
+    let mut imported = Vec::new();
+    for entry in entries {
+        if data.profiles.contains_key(&entry.name) {
+            println!("Skipping '{}' (already exists).", entry.name);
+            continue;
         }
-    }
-
-    // Always sync active config from gcloud
-    if let Ok(Some(active)) = gcloud::read_active_config() {
-        if data.profiles.contains_key(&active) && data.active_profile.as_deref() != Some(&active) {
-            data.active_profile = Some(active);
-            changed = true;
+        let mut profile = Profile {
+            user_account: entry.account.clone(),
+            user_project: entry.project.clone(),
+            adc_account: entry.account,
+            adc_quota_project: entry.project,
+            tags: entry.tags,
+            ..Default::default()
+        };
+        if data.sync_mode != SyncMode::Off {
+            gcloud::create_configuration(&entry.name, &profile.user_account, &profile.user_project)?;
         }
+        profile.touch();
+        data.insert_profile(&entry.name, profile.clone());
+        // Saved one by one, so a gcloud failure further down leaves no orphaned configuration
+        store.save_profiles(&data)?;
+        println!("Imported '{}'.", entry.name);
+        imported.push((entry.name, profile));
+    }
+    for (name, profile) in &imported {
+        hooks.run(HookEvent::ProfileAdded, hooks::profile_data(name, profile));
     }
+    Ok(imported.len())
+}
 
-    if changed {
-        store.save_profiles(&data)?;
+fn sync_on_startup(store: &Store, hooks: &Hooks) -> Result<()> {
+    let data = store.load_profiles()?;
+
+    // First run: import if no profiles exist
+    if data.profiles.is_empty() {
+        import_profiles(store, hooks)?;
+        return Ok(());
     }
 
+    app::sync_with_gcloud(store)?;
     Ok(())
 }
 
-fn run_tui() -> Result<()> {
+fn run_tui(settings: &GlobalSettings, sync_target: Option<sync::SyncTarget>, verbose: bool) -> Result<()> {
+    let theme = Theme::from_settings(&settings.theme).context("Invalid [theme] in gcloud-switch.toml")?;
+    settings.table.validate().context("Invalid [table] in gcloud-switch.toml")?;
     let store = Store::new()?;
-    sync_on_startup(&store)?;
+    sync_on_startup(&store, &settings.hooks)?;
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -560,22 +2359,59 @@ fn run_tui() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new()?;
+    let mut app = App::new(
+        theme,
+        sync_target,
+        settings.stay_open,
+        settings.hooks.clone(),
+        settings.legacy_configs,
+        settings.table.clone(),
+        settings.guard.clone(),
+    )?;
+    app.auth_recheck = match settings.auth_recheck_interval.unwrap_or(DEFAULT_AUTH_RECHECK_MINUTES) {
+        0 => None,
+        minutes => Some(std::time::Duration::from_secs(minutes * 60)),
+    };
+    app.toast_duration = std::time::Duration::from_secs(settings.toast_duration.unwrap_or(DEFAULT_TOAST_SECONDS));
 
     let loop_result: Result<()> = (|| {
         loop {
             app.check_auth_results();
+            app.schedule_auth_checks();
             app.check_project_results();
+            app.check_location_results();
+            app.check_number_results();
+            app.check_cluster_results();
+            app.check_sync_results()?;
+            app.check_gke_results();
+            app.check_file_changes();
+            app.check_auth_recheck();
+            app.expire_toasts();
             terminal.draw(|frame| ui::draw(frame, &mut app))?;
 
             if app.handle_event()? {
                 break;
             }
 
+            // Run here rather than in the key handler, so each step can be drawn
+            if std::mem::take(&mut app.activation_queued) {
+                let activated = app.do_activate(&mut |app| {
+                    let _ = terminal.draw(|frame| ui::draw(frame, app));
+                });
+                match activated {
+                    Ok(()) if app.quit_after_activate => break,
+                    Ok(()) => {}
+                    Err(e) => {
+                        app.notify_error(format!("Activation failed: {}", e));
+                        app.show_failure(&e);
+                    }
+                }
+            }
+
             // Handle pending actions that need TUI suspended (interactive gcloud commands)
             if !matches!(app.pending_action, PendingAction::None) {
-                let is_activate = matches!(app.pending_action, PendingAction::ReauthAndActivate);
-                app.pending_action = PendingAction::None;
+                let action = std::mem::replace(&mut app.pending_action, PendingAction::None);
+                let is_activate = matches!(action, PendingAction::ReauthAndActivate);
 
                 // Suspend TUI: leave alternate screen and restore normal terminal mode
                 disable_raw_mode()?;
@@ -589,15 +2425,40 @@ fn run_tui() -> Result<()> {
                     use std::io::Write;
                     io::stdout().flush()?;
                 }
+                print_login_hint();
 
                 // Run interactive gcloud commands
-                let reauth_result = app.execute_reauth();
+                let reauth_result = match action {
+                    PendingAction::ReauthMarked => app.execute_batch_reauth(),
+                    PendingAction::ReauthExpired => app.execute_expired_reauth(),
+                    PendingAction::AddAccount => app.execute_add_account(),
+                    _ => app.execute_reauth(),
+                };
+                if let Err(e) = &reauth_result {
+                    let what = if matches!(action, PendingAction::AddAccount) {
+                        "Login"
+                    } else {
+                        "Re-authentication"
+                    };
+                    app.notify_error(format!("{} failed: {}", what, e));
+                    app.show_failure(e);
+                }
 
                 // If reauth succeeded and this was an activate flow, do the activation
                 if is_activate && reauth_result.is_ok() {
-                    let _ = app.do_activate();
-                    if app.quit_after_activate {
+                    // The TUI is still suspended: steps go to the terminal
+                    let activated = app.do_activate(&mut |app| {
                         if let Some(msg) = &app.status_message {
+                            println!("{}", msg);
+                        }
+                    });
+                    if let Err(e) = &activated {
+                        app.notify_error(format!("Activation failed: {}", e));
+                        app.show_failure(e);
+                    }
+                    // Stay open on failure so the error can be read
+                    if app.quit_after_activate && activated.is_ok() {
+                        if let Some(msg) = app.final_message() {
                             use std::io::Write;
                             print!("\r\n{}\r\n", msg);
                             io::stdout().flush()?;
@@ -606,7 +2467,8 @@ fn run_tui() -> Result<()> {
                     }
                 }
 
-                // Resume TUI
+                // Resume TUI; logins may have happened meanwhile, in here or elsewhere
+                app.recheck_auth();
                 enable_raw_mode()?;
                 execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
                 // Force ratatui to do a full redraw since the screen was cleared
@@ -628,9 +2490,25 @@ fn run_tui() -> Result<()> {
     let _ = terminal.show_cursor();
     use std::io::Write;
     let _ = io::stdout().flush();
+    // Only decides how the TUI starts next time; not worth an error on the way out
+    let _ = app.remember_layout();
 
-    // Print final status message if any
-    if let Some(msg) = &app.status_message {
+    // Quitting right after an activation must not cut kubectl's context refresh short
+    if let Some(refresh) = &app.gke_refresh {
+        print!("\r\nRefreshing kubectl contexts for '{}'\u{2026}", refresh.profile);
+        let _ = io::stdout().flush();
+        app.wait_for_gke_refresh();
+    }
+
+    if verbose && !app.log.is_empty() {
+        // The log already ends with the final status message
+        print!("\r\n");
+        for entry in &app.log {
+            print!("{} {}\r\n", ui::format_clock(entry.at), entry.message);
+        }
+        let _ = io::stdout().flush();
+    } else if let Some(msg) = app.final_message() {
+        // Print final status message if any
         print!("\r\n{}\r\n", msg);
         let _ = io::stdout().flush();
     }
@@ -640,132 +2518,398 @@ fn run_tui() -> Result<()> {
 
 const REPO: &str = "tjirsch/rs-gcloud-switch";
 const API_URL: &str = "https://api.github.com/repos";
+const INSTALLER_ASSET: &str = "gcloud-switch-installer.sh";
+/// Release assets listing SHA-256 checksums of the other assets, in order of preference:
+/// ours (attach-checksum.yml) covers the installer and every archive, cargo-dist's the archives.
+const CHECKSUM_LISTS: [&str; 2] = ["SHA256SUMS", "sha256.sum"];
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
 
-fn run_self_update(download_readme: bool, open_readme: bool, check_only: bool, skip_checksum: bool, editor: Option<&str>) -> Result<()> {
-    let current_version = env!("CARGO_PKG_VERSION");
-    println!("Current version: {}", current_version);
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    /// Release notes (markdown).
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
 
-    let client = reqwest::blocking::Client::builder()
-        .user_agent("gcloud-switch-update-checker")
-        .build()?;
+impl Release {
+    fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
 
-    let url = format!("{}/{}/releases/latest", API_URL, REPO);
-    let response = client.get(&url).send()?;
+    fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
 
+/// Published releases on `channel`, newest first by version rather than publish date (a
+/// patch for an older line can be published after a newer release).
+fn channel_releases(client: &reqwest::blocking::Client, channel: UpdateChannel) -> Result<Vec<Release>> {
+    let url = format!("{}/{}/releases?per_page=50", API_URL, REPO);
+    let response = client.get(&url).send()?;
     if !response.status().is_success() {
         anyhow::bail!("Failed to fetch release info: {}", response.status());
     }
+    let mut releases: Vec<Release> = response.json()?;
+    releases.retain(|r| !r.draft && (channel == UpdateChannel::Prerelease || !r.prerelease));
+    releases.sort_by(|a, b| compare_versions(b.version(), a.version()).cmp(&0));
+    Ok(releases)
+}
 
-    #[derive(Deserialize)]
-    struct Asset {
-        name: String,
-        browser_download_url: String,
+/// Release notes of every release newer than `current`, newest first.
+fn print_changelog(releases: &[Release], current: &str) {
+    println!("\nChanges since {}:", current);
+    for release in releases.iter().filter(|r| compare_versions(current, r.version()) < 0) {
+        println!("\n## {}{}", release.tag_name, if release.prerelease { " (prerelease)" } else { "" });
+        match release.body.as_deref().map(str::trim) {
+            Some(body) if !body.is_empty() => println!("{}", body),
+            _ => println!("(no release notes)"),
+        }
+    }
+}
+
+/// Run `command` with CLOUDSDK_CONFIG pointing at a temporary copy of `name` alone, and
+/// remove the copy afterwards. Returns the command's exit code.
+fn run_isolated(store: &Store, name: &str, profile: &Profile, command: &[String]) -> Result<i32> {
+    let config = isolate::IsolatedConfig::create(store, name, profile)?;
+    let mut child = std::process::Command::new(&command[0]);
+    child
+        .args(&command[1..])
+        .env("CLOUDSDK_CONFIG", config.dir())
+        .env_remove("CLOUDSDK_ACTIVE_CONFIG_NAME");
+    // Client libraries would otherwise follow a key file set in the calling shell
+    match config.adc_path() {
+        Some(adc) => child.env("GOOGLE_APPLICATION_CREDENTIALS", adc),
+        None => child.env_remove("GOOGLE_APPLICATION_CREDENTIALS"),
+    };
+    // Ctrl-C and Ctrl-\ reach the command and this process alike. Catch them here (the
+    // command gets the default action, since exec resets handlers) so this process
+    // outlives the command and removes the credentials copy.
+    #[cfg(unix)]
+    let handlers = [signal_hook::consts::SIGINT, signal_hook::consts::SIGQUIT]
+        .into_iter()
+        .map(|signal| signal_hook::flag::register(signal, std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false))))
+        .collect::<io::Result<Vec<_>>>()?;
+    let status = child.status();
+    #[cfg(unix)]
+    for id in handlers {
+        signal_hook::low_level::unregister(id);
+    }
+    let status = status.with_context(|| format!("Failed to run {}", command[0]))?;
+    // Killed by a signal: the shell convention of 128 + signal number
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return Ok(128 + signal);
+    }
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Make the user type a guarded profile's name; without a terminal only --i-know will do.
+fn confirm_guarded(name: &str) -> Result<()> {
+    use std::io::Write;
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        anyhow::bail!("Profile '{}' is guarded; pass --i-know to switch without a terminal", name);
+    }
+    eprint!("Type '{}' to switch to this guarded profile: ", name);
+    io::stderr().flush()?;
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf)?;
+    if buf.trim() != name {
+        anyhow::bail!("'{}' does not match; not switched", buf.trim());
+    }
+    Ok(())
+}
+
+fn confirm_install(version: &str) -> Result<bool> {
+    use std::io::Write;
+    if !io::stdin().is_terminal() {
+        anyhow::bail!("Not asking for confirmation without a terminal; pass --yes to install {}", version);
+    }
+    print!("\nInstall {}? [y/N]: ", version);
+    io::stdout().flush()?;
+    let mut buf = String::new();
+    io::stdin().read_line(&mut buf)?;
+    Ok(buf.trim().eq_ignore_ascii_case("y") || buf.trim().eq_ignore_ascii_case("yes"))
+}
+
+/// Expected SHA-256 (lowercase hex) of a release asset and the file it came from: a
+/// checksum list, else the asset's `.sha256` sidecar. None if the release has neither.
+fn expected_sha256(
+    client: &reqwest::blocking::Client,
+    release: &Release,
+    asset_name: &str,
+) -> Result<Option<(String, String)>> {
+    let sidecar = format!("{}.sha256", asset_name);
+    for list in CHECKSUM_LISTS {
+        let Some(asset) = release.asset(list) else {
+            continue;
+        };
+        let content = client.get(&asset.browser_download_url).send()?.error_for_status()?.text()?;
+        if let Some(hash) = parse_checksums(&content, asset_name) {
+            return Ok(Some((hash, list.to_string())));
+        }
+    }
+    if let Some(asset) = release.asset(&sidecar) {
+        let content = client.get(&asset.browser_download_url).send()?.error_for_status()?.text()?;
+        let hash = content.split_whitespace().next().unwrap_or("").to_lowercase();
+        return Ok(Some((hash, sidecar)));
     }
+    Ok(None)
+}
+
+/// Hash of `asset_name` in `sha256sum` output ("<hash>  <name>", or "<hash> *<name>" for
+/// binary mode; names may carry a directory).
+fn parse_checksums(content: &str, asset_name: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+        let name = name.trim_start().trim_start_matches('*');
+        let name = name.rsplit('/').next().unwrap_or(name);
+        (name == asset_name).then(|| hash.to_lowercase())
+    })
+}
 
-    #[derive(Deserialize)]
-    struct Release {
-        tag_name: String,
-        html_url: String,
-        #[serde(default)]
-        assets: Vec<Asset>,
+fn verify_sha256(asset_name: &str, bytes: &[u8], expected: &str, release_url: &str) -> Result<()> {
+    use sha2::{Digest, Sha256};
+    let actual = hex::encode(Sha256::digest(bytes));
+    if actual != expected {
+        anyhow::bail!(
+            "Checksum mismatch for {} — the download may have been tampered with.\n\
+             Expected: {}\n\
+             Got:      {}\n\
+             Aborting. Download the release manually from {}",
+            asset_name, expected, actual, release_url
+        );
     }
+    Ok(())
+}
+
+fn run_self_update(
+    download_readme: bool,
+    open_readme: bool,
+    check_only: bool,
+    skip_checksum: bool,
+    yes: bool,
+    channel: UpdateChannel,
+    editor: Option<&str>,
+) -> Result<()> {
+    let current_version = env!("CARGO_PKG_VERSION");
+    println!("Current version: {}", current_version);
+
+    let client = net::client("Self-update")?;
 
-    let release: Release = response.json()?;
-    let latest_version = release.tag_name.trim_start_matches('v');
-    println!("Latest version: {}", latest_version);
+    let releases = channel_releases(&client, channel)?;
+    let release = releases.first().context("No releases found")?;
+    let latest_version = release.version();
+    if release.prerelease {
+        println!("Latest version: {} (prerelease)", latest_version);
+    } else {
+        println!("Latest version: {}", latest_version);
+    }
 
     if compare_versions(current_version, latest_version) < 0 {
         println!("\n⚠️  A new version is available!");
         println!("   Current: {}", current_version);
         println!("   Latest:  {}", latest_version);
         println!("   Release: {}", release.html_url);
+        print_changelog(&releases, current_version);
         if check_only {
             println!("\nRun `gcloud-switch self-update` to install.");
             return Ok(());
         }
+        if !yes && !confirm_install(latest_version)? {
+            println!("Update cancelled.");
+            return Ok(());
+        }
         println!("\n📥 Installing update...");
 
-        let installer_url = format!(
-            "https://github.com/{}/releases/latest/download/gcloud-switch-installer.sh",
-            REPO
-        );
-
-        // Download installer as bytes for checksum verification
-        let installer_bytes = client.get(&installer_url).send()?.bytes()?;
-
-        // Checksum verification
-        let checksum_asset = release.assets.iter()
-            .find(|a| a.name == "gcloud-switch-installer.sh.sha256");
-        match checksum_asset {
+        let archive = release_archive_name();
+        let restart_needed = match archive.as_deref().and_then(|name| release.asset(name)) {
             Some(asset) => {
-                let expected_raw = client.get(&asset.browser_download_url)
-                    .send()?.text()?;
-                let expected = expected_raw.trim().split_whitespace().next().unwrap_or("").to_lowercase();
-                use sha2::{Digest, Sha256};
-                let actual = hex::encode(Sha256::digest(&installer_bytes));
-                if actual != expected {
-                    anyhow::bail!(
-                        "Checksum mismatch — installer may have been tampered with.\n\
-                         Expected: {}\n\
-                         Got:      {}\n\
-                         Aborting. Download the release manually from {}",
-                        expected, actual, release.html_url
-                    );
-                }
-                println!("✅ Checksum verified");
-            }
-            None if skip_checksum => {
-                eprintln!(
-                    "⚠️  No checksum file found in this release. \
-                     Proceeding without verification (--skip-checksum)."
-                );
+                install_binary(&client, release, asset, skip_checksum)?;
+                false
             }
+            // No prebuilt binary for this platform: let the installer script handle it
             None => {
-                anyhow::bail!(
-                    "No checksum file (gcloud-switch-installer.sh.sha256) found in this release.\n\
-                     Cannot verify installer integrity. Aborting.\n\
-                     If you are confident in the download, re-run with --skip-checksum."
-                );
+                install_with_script(&client, release, skip_checksum)?;
+                true
             }
-        }
-
-        let temp_file = std::env::temp_dir()
-            .join(format!("gcloud-switch-installer-{}.sh", std::process::id()));
-        std::fs::write(&temp_file, &installer_bytes)?;
-
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            std::fs::set_permissions(&temp_file, std::fs::Permissions::from_mode(0o755))?;
-
-            let status = std::process::Command::new("sh").arg(&temp_file).status()?;
-            let _ = std::fs::remove_file(&temp_file);
+        };
 
-            if status.success() {
-                println!("✅ Update installed successfully!");
-                println!("   Please restart your terminal or run: source ~/.profile");
-                if download_readme {
-                    match download_and_open_readme(&client, REPO, latest_version, open_readme, editor) {
-                        Ok(Some(path)) => println!("README: {}", path.display()),
-                        Ok(None) => {}
-                        Err(e) => eprintln!("⚠️  Warning: Could not download README: {}", e),
-                    }
-                }
-            } else {
-                anyhow::bail!("Failed to run installer script");
+        println!("✅ Update installed successfully!");
+        if restart_needed {
+            println!("   Please restart your terminal or run: source ~/.profile");
+        }
+        if download_readme {
+            match download_and_open_readme(&client, REPO, latest_version, open_readme, editor) {
+                Ok(Some(path)) => println!("README: {}", path.display()),
+                Ok(None) => {}
+                Err(e) => eprintln!("⚠️  Warning: Could not download README: {}", e),
             }
         }
+    } else {
+        println!("✅ You are running the latest version!");
+    }
+
+    Ok(())
+}
 
-        #[cfg(windows)]
-        {
+/// Check a downloaded release asset against the release's checksums. Without any, abort
+/// unless `skip_checksum`.
+fn verify_asset(
+    client: &reqwest::blocking::Client,
+    release: &Release,
+    asset_name: &str,
+    bytes: &[u8],
+    skip_checksum: bool,
+) -> Result<()> {
+    match expected_sha256(client, release, asset_name)? {
+        Some((expected, source)) => {
+            verify_sha256(asset_name, bytes, &expected, &release.html_url)?;
+            println!("✅ Checksum verified ({})", source);
+        }
+        None if skip_checksum => {
+            eprintln!(
+                "⚠️  No checksum file found in this release. \
+                 Proceeding without verification (--skip-checksum)."
+            );
+        }
+        None => {
             anyhow::bail!(
-                "Automatic installation on Windows is not yet supported. Please download and run the installer manually."
+                "No checksums (SHA256SUMS, sha256.sum or {}.sha256) found in this release.\n\
+                 Cannot verify the integrity of {}. Aborting.\n\
+                 If you are confident in the download, re-run with --skip-checksum.",
+                asset_name, asset_name
             );
         }
-    } else {
-        println!("✅ You are running the latest version!");
+    }
+    Ok(())
+}
+
+/// cargo-dist archive name for the platform this binary was built for, e.g.
+/// `gcloud-switch-aarch64-apple-darwin.tar.xz`. None on platforms without release builds.
+fn release_archive_name() -> Option<String> {
+    let os = match std::env::consts::OS {
+        "macos" => "apple-darwin",
+        "linux" => "unknown-linux-gnu",
+        _ => return None,
+    };
+    Some(format!("gcloud-switch-{}-{}.tar.xz", std::env::consts::ARCH, os))
+}
+
+/// Download the release archive for this platform, verify it, and replace the running
+/// executable with the binary inside. The new file is written next to the old one and
+/// renamed over it, so the swap is atomic and a failed download leaves the old binary.
+fn install_binary(
+    client: &reqwest::blocking::Client,
+    release: &Release,
+    asset: &Asset,
+    skip_checksum: bool,
+) -> Result<()> {
+    let bytes = client.get(&asset.browser_download_url).send()?.error_for_status()?.bytes()?;
+    verify_asset(client, release, &asset.name, &bytes, skip_checksum)?;
+
+    // Fresh 0700 directory with a random name, so nobody can pre-create or swap it
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("gcloud-switch-update-");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(std::fs::Permissions::from_mode(0o700));
+    }
+    let work_dir = builder.tempdir().context("Failed to create a temporary directory")?;
+    replace_executable(work_dir.path(), &asset.name, &bytes)
+}
+
+fn replace_executable(work_dir: &Path, archive_name: &str, archive: &[u8]) -> Result<()> {
+    let archive_path = work_dir.join(archive_name);
+    std::fs::write(&archive_path, archive)?;
+    let status = std::process::Command::new("tar")
+        .arg("-xJf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(work_dir)
+        .status()
+        .context("Failed to run tar")?;
+    if !status.success() {
+        anyhow::bail!("Failed to extract {}", archive_name);
+    }
+    // cargo-dist puts the binary in a directory named after the archive
+    let extracted = work_dir.join(archive_name.trim_end_matches(".tar.xz")).join("gcloud-switch");
+    let new_binary = if extracted.exists() { extracted } else { work_dir.join("gcloud-switch") };
+    if !new_binary.exists() {
+        anyhow::bail!("{} does not contain a gcloud-switch binary", archive_name);
+    }
+
+    let current = std::env::current_exe()
+        .and_then(|p| p.canonicalize())
+        .context("Could not locate the running executable")?;
+    let dir = current.parent().context("Executable has no parent directory")?;
+    let staged = dir.join(format!(".gcloud-switch.new-{}", std::process::id()));
+    std::fs::copy(&new_binary, &staged)
+        .with_context(|| format!("Failed to write to {} (no permission?)", dir.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    // Replacing the file of a running program is fine on Unix: the process keeps the old inode
+    if let Err(e) = std::fs::rename(&staged, &current) {
+        let _ = std::fs::remove_file(&staged);
+        return Err(e).with_context(|| format!("Failed to replace {}", current.display()));
+    }
+    println!("Replaced {}", current.display());
+    Ok(())
+}
+
+/// Download the release's installer script, verify it and run it.
+fn install_with_script(client: &reqwest::blocking::Client, release: &Release, skip_checksum: bool) -> Result<()> {
+    // Download installer as bytes for checksum verification, from the same release the
+    // checksums come from
+    let installer_url = release
+        .asset(INSTALLER_ASSET)
+        .map(|a| a.browser_download_url.clone())
+        .unwrap_or_else(|| {
+            format!("https://github.com/{}/releases/download/{}/{}", REPO, release.tag_name, INSTALLER_ASSET)
+        });
+    let installer_bytes = client.get(&installer_url).send()?.error_for_status()?.bytes()?;
+
+    verify_asset(client, release, INSTALLER_ASSET, &installer_bytes, skip_checksum)?;
+
+    let temp_file = std::env::temp_dir()
+        .join(format!("gcloud-switch-installer-{}.sh", std::process::id()));
+    std::fs::write(&temp_file, &installer_bytes)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&temp_file, std::fs::Permissions::from_mode(0o755))?;
+
+        let status = std::process::Command::new("sh").arg(&temp_file).status()?;
+        let _ = std::fs::remove_file(&temp_file);
+
+        if !status.success() {
+            anyhow::bail!("Failed to run installer script");
+        }
     }
 
+    #[cfg(windows)]
+    {
+        anyhow::bail!(
+            "Automatic installation on Windows is not yet supported. Please download and run the installer manually."
+        );
+    }
     Ok(())
 }
 
@@ -823,7 +2967,7 @@ fn open_file(path: &Path, editor: Option<&str>) -> Result<()> {
         .ok_or_else(|| anyhow::anyhow!("File path {:?} contains non-UTF-8 characters", path))?;
 
     let editor_env = std::env::var("EDITOR").ok();
-    let editor = editor.or_else(|| editor_env.as_deref());
+    let editor = editor.or(editor_env.as_deref());
 
     if let Some(editor) = editor {
         println!("   Opening '{}' with '{}'...", path_str, editor);
@@ -852,46 +2996,16 @@ fn open_file(path: &Path, editor: Option<&str>) -> Result<()> {
     }
 
     // No editor configured — use OS default
-    #[cfg(target_os = "macos")]
-    {
-        println!("   Opening '{}' with system default app...", path_str);
-        std::process::Command::new("open").arg(path_str).status()?;
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        println!("   Opening '{}' with xdg-open...", path_str);
-        if std::process::Command::new("xdg-open")
-            .arg(path_str)
-            .status()
-            .is_err()
-        {
-            anyhow::bail!(
-                "Could not open '{}': xdg-open failed and neither editor nor $EDITOR is set",
-                path_str
-            );
-        }
-    }
-
-    #[cfg(target_os = "windows")]
-    {
-        println!("   Opening '{}' with system default app...", path_str);
-        std::process::Command::new("cmd")
-            .args(["/C", "start", "", path_str])
-            .status()?;
-    }
-
-    Ok(())
+    println!("   Opening '{}' with the system default app...", path_str);
+    opener::open(path_str)
+        .with_context(|| format!("Could not open '{}' and neither editor nor $EDITOR is set", path_str))
 }
 
 fn run_open_readme(editor: Option<&str>) -> Result<()> {
-    let client = reqwest::blocking::Client::builder()
-        .user_agent("gcloud-switch-open-readme")
-        .build()?;
+    let client = net::client("Downloading the README")?;
     println!("📄 Downloading README...");
-    match download_and_open_readme(&client, REPO, "latest", true, editor)? {
-        Some(path) => println!("README saved to: {}", path.display()),
-        None => {}
+    if let Some(path) = download_and_open_readme(&client, REPO, "latest", true, editor)? {
+        println!("README saved to: {}", path.display());
     }
     Ok(())
 }
@@ -975,10 +3089,19 @@ fn completion_install_path(shell: clap_complete::Shell) -> Result<(std::path::Pa
     Ok((path, msg))
 }
 
+/// Compare two semver strings: -1, 0 or 1. A prerelease sorts before its release
+/// (1.2.0-rc.1 < 1.2.0); build metadata is ignored.
 fn compare_versions(v1: &str, v2: &str) -> i32 {
-    let parse_version = |v: &str| -> Vec<u32> { v.split('.').map(|s| s.parse::<u32>().unwrap_or(0)).collect() };
-    let v1_parts = parse_version(v1);
-    let v2_parts = parse_version(v2);
+    let split = |v: &str| -> (Vec<u32>, Option<String>) {
+        let v = v.split('+').next().unwrap_or(v);
+        let (core, pre) = match v.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (v, None),
+        };
+        (core.split('.').map(|s| s.parse::<u32>().unwrap_or(0)).collect(), pre)
+    };
+    let (v1_parts, v1_pre) = split(v1);
+    let (v2_parts, v2_pre) = split(v2);
     let max_len = v1_parts.len().max(v2_parts.len());
     for i in 0..max_len {
         let a = v1_parts.get(i).copied().unwrap_or(0);
@@ -990,5 +3113,34 @@ fn compare_versions(v1: &str, v2: &str) -> i32 {
             return 1;
         }
     }
-    0
+    let ordering = match (v1_pre, v2_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_prerelease(&a, &b),
+    };
+    ordering as i32
+}
+
+/// Semver precedence of prerelease tags: dot-separated identifiers compared in turn,
+/// numbers numerically and below alphanumerics, a shorter tag first when all else is equal.
+fn compare_prerelease(a: &str, b: &str) -> Ordering {
+    let mut a_ids = a.split('.');
+    let mut b_ids = b.split('.');
+    loop {
+        let ordering = match (a_ids.next(), b_ids.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
 }
@@ -0,0 +1,94 @@
+//! Outbound network access: one HTTP client setup for update checks, token validation and
+//! sync, plus the offline switch. Proxies come from HTTPS_PROXY / HTTP_PROXY / ALL_PROXY
+//! and NO_PROXY, which reqwest (and libgit2, via auto-detection) read from the environment.
+
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+
+/// Environment variable that turns on offline mode like `--offline`.
+pub const OFFLINE_ENV: &str = "GCLOUD_SWITCH_OFFLINE";
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+struct Settings {
+    offline: bool,
+    timeout: Duration,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Set once at startup from the command line and global settings. Later calls are ignored.
+pub fn configure(offline: bool, timeout_secs: u64) {
+    let _ = SETTINGS.set(Settings {
+        offline,
+        timeout: Duration::from_secs(timeout_secs.max(1)),
+    });
+}
+
+/// Whether GCLOUD_SWITCH_OFFLINE is set to something other than "", "0" or "false".
+pub fn offline_from_env() -> bool {
+    std::env::var(OFFLINE_ENV).is_ok_and(|v| !matches!(v.trim(), "" | "0" | "false"))
+}
+
+pub fn is_offline() -> bool {
+    SETTINGS.get().is_some_and(|s| s.offline)
+}
+
+fn timeout() -> Duration {
+    SETTINGS
+        .get()
+        .map_or(Duration::from_secs(DEFAULT_TIMEOUT_SECS), |s| s.timeout)
+}
+
+/// Fail with a clear message when offline; `what` names the operation ("Sync", "Self-update").
+pub fn ensure_online(what: &str) -> Result<()> {
+    if is_offline() {
+        anyhow::bail!(
+            "{} needs network access, but offline mode is on (--offline or {})",
+            what,
+            OFFLINE_ENV
+        );
+    }
+    Ok(())
+}
+
+/// Like `ensure_online`, but lets through git remotes on the local filesystem.
+pub fn ensure_reachable(what: &str, url: &str) -> Result<()> {
+    let local = url.starts_with("file://") || Path::new(url).is_absolute() || Path::new(url).exists();
+    if local {
+        return Ok(());
+    }
+    ensure_online(what)
+}
+
+/// HTTP client with the configured timeout. Errors when offline, so no request slips out.
+pub fn client(what: &str) -> Result<Client> {
+    ensure_online(what)?;
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(timeout())
+        .connect_timeout(connect_timeout())
+        .build()
+        .context("Failed to build HTTP client")
+}
+
+/// `client` for async code (token checks on the [`crate::runtime`]), with the same settings.
+pub fn async_client(what: &str) -> Result<reqwest::Client> {
+    ensure_online(what)?;
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(timeout())
+        .connect_timeout(connect_timeout())
+        .build()
+        .context("Failed to build HTTP client")
+}
+
+const USER_AGENT: &str = concat!("gcloud-switch/", env!("CARGO_PKG_VERSION"));
+
+fn connect_timeout() -> Duration {
+    timeout().min(Duration::from_secs(10))
+}
@@ -0,0 +1,122 @@
+//! Desktop notifications about the active profile's credentials, from a background thread
+//! of `gcloud-switch daemon`. gcloud renews access tokens by itself while the refresh token
+//! is good, so only credentials that can no longer be refreshed are reported: once when
+//! that is noticed, and again shortly before the last access token runs out.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use gcloud_switch::gcloud;
+use gcloud_switch::profile;
+use gcloud_switch::store::Store;
+
+/// `[notifications]` in gcloud-switch.toml.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifySettings {
+    pub enabled: bool,
+    /// How often to check the active profile, in minutes.
+    pub interval_minutes: u64,
+    /// Remind again when the last access token has this many minutes left.
+    pub warn_minutes: u64,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: 5,
+            warn_minutes: 10,
+        }
+    }
+}
+
+impl NotifySettings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What has been reported for an account whose credentials stopped refreshing.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Reported {
+    Invalid,
+    ExpiringSoon,
+}
+
+pub fn spawn(settings: NotifySettings) {
+    std::thread::spawn(move || {
+        let mut reported = HashMap::new();
+        loop {
+            if let Err(e) = check(&settings, &mut reported) {
+                tracing::warn!(error = %format!("{:#}", e), "credential notification check failed");
+            }
+            std::thread::sleep(Duration::from_secs(settings.interval_minutes.max(1) * 60));
+        }
+    });
+}
+
+fn check(settings: &NotifySettings, reported: &mut HashMap<String, Reported>) -> anyhow::Result<()> {
+    let data = Store::new()?.load_profiles()?;
+    let Some((name, profile)) = data
+        .active_profile
+        .as_ref()
+        .and_then(|name| Some((name, data.profiles.get(name)?)))
+    else {
+        return Ok(());
+    };
+    let mut accounts = vec![("user", profile.user_account.as_str())];
+    if profile.adc_account != profile.user_account {
+        accounts.push(("ADC", profile.adc_account.as_str()));
+    }
+    // Only the active profile matters; forget accounts of earlier ones
+    reported.retain(|account, _| accounts.iter().any(|(_, a)| a == account));
+
+    for (kind, account) in accounts {
+        let status = if account.is_empty() { None } else { Some(gcloud::check_account_auth_blocking(account)) };
+        let Some((reason, _)) = status.and_then(|status| status.explain()) else {
+            reported.remove(account);
+            continue;
+        };
+        let left = gcloud::read_token_expiry(account)
+            .ok()
+            .flatten()
+            .map(|expiry| (expiry - profile::now()) / 60)
+            .filter(|minutes| *minutes > 0);
+        let next = if left.is_some_and(|minutes| minutes <= settings.warn_minutes as i64) {
+            Reported::ExpiringSoon
+        } else {
+            Reported::Invalid
+        };
+        if reported.get(account).is_some_and(|r| *r == next || *r == Reported::ExpiringSoon) {
+            continue;
+        }
+        let (summary, body) = match left {
+            Some(minutes) => ("gcloud-switch: credentials expiring", format!(
+                "The {} credentials of '{}' ({}) can no longer be refreshed; the current token expires in {} min. Run `gcloud-switch switch {}` to sign in again.",
+                kind, name, account, minutes, name
+            )),
+            None => ("gcloud-switch: credentials expired", format!(
+                "The {} credentials of '{}' ({}) are unusable: {}. Run `gcloud-switch switch {}` to sign in again.",
+                kind, name, account, reason, name
+            )),
+        };
+        show(summary, &body);
+        reported.insert(account.to_string(), next);
+    }
+    Ok(())
+}
+
+fn show(summary: &str, body: &str) {
+    let shown = notify_rust::Notification::new()
+        .appname("gcloud-switch")
+        .summary(summary)
+        .body(body)
+        .show();
+    match shown {
+        Ok(_) => tracing::info!(body, "desktop notification"),
+        Err(e) => tracing::warn!(error = %e, "desktop notification failed"),
+    }
+}
@@ -0,0 +1,39 @@
+//! Hand a file or URL to the platform's default application: `open` on macOS, `xdg-open`
+//! on Linux, `start` on Windows. Used for the downloaded README and the Cloud Console.
+
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+
+/// The opener command for this platform, with the arguments before the target.
+fn command() -> Result<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        Ok(("open", &[]))
+    } else if cfg!(windows) {
+        Ok(("cmd", &["/C", "start", ""]))
+    } else if cfg!(target_os = "linux") {
+        Ok(("xdg-open", &[]))
+    } else {
+        anyhow::bail!("Unsupported platform for opening files")
+    }
+}
+
+/// Open `target` (a path or URL) with the system default app. The opener's own output is
+/// discarded so it cannot draw over the TUI; a failure is reported through its exit status.
+pub fn open(target: &str) -> Result<()> {
+    let (program, args) = command()?;
+    // cmd would read an unescaped & in a URL as a command separator
+    let target = if cfg!(windows) { target.replace('&', "^&") } else { target.to_string() };
+    let status = Command::new(program)
+        .args(args)
+        .arg(&target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !status.success() {
+        anyhow::bail!("{} could not open '{}' ({})", program, target, status);
+    }
+    Ok(())
+}
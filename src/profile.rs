@@ -8,6 +8,9 @@ pub enum SyncMode {
     Strict,
     Add,
     Off,
+    /// Strict, and account/project edits are also written to the profile's gcloud
+    /// configuration, active or not.
+    Mirror,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -19,17 +22,213 @@ pub struct Profile {
     /// Unix timestamp (seconds) when this profile was last modified. Used for sync merge (newer wins). None = treat as old.
     #[serde(default)]
     pub updated_at: Option<i64>,
+    /// Device that last pushed a change to this profile (set on sync push).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_by: Option<String>,
+    /// Free-form labels (e.g. "prod", "team-a") for grouping and display.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Contexts for other clouds switched along with this profile, by provider
+    /// ("aws" -> AWS CLI profile, "azure" -> subscription). See `providers`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub contexts: BTreeMap<String, String>,
+    /// GKE clusters whose kubectl contexts are refreshed on activation, as `location/name`
+    /// or `project/location/name`. The first one becomes kubectl's current context.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clusters: Vec<String>,
+    /// Docker registries (hosts, or Artifact Registry locations like "europe-west1") whose
+    /// credential helper is set to gcloud on activation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub registries: Vec<String>,
+    /// Settings for `gcloud-switch ssh`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<SshSettings>,
+    /// Settings for `gcloud-switch terraform`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terraform: Option<TerraformSettings>,
+    /// Snapshot of a gcloud configuration's other properties (`section/key`, without
+    /// account and project), written to the profile's configuration on activation.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gcloud_properties: BTreeMap<String, String>,
+    /// False keeps the profile on this machine: sync neither pushes it nor lets the remote
+    /// change or delete it.
+    #[serde(default = "default_sync", skip_serializing_if = "is_synced")]
+    pub sync: bool,
+    /// Ask before activating: the TUI wants a y, the CLI and daemon want `--force`.
+    /// For production profiles that should never be switched to by accident.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub confirm_before_activate: bool,
+    /// Refuse edits, renames and deletion until unlocked, for shared profiles handed out
+    /// by a platform team. Sync still updates it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
 }
 
+/// `[guard]` in gcloud-switch.toml: profiles that are only switched to deliberately, by
+/// typing their name or passing `--i-know`. Picked by name or by tag, e.g. every "prod".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuardSettings {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+}
+
+impl GuardSettings {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.profiles.is_empty()
+    }
+
+    pub fn is_guarded(&self, name: &str, profile: &Profile) -> bool {
+        self.profiles.iter().any(|p| p == name) || profile.tags.iter().any(|tag| self.tags.contains(tag))
+    }
+}
+
+/// Per-profile `gcloud compute ssh` settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SshSettings {
+    /// Zone of the profile's VMs. Falls back to the gcloud configuration's compute/zone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    /// Connect through Identity-Aware Proxy (`--tunnel-through-iap`), for VMs without an
+    /// external IP.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub iap: bool,
+}
+
+impl SshSettings {
+    /// "zone=europe-west1-b iap", for display and comparison.
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(zone) = &self.zone {
+            parts.push(format!("zone={}", zone));
+        }
+        if self.iap {
+            parts.push("iap".to_string());
+        }
+        parts.join(" ")
+    }
+}
+
+/// Per-profile Terraform settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TerraformSettings {
+    /// Service account Terraform should impersonate (GOOGLE_IMPERSONATE_SERVICE_ACCOUNT).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonate_service_account: Option<String>,
+    /// Backend config rendered by `terraform --backend-config`, with {profile}, {project},
+    /// {account} and {quota_project} replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+}
+
+fn default_sync() -> bool {
+    true
+}
+
+fn is_synced(sync: &bool) -> bool {
+    *sync
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            user_account: String::new(),
+            user_project: String::new(),
+            adc_account: String::new(),
+            adc_quota_project: String::new(),
+            updated_at: None,
+            updated_by: None,
+            tags: Vec::new(),
+            contexts: BTreeMap::new(),
+            clusters: Vec::new(),
+            registries: Vec::new(),
+            ssh: None,
+            terraform: None,
+            gcloud_properties: BTreeMap::new(),
+            sync: true,
+            confirm_before_activate: false,
+            locked: false,
+        }
+    }
+}
+
+/// Current Unix timestamp in seconds.
+pub fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Check a new profile name: letters, digits and '-', starting with a letter. Shared by
+/// the CLI, the TUI, the wizard and bulk import so they agree on what is a valid name.
+pub fn validate_name(name: &str) -> anyhow::Result<()> {
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        anyhow::bail!("Profile names must start with a letter");
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        anyhow::bail!("Profile names may only use letters, digits and '-'");
+    }
+    Ok(())
+}
+
+/// How long a deletion is remembered for sync. A machine that has not synced for longer
+/// may bring the profile back.
+pub const TOMBSTONE_TTL_SECS: i64 = 90 * 24 * 60 * 60;
+
 impl Profile {
     /// Set updated_at to current time (for sync merge).
     pub fn touch(&mut self) {
-        self.updated_at = Some(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs() as i64,
-        );
+        self.updated_at = Some(now());
+    }
+
+    /// Synced fields with display labels, for showing and comparing profiles.
+    pub fn fields(&self) -> [(&'static str, String); 14] {
+        let terraform = self.terraform.as_ref();
+        [
+            ("User account", self.user_account.clone()),
+            ("User project", self.user_project.clone()),
+            ("ADC account", self.adc_account.clone()),
+            ("Quota project", self.adc_quota_project.clone()),
+            ("Tags", self.tags.join(" ")),
+            ("Other clouds", self.contexts_label()),
+            ("GKE clusters", self.clusters.join(" ")),
+            ("Registries", self.registries.join(" ")),
+            ("SSH", self.ssh.as_ref().map(SshSettings::label).unwrap_or_default()),
+            ("Terraform SA", terraform.and_then(|t| t.impersonate_service_account.clone()).unwrap_or_default()),
+            ("TF backend", terraform.and_then(|t| t.backend.as_deref()).unwrap_or_default().trim().replace('\n', "; ")),
+            ("gcloud props", self.gcloud_properties.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(" ")),
+            ("Confirm", if self.confirm_before_activate { "yes" } else { "" }.to_string()),
+            ("Locked", if self.locked { "yes" } else { "" }.to_string()),
+        ]
+    }
+
+    /// Error if the profile is locked against changes.
+    pub fn ensure_unlocked(&self, name: &str) -> anyhow::Result<()> {
+        if self.locked {
+            anyhow::bail!("Profile '{}' is locked; run 'gcloud-switch unlock {}' to change it", name, name);
+        }
+        Ok(())
+    }
+
+    /// Other-cloud contexts as "aws=dev azure=Sub-1".
+    pub fn contexts_label(&self) -> String {
+        self.contexts
+            .iter()
+            .map(|(provider, context)| format!("{}={}", provider, context))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Fields whose values differ from `other`, as (label, self's value, other's value).
+    pub fn diff(&self, other: &Profile) -> Vec<(&'static str, String, String)> {
+        self.fields()
+            .into_iter()
+            .zip(other.fields())
+            .filter(|((_, a), (_, b))| a != b)
+            .map(|((label, a), (_, b))| (label, a, b))
+            .collect()
     }
 }
 
@@ -39,6 +238,36 @@ pub struct ProfilesFile {
     pub active_profile: Option<String>,
     #[serde(default)]
     pub sync_mode: SyncMode,
+    /// Deleted profile names with their deletion time, so sync removes them on other
+    /// machines instead of bringing them back. Expire after TOMBSTONE_TTL_SECS.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deleted: BTreeMap<String, i64>,
     #[serde(default)]
     pub profiles: BTreeMap<String, Profile>,
 }
+
+impl ProfilesFile {
+    /// Remove a profile and remember the deletion for sync (unless it was local-only).
+    pub fn remove_profile(&mut self, name: &str) -> Option<Profile> {
+        let removed = self.profiles.remove(name);
+        if removed.as_ref().is_some_and(|p| p.sync) {
+            self.deleted.insert(name.to_string(), now());
+        }
+        if self.active_profile.as_deref() == Some(name) {
+            self.active_profile = None;
+        }
+        self.expire_tombstones();
+        removed
+    }
+
+    /// Insert a profile, forgetting an earlier deletion of the same name.
+    pub fn insert_profile(&mut self, name: &str, profile: Profile) {
+        self.deleted.remove(name);
+        self.profiles.insert(name.to_string(), profile);
+    }
+
+    pub fn expire_tombstones(&mut self) {
+        let cutoff = now() - TOMBSTONE_TTL_SECS;
+        self.deleted.retain(|_, deleted_at| *deleted_at > cutoff);
+    }
+}
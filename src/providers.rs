@@ -0,0 +1,108 @@
+//! Other clouds switched alongside gcloud. gcloud stays the primary provider; a profile's
+//! `contexts` map names the matching context for each optional one, e.g. the AWS CLI
+//! profile or the Azure subscription to use with it.
+
+use std::process::Command;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+
+use crate::profile::Profile;
+
+pub trait Provider: Sync {
+    /// Key in a profile's `contexts` map.
+    fn name(&self) -> &'static str;
+    /// Make `context` current for the provider's own CLI. Providers configured only
+    /// through the environment do nothing here; see [`env_exports`].
+    fn activate(&self, context: &str) -> Result<()>;
+    /// Shell lines for `gcloud-switch env`: set the variables for `context`, or clear
+    /// them when the profile has none for this provider.
+    fn env(&self, context: Option<&str>) -> Vec<String>;
+}
+
+/// AWS: the CLI and SDKs read the profile from `AWS_PROFILE`, which only the shell can
+/// change, so switching happens through `eval "$(gcloud-switch env)"`.
+pub struct Aws;
+
+impl Provider for Aws {
+    fn name(&self) -> &'static str {
+        "aws"
+    }
+
+    fn activate(&self, _context: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn env(&self, context: Option<&str>) -> Vec<String> {
+        vec![export_line("AWS_PROFILE", context)]
+    }
+}
+
+/// Azure: `az account set --subscription <context>` (name or id) in the az CLI's state.
+pub struct Azure;
+
+impl Provider for Azure {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    fn activate(&self, context: &str) -> Result<()> {
+        let start = Instant::now();
+        let output = Command::new(if cfg!(windows) { "az.cmd" } else { "az" })
+            .args(["account", "set", "--subscription", context])
+            .output()
+            .context("Failed to run az (is the Azure CLI installed and in PATH?)")?;
+        tracing::debug!(subscription = context, status = %output.status, elapsed = ?start.elapsed(), "az account set");
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("az account set --subscription {} failed: {}", context, stderr.trim());
+        }
+        Ok(())
+    }
+
+    fn env(&self, _context: Option<&str>) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+pub static PROVIDERS: [&dyn Provider; 2] = [&Aws, &Azure];
+
+pub fn find(name: &str) -> Option<&'static dyn Provider> {
+    PROVIDERS.iter().copied().find(|p| p.name() == name)
+}
+
+/// Activate the profile's context for every other provider it names. Runs them all and
+/// reports the failures together, since gcloud itself is already switched by then.
+pub fn activate(profile: &Profile) -> Result<()> {
+    let mut failures = Vec::new();
+    for (name, context) in &profile.contexts {
+        let result = match find(name) {
+            Some(provider) => provider.activate(context),
+            None => Err(anyhow::anyhow!("unknown provider '{}'", name)),
+        };
+        if let Err(e) = result {
+            failures.push(format!("{}: {:#}", name, e));
+        }
+    }
+    if !failures.is_empty() {
+        anyhow::bail!("Switched gcloud, but not {}", failures.join("; "));
+    }
+    Ok(())
+}
+
+/// Shell lines setting each provider's environment for `profile` (None: no active profile).
+pub fn env_exports(profile: Option<&Profile>) -> Vec<String> {
+    PROVIDERS
+        .iter()
+        .flat_map(|p| p.env(profile.and_then(|profile| profile.contexts.get(p.name()).map(String::as_str))))
+        .collect()
+}
+
+/// `export NAME='value'` for POSIX shells, or `unset NAME` without a value so a stale
+/// one from an earlier profile does not linger.
+pub fn export_line(name: &str, value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("export {}='{}'", name, value.replace('\'', r"'\''")),
+        None => format!("unset {}", name),
+    }
+}
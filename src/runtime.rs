@@ -0,0 +1,37 @@
+//! The async runtime behind token checks and non-interactive gcloud commands. Those are
+//! async fns with their timeouts built in; the TUI spawns them as tasks and aborts the ones
+//! whose result is no longer wanted, which also stops the gcloud command a task is waiting
+//! for. Synchronous callers (CLI commands, the daemon) wait for them with [`block_on`].
+
+use std::future::Future;
+use std::sync::OnceLock;
+
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// The shared runtime, started on first use.
+fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .thread_name("gcloud-switch-rt")
+            .enable_all()
+            .build()
+            .expect("failed to start the async runtime")
+    })
+}
+
+/// Run a future to completion from synchronous code. Not for use inside a task.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
+
+/// Run a future in the background. Aborting the handle drops the future where it waits.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    runtime().spawn(future)
+}
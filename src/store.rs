@@ -1,10 +1,70 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 
+use crate::gcloud::{self, AuthStatus};
 use crate::profile::{Profile, ProfilesFile};
 
+/// This machine's state.toml: things worth remembering between launches that are not
+/// settings and never synced.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Last token check per account.
+    #[serde(default)]
+    pub auth: BTreeMap<String, AuthRecord>,
+    /// Profile the TUI's cursor was on when it last quit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_selected: Option<String>,
+    /// Project numbers by project id, resolved once since they never change.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub project_numbers: BTreeMap<String, String>,
+    /// When each profile was last activated on this machine (Unix seconds).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub last_activated: BTreeMap<String, i64>,
+    /// How the TUI was left when it last quit, for `[table] restore`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<TuiLayout>,
+    /// Compute Engine regions and zones, listed once for the add/edit form's suggestions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compute_regions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compute_zones: Vec<String>,
+}
+
+/// The TUI's view when it last quit, next to `State::last_selected`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TuiLayout {
+    /// Rows sorted by last activation (`m`) instead of by name.
+    #[serde(default)]
+    pub recent_first: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub filter: String,
+    /// Detail pane (`v`) shown.
+    #[serde(default)]
+    pub details: bool,
+    /// Activity log pane (`l`) shown.
+    #[serde(default)]
+    pub log: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthRecord {
+    pub status: AuthStatus,
+    /// Unix seconds.
+    pub checked_at: u64,
+    /// Hash of the refresh token that was checked, so a new login invalidates the record.
+    pub fingerprint: String,
+}
+
+/// Serializes read-modify-write of state.toml between threads (the TUI's auth workers).
+static STATE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone)]
 pub struct Store {
     base_dir: PathBuf,
 }
@@ -12,21 +72,17 @@ pub struct Store {
 impl Store {
     pub fn new() -> Result<Self> {
         // Store inside gcloud's config directory so everything lives together.
-        // Respects CLOUDSDK_CONFIG, just like gcloud itself.
-        let gcloud_dir = if let Ok(custom) = std::env::var("CLOUDSDK_CONFIG") {
-            PathBuf::from(custom)
-        } else {
-            dirs::home_dir()
-                .context("Could not determine home directory")?
-                .join(".config")
-                .join("gcloud")
-        };
-        let base_dir = gcloud_dir.join("gcloud-switch");
+        let base_dir = gcloud::gcloud_config_dir()?.join("gcloud-switch");
         fs::create_dir_all(&base_dir)?;
         fs::create_dir_all(base_dir.join("adc"))?;
         Ok(Self { base_dir })
     }
 
+    /// Directory holding profiles.toml and the stored ADC files.
+    pub fn dir(&self) -> &Path {
+        &self.base_dir
+    }
+
     fn profiles_path(&self) -> PathBuf {
         self.base_dir.join("profiles.toml")
     }
@@ -66,13 +122,96 @@ impl Store {
     }
 
     pub fn save_profiles(&self, profiles: &ProfilesFile) -> Result<()> {
+        let start = Instant::now();
         let content =
             toml::to_string_pretty(profiles).context("Failed to serialize profiles.toml")?;
         fs::write(self.profiles_path(), content)?;
+        tracing::debug!(
+            profiles = profiles.profiles.len(),
+            active = ?profiles.active_profile,
+            elapsed = ?start.elapsed(),
+            "saved profiles.toml"
+        );
+        Ok(())
+    }
+
+    /// The remote's profiles.toml as of the last sync push or pull: the base of the three-way merge.
+    fn sync_base_path(&self) -> PathBuf {
+        self.base_dir.join("sync-base.toml")
+    }
+
+    pub fn load_sync_base(&self) -> Result<Option<ProfilesFile>> {
+        let path = self.sync_base_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        // A damaged base only costs field-level merging, so fall back to whole profiles
+        Ok(toml::from_str(&content).ok())
+    }
+
+    pub fn save_sync_base(&self, profiles: &ProfilesFile) -> Result<()> {
+        let content =
+            toml::to_string_pretty(profiles).context("Failed to serialize sync-base.toml")?;
+        fs::write(self.sync_base_path(), content)?;
+        tracing::debug!(profiles = profiles.profiles.len(), "saved sync-base.toml");
+        Ok(())
+    }
+
+    fn state_path(&self) -> PathBuf {
+        self.base_dir.join("state.toml")
+    }
+
+    pub fn load_state(&self) -> Result<State> {
+        let path = self.state_path();
+        if !path.exists() {
+            return Ok(State::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        // Only a cache: a damaged file is as good as none
+        Ok(toml::from_str(&content).unwrap_or_default())
+    }
+
+    /// Written through a temporary file, so another process never reads half of it.
+    pub fn save_state(&self, state: &State) -> Result<()> {
+        let content = toml::to_string_pretty(state).context("Failed to serialize state.toml")?;
+        let tmp = self.base_dir.join("state.toml.tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, self.state_path())?;
+        Ok(())
+    }
+
+    /// Load state.toml, change it and write it back, one thread at a time.
+    pub fn update_state(&self, change: impl FnOnce(&mut State)) -> Result<()> {
+        let _guard = STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut state = self.load_state()?;
+        change(&mut state);
+        self.save_state(&state)
+    }
+
+    /// Remember that `name` was activated just now. Only logged on failure: a missing
+    /// timestamp is not worth failing an activation over.
+    pub fn record_activation(&self, name: &str) {
+        let saved = self.update_state(|state| {
+            state.last_activated.insert(name.to_string(), crate::profile::now());
+        });
+        if let Err(e) = saved {
+            tracing::warn!(error = %format!("{:#}", e), "could not save state.toml");
+        }
+    }
+
+    /// Forget the merge base, e.g. when switching to another remote.
+    pub fn clear_sync_base(&self) -> Result<()> {
+        let path = self.sync_base_path();
+        if path.exists() {
+            fs::remove_file(&path)?;
+            tracing::debug!("removed sync-base.toml");
+        }
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub fn load_adc_json(&self, profile_name: &str) -> Result<Option<serde_json::Value>> {
         let path = self.adc_path(profile_name);
         if !path.exists() {
@@ -86,7 +225,18 @@ impl Store {
     pub fn save_adc_json(&self, profile_name: &str, value: &serde_json::Value) -> Result<()> {
         let path = self.adc_path(profile_name);
         let content = serde_json::to_string_pretty(value)?;
-        fs::write(path, content)?;
+        fs::write(&path, content)?;
+        tracing::debug!(profile = profile_name, path = %path.display(), "saved ADC file");
+        Ok(())
+    }
+
+    /// Remove a profile's stored ADC file, if any.
+    pub fn remove_adc(&self, profile_name: &str) -> Result<()> {
+        let path = self.adc_path(profile_name);
+        if path.exists() {
+            fs::remove_file(&path)?;
+            tracing::debug!(profile = profile_name, "removed ADC file");
+        }
         Ok(())
     }
 
@@ -97,18 +247,19 @@ impl Store {
     pub fn add_profile(&self, name: &str, mut profile: Profile) -> Result<()> {
         profile.touch();
         let mut data = self.load_profiles()?;
-        data.profiles.insert(name.to_string(), profile);
+        data.insert_profile(name, profile);
+        tracing::info!(profile = name, "add profile");
         self.save_profiles(&data)
     }
 
     pub fn delete_profile(&self, name: &str) -> Result<()> {
         let mut data = self.load_profiles()?;
-        data.profiles.remove(name);
-
-        // Clear active state if this was the active profile
-        if data.active_profile.as_deref() == Some(name) {
-            data.active_profile = None;
-        }
+        // Also clears the active state if this was the active profile
+        data.remove_profile(name);
+        tracing::info!(profile = name, "delete profile");
+        let _ = self.update_state(|state| {
+            state.last_activated.remove(name);
+        });
 
         self.save_profiles(&data)?;
 
@@ -120,4 +271,40 @@ impl Store {
 
         Ok(())
     }
+
+    /// Rename a profile, carrying over its ADC file and active state.
+    pub fn rename_profile(&self, old: &str, new: &str) -> Result<()> {
+        let mut data = self.load_profiles()?;
+        if data.profiles.contains_key(new) {
+            anyhow::bail!("Profile '{}' already exists", new);
+        }
+        let original = data.clone();
+        let was_active = data.active_profile.as_deref() == Some(old);
+        let mut profile = data
+            .remove_profile(old)
+            .with_context(|| format!("Profile '{}' not found", old))?;
+        profile.touch();
+        data.insert_profile(new, profile);
+        if was_active {
+            data.active_profile = Some(new.to_string());
+        }
+        tracing::info!(old, new, "rename profile");
+
+        // Profiles first: if that fails, nothing has moved. If the ADC then can't follow,
+        // put the old profiles back so the ADC still belongs to its profile.
+        self.save_profiles(&data)?;
+        let old_adc = self.adc_path(old);
+        if old_adc.exists() {
+            if let Err(e) = fs::rename(&old_adc, self.adc_path(new)) {
+                self.save_profiles(&original)?;
+                return Err(e).with_context(|| format!("Failed to rename {}", old_adc.display()));
+            }
+        }
+        let _ = self.update_state(|state| {
+            if let Some(ts) = state.last_activated.remove(old) {
+                state.last_activated.insert(new.to_string(), ts);
+            }
+        });
+        Ok(())
+    }
 }
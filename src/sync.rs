@@ -1,28 +1,261 @@
-//! Sync profile metadata via a user-supplied Git remote.
+//! Sync profile metadata via a user-supplied Git remote, GitHub Gist or HTTPS endpoint.
 //! No credentials are synced; only profile names and account/project identifiers.
-//! Uses the system `git` CLI so the user's git auth (SSH or credential helper) is used.
+//! Git uses libgit2 (git2), so no `git` binary is needed. Remotes authenticate like git
+//! does: the SSH agent, then the default keys in ~/.ssh, then the configured credential
+//! helper. Gist and HTTPS send the sync token as a bearer token.
+//! With encryption on, each file is uploaded as `<file>.age` (see crypto.rs) and the
+//! plaintext never leaves the machine.
 
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fs;
-use std::io::{self, Write};
 use std::path::Path;
-use std::process::Command;
+use std::time::Instant;
 
 use anyhow::{Context, Result};
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    Cred, CredentialType, ErrorCode, FetchOptions, ProxyOptions, PushOptions, RemoteCallbacks,
+    Repository, Signature,
+};
 
-use crate::profile::{Profile, ProfilesFile};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto;
+use crate::net;
+use crate::profile::{self, Profile, ProfilesFile};
 use crate::store::Store;
 
-fn run_git(repo_path: &Path, args: &[&str]) -> Result<Vec<u8>> {
-    let out = Command::new("git")
-        .current_dir(repo_path)
-        .args(args)
-        .output()
-        .context("Failed to run git")?;
-    if !out.status.success() {
-        let stderr = String::from_utf8_lossy(&out.stderr);
-        anyhow::bail!("git failed: {}", stderr);
+/// Where synced files live.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncBackend {
+    /// A git repository; `remote_url` is its clone URL.
+    #[default]
+    Git,
+    /// A (secret) GitHub Gist; `remote_url` is the gist URL or id.
+    Gist,
+    /// Any HTTPS endpoint; each file is GET/PUT at `<remote_url>/<file>`.
+    Http,
+}
+
+impl SyncBackend {
+    pub fn is_git(&self) -> bool {
+        *self == SyncBackend::Git
+    }
+}
+
+/// Remote and files to sync, from the global settings.
+#[derive(Clone)]
+pub struct SyncTarget {
+    pub backend: SyncBackend,
+    pub remote_url: String,
+    pub branch: String,
+    pub files: Vec<String>,
+    /// Bearer token for the Gist and HTTPS backends.
+    pub token: Option<String>,
+    /// Sync key; when set, files are encrypted before they leave the machine.
+    pub identity: Option<age::x25519::Identity>,
+    /// This machine's name in the device history.
+    pub device: String,
+}
+
+impl SyncTarget {
+    /// Name of a sync file on the remote: `<file>.age` when encrypting.
+    fn remote_name(&self, filename: &str) -> String {
+        if self.identity.is_some() {
+            format!("{}.age", filename)
+        } else {
+            filename.to_string()
+        }
+    }
+
+    /// Content as it is uploaded.
+    fn seal(&self, content: &str) -> Result<String> {
+        match &self.identity {
+            Some(identity) => crypto::encrypt(identity, content),
+            None => Ok(content.to_string()),
+        }
+    }
+
+    /// Take `filename` from pulled remote files, decrypting it when encrypting. A plain
+    /// copy is used if there is no encrypted one yet (encryption was just turned on).
+    fn take_remote(&self, remote: &mut BTreeMap<String, String>, filename: &str) -> Result<Option<String>> {
+        if let Some(identity) = &self.identity {
+            if let Some(content) = remote.remove(&self.remote_name(filename)) {
+                return crypto::decrypt(identity, &content)
+                    .with_context(|| format!("Failed to decrypt {}", filename))
+                    .map(Some);
+            }
+        }
+        Ok(remote.remove(filename))
+    }
+}
+
+/// How a pull settles a profile changed on both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The newer side wins; when neither is newer ask the pull's `choose` callback, or
+    /// without one keep local and return a `Conflict`.
+    Defer,
+    /// Keep local values for everything changed on both sides.
+    Ours,
+    /// Take remote values for everything changed on both sides.
+    Theirs,
+    /// The newer side wins; when neither is newer keep local.
+    Newer,
+}
+
+impl MergeStrategy {
+    /// Which side wins a field changed on both sides: Greater keeps local, Less takes
+    /// remote, Equal is a conflict.
+    fn winner(self, local_ts: i64, remote_ts: i64) -> Ordering {
+        match self {
+            MergeStrategy::Ours => Ordering::Greater,
+            MergeStrategy::Theirs => Ordering::Less,
+            // Undated profiles on both sides: keep local
+            _ if local_ts == 0 && remote_ts == 0 => Ordering::Greater,
+            MergeStrategy::Newer => local_ts.cmp(&remote_ts).then(Ordering::Greater),
+            MergeStrategy::Defer => local_ts.cmp(&remote_ts),
+        }
+    }
+}
+
+/// Per-device sync history, kept on the remote next to the synced files and as a local
+/// copy in the store directory.
+pub const DEVICES_FILE: &str = "devices.toml";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DevicesFile {
+    #[serde(default)]
+    pub devices: BTreeMap<String, DeviceInfo>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_push: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_pull: Option<i64>,
+}
+
+impl DevicesFile {
+    /// Parse a devices file; a missing or damaged one is empty (it is only history).
+    fn parse(content: Option<&str>) -> Self {
+        content.and_then(|c| toml::from_str(c).ok()).unwrap_or_default()
+    }
+
+    /// Combine with another copy, keeping the latest times of each device.
+    fn merge(&mut self, other: &DevicesFile) {
+        for (name, theirs) in &other.devices {
+            let ours = self.devices.entry(name.clone()).or_default();
+            ours.last_push = ours.last_push.max(theirs.last_push);
+            ours.last_pull = ours.last_pull.max(theirs.last_pull);
+        }
+    }
+
+    fn record(&mut self, device: &str) -> &mut DeviceInfo {
+        self.devices.entry(device.to_string()).or_default()
     }
-    Ok(out.stdout)
+}
+
+fn load_devices(store: &Store) -> Result<DevicesFile> {
+    let path = store.sync_file_path(DEVICES_FILE);
+    Ok(DevicesFile::parse(fs::read_to_string(path).ok().as_deref()))
+}
+
+fn save_devices(store: &Store, devices: &DevicesFile) -> Result<()> {
+    let content = toml::to_string_pretty(devices).context("Failed to serialize devices.toml")?;
+    fs::write(store.sync_file_path(DEVICES_FILE), content)?;
+    Ok(())
+}
+
+/// Files exchanged with the remote: the configured sync files plus the device history.
+fn synced_names(target: &SyncTarget) -> Vec<String> {
+    let mut names = target.files.clone();
+    names.push(DEVICES_FILE.to_string());
+    names
+}
+
+/// Fields of a profile changed differently on both sides with the same timestamp, so
+/// neither version wins. `local` and `remote` are the merged profile with either side's
+/// values for those fields.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub name: String,
+    pub local: Profile,
+    pub remote: Profile,
+}
+
+/// Key files tried, in order, when the SSH agent has no usable key.
+const DEFAULT_SSH_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Callbacks that answer libgit2's credential requests. Each source is tried once;
+/// libgit2 keeps asking until one works, so running out must end in an error.
+fn remote_callbacks<'a>() -> RemoteCallbacks<'a> {
+    let mut tried_agent = false;
+    let mut next_key = 0;
+    let mut tried_helper = false;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        let username = username.unwrap_or("git");
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username);
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            if !tried_agent {
+                tried_agent = true;
+                return Cred::ssh_key_from_agent(username);
+            }
+            if let Some(ssh_dir) = dirs::home_dir().map(|home| home.join(".ssh")) {
+                while let Some(name) = DEFAULT_SSH_KEYS.get(next_key) {
+                    next_key += 1;
+                    let key = ssh_dir.join(name);
+                    if key.exists() {
+                        return Cred::ssh_key(username, None, &key, None);
+                    }
+                }
+            }
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !tried_helper {
+            tried_helper = true;
+            let config = git2::Config::open_default()?;
+            return Cred::credential_helper(&config, url, Some(username).filter(|u| *u != "git"));
+        }
+        Err(git2::Error::from_str(
+            "no accepted credentials (tried ssh-agent, ~/.ssh keys and the git credential helper)",
+        ))
+    });
+    callbacks
+}
+
+fn fetch_options<'a>() -> FetchOptions<'a> {
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(remote_callbacks());
+    options.proxy_options(proxy_options());
+    options
+}
+
+/// Proxy from git's http.proxy or the HTTPS_PROXY / NO_PROXY environment.
+fn proxy_options<'a>() -> ProxyOptions<'a> {
+    let mut options = ProxyOptions::new();
+    options.auto();
+    options
+}
+
+/// Refuse to contact a remote that is not on this machine when offline.
+fn ensure_origin_reachable(repo: &Repository) -> Result<()> {
+    let remote = repo.find_remote("origin")?;
+    net::ensure_reachable("Sync", remote.url().unwrap_or(""))
+}
+
+fn open_repo(store: &Store) -> Result<Repository> {
+    let repo_path = store.sync_repo_path();
+    Repository::open(&repo_path)
+        .with_context(|| format!("Failed to open sync repo at {}", repo_path.display()))
 }
 
 /// Ensure sync repo is cloned. If it doesn't exist, clone the remote (or init + remote if empty).
@@ -31,174 +264,776 @@ pub fn ensure_cloned(store: &Store, remote_url: &str, branch: &str) -> Result<()
     if repo_path.join(".git").exists() {
         return Ok(());
     }
+    net::ensure_reachable("Sync", remote_url)?;
     let parent = repo_path.parent().context("repo path has no parent")?;
     fs::create_dir_all(parent)?;
-    let path_str = repo_path.as_os_str().to_str().context("repo path")?;
-    let s = Command::new("git")
-        .current_dir(parent)
-        .args(["clone", "--branch", branch, remote_url, path_str])
-        .status();
-    if s.as_ref().map(|st| !st.success()).unwrap_or(true) {
-        let s2 = Command::new("git")
-            .current_dir(parent)
-            .args(["clone", remote_url, path_str])
-            .status();
-        if s2.as_ref().map(|st| !st.success()).unwrap_or(true) {
-            // Empty remote: init and add remote; first push will create the branch
-            fs::create_dir_all(&repo_path)?;
-            Command::new("git").current_dir(&repo_path).args(["init"]).status().context("git init")?;
-            Command::new("git")
-                .current_dir(&repo_path)
-                .args(["remote", "add", "origin", remote_url])
-                .status()
-                .context("git remote add")?;
+    let start = Instant::now();
+    let cloned = RepoBuilder::new()
+        .branch(branch)
+        .fetch_options(fetch_options())
+        .clone(remote_url, &repo_path)
+        .or_else(|_| {
+            RepoBuilder::new()
+                .fetch_options(fetch_options())
+                .clone(remote_url, &repo_path)
+        });
+    tracing::debug!(remote_url, branch, cloned = cloned.is_ok(), elapsed = ?start.elapsed(), "git clone");
+    if cloned.is_err() {
+        // Empty remote: init and add remote; first push will create the branch
+        fs::create_dir_all(&repo_path)?;
+        let repo = Repository::init(&repo_path).context("git init")?;
+        repo.remote("origin", remote_url).context("git remote add")?;
+        // Commit onto the sync branch rather than init.defaultBranch
+        repo.set_head(&format!("refs/heads/{}", branch))?;
+    }
+    Ok(())
+}
+
+/// Stage a file (path relative to the repo root).
+fn add_file(repo: &Repository, filename: &str) -> Result<()> {
+    let mut index = repo.index()?;
+    index
+        .add_path(Path::new(filename))
+        .with_context(|| format!("git add {}", filename))?;
+    index.write()?;
+    Ok(())
+}
+
+/// Commit the index onto HEAD. Returns false without committing if nothing changed.
+fn commit_index(repo: &Repository, message: &str) -> Result<bool> {
+    let tree_id = repo.index()?.write_tree()?;
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(e) if matches!(e.code(), ErrorCode::UnbornBranch | ErrorCode::NotFound) => None,
+        Err(e) => return Err(e.into()),
+    };
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree_id) {
+        return Ok(false);
+    }
+    let tree = repo.find_tree(tree_id)?;
+    // Fall back to a fixed identity when user.name / user.email are not configured
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("gcloud-switch", "gcloud-switch@localhost"))?;
+    let parents: Vec<_> = parent.iter().collect();
+    let commit = repo
+        .commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+        .context("git commit")?;
+    tracing::debug!(%commit, message, "git commit");
+    Ok(true)
+}
+
+/// Push HEAD to `branch` on origin. Rejections (e.g. non-fast-forward) are errors.
+fn push(repo: &Repository, branch: &str) -> Result<()> {
+    let head = repo.head().context("Nothing to push: the sync repo has no commits")?;
+    let head_ref = head.name().context("HEAD is not a named reference")?;
+    let refspec = format!("{}:refs/heads/{}", head_ref, branch);
+    ensure_origin_reachable(repo)?;
+
+    let rejected = RefCell::new(None);
+    let mut callbacks = remote_callbacks();
+    callbacks.push_update_reference(|refname, status| {
+        if let Some(reason) = status {
+            *rejected.borrow_mut() = Some(format!("{} rejected: {}", refname, reason));
         }
+        Ok(())
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    options.proxy_options(proxy_options());
+    let mut remote = repo.find_remote("origin")?;
+    let start = Instant::now();
+    let pushed = remote.push(&[refspec.as_str()], Some(&mut options));
+    tracing::debug!(refspec, ok = pushed.is_ok(), elapsed = ?start.elapsed(), "git push");
+    if let Err(e) = pushed {
+        if e.code() == ErrorCode::NotFastForward {
+            anyhow::bail!("The remote has changes that are not here yet; run sync pull first");
+        }
+        return Err(e).with_context(|| format!("git push origin {}", branch));
+    }
+    drop(options);
+    if let Some(reason) = rejected.into_inner() {
+        anyhow::bail!("git push failed: {}", reason);
     }
     Ok(())
 }
 
-/// Push current sync files to the remote. Clones if needed.
-pub fn sync_push(store: &Store, remote_url: &str, branch: &str, sync_files: &[String]) -> Result<()> {
-    ensure_cloned(store, remote_url, branch)?;
-    let repo_path = store.sync_repo_path();
+/// Push current sync files to the remote. Clones the git repo if needed.
+pub fn sync_push(store: &Store, target: &SyncTarget) -> Result<()> {
+    let has_profiles = target.files.iter().any(|f| f == "profiles.toml");
+    if has_profiles {
+        stamp_updated_by(store, target)?;
+    }
+    let mut files = Vec::new();
+    for filename in &target.files {
+        if let Some(content) = local_content(store, filename)? {
+            files.push((filename.clone(), content));
+        }
+    }
+    let mut devices = load_devices(store)?;
+    devices.merge(&remote_devices(store, target)?);
+    devices.record(&target.device).last_push = Some(profile::now());
+    files.push((DEVICES_FILE.to_string(), toml::to_string_pretty(&devices)?));
 
-    for filename in sync_files {
-        let local_path = store.sync_file_path(filename);
-        let repo_file_path = repo_path.join(filename);
-        
-        if filename == "profiles.toml" {
-            let data = store.load_profiles()?;
-            let content = toml::to_string_pretty(&data).context("Failed to serialize profiles.toml")?;
-            fs::write(&repo_file_path, content)?;
-        } else {
-            if local_path.exists() {
-                fs::copy(&local_path, &repo_file_path)?;
+    match target.backend {
+        SyncBackend::Git => git_push(store, target, &files)?,
+        SyncBackend::Gist | SyncBackend::Http => {
+            let mut sealed = Vec::new();
+            for (filename, content) in &files {
+                sealed.push((target.remote_name(filename), target.seal(content)?));
+            }
+            if target.backend == SyncBackend::Gist {
+                gist_push(target, &sealed)?;
+            } else {
+                http_push(target, &sealed)?;
             }
         }
-        
-        run_git(&repo_path, &["add", filename])?;
     }
-    
-    if run_git(&repo_path, &["commit", "-m", "gcloud-switch sync"]).is_err() {
-        // Nothing to commit (working tree clean) is ok
+    save_devices(store, &devices)?;
+    // The remote now matches local: the base for the next three-way merge
+    if has_profiles {
+        store.save_sync_base(&pushed_profiles(store)?)?;
     }
-    run_git(
-        &repo_path,
-        &["push", "-u", "origin", branch],
-    )?;
     Ok(())
 }
 
-/// Fetch and merge: get remote sync files, merge profiles.toml by timestamp (newer wins), resolve conflicts by prompting.
-pub fn sync_pull(store: &Store, remote_url: &str, branch: &str, sync_files: &[String]) -> Result<()> {
-    ensure_cloned(store, remote_url, branch)?;
-    let repo_path = store.sync_repo_path();
+/// Mark profiles changed here since the last sync as last changed by this device.
+fn stamp_updated_by(store: &Store, target: &SyncTarget) -> Result<()> {
+    let mut data = store.load_profiles()?;
+    let base = store.load_sync_base()?.unwrap_or_default();
+    let mut changed = false;
+    for (name, profile) in data.profiles.iter_mut().filter(|(_, p)| p.sync) {
+        if edited_since_sync(&base, name, profile) && profile.updated_by.as_deref() != Some(target.device.as_str()) {
+            profile.updated_by = Some(target.device.clone());
+            changed = true;
+        }
+    }
+    if changed {
+        store.save_profiles(&data)?;
+    }
+    Ok(())
+}
 
-    run_git(&repo_path, &["fetch", "origin", branch])?;
+/// Synced profiles added or changed here since the last push or pull, or None if this
+/// machine has not synced yet.
+pub fn local_changes(store: &Store) -> Result<Option<Vec<String>>> {
+    let Some(base) = store.load_sync_base()? else {
+        return Ok(None);
+    };
+    let data = store.load_profiles()?;
+    Ok(Some(
+        data.profiles
+            .iter()
+            .filter(|(name, profile)| profile.sync && edited_since_sync(&base, name, profile))
+            .map(|(name, _)| name.clone())
+            .collect(),
+    ))
+}
 
-    let remote_ref = format!("origin/{}", branch);
-    
-    // First, checkout the remote branch to get all files
-    run_git(&repo_path, &["checkout", "-B", branch, remote_ref.as_str()])?;
+fn edited_since_sync(base: &ProfilesFile, name: &str, profile: &Profile) -> bool {
+    base.profiles.get(name).is_none_or(|b| !b.diff(profile).is_empty())
+}
+
+/// The remote's device history as of the last fetch, without touching the synced files.
+fn remote_devices(store: &Store, target: &SyncTarget) -> Result<DevicesFile> {
+    let names = [DEVICES_FILE.to_string()];
+    let mut remote = match target.backend {
+        SyncBackend::Git => {
+            // A push only succeeds on top of the remote's head, so the checkout is current
+            ensure_cloned(store, &target.remote_url, &target.branch)?;
+            read_repo_files(store, target, &names)?
+        }
+        SyncBackend::Gist => gist_pull(target)?,
+        SyncBackend::Http => http_pull(target, &names)?,
+    };
+    Ok(DevicesFile::parse(target.take_remote(&mut remote, DEVICES_FILE)?.as_deref()))
+}
+
+/// Device history from the remote combined with the local copy, for `sync status`.
+pub fn sync_status(store: &Store, target: &SyncTarget) -> Result<DevicesFile> {
+    let mut remote = fetch_remote(store, target)?;
+    let mut devices = load_devices(store)?;
+    devices.merge(&DevicesFile::parse(remote.remove(DEVICES_FILE).as_deref()));
+    Ok(devices)
+}
+
+/// The local profiles as they are pushed: without the local-only (`sync = false`) ones.
+fn pushed_profiles(store: &Store) -> Result<ProfilesFile> {
+    let mut data = store.load_profiles()?;
+    data.profiles.retain(|_, profile| profile.sync);
+    Ok(data)
+}
+
+/// Content of a sync file as it should be uploaded; profiles.toml is serialized from the
+/// store so it is always normalized. None if the file doesn't exist locally.
+fn local_content(store: &Store, filename: &str) -> Result<Option<String>> {
+    if filename == "profiles.toml" {
+        return Ok(Some(
+            toml::to_string_pretty(&pushed_profiles(store)?)
+                .context("Failed to serialize profiles.toml")?,
+        ));
+    }
+    let local_path = store.sync_file_path(filename);
+    if !local_path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(fs::read_to_string(&local_path)?))
+}
+
+fn git_push(store: &Store, target: &SyncTarget, files: &[(String, String)]) -> Result<()> {
+    ensure_cloned(store, &target.remote_url, &target.branch)?;
+    let repo_path = store.sync_repo_path();
+    let repo = open_repo(store)?;
+
+    for (filename, content) in files {
+        let remote_name = target.remote_name(filename);
+        write_sealed(target, &repo_path.join(&remote_name), content)?;
+        add_file(&repo, &remote_name)?;
+        if remote_name != *filename {
+            remove_plain_file(&repo, &repo_path, filename)?;
+        }
+    }
     
-    for filename in sync_files {
-        let repo_file_path = repo_path.join(filename);
-        
-        if filename == "profiles.toml" {
-            let remote_content = if repo_file_path.exists() {
-                fs::read_to_string(&repo_file_path)?
-            } else {
-                String::new()
-            };
-            
-            let local = store.load_profiles()?;
-            let remote_profiles: ProfilesFile = toml::from_str(&remote_content)
-                .unwrap_or_else(|_| ProfilesFile::default());
-
-            let merged = merge_profiles(&local, &remote_profiles)?;
-            store.save_profiles(&merged)?;
-            
-            // Update sync repo with merged version
-            let content = toml::to_string_pretty(&merged)?;
-            fs::write(&repo_file_path, content)?;
-            run_git(&repo_path, &["add", filename])?;
-        } else {
-            // For non-profiles.toml files, just copy remote version to local
-            if repo_file_path.exists() {
-                let local_path = store.sync_file_path(filename);
-                if let Some(parent) = local_path.parent() {
-                    fs::create_dir_all(parent)?;
+    commit_index(&repo, "gcloud-switch sync")?;
+    push(&repo, &target.branch)?;
+    Ok(())
+}
+
+/// Write a file into the sync repo. Encryption is randomized, so an encrypted file that
+/// already decrypts to `content` is left alone rather than producing a new commit.
+fn write_sealed(target: &SyncTarget, path: &Path, content: &str) -> Result<()> {
+    if let (Some(identity), Ok(existing)) = (&target.identity, fs::read_to_string(path)) {
+        if crypto::decrypt(identity, &existing).is_ok_and(|plain| plain == content) {
+            return Ok(());
+        }
+    }
+    fs::write(path, target.seal(content)?)?;
+    Ok(())
+}
+
+/// Drop a plaintext copy of a file from the sync repo once it is synced encrypted.
+/// Older commits still hold it; rewriting the remote's history is left to the user.
+fn remove_plain_file(repo: &Repository, repo_path: &Path, filename: &str) -> Result<()> {
+    let path = repo_path.join(filename);
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    let mut index = repo.index()?;
+    if index.get_path(Path::new(filename), 0).is_some() {
+        index.remove_path(Path::new(filename))?;
+        index.write()?;
+    }
+    Ok(())
+}
+
+/// Fetch `branch` from origin and reset the local branch and working tree to it
+/// (the equivalent of `git fetch` + `git checkout -B <branch> origin/<branch>`).
+fn checkout_remote_branch(repo: &Repository, branch: &str) -> Result<()> {
+    let tracking = format!("refs/remotes/origin/{}", branch);
+    let refspec = format!("+refs/heads/{}:{}", branch, tracking);
+    ensure_origin_reachable(repo)?;
+    let start = Instant::now();
+    repo.find_remote("origin")?
+        .fetch(&[refspec.as_str()], Some(&mut fetch_options()), None)
+        .with_context(|| format!("git fetch origin {}", branch))?;
+    tracing::debug!(refspec, elapsed = ?start.elapsed(), "git fetch");
+    let commit = repo
+        .find_reference(&tracking)
+        .with_context(|| format!("Branch '{}' not found on the remote", branch))?
+        .peel_to_commit()?;
+
+    let local = format!("refs/heads/{}", branch);
+    repo.reference(&local, commit.id(), true, "gcloud-switch sync: reset to remote")?;
+    repo.set_head(&local)?;
+    repo.checkout_head(Some(CheckoutBuilder::new().force()))
+        .with_context(|| format!("git checkout {}", branch))?;
+    Ok(())
+}
+
+/// Fetch and merge: get remote sync files, merge profiles.toml by timestamp (newer wins).
+/// Profiles changed on both sides are settled by `strategy`; what it leaves open goes to
+/// `choose`, or without one the local profile is kept and the conflicts are returned for
+/// the caller to resolve.
+pub fn sync_pull(
+    store: &Store,
+    target: &SyncTarget,
+    strategy: MergeStrategy,
+    mut choose: Option<&mut ChooseSide<'_>>,
+) -> Result<Vec<Conflict>> {
+    let mut remote = fetch_remote(store, target)?;
+    let mut conflicts = Vec::new();
+    let mut merged_files = Vec::new();
+    for filename in &target.files {
+        let remote_content = remote.remove(filename);
+        if let Some(merged) = merge_file(store, filename, remote_content, strategy, choose.as_deref_mut(), &mut conflicts)? {
+            merged_files.push((filename.clone(), merged));
+        }
+    }
+    if target.backend.is_git() {
+        commit_merge(store, target, &merged_files)?;
+    }
+    // Recorded locally; the remote sees it with the next push
+    let mut devices = load_devices(store)?;
+    devices.merge(&DevicesFile::parse(remote.remove(DEVICES_FILE).as_deref()));
+    devices.record(&target.device).last_pull = Some(profile::now());
+    save_devices(store, &devices)?;
+    Ok(conflicts)
+}
+
+/// Remote sync files (and the device history) by their local name, decrypted. Files
+/// missing on the remote are left out.
+fn fetch_remote(store: &Store, target: &SyncTarget) -> Result<BTreeMap<String, String>> {
+    let names = synced_names(target);
+    let mut remote = match target.backend {
+        SyncBackend::Git => git_fetch(store, target, &names)?,
+        SyncBackend::Gist => gist_pull(target)?,
+        SyncBackend::Http => http_pull(target, &names)?,
+    };
+    let mut files = BTreeMap::new();
+    for filename in &names {
+        if let Some(content) = target.take_remote(&mut remote, filename)? {
+            files.insert(filename.clone(), content);
+        }
+    }
+    Ok(files)
+}
+
+/// What a pull would change, from `preview_pull`.
+#[derive(Debug, Default)]
+pub struct PullPreview {
+    pub profiles: Vec<ProfileChange>,
+    /// Other synced files whose local copy would be replaced.
+    pub files: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ProfileChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    /// Changed fields as (label, local value, incoming value).
+    pub fields: Vec<(&'static str, String, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Updated,
+    Deleted,
+    /// Changed on both sides; `fields` has the remote values, and the pull would prompt.
+    Conflict,
+}
+
+/// Run the merge of a pull without saving anything, and report per profile what it would
+/// add, update, delete or ask about.
+pub fn preview_pull(store: &Store, target: &SyncTarget, strategy: MergeStrategy) -> Result<PullPreview> {
+    let mut remote = fetch_remote(store, target)?;
+    let mut preview = PullPreview::default();
+    for filename in &target.files {
+        if filename != "profiles.toml" {
+            if let Some(content) = remote.remove(filename) {
+                let local = fs::read_to_string(store.sync_file_path(filename)).ok();
+                if local.as_deref() != Some(content.as_str()) {
+                    preview.files.push(filename.clone());
                 }
-                fs::copy(&repo_file_path, &local_path)?;
             }
+            continue;
+        }
+        let local = store.load_profiles()?;
+        let remote_profiles: ProfilesFile =
+            toml::from_str(&remote.remove(filename).unwrap_or_default()).unwrap_or_default();
+        let base = store.load_sync_base()?;
+        let mut conflicts = Vec::new();
+        // Report conflicts instead of asking about them
+        let merged = merge_profiles(&local, &remote_profiles, base.as_ref(), strategy, None, &mut conflicts)?;
+
+        for (name, profile) in &merged.profiles {
+            if let Some(conflict) = conflicts.iter().find(|c| c.name == *name) {
+                preview.profiles.push(ProfileChange {
+                    name: name.clone(),
+                    kind: ChangeKind::Conflict,
+                    fields: conflict.local.diff(&conflict.remote),
+                });
+                continue;
+            }
+            let (kind, fields) = match local.profiles.get(name) {
+                None => (ChangeKind::Added, Profile::default().diff(profile)),
+                Some(old) => (ChangeKind::Updated, old.diff(profile)),
+            };
+            if kind == ChangeKind::Added || !fields.is_empty() {
+                preview.profiles.push(ProfileChange { name: name.clone(), kind, fields });
+            }
+        }
+        for name in local.profiles.keys().filter(|n| !merged.profiles.contains_key(*n)) {
+            preview.profiles.push(ProfileChange {
+                name: name.clone(),
+                kind: ChangeKind::Deleted,
+                fields: Vec::new(),
+            });
+        }
+    }
+    preview.profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(preview)
+}
+
+/// Bring one pulled file into the store. profiles.toml is merged with the local profiles
+/// and the merged content returned without the local-only profiles; other files are
+/// copied over as they are.
+fn merge_file(
+    store: &Store,
+    filename: &str,
+    remote_content: Option<String>,
+    strategy: MergeStrategy,
+    choose: Option<&mut ChooseSide<'_>>,
+    conflicts: &mut Vec<Conflict>,
+) -> Result<Option<String>> {
+    if filename == "profiles.toml" {
+        let local = store.load_profiles()?;
+        let remote_profiles: ProfilesFile = toml::from_str(&remote_content.unwrap_or_default())
+            .unwrap_or_else(|_| ProfilesFile::default());
+
+        let base = store.load_sync_base()?;
+        let first_conflict = conflicts.len();
+        let merged = merge_profiles(&local, &remote_profiles, base.as_ref(), strategy, choose, conflicts)?;
+        store.save_profiles(&merged)?;
+        for name in local.profiles.keys().filter(|n| merged.deleted.contains_key(*n)) {
+            store.remove_adc(name)?;
+        }
+
+        // The remote as merged is the common ancestor for the next pull; local edits that
+        // are not pushed yet must still differ from it. Unresolved conflicts keep their old
+        // base, so the next pull still sees both edits.
+        let mut new_base = remote_profiles.clone();
+        for conflict in &conflicts[first_conflict..] {
+            match base.as_ref().and_then(|b| b.profiles.get(&conflict.name)) {
+                Some(old) => new_base.profiles.insert(conflict.name.clone(), old.clone()),
+                None => new_base.profiles.remove(&conflict.name),
+            };
+        }
+        store.save_sync_base(&new_base)?;
+
+        // What goes back to the sync repo leaves out local-only profiles, as a push does
+        let mut pushed = merged;
+        pushed.profiles.retain(|_, profile| profile.sync);
+        return Ok(Some(toml::to_string_pretty(&pushed)?));
+    }
+    if let Some(content) = remote_content {
+        let local_path = store.sync_file_path(filename);
+        if let Some(parent) = local_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&local_path, content)?;
+    }
+    Ok(None)
+}
+
+/// Check out the remote branch in the sync repo and read `names` from it.
+fn git_fetch(store: &Store, target: &SyncTarget, names: &[String]) -> Result<BTreeMap<String, String>> {
+    ensure_cloned(store, &target.remote_url, &target.branch)?;
+    let repo = open_repo(store)?;
+    checkout_remote_branch(&repo, &target.branch)?;
+    read_repo_files(store, target, names)
+}
+
+/// Read files from the sync repo's working tree under both their plain and encrypted names.
+fn read_repo_files(store: &Store, target: &SyncTarget, names: &[String]) -> Result<BTreeMap<String, String>> {
+    let repo_path = store.sync_repo_path();
+    let mut remote = BTreeMap::new();
+    for filename in names {
+        for name in [filename.clone(), target.remote_name(filename)] {
+            let path = repo_path.join(&name);
+            if path.exists() {
+                remote.insert(name, fs::read_to_string(&path)?);
+            }
+        }
+    }
+    Ok(remote)
+}
+
+/// Write merged files back into the sync repo and commit them, so the next push carries
+/// the merge.
+fn commit_merge(store: &Store, target: &SyncTarget, merged_files: &[(String, String)]) -> Result<()> {
+    let repo_path = store.sync_repo_path();
+    let repo = open_repo(store)?;
+    for (filename, merged) in merged_files {
+        let remote_name = target.remote_name(filename);
+        write_sealed(target, &repo_path.join(&remote_name), merged)?;
+        add_file(&repo, &remote_name)?;
+        if remote_name != *filename {
+            remove_plain_file(&repo, &repo_path, filename)?;
         }
     }
     
-    // Commit merged state
-    if run_git(&repo_path, &["commit", "-m", "gcloud-switch sync merge"]).is_err() {
-        // No change after merge is ok
+    // Commit merged state (no change after merge is ok)
+    commit_index(&repo, "gcloud-switch sync merge")?;
+    Ok(())
+}
+
+fn http_client() -> Result<Client> {
+    net::client("Sync")
+}
+
+/// Send a gist or http sync request, logging its status and duration.
+fn send_logged(request: RequestBuilder, method: &str, url: &str) -> Result<Response> {
+    let start = Instant::now();
+    let response = request.send().with_context(|| format!("{} {}", method, url))?;
+    tracing::debug!(method, url, status = %response.status(), elapsed = ?start.elapsed(), "sync request");
+    Ok(response)
+}
+
+fn with_token(request: RequestBuilder, target: &SyncTarget) -> RequestBuilder {
+    match &target.token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
     }
+}
 
+/// Gist id from a gist URL (`https://gist.github.com/<user>/<id>`) or a bare id.
+fn gist_api_url(remote_url: &str) -> String {
+    let id = remote_url.trim_end_matches('/').rsplit('/').next().unwrap_or(remote_url);
+    format!("https://api.github.com/gists/{}", id)
+}
+
+#[derive(Deserialize)]
+struct Gist {
+    files: BTreeMap<String, GistFile>,
+}
+
+#[derive(Deserialize)]
+struct GistFile {
+    content: Option<String>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+fn gist_pull(target: &SyncTarget) -> Result<BTreeMap<String, String>> {
+    let url = gist_api_url(&target.remote_url);
+    let request = with_token(http_client()?.get(&url), target)
+        .header("Accept", "application/vnd.github+json");
+    let response = send_logged(request, "GET", &url)?;
+    if !response.status().is_success() {
+        anyhow::bail!("GET {} failed: {}", url, response.status());
+    }
+    let gist: Gist = response.json().context("Unexpected response from the Gist API")?;
+    let mut files = BTreeMap::new();
+    for (name, file) in gist.files {
+        if file.truncated {
+            anyhow::bail!("Gist file '{}' is too large for the Gist API", name);
+        }
+        if let Some(content) = file.content {
+            files.insert(name, content);
+        }
+    }
+    Ok(files)
+}
+
+fn gist_push(target: &SyncTarget, files: &[(String, String)]) -> Result<()> {
+    if target.token.is_none() {
+        anyhow::bail!("Gist sync needs a GitHub token with the gist scope: set GCLOUD_SWITCH_SYNC_TOKEN or sync_token");
+    }
+    let url = gist_api_url(&target.remote_url);
+    let body = serde_json::json!({
+        "files": files
+            .iter()
+            .map(|(name, content)| (name.clone(), serde_json::json!({ "content": content })))
+            .collect::<serde_json::Map<_, _>>(),
+    });
+    let request = with_token(http_client()?.patch(&url), target)
+        .header("Accept", "application/vnd.github+json")
+        .json(&body);
+    let response = send_logged(request, "PATCH", &url)?;
+    if !response.status().is_success() {
+        anyhow::bail!("PATCH {} failed: {}", url, response.status());
+    }
     Ok(())
 }
 
-/// Merge local and remote: newer wins per profile; new remote profiles inserted; on conflict prompt which to keep.
-fn merge_profiles(local: &ProfilesFile, remote: &ProfilesFile) -> Result<ProfilesFile> {
+fn http_file_url(target: &SyncTarget, filename: &str) -> String {
+    format!("{}/{}", target.remote_url.trim_end_matches('/'), filename)
+}
+
+fn http_pull(target: &SyncTarget, names: &[String]) -> Result<BTreeMap<String, String>> {
+    let client = http_client()?;
+    let mut files = BTreeMap::new();
+    for name in names {
+        let filename = &target.remote_name(name);
+        let url = http_file_url(target, filename);
+        let response = send_logged(with_token(client.get(&url), target), "GET", &url)?;
+        // Nothing pushed yet
+        if response.status() == StatusCode::NOT_FOUND {
+            continue;
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("GET {} failed: {}", url, response.status());
+        }
+        files.insert(filename.clone(), response.text()?);
+    }
+    Ok(files)
+}
+
+fn http_push(target: &SyncTarget, files: &[(String, String)]) -> Result<()> {
+    let client = http_client()?;
+    for (filename, content) in files {
+        let url = http_file_url(target, filename);
+        let request = with_token(client.put(&url), target).body(content.clone());
+        let response = send_logged(request, "PUT", &url)?;
+        if !response.status().is_success() {
+            anyhow::bail!("PUT {} failed: {}", url, response.status());
+        }
+    }
+    Ok(())
+}
+
+/// Merge local and remote; new remote profiles are inserted. A profile that is also in
+/// `base` (the profiles as of the last sync) is merged field by field, so edits to
+/// different fields on the two sides both survive. Without a base, the profile is settled
+/// as a whole. Changes on both sides go to the side `strategy` picks; when it picks neither,
+/// `choose` says which to keep, or without it local is kept and both sides are recorded in
+/// `conflicts`.
+fn merge_profiles(
+    local: &ProfilesFile,
+    remote: &ProfilesFile,
+    base: Option<&ProfilesFile>,
+    strategy: MergeStrategy,
+    mut choose: Option<&mut ChooseSide<'_>>,
+    conflicts: &mut Vec<Conflict>,
+) -> Result<ProfilesFile> {
     let mut out = local.clone();
-    for (name, remote_prof) in &remote.profiles {
+    // Local-only profiles are left alone, whatever the remote has under their name
+    let local_only = |name: &String| local.profiles.get(name).is_some_and(|p| !p.sync);
+    for (name, remote_prof) in remote.profiles.iter().filter(|(name, _)| !local_only(name)) {
         match out.profiles.get(name) {
             Some(local_prof) => {
                 let local_ts = local_prof.updated_at.unwrap_or(0);
                 let remote_ts = remote_prof.updated_at.unwrap_or(0);
-                if remote_ts > local_ts {
-                    out.profiles.insert(name.clone(), remote_prof.clone());
-                } else if remote_ts == local_ts && remote_ts != 0 && *local_prof != *remote_prof {
-                    let choice = prompt_which_to_keep(name, local_prof, remote_prof)?;
-                    match choice {
-                        MergeChoice::Local => {}
-                        MergeChoice::Remote => {
-                            out.profiles.insert(name.clone(), remote_prof.clone());
-                        }
+                let winner = strategy.winner(local_ts, remote_ts);
+                let base_prof = base.and_then(|b| b.profiles.get(name));
+                let (ours, theirs) = match base_prof {
+                    Some(base_prof) => merge_fields(local_prof, remote_prof, base_prof, winner),
+                    None if local_prof.diff(remote_prof).is_empty() => {
+                        (local_prof.clone(), local_prof.clone())
                     }
+                    None => match winner {
+                        Ordering::Less => (remote_prof.clone(), remote_prof.clone()),
+                        Ordering::Equal => (local_prof.clone(), remote_prof.clone()),
+                        Ordering::Greater => (local_prof.clone(), local_prof.clone()),
+                    },
+                };
+                if ours.diff(&theirs).is_empty() {
+                    out.profiles.insert(name.clone(), ours);
+                    continue;
                 }
+                let conflict = Conflict {
+                    name: name.clone(),
+                    local: ours,
+                    remote: theirs,
+                };
+                let Some(choose) = choose.as_deref_mut() else {
+                    out.profiles.insert(name.clone(), conflict.local.clone());
+                    conflicts.push(conflict);
+                    continue;
+                };
+                let kept = match choose(&conflict)? {
+                    MergeChoice::Local => conflict.local,
+                    MergeChoice::Remote => conflict.remote,
+                };
+                out.profiles.insert(name.clone(), kept);
             }
             None => {
                 out.profiles.insert(name.clone(), remote_prof.clone());
             }
         }
     }
-    Ok(out)
-}
 
-enum MergeChoice {
-    Local,
-    Remote,
+    // Deletions from either side win over older versions of the profile; a profile
+    // edited or re-created after its deletion stays and the tombstone is dropped.
+    for (name, &deleted_at) in remote.deleted.iter().filter(|(name, _)| !local_only(name)) {
+        let known = out.deleted.entry(name.clone()).or_insert(deleted_at);
+        *known = (*known).max(deleted_at);
+    }
+    let deleted = std::mem::take(&mut out.deleted);
+    for (name, deleted_at) in deleted {
+        match out.profiles.get(&name) {
+            Some(profile) if profile.updated_at.unwrap_or(0) > deleted_at => {}
+            _ => {
+                out.remove_profile(&name);
+                out.deleted.insert(name, deleted_at);
+            }
+        }
+    }
+    conflicts.retain(|c| out.profiles.contains_key(&c.name));
+    out.expire_tombstones();
+    Ok(out)
 }
 
-fn prompt_which_to_keep(name: &str, local: &Profile, remote: &Profile) -> Result<MergeChoice> {
-    eprintln!("Profile '{}' changed on both sides.", name);
-    eprintln!(
-        "  Local:  {} / {} (adc: {} / {})",
-        local.user_account,
-        local.user_project,
-        local.adc_account,
-        local.adc_quota_project
+/// Three-way merge of one profile. Returns the merged profile twice: once keeping local and
+/// once taking remote for fields changed differently on both sides when `newer` is Equal,
+/// so the two differ only on true conflicts. Other fields changed on both sides go to the
+/// side `newer` picks (Greater: local, Less: remote).
+fn merge_fields(local: &Profile, remote: &Profile, base: &Profile, newer: Ordering) -> (Profile, Profile) {
+    let local_ts = local.updated_at.unwrap_or(0);
+    let remote_ts = remote.updated_at.unwrap_or(0);
+    let mut ours = local.clone();
+    let mut theirs = local.clone();
+    merge_field(&mut ours.user_account, &mut theirs.user_account, &remote.user_account, &base.user_account, newer);
+    merge_field(&mut ours.user_project, &mut theirs.user_project, &remote.user_project, &base.user_project, newer);
+    merge_field(&mut ours.adc_account, &mut theirs.adc_account, &remote.adc_account, &base.adc_account, newer);
+    merge_field(
+        &mut ours.adc_quota_project,
+        &mut theirs.adc_quota_project,
+        &remote.adc_quota_project,
+        &base.adc_quota_project,
+        newer,
     );
-    eprintln!(
-        "  Remote: {} / {} (adc: {} / {})",
-        remote.user_account,
-        remote.user_project,
-        remote.adc_account,
-        remote.adc_quota_project
+    merge_field(&mut ours.tags, &mut theirs.tags, &remote.tags, &base.tags, newer);
+    merge_field(&mut ours.contexts, &mut theirs.contexts, &remote.contexts, &base.contexts, newer);
+    merge_field(&mut ours.clusters, &mut theirs.clusters, &remote.clusters, &base.clusters, newer);
+    merge_field(&mut ours.registries, &mut theirs.registries, &remote.registries, &base.registries, newer);
+    merge_field(&mut ours.ssh, &mut theirs.ssh, &remote.ssh, &base.ssh, newer);
+    merge_field(&mut ours.terraform, &mut theirs.terraform, &remote.terraform, &base.terraform, newer);
+    merge_field(
+        &mut ours.gcloud_properties,
+        &mut theirs.gcloud_properties,
+        &remote.gcloud_properties,
+        &base.gcloud_properties,
+        newer,
     );
-    eprint!("Keep (L)ocal or (R)emote? [L/r]: ");
-    io::stderr().flush()?;
-    let mut buf = String::new();
-    io::stdin().read_line(&mut buf)?;
-    let choice = buf.trim().to_lowercase();
-    if choice.starts_with('r') {
-        Ok(MergeChoice::Remote)
-    } else {
-        Ok(MergeChoice::Local)
+    merge_field(
+        &mut ours.confirm_before_activate,
+        &mut theirs.confirm_before_activate,
+        &remote.confirm_before_activate,
+        &base.confirm_before_activate,
+        newer,
+    );
+    merge_field(&mut ours.locked, &mut theirs.locked, &remote.locked, &base.locked, newer);
+    let updated_at = local.updated_at.max(remote.updated_at);
+    let updated_by = if remote_ts > local_ts { &remote.updated_by } else { &local.updated_by };
+    for merged in [&mut ours, &mut theirs] {
+        merged.updated_at = updated_at;
+        merged.updated_by = updated_by.clone();
+    }
+    (ours, theirs)
+}
+
+/// Merge one field; `ours` and `theirs` both start as the local value.
+fn merge_field<T: PartialEq + Clone>(ours: &mut T, theirs: &mut T, remote: &T, base: &T, newer: Ordering) {
+    if *ours == *remote || *remote == *base {
+        return;
+    }
+    // Only remote changed it, or both did and remote is newer
+    if *ours == *base || newer == Ordering::Less {
+        *ours = remote.clone();
+        *theirs = remote.clone();
+    } else if newer == Ordering::Equal {
+        *theirs = remote.clone();
     }
 }
+
+/// Which side of a `Conflict` a pull's `choose` callback keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeChoice {
+    Local,
+    Remote,
+}
+
+/// The callback a pull asks which side of a `Conflict` to keep.
+pub type ChooseSide<'a> = dyn FnMut(&Conflict) -> Result<MergeChoice> + 'a;
@@ -0,0 +1,48 @@
+//! `gcloud-switch terraform`: environment for running Terraform's Google provider against
+//! a profile without activating it, and the profile's backend config rendered from its
+//! template.
+
+use anyhow::{Context, Result};
+
+use crate::profile::Profile;
+use crate::providers::export_line;
+use crate::store::Store;
+
+/// Shell lines exporting the Google provider's variables for `name`. Unset ones are
+/// cleared, so nothing from the previously evaluated profile leaks into the next run.
+/// `impersonate` overrides the profile's service account.
+pub fn exports(store: &Store, name: &str, profile: &Profile, impersonate: Option<&str>) -> Vec<String> {
+    let project = Some(profile.user_project.as_str()).filter(|p| !p.is_empty());
+    let impersonate = impersonate.or_else(|| {
+        profile
+            .terraform
+            .as_ref()
+            .and_then(|t| t.impersonate_service_account.as_deref())
+    });
+    // The profile's stored ADC, so Terraform does not depend on which profile is active
+    let adc = store.adc_path(name);
+    let credentials = adc.exists().then(|| adc.display().to_string());
+    vec![
+        export_line("GOOGLE_PROJECT", project),
+        export_line("GOOGLE_IMPERSONATE_SERVICE_ACCOUNT", impersonate),
+        export_line("GOOGLE_APPLICATION_CREDENTIALS", credentials.as_deref()),
+    ]
+}
+
+/// The profile's backend template with its placeholders filled in.
+pub fn render_backend(name: &str, profile: &Profile) -> Result<String> {
+    let template = profile
+        .terraform
+        .as_ref()
+        .and_then(|t| t.backend.as_deref())
+        .with_context(|| format!("Profile '{}' has no [profiles.{}.terraform] backend template", name, name))?;
+    let mut rendered = template
+        .replace("{profile}", name)
+        .replace("{project}", &profile.user_project)
+        .replace("{account}", &profile.user_account)
+        .replace("{quota_project}", &profile.adc_quota_project);
+    if !rendered.ends_with('\n') {
+        rendered.push('\n');
+    }
+    Ok(rendered)
+}
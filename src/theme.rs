@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+/// `[theme]` section of gcloud-switch.toml: a built-in theme plus per-role color overrides.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeSettings {
+    /// Built-in theme to start from: "dark" (default) or "light".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Role overrides, e.g. `selection_bg = "#005f87"`. Values are color names,
+    /// 256-color indices ("24") or hex ("#rrggbb").
+    #[serde(flatten)]
+    pub colors: BTreeMap<String, String>,
+}
+
+impl ThemeSettings {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.colors.is_empty()
+    }
+}
+
+/// Colors for each semantic role in the TUI.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub header_fg: Color,
+    pub header_bg: Color,
+    pub row_fg: Color,
+    pub row_bg: Color,
+    /// Text of the active profile's row.
+    pub active_fg: Color,
+    pub selection_fg: Color,
+    pub selection_bg: Color,
+    /// Selected column within the selected row.
+    pub column_fg: Color,
+    pub column_bg: Color,
+    /// Table cell being edited in place.
+    pub edit_fg: Color,
+    pub edit_bg: Color,
+    /// Rows marked for a batch operation.
+    pub marked_bg: Color,
+    /// Rows of guarded profiles (`[guard]`), so production stands out.
+    pub guarded_fg: Color,
+    pub guarded_bg: Color,
+    /// Highlighted entry of the suggestion dropdown.
+    pub suggestion_fg: Color,
+    pub suggestion_bg: Color,
+    /// Title and popup borders.
+    pub accent: Color,
+    /// Values and typed input outside the table.
+    pub text: Color,
+    /// Secondary text: unfocused labels, dropdown entries.
+    pub secondary: Color,
+    /// Hints, placeholders and descriptions.
+    pub muted: Color,
+    /// Input prompts and warnings.
+    pub prompt: Color,
+    pub error: Color,
+    /// Key names in the help line.
+    pub key: Color,
+    /// Status bar messages.
+    pub status: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            header_fg: Color::Black,
+            header_bg: Color::Indexed(254),
+            row_fg: Color::Black,
+            row_bg: Color::Indexed(255),
+            active_fg: Color::Green,
+            selection_fg: Color::White,
+            selection_bg: Color::Blue,
+            column_fg: Color::White,
+            column_bg: Color::Indexed(75),
+            edit_fg: Color::Black,
+            edit_bg: Color::Indexed(255),
+            marked_bg: Color::Indexed(229),
+            guarded_fg: Color::Indexed(88),
+            guarded_bg: Color::Indexed(224),
+            suggestion_fg: Color::White,
+            suggestion_bg: Color::Indexed(24),
+            accent: Color::Cyan,
+            text: Color::White,
+            secondary: Color::Gray,
+            muted: Color::DarkGray,
+            prompt: Color::Yellow,
+            error: Color::Red,
+            key: Color::Red,
+            status: Color::Green,
+        }
+    }
+
+    /// For terminals with a light background: no row fill, dark text.
+    pub fn light() -> Self {
+        Self {
+            header_fg: Color::Black,
+            header_bg: Color::Indexed(252),
+            row_fg: Color::Black,
+            row_bg: Color::Reset,
+            active_fg: Color::Indexed(28),
+            selection_fg: Color::White,
+            selection_bg: Color::Indexed(25),
+            column_fg: Color::White,
+            column_bg: Color::Indexed(32),
+            edit_fg: Color::Black,
+            edit_bg: Color::Indexed(254),
+            marked_bg: Color::Indexed(230),
+            guarded_fg: Color::Indexed(88),
+            guarded_bg: Color::Indexed(224),
+            suggestion_fg: Color::White,
+            suggestion_bg: Color::Indexed(25),
+            accent: Color::Indexed(25),
+            text: Color::Black,
+            secondary: Color::Indexed(238),
+            muted: Color::Indexed(244),
+            prompt: Color::Indexed(130),
+            error: Color::Indexed(160),
+            key: Color::Indexed(160),
+            status: Color::Indexed(28),
+        }
+    }
+
+    /// Build the theme from settings: the named built-in, then each override applied.
+    pub fn from_settings(settings: &ThemeSettings) -> Result<Self> {
+        let mut theme = match settings.name.as_deref() {
+            None | Some("dark") => Self::dark(),
+            Some("light") => Self::light(),
+            Some(other) => bail!("Unknown theme '{}' (expected \"dark\" or \"light\")", other),
+        };
+        for (role, value) in &settings.colors {
+            let color = Color::from_str(value)
+                .ok()
+                .with_context(|| format!("Invalid color '{}' for theme role '{}'", value, role))?;
+            match theme.role_mut(role) {
+                Some(slot) => *slot = color,
+                None => bail!("Unknown theme role '{}'", role),
+            }
+        }
+        Ok(theme)
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Color> {
+        Some(match role {
+            "header_fg" => &mut self.header_fg,
+            "header_bg" => &mut self.header_bg,
+            "row_fg" => &mut self.row_fg,
+            "row_bg" => &mut self.row_bg,
+            "active_fg" => &mut self.active_fg,
+            "selection_fg" => &mut self.selection_fg,
+            "selection_bg" => &mut self.selection_bg,
+            "column_fg" => &mut self.column_fg,
+            "column_bg" => &mut self.column_bg,
+            "edit_fg" => &mut self.edit_fg,
+            "edit_bg" => &mut self.edit_bg,
+            "marked_bg" => &mut self.marked_bg,
+            "guarded_fg" => &mut self.guarded_fg,
+            "guarded_bg" => &mut self.guarded_bg,
+            "suggestion_fg" => &mut self.suggestion_fg,
+            "suggestion_bg" => &mut self.suggestion_bg,
+            "accent" => &mut self.accent,
+            "text" => &mut self.text,
+            "secondary" => &mut self.secondary,
+            "muted" => &mut self.muted,
+            "prompt" => &mut self.prompt,
+            "error" => &mut self.error,
+            "key" => &mut self.key,
+            "status" => &mut self.status,
+            _ => return None,
+        })
+    }
+}
@@ -1,16 +1,29 @@
 use ratatui::{
-    layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
     widgets::{
         Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Scrollbar,
-        ScrollbarOrientation, ScrollbarState, Table,
+        ScrollbarOrientation, ScrollbarState, Table, Wrap,
     },
     Frame,
 };
 
-use crate::app::{App, Column, InputMode};
-use crate::profile::SyncMode;
+use gcloud_switch::gcloud::{AuthStatus, PlanStep};
+use gcloud_switch::profile::SyncMode;
+
+use crate::app::{App, AuthView, Column, InputMode, Severity, SyncDirection, FIELD_NAME, FORM_LABELS};
+use crate::theme::Theme;
+
+/// Width and minimum height of the detail pane shown to the right of the table.
+const DETAIL_WIDTH: u16 = 48;
+const DETAIL_HEIGHT: u16 = 16;
+
+/// Height of the activity log pane below the table, including its top border.
+const LOG_HEIGHT: u16 = 8;
+
+/// Widest a toast gets; narrower terminals use most of their width.
+const TOAST_WIDTH: u16 = 50;
 
 pub fn draw(frame: &mut Frame, app: &mut App) {
     let frame_area = frame.area();
@@ -21,21 +34,29 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
     // Build the actual help line for the current mode
     let help_line = build_help_line(app);
 
-    // Calculate table content width
-    let table_width = table_content_width(app);
+    // Calculate table content width (plus the detail pane beside it)
+    let mut table_width = table_content_width(app);
+    if app.show_details {
+        table_width += DETAIL_WIDTH as usize;
+    }
 
     // Minimum width: max of normal help and table, capped at terminal width
     let content_width = (normal_help_width.max(table_width) as u16).min(frame_area.width);
 
     // Table height
-    let table_h: u16 = if app.profile_names.is_empty() {
+    let mut table_h: u16 = if app.view.is_empty() {
         1
     } else {
-        2 + (app.profile_names.len() as u16) * 2
+        (1 + app.view.len() as u16) * app.table.row_height()
     };
+    if app.show_details {
+        table_h = table_h.max(DETAIL_HEIGHT);
+    }
 
-    // Total content height: table + status bar + help
-    let total_h = table_h + 2;
+    let log_h = if app.show_log { LOG_HEIGHT } else { 0 };
+
+    // Total content height: gcloud context + table + log + status bar + help
+    let total_h = 1 + table_h + log_h + 2;
 
     // Center horizontally; center vertically if content fits
     let x = (frame_area.width.saturating_sub(content_width)) / 2;
@@ -53,126 +74,1004 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
         height,
     };
 
+    let [context_area, centered] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(centered);
     let chunks = Layout::vertical([
         table_constraint,
+        Constraint::Length(log_h),
         Constraint::Length(1),
         Constraint::Length(1),
     ])
     .split(centered);
+    draw_context(frame, app, context_area);
+
+    let table_area = if app.show_details {
+        let [table_area, detail_area] =
+            Layout::horizontal([Constraint::Min(20), Constraint::Length(DETAIL_WIDTH)])
+                .areas(chunks[0]);
+        draw_details(frame, app, detail_area);
+        table_area
+    } else {
+        chunks[0]
+    };
+
+    app.hit_areas.table = table_area;
+    app.hit_areas.suggestions = None;
+    draw_table(frame, app, table_area);
+    if app.show_log {
+        draw_log(frame, app, chunks[1]);
+    }
+    draw_status_bar(frame, app, chunks[2]);
+    frame.render_widget(Paragraph::new(help_line), chunks[3]);
+    draw_toasts(frame, app);
+    if app.form.is_some() {
+        draw_form(frame, app);
+    } else {
+        draw_suggestions(frame, app);
+    }
+    draw_account_picker(frame, app);
+    draw_cluster_picker(frame, app);
+    draw_conflict(frame, app);
+    draw_compare(frame, app);
+    draw_history(frame, app);
+    draw_dry_run(frame, app);
+    draw_error_detail(frame, app);
+}
+
+fn severity_color(theme: &Theme, severity: Severity) -> ratatui::style::Color {
+    match severity {
+        Severity::Info => theme.status,
+        Severity::Warn => theme.prompt,
+        Severity::Error => theme.error,
+    }
+}
+
+/// The live toasts in the bottom right corner, newest lowest, each wrapped to its message.
+/// Older ones are left out when the terminal has no room for them.
+fn draw_toasts(frame: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let frame_area = frame.area();
+    let width = TOAST_WIDTH.min(frame_area.width.saturating_sub(2));
+    if width < 10 {
+        return;
+    }
+    let x = frame_area.width - width - 1;
+    let mut bottom = frame_area.height.saturating_sub(2);
+    for toast in app.toasts.iter().rev() {
+        let block = Block::default()
+            .borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM)
+            .border_style(Style::default().fg(severity_color(theme, toast.severity)));
+        let text_width = (width - 2) as usize;
+        let lines = (toast.message.chars().count() + 1).div_ceil(text_width).max(1) as u16;
+        let height = lines + 2;
+        if height > bottom {
+            break;
+        }
+        let area = Rect { x, y: bottom - height, width, height };
+        let paragraph = Paragraph::new(format!(" {}", toast.message))
+            .style(Style::default().fg(theme.text))
+            .block(block)
+            .wrap(Wrap { trim: false });
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+        bottom -= height;
+    }
+}
+
+/// The activity log as a modal (`H`), newest first and wrapped, for messages that were
+/// gone (as toasts) before they could be read.
+fn draw_history(frame: &mut Frame, app: &mut App) {
+    if app.input_mode != InputMode::Messages {
+        return;
+    }
+    let theme = app.theme;
+    let frame_area = frame.area();
+    let width = (frame_area.width * 4 / 5).max(40).min(frame_area.width);
+    let height = (frame_area.height * 3 / 5).max(8).min(frame_area.height);
+    let area = Rect {
+        x: (frame_area.width - width) / 2,
+        y: (frame_area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let mut lines: Vec<Line> = app
+        .log
+        .iter()
+        .rev()
+        .map(|entry| {
+            Line::from(vec![
+                Span::styled(format!("{} ", format_clock(entry.at)), Style::default().fg(theme.muted)),
+                Span::styled(entry.message.clone(), Style::default().fg(severity_color(&theme, entry.severity))),
+            ])
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::from(Span::styled("(no messages yet)", Style::default().fg(theme.muted))));
+    }
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent))
+        .title(" Messages (newest first) ");
+    let inner = block.inner(area);
+    let wrapped: usize = lines
+        .iter()
+        .map(|line| line.width().div_ceil(inner.width.max(1) as usize).max(1))
+        .sum();
+    app.history_max_scroll = (wrapped as u16).saturating_sub(inner.height);
+    app.history_scroll = app.history_scroll.min(app.history_max_scroll);
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((app.history_scroll, 0));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+/// The dry run (`D`): each key's steps as a shell-like listing, commands in full.
+fn draw_dry_run(frame: &mut Frame, app: &mut App) {
+    let theme = app.theme;
+    let Some(view) = app.dry_run.as_mut() else {
+        return;
+    };
+    let frame_area = frame.area();
+    let width = (frame_area.width * 4 / 5).max(40).min(frame_area.width);
+    let height = (frame_area.height * 3 / 5).max(8).min(frame_area.height);
+    let area = Rect {
+        x: (frame_area.width - width) / 2,
+        y: (frame_area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let mut lines = Vec::new();
+    for (heading, steps) in &view.sections {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        lines.push(Line::from(Span::styled(
+            heading.clone(),
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        )));
+        if steps.is_empty() {
+            lines.push(Line::from(Span::styled("  (nothing to do)", Style::default().fg(theme.muted))));
+        }
+        lines.extend(steps.iter().map(|step| {
+            let color = match step {
+                PlanStep::Command(_) => theme.text,
+                PlanStep::Other(_) => theme.muted,
+            };
+            Line::from(Span::styled(format!("  {}", step), Style::default().fg(color)))
+        }));
+    }
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent))
+        .title(format!(" Dry run: '{}' (nothing is run) ", view.name));
+    let inner = block.inner(area);
+    let wrapped: usize = lines
+        .iter()
+        .map(|line| line.width().div_ceil(inner.width.max(1) as usize).max(1))
+        .sum();
+    view.max_scroll = (wrapped as u16).saturating_sub(inner.height);
+    view.scroll = view.scroll.min(view.max_scroll);
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((view.scroll, 0));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_compare(frame: &mut Frame, app: &mut App) {
+    let theme = app.theme;
+    let Some(view) = app.compare.as_mut() else {
+        return;
+    };
+    let frame_area = frame.area();
+    let label_w = view.rows.iter().map(|row| row.label.chars().count()).max().unwrap_or(0) as u16;
+    let value_w = view
+        .rows
+        .iter()
+        .map(|row| row.a.chars().count().max(row.b.chars().count()))
+        .chain([view.a.chars().count(), view.b.chars().count()])
+        .max()
+        .unwrap_or(0) as u16;
+    // Marker, label and two values with their gaps, inside the border
+    let width = (2 + label_w + 2 + 2 * value_w + 2 + 2).min(frame_area.width * 9 / 10).max(40).min(frame_area.width);
+    let shown_len = view.visible_rows().count() as u16;
+    let height = (shown_len + 3).max(5).min(frame_area.height);
+    let area = Rect {
+        x: (frame_area.width - width) / 2,
+        y: (frame_area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let differing = view.rows.iter().filter(|row| row.differ).count();
+    let title = if differing == 0 {
+        format!(" {} \u{2194} {}: identical ", view.a, view.b)
+    } else {
+        format!(" {} \u{2194} {}: {} of {} differ ", view.a, view.b, differing, view.rows.len())
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent))
+        .title(title);
+    // The header takes one line
+    let body = block.inner(area).height.saturating_sub(1);
+    view.max_scroll = shown_len.saturating_sub(body);
+    view.scroll = view.scroll.min(view.max_scroll);
+    let view = &*view;
+
+    let header = Style::default().fg(theme.muted).add_modifier(Modifier::BOLD);
+    let or_dash = |v: &str| if v.is_empty() { "-".to_string() } else { v.to_string() };
+    let rows: Vec<Row> = view
+        .visible_rows()
+        .skip(view.scroll as usize)
+        .map(|row| {
+            let (marker, style) = if row.differ {
+                ("*", Style::default().fg(theme.prompt))
+            } else {
+                (" ", Style::default().fg(theme.muted))
+            };
+            Row::new(vec![
+                Cell::from(marker).style(style),
+                Cell::from(row.label.clone()).style(Style::default().fg(theme.muted)),
+                Cell::from(or_dash(&row.a)).style(style),
+                Cell::from(or_dash(&row.b)).style(style),
+            ])
+        })
+        .collect();
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(1),
+            Constraint::Length(label_w),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(Row::new(vec![
+        Cell::from(""),
+        Cell::from(""),
+        Cell::from(view.a.clone()).style(header),
+        Cell::from(view.b.clone()).style(header),
+    ]))
+    .column_spacing(2)
+    .block(block);
+    frame.render_widget(Clear, area);
+    frame.render_widget(table, area);
+}
+
+fn draw_conflict(frame: &mut Frame, app: &App) {
+    if app.input_mode != InputMode::SyncConflict {
+        return;
+    }
+    let Some(conflict) = app.conflicts.first() else {
+        return;
+    };
+    let theme = &app.theme;
+    let (local, remote) = (&conflict.local, &conflict.remote);
+    let fields: Vec<_> = local
+        .fields()
+        .into_iter()
+        .zip(remote.fields())
+        .map(|((label, l), (_, r))| (label, l, r))
+        .collect();
+    let value_w = fields
+        .iter()
+        .map(|(_, l, r)| l.len().max(r.len()))
+        .max()
+        .unwrap_or(0)
+        .max("Remote".len());
+
+    let header = Style::default().fg(theme.muted).add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(vec![
+        Span::styled(format!(" {:<14}", ""), header),
+        Span::styled(format!("{:<w$}  ", "Local", w = value_w), header),
+        Span::styled("Remote", header),
+    ])];
+    for (label, l, r) in &fields {
+        // Only the fields that differ matter for the decision
+        let style = if l == r {
+            Style::default().fg(theme.muted)
+        } else {
+            Style::default().fg(theme.prompt)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {:<14}", label), Style::default().fg(theme.muted)),
+            Span::styled(format!("{:<w$}  ", l, w = value_w), style),
+            Span::styled(r.clone(), style),
+        ]));
+    }
 
-    draw_table(frame, app, chunks[0]);
-    draw_status_bar(frame, app, chunks[1]);
-    frame.render_widget(Paragraph::new(help_line), chunks[2]);
-    draw_suggestions(frame, app, chunks[0]);
+    let title = if app.conflicts.len() > 1 {
+        format!(" Changed on both sides: {} (1 of {}) ", conflict.name, app.conflicts.len())
+    } else {
+        format!(" Changed on both sides: {} ", conflict.name)
+    };
+    let frame_area = frame.area();
+    let width = ((15 + 2 * value_w + 2 + 2) as u16)
+        .max(title.chars().count() as u16 + 2)
+        .min(frame_area.width);
+    let height = (lines.len() as u16 + 2).min(frame_area.height);
+    let area = Rect {
+        x: (frame_area.width - width) / 2,
+        y: (frame_area.height - height) / 2,
+        width,
+        height,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent))
+        .title(title);
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_error_detail(frame: &mut Frame, app: &mut App) {
+    let theme = app.theme;
+    let Some(detail) = app.error_detail.as_mut() else {
+        return;
+    };
+    let frame_area = frame.area();
+    let width = (frame_area.width * 4 / 5).max(40).min(frame_area.width);
+    let height = (frame_area.height * 3 / 5).max(8).min(frame_area.height);
+    let area = Rect {
+        x: (frame_area.width - width) / 2,
+        y: (frame_area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let failure = &detail.failure;
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("$ {}", failure.command),
+            Style::default().fg(theme.text).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            failure.status.to_string(),
+            Style::default().fg(theme.muted),
+        )),
+        Line::default(),
+    ];
+    if failure.output.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "(no output)",
+            Style::default().fg(theme.muted),
+        )));
+    }
+    lines.extend(failure.output.lines().map(|line| {
+        Line::from(Span::styled(line.to_string(), Style::default().fg(theme.secondary)))
+    }));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.error))
+        .title(" Command failed ");
+    let inner = block.inner(area);
+    let wrapped: usize = lines
+        .iter()
+        .map(|line| line.width().div_ceil(inner.width.max(1) as usize).max(1))
+        .sum();
+    detail.max_scroll = (wrapped as u16).saturating_sub(inner.height);
+    detail.scroll = detail.scroll.min(detail.max_scroll);
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((detail.scroll, 0));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn draw_account_picker(frame: &mut Frame, app: &App) {
+    let Some(picker) = app.account_picker.as_ref() else {
+        return;
+    };
+    let theme = &app.theme;
+    let add_label = "+ Add new account\u{2026}";
+    let frame_area = frame.area();
+    let width = (picker
+        .accounts
+        .iter()
+        .map(|a| a.len())
+        .max()
+        .unwrap_or(0)
+        .max(add_label.len()) as u16
+        + 4)
+        .max(30).min(frame_area.width);
+    let height = (picker.accounts.len() as u16 + 3).min(frame_area.height);
+    let area = Rect {
+        x: (frame_area.width - width) / 2,
+        y: (frame_area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let highlight = Style::default()
+        .bg(theme.suggestion_bg)
+        .fg(theme.suggestion_fg)
+        .add_modifier(Modifier::BOLD);
+    let mut items: Vec<ListItem> = picker
+        .accounts
+        .iter()
+        .map(|a| ListItem::new(a.as_str()).style(Style::default().fg(theme.text)))
+        .collect();
+    items.push(ListItem::new(add_label).style(Style::default().fg(theme.accent)));
+    let list = List::new(items).highlight_style(highlight).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.accent))
+            .title(" Authenticated accounts "),
+    );
+    let mut state = ListState::default().with_selected(Some(picker.index));
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_cluster_picker(frame: &mut Frame, app: &App) {
+    let Some(picker) = app.cluster_picker.as_ref() else {
+        return;
+    };
+    let theme = &app.theme;
+    let mut items: Vec<ListItem> = picker
+        .clusters
+        .iter()
+        .map(|(spec, chosen)| {
+            let mark = if *chosen { "[x] " } else { "[ ] " };
+            ListItem::new(format!("{}{}", mark, spec)).style(Style::default().fg(theme.text))
+        })
+        .collect();
+    let note = if picker.loading {
+        Some(format!("{} listing clusters\u{2026}", spinner(app)))
+    } else if let Some(error) = &picker.error {
+        Some(error.lines().next().unwrap_or_default().to_string())
+    } else if picker.clusters.is_empty() {
+        Some("No GKE clusters in the project".to_string())
+    } else {
+        None
+    };
+    if let Some(note) = &note {
+        let color = if picker.error.is_some() { theme.error } else { theme.muted };
+        items.push(ListItem::new(note.clone()).style(Style::default().fg(color)));
+    }
+
+    let title = format!(" GKE clusters for {} ", picker.profile);
+    let frame_area = frame.area();
+    let width = (picker
+        .clusters
+        .iter()
+        .map(|(spec, _)| spec.chars().count() + 4)
+        .chain(note.iter().map(|n| n.chars().count()))
+        .max()
+        .unwrap_or(0)
+        .max(title.len()) as u16
+        + 4)
+        .max(30).min(frame_area.width);
+    let height = (items.len() as u16 + 2).min(frame_area.height);
+    let area = Rect {
+        x: (frame_area.width - width) / 2,
+        y: (frame_area.height - height) / 2,
+        width,
+        height,
+    };
+    let highlight = Style::default()
+        .bg(theme.suggestion_bg)
+        .fg(theme.suggestion_fg)
+        .add_modifier(Modifier::BOLD);
+    let list = List::new(items).highlight_style(highlight).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.accent))
+            .title(title),
+    );
+    let selected = (!picker.clusters.is_empty()).then_some(picker.index);
+    let mut state = ListState::default().with_selected(selected);
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_log(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    let block = Block::default()
+        .borders(Borders::TOP)
+        .border_style(Style::default().fg(theme.muted))
+        .title(Span::styled(" Log ", Style::default().fg(theme.muted)));
+    let rows = block.inner(area).height as usize;
+    let lines: Vec<Line> = app
+        .log
+        .iter()
+        .skip(app.log.len().saturating_sub(rows))
+        .map(|entry| {
+            Line::from(vec![
+                Span::styled(
+                    format!(" {} ", format_clock(entry.at)),
+                    Style::default().fg(theme.muted),
+                ),
+                Span::styled(entry.message.clone(), Style::default().fg(severity_color(theme, entry.severity))),
+            ])
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Width of the label column in the add/edit form.
+const FORM_LABEL_WIDTH: u16 = 20;
+
+fn draw_form(frame: &mut Frame, app: &mut App) {
+    let theme = app.theme;
+    let Some(form) = app.form.as_ref() else {
+        return;
+    };
+    let frame_area = frame.area();
+    let width = 66.min(frame_area.width);
+    let height = (FORM_LABELS.len() as u16 * 2 + 2).min(frame_area.height);
+    let area = Rect {
+        x: (frame_area.width - width) / 2,
+        y: (frame_area.height - height) / 2,
+        width,
+        height,
+    };
+    let title = match (&form.editing, &form.clone_of) {
+        (Some(name), _) => format!(" Edit profile '{}' ", name),
+        (None, Some(source)) => format!(" Clone of '{}' ", source),
+        (None, None) => " New profile ".to_string(),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent))
+        .title(title);
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let mut lines = Vec::new();
+    for (field, label) in FORM_LABELS.iter().enumerate() {
+        let focused = field == form.focus;
+        let label_style = if focused {
+            Style::default().fg(theme.prompt).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.secondary)
+        };
+        let value = &form.values[field];
+        let value_span = if value.is_empty() {
+            // Show the default an empty field will take
+            let placeholder = form.placeholder(field).unwrap_or("");
+            Span::styled(placeholder.to_string(), Style::default().fg(theme.muted))
+        } else {
+            Span::styled(value.clone(), Style::default().fg(theme.text))
+        };
+        let locked = field == FIELD_NAME && form.editing.is_some();
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(" {:<w$}", label, w = FORM_LABEL_WIDTH as usize - 1),
+                label_style,
+            ),
+            if locked {
+                Span::styled(value.clone(), Style::default().fg(theme.muted))
+            } else {
+                value_span
+            },
+        ]));
+        lines.push(match &form.errors[field] {
+            Some(err) => Line::from(Span::styled(
+                format!("{:w$}{}", "", err, w = FORM_LABEL_WIDTH as usize),
+                Style::default().fg(theme.error),
+            )),
+            None => Line::default(),
+        });
+    }
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    let field_x = inner.x + FORM_LABEL_WIDTH;
+    let field_y = inner.y + form.focus as u16 * 2;
+    frame.set_cursor_position((field_x + form.cursor as u16, field_y));
+    draw_dropdown(frame, app, field_x, field_y + 1);
+}
+
+fn draw_details(frame: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.theme;
+    let block = Block::default()
+        .borders(Borders::LEFT)
+        .border_style(Style::default().fg(theme.muted));
+    let Some(detail) = app.selected_detail() else {
+        frame.render_widget(Paragraph::new("").block(block), area);
+        return;
+    };
+
+    let label = |text: &str| Span::styled(format!(" {:<11}", text), Style::default().fg(theme.muted));
+    let value = |text: String| Span::styled(text, Style::default().fg(theme.text));
+    let dim = |text: &str| Span::styled(text.to_string(), Style::default().fg(theme.muted));
+
+    let adc_line = if detail.has_adc {
+        value("stored".to_string())
+    } else {
+        Span::styled("not stored (needs login)", Style::default().fg(theme.prompt))
+    };
+    let adc_path = detail.adc_path.display().to_string();
+    let expiry = match detail.user_token_expiry {
+        Some(ts) => value(format_expiry(ts)),
+        None => dim("unknown"),
+    };
+    let config_path = detail
+        .config_path
+        .as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    let (config_state, region, zone) = match &detail.config_properties {
+        Some(props) => (
+            if detail.is_gcloud_active {
+                value("exists (gcloud active)".to_string())
+            } else {
+                value("exists".to_string())
+            },
+            props.get("compute/region").cloned(),
+            props.get("compute/zone").cloned(),
+        ),
+        None => (
+            Span::styled("missing", Style::default().fg(theme.prompt)),
+            None,
+            None,
+        ),
+    };
+    let or_dash = |v: Option<String>| match v {
+        Some(v) if !v.is_empty() => value(v),
+        _ => dim("-"),
+    };
+    let project_number = detail.project_number.clone();
+
+    let idx = app.selected_row;
+    let name = app.profile_names[idx].clone();
+    let profile = &app.profiles[idx];
+    let updated = match (profile.updated_at, &profile.updated_by) {
+        (Some(ts), Some(device)) => value(format!("{} on {}", format_timestamp(ts), device)),
+        (Some(ts), None) => value(format_timestamp(ts)),
+        (None, _) => dim("-"),
+    };
+    let last_used = match app.last_activated.get(&name) {
+        Some(&ts) => value(format_timestamp(ts)),
+        None => dim("never"),
+    };
+    let tags = if profile.tags.is_empty() {
+        dim("-")
+    } else {
+        value(profile.tags.join(", "))
+    };
+
+    let login = match app.selected_auth_problem() {
+        Some((status, account)) => Span::styled(
+            format!("{}: {}", account, status.explain().map_or("", |(reason, _)| reason)),
+            Style::default().fg(theme.prompt),
+        ),
+        None => dim("-"),
+    };
+    let project_number = match project_number {
+        Some(number) => value(number),
+        None if app.numbers_pending.contains(&profile.user_project) => dim("resolving\u{2026}"),
+        None => dim("-"),
+    };
+
+    let lines = vec![
+        Line::from(Span::styled(
+            format!(" {}", name),
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        )),
+        Line::default(),
+        Line::from(vec![label("Project #"), project_number]),
+        Line::from(vec![label("User token"), expiry]),
+        Line::from(vec![label("Login"), login]),
+        Line::from(vec![label("ADC"), adc_line]),
+        Line::from(vec![label(""), dim(&adc_path)]),
+        Line::from(vec![label("Updated"), updated]),
+        Line::from(vec![label("Last used"), last_used]),
+        Line::from(vec![label("Tags"), tags]),
+        Line::from(vec![label("Clouds"), or_dash(Some(profile.contexts_label()))]),
+        Line::from(vec![label("GKE"), or_dash(Some(profile.clusters.join(", ")))]),
+        Line::from(vec![label("Docker"), or_dash(Some(profile.registries.join(", ")))]),
+        Line::from(vec![label("SSH"), or_dash(profile.ssh.as_ref().map(|s| s.label()))]),
+        Line::from(vec![
+            label("Terraform SA"),
+            or_dash(profile.terraform.as_ref().and_then(|t| t.impersonate_service_account.clone())),
+        ]),
+        Line::from(vec![
+            label("Sync"),
+            value(if profile.sync { "yes" } else { "local only" }.to_string()),
+        ]),
+        Line::from(vec![
+            label("Activate"),
+            value(if profile.confirm_before_activate { "asks first" } else { "directly" }.to_string()),
+        ]),
+        Line::from(vec![label("Locked"), value(if profile.locked { "yes" } else { "no" }.to_string())]),
+        Line::from(vec![
+            label("Snapshot"),
+            or_dash(Some(match profile.gcloud_properties.len() {
+                0 => String::new(),
+                1 => "1 gcloud property".to_string(),
+                n => format!("{} gcloud properties", n),
+            })),
+        ]),
+        Line::default(),
+        Line::from(vec![label("gcloud cfg"), config_state]),
+        Line::from(vec![label(""), dim(&config_path)]),
+        Line::from(vec![label("Region"), or_dash(region)]),
+        Line::from(vec![label("Zone"), or_dash(zone)]),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(ratatui::widgets::Wrap { trim: false }),
+        area,
+    );
+}
+
+/// Seconds since the unix epoch, for comparing against stored timestamps.
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Compact duration like "45m", "3h", "2d".
+fn format_duration(secs: i64) -> String {
+    let secs = secs.abs();
+    if secs < 60 {
+        "<1m".to_string()
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else if secs < 86400 {
+        format!("{}h", secs / 3600)
+    } else {
+        format!("{}d", secs / 86400)
+    }
+}
+
+/// "expires in 45m" for a future access token expiry, "lapsed 2d ago" for a past one.
+/// A lapsed access token is renewed on next use; only the login can expire for real.
+pub fn format_expiry(ts: i64) -> String {
+    let remaining = ts - now_secs();
+    if remaining > 0 {
+        format!("expires in {}", format_duration(remaining))
+    } else {
+        format!("lapsed {} ago", format_duration(remaining))
+    }
+}
+
+/// Table countdown for an access token expiry: " 45m" while valid, " lapsed 2d" after.
+fn format_countdown(expiry: Option<i64>) -> String {
+    match expiry {
+        Some(ts) if ts > now_secs() => format!(" {}", format_duration(ts - now_secs())),
+        Some(ts) => format!(" lapsed {}", format_duration(ts - now_secs())),
+        None => String::new(),
+    }
+}
+
+/// Format unix seconds as a `HH:MM:SS` UTC time of day.
+pub fn format_clock(ts: i64) -> String {
+    let secs = ts.rem_euclid(86400);
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
+
+/// Format unix seconds as `YYYY-MM-DD HH:MM UTC`.
+fn format_timestamp(ts: i64) -> String {
+    let secs = ts.rem_euclid(86400);
+    format!("{} {:02}:{:02} UTC", format_date(ts), secs / 3600, (secs % 3600) / 60)
+}
+
+/// Format unix seconds as a `YYYY-MM-DD` UTC date.
+pub fn format_date(ts: i64) -> String {
+    let days = ts.div_euclid(86400);
+    // Civil date from days (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
 }
 
 fn table_content_width(app: &App) -> usize {
     if app.profile_names.is_empty() {
         return 36;
     }
-    let header_labels: [(&str, &str); 3] = [
-        ("Profile", ""),
-        ("User Account", "Project"),
-        ("ADC Account", "Quota Project"),
-    ];
+    let compact = app.table.compact;
     let mut col_max = [0usize; 3];
-    for (i, (line1, line2)) in header_labels.iter().enumerate() {
-        col_max[i] = col_max[i].max(line1.len()).max(line2.len());
+    for (i, (line1, line2)) in HEADER_LABELS.iter().enumerate() {
+        col_max[i] = cell_width(compact, line1.len(), line2.len());
     }
-    for (name, profile) in app.profile_names.iter().zip(app.profiles.iter()) {
-        col_max[0] = col_max[0].max(name.len());
-        col_max[1] = col_max[1]
-            .max(profile.user_account.len() + 3)
-            .max(profile.user_project.len());
-        col_max[2] = col_max[2]
-            .max(profile.adc_account.len() + 3)
-            .max(profile.adc_quota_project.len());
+    for (i, (name, profile)) in app.profile_names.iter().zip(app.profiles.iter()).enumerate() {
+        let user_countdown = format_countdown(app.user_token_expiry.get(i).copied().flatten());
+        col_max[0] = col_max[0].max(profile_name_width(name));
+        col_max[1] = col_max[1].max(cell_width(
+            compact,
+            profile.user_account.len() + 3 + user_countdown.len(),
+            profile.user_project.len(),
+        ));
+        col_max[2] = col_max[2].max(cell_width(
+            compact,
+            profile.adc_account.len() + 3,
+            profile.adc_quota_project.len(),
+        ));
     }
-    col_max.iter().sum::<usize>() + 4
+    col_max[..app.table.columns()].iter().sum::<usize>() + 4
+}
+
+/// Column titles, as (account line, project line).
+const HEADER_LABELS: [(&str, &str); 3] = [
+    ("Profile", ""),
+    ("User Account", "Project"),
+    ("ADC Account", "Quota Project"),
+];
+
+/// Between account and project in compact rows.
+const COMPACT_SEP: &str = " \u{00B7} ";
+
+/// An account-over-project cell, or both on one line in compact rows.
+fn cell_text(compact: bool, top: String, bottom: &str) -> String {
+    match (compact, bottom.is_empty()) {
+        (false, _) => format!("{}\n{}", top, bottom),
+        (true, true) => top,
+        (true, false) => format!("{}{}{}", top, COMPACT_SEP, bottom),
+    }
+}
+
+/// Width of a cell whose lines are `top` and `bottom` wide.
+fn cell_width(compact: bool, top: usize, bottom: usize) -> usize {
+    if !compact {
+        top.max(bottom)
+    } else if bottom == 0 {
+        top
+    } else {
+        top + COMPACT_SEP.chars().count() + bottom
+    }
+}
+
+/// Screen position where the text being edited starts: the account or project line of
+/// the edited cell (one line in compact rows), from the geometry of the last `draw_table`.
+fn edit_origin(app: &App) -> (u16, u16) {
+    let height = app.table.row_height();
+    let col = if app.edit_col == Column::Adc { 2 } else { 1 };
+    let mut x = app.hit_areas.col_x[col];
+    let row = app.selected_view_pos().unwrap_or(0).saturating_sub(app.table_state.offset()) as u16;
+    let mut y = app.hit_areas.table.y + height + row * height;
+    if app.input_mode == InputMode::EditProject {
+        if app.table.compact {
+            x += (app.edit_account_buffer.chars().count() + COMPACT_SEP.chars().count()) as u16;
+        } else {
+            y += 1;
+        }
+    }
+    (x, y)
 }
 
 fn draw_table(frame: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.theme;
     if app.profile_names.is_empty() {
         let empty = Paragraph::new("  No profiles. Press 'n' to add one.")
-            .style(Style::default().fg(Color::DarkGray));
+            .style(Style::default().fg(theme.muted));
+        frame.render_widget(empty, area);
+        return;
+    }
+    if app.view.is_empty() {
+        let empty = Paragraph::new("  No profiles match the filter.")
+            .style(Style::default().fg(theme.muted));
         frame.render_widget(empty, area);
         return;
     }
 
-    let header_labels: [(&str, &str); 3] = [
-        ("Profile", ""),
-        ("User Account", "Project"),
-        ("ADC Account", "Quota Project"),
-    ];
-    let header_cells = header_labels.iter().map(|(line1, line2)| {
+    let columns = app.table.columns();
+    let row_height = app.table.row_height();
+    let compact = app.table.compact;
+    let header_cells = HEADER_LABELS[..columns].iter().map(|(line1, line2)| {
         let style = Style::default()
-            .fg(Color::Black)
+            .fg(theme.header_fg)
             .add_modifier(Modifier::BOLD);
-        if line2.is_empty() {
-            Cell::from(*line1).style(style)
-        } else {
-            Cell::from(format!("{}\n{}", line1, line2)).style(style)
-        }
+        Cell::from(cell_text(compact, line1.to_string(), line2)).style(style)
     });
     let header = Row::new(header_cells)
-        .height(2)
-        .style(Style::default().bg(Color::Indexed(254)));
+        .height(row_height)
+        .style(Style::default().bg(theme.header_bg));
+
+    // Only the rows that fit are built. The offset follows the selection the way ratatui's
+    // own scrolling would, and is kept in `table_state` for scrolling and mouse hits.
+    let visible_rows = area.height.saturating_sub(row_height) / row_height;
+    app.hit_areas.visible_rows = visible_rows as usize;
+    let page = (visible_rows as usize).max(1);
+    let mut offset = app.table_state.offset().min(app.view.len().saturating_sub(1));
+    if let Some(pos) = app.table_state.selected() {
+        if pos < offset {
+            offset = pos;
+        } else if pos >= offset + page {
+            offset = pos + 1 - page;
+        }
+    }
+    *app.table_state.offset_mut() = offset;
 
+    let spin = (app.auth_pending > 0).then(|| spinner(app));
     let rows = app
-        .profile_names
+        .view
         .iter()
-        .zip(app.profiles.iter())
-        .enumerate()
-        .map(|(i, (name, profile))| {
+        .skip(offset)
+        .take(page)
+        .map(|&i| (i, &app.profile_names[i], &app.profiles[i]))
+        .map(|(i, name, profile)| {
             let is_active = app.active_profile.as_deref() == Some(name.as_str());
             let is_selected = i == app.selected_row;
-            let profile_name = name.to_string();
+            let is_marked = app.marked.contains(name);
+            let mut profile_name = if is_marked {
+                format!("{}{}", MARK, name)
+            } else {
+                name.to_string()
+            };
+            if !profile.sync {
+                profile_name.push_str(LOCAL);
+            }
+            if profile.confirm_before_activate {
+                profile_name.push_str(PROTECTED);
+            }
+            if profile.locked {
+                profile_name.push_str(LOCKED);
+            }
+            if app.drift.get(i).is_some_and(|d| !d.is_empty()) {
+                profile_name.push_str(DRIFT);
+            }
 
             let is_editing = i == app.selected_row
                 && matches!(app.input_mode, InputMode::EditAccount | InputMode::EditProject);
 
-            let user_auth_status = app.user_auth_valid.get(i).copied().flatten();
-            let user_lock = match user_auth_status {
-                Some(true) => " \u{1F511}",
-                Some(false) => " \u{1F512}",
-                None => "",
+            let user_lock = auth_icon(app.user_auth_status.get(i).copied().flatten(), &profile.user_account, spin);
+            // While editing, the separator stays even with an empty project so the cursor lines up
+            let editing_text = || {
+                if compact {
+                    format!("{}{}{}", app.edit_account_buffer, COMPACT_SEP, app.edit_project_buffer)
+                } else {
+                    format!("{}\n{}", app.edit_account_buffer, app.edit_project_buffer)
+                }
             };
             let user_info = if is_editing && app.edit_col == Column::User {
-                format!("{}\n{}", app.edit_account_buffer, app.edit_project_buffer)
+                editing_text()
             } else {
-                format!("{}{}\n{}", profile.user_account, user_lock, profile.user_project)
+                let countdown = format_countdown(app.user_token_expiry.get(i).copied().flatten());
+                cell_text(compact, format!("{}{}{}", profile.user_account, user_lock, countdown), &profile.user_project)
             };
 
-            let adc_auth_status = app.adc_auth_valid.get(i).copied().flatten();
-            let adc_lock = match adc_auth_status {
-                Some(true) => " \u{1F511}",
-                Some(false) => " \u{1F512}",
-                None => "",
-            };
+            let adc_lock = auth_icon(app.adc_auth_status.get(i).copied().flatten(), &profile.adc_account, spin);
             let adc_info = if is_editing && app.edit_col == Column::Adc {
-                format!("{}\n{}", app.edit_account_buffer, app.edit_project_buffer)
+                editing_text()
             } else {
-                format!("{}{}\n{}", profile.adc_account, adc_lock, profile.adc_quota_project)
+                let missing = if app.adc_stored.get(i).copied().unwrap_or(true) { "" } else { NO_ADC };
+                cell_text(
+                    compact,
+                    format!("{}{}{}", profile.adc_account, adc_lock, missing),
+                    &profile.adc_quota_project,
+                )
             };
 
-            let light_grey       = Color::Indexed(255);
-            let highlight_bg     = Color::Blue ;
-            let col_highlight_bg = Color::Indexed(75); // lighter blue for selected column
-            let edit_bg          = Color::Indexed(255); // Light Grey edit background
-
             let base_style = if is_selected {
-                Style::default().bg(highlight_bg).fg(Color::White)
+                Style::default().bg(theme.selection_bg).fg(theme.selection_fg)
+            } else if is_marked {
+                Style::default().bg(theme.marked_bg).fg(if is_active { theme.active_fg } else { theme.row_fg })
+            } else if app.guard.is_guarded(name, profile) {
+                Style::default().bg(theme.guarded_bg).fg(theme.guarded_fg).add_modifier(Modifier::BOLD)
             } else if is_active {
-                Style::default().bg(light_grey).fg(Color::Green).add_modifier(Modifier::BOLD)
+                Style::default().bg(theme.row_bg).fg(theme.active_fg).add_modifier(Modifier::BOLD)
             } else {
-                Style::default().bg(light_grey).fg(Color::Black)
+                Style::default().bg(theme.row_bg).fg(theme.row_fg)
             };
 
             let col_style = |col: Column, editing: bool| -> Style {
                 if editing {
-                    Style::default().bg(edit_bg).fg(Color::Black)
+                    Style::default().bg(theme.edit_bg).fg(theme.edit_fg)
                 } else if is_selected && app.selected_col == col {
-                    Style::default().bg(col_highlight_bg).fg(Color::White).add_modifier(Modifier::BOLD)
+                    Style::default().bg(theme.column_bg).fg(theme.column_fg).add_modifier(Modifier::BOLD)
                 } else {
                     base_style
                 }
@@ -182,54 +1081,66 @@ fn draw_table(frame: &mut Frame, app: &mut App, area: Rect) {
             let user_style    = col_style(Column::User, is_editing && app.edit_col == Column::User);
             let adc_style     = col_style(Column::Adc,  is_editing && app.edit_col == Column::Adc);
 
-            Row::new(vec![
+            let mut cells = vec![
                 Cell::from(profile_name).style(profile_style),
                 Cell::from(user_info   ).style(user_style   ),
-                Cell::from(adc_info    ).style(adc_style    ),
-            ])
-            .height(2).style(base_style)
+            ];
+            if columns == 3 {
+                cells.push(Cell::from(adc_info).style(adc_style));
+            }
+            Row::new(cells).height(row_height).style(base_style)
         });
 
-    // Calculate max content width per column
-    let mut col_max = [0usize; 3];
-    // Header widths
-    for (i, (line1, line2)) in header_labels.iter().enumerate() {
-        col_max[i] = col_max[i].max(line1.len()).max(line2.len());
-    }
-    // Data widths
-    for (name, profile) in app.profile_names.iter().zip(app.profiles.iter()) {
-        let profile_w = name.len();
-        col_max[0] = col_max[0].max(profile_w);
-        col_max[1] = col_max[1]
-            .max(profile.user_account.len())
-            .max(profile.user_project.len());
-        col_max[2] = col_max[2]
-            .max(profile.adc_account.len())
-            .max(profile.adc_quota_project.len());
-    }
-    let total: usize = col_max.iter().sum::<usize>().max(1);
-    let widths = col_max.map(|w| {
-        Constraint::Percentage((w as u16 * 100 / total as u16).max(1))
+    // Max content width per column, over all profiles so scrolling doesn't shift columns
+    let col_max = *app.column_widths.get_or_insert_with(|| {
+        let mut col_max = [0usize; 3];
+        // Header widths
+        for (i, (line1, line2)) in HEADER_LABELS.iter().enumerate() {
+            col_max[i] = cell_width(compact, line1.len(), line2.len());
+        }
+        // Data widths
+        for (name, profile) in app.profile_names.iter().zip(app.profiles.iter()) {
+            col_max[0] = col_max[0].max(profile_name_width(name));
+            col_max[1] = col_max[1].max(cell_width(compact, profile.user_account.len(), profile.user_project.len()));
+            col_max[2] = col_max[2].max(cell_width(
+                compact,
+                profile.adc_account.len() + NO_ADC.chars().count(),
+                profile.adc_quota_project.len(),
+            ));
+        }
+        col_max
     });
+    let widths: Vec<Constraint> = match &app.table.widths {
+        Some(widths) => widths.iter().map(|&w| Constraint::Fill(w)).collect(),
+        None => {
+            let total: usize = col_max[..columns].iter().sum::<usize>().max(1);
+            col_max[..columns]
+                .iter()
+                .map(|&w| Constraint::Percentage((w as u16 * 100 / total as u16).max(1)))
+                .collect()
+        }
+    };
+    let col_areas = Layout::horizontal(widths.clone()).split(area);
+    app.hit_areas.col_x = [
+        col_areas[0].x,
+        col_areas[1].x,
+        col_areas.get(2).map_or(u16::MAX, |a| a.x),
+    ];
 
     let table = Table::new(rows, widths)
         .header(header)
-        .column_spacing(0)
-        .row_highlight_style(Style::default());
+        .column_spacing(0);
 
-    frame.render_stateful_widget(table, area, &mut app.table_state);
+    frame.render_widget(table, area);
 
     // Scrollbar for the table when rows overflow
-    let header_height = 2u16;
-    let row_height = 2u16;
-    let visible_rows = area.height.saturating_sub(header_height) / row_height;
-    let total_rows = app.profile_names.len();
+    let total_rows = app.view.len();
     if total_rows as u16 > visible_rows {
         let max_offset = total_rows.saturating_sub(visible_rows as usize);
         let mut scrollbar_state = ScrollbarState::new(max_offset)
             .position(app.table_state.offset().min(max_offset));
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .style(Style::default().fg(Color::DarkGray));
+            .style(Style::default().fg(theme.muted));
         frame.render_stateful_widget(
             scrollbar,
             area.inner(ratatui::layout::Margin { horizontal: 0, vertical: 1 }),
@@ -239,115 +1150,305 @@ fn draw_table(frame: &mut Frame, app: &mut App, area: Rect) {
 
     // Position the terminal cursor for blinking edit cursor
     if matches!(app.input_mode, InputMode::EditAccount | InputMode::EditProject) {
-        let inner_w = area.width as usize;
-        // Compute actual column widths (matching the percentage constraints)
-        let col_px: Vec<usize> = col_max
-            .iter()
-            .map(|w| (w * inner_w / total).max(1))
-            .collect();
-
-        let col_offset: usize = match app.edit_col {
-            Column::User => col_px[0],
-            Column::Adc  => col_px[0] + col_px[1],
-            Column::Both => col_px[0],
-        };
+        let (x, y) = edit_origin(app);
+        frame.set_cursor_position((x + app.edit_cursor_pos as u16, y));
+    }
+}
+
+/// Prefix shown before the name of a profile marked for a batch operation.
+const MARK: &str = "\u{2713} ";
+
+/// Suffix shown after the name of a local-only profile (left out of sync).
+const LOCAL: &str = " \u{2302}";
 
-        let cursor_x = area.x + col_offset as u16 + app.edit_cursor_pos as u16;
-        let scroll_offset = app.table_state.offset();
-        let cursor_y = area.y
-            + 2  // header height
-            + (app.selected_row.saturating_sub(scroll_offset) as u16) * 2
-            + if app.input_mode == InputMode::EditProject { 1 } else { 0 };
+/// Suffix shown after the name of a profile that asks before activation.
+const PROTECTED: &str = " \u{203C}";
 
-        frame.set_cursor_position((cursor_x, cursor_y));
+/// Suffix shown after the name of a profile locked against changes.
+const LOCKED: &str = " \u{26BF}";
+
+/// Suffix shown after the name of a profile that differs from the live gcloud state.
+const DRIFT: &str = " \u{21C4}";
+
+/// Room for a profile name with all its markers, so toggling one doesn't resize the column.
+fn profile_name_width(name: &str) -> usize {
+    [MARK, LOCAL, PROTECTED, LOCKED, DRIFT]
+        .iter()
+        .fold(name.len(), |w, marker| w + marker.chars().count())
+}
+
+/// Shown in the ADC column when no ADC credentials are stored for the profile,
+/// i.e. activating it needs `gcloud auth application-default login`.
+const NO_ADC: &str = " \u{2205}";
+
+/// Spinner frames shown while background work is running.
+const SPINNER: [&str; 10] = [
+    "\u{280B}", "\u{2819}", "\u{2839}", "\u{2838}", "\u{283C}", "\u{2834}", "\u{2826}",
+    "\u{2827}", "\u{2807}", "\u{280F}",
+];
+
+fn spinner(app: &App) -> &'static str {
+    SPINNER[app.busy_tick % SPINNER.len()]
+}
+
+/// Icon appended to an account cell: key = valid, lock = expired or never logged in,
+/// no-entry sign = revoked by an admin, gear = not checkable,
+/// question mark = not checked (offline), warning sign = check failed (network), `spin` (the current spinner frame) while the check is still running.
+fn auth_icon(status: Option<AuthStatus>, account: &str, spin: Option<&str>) -> String {
+    match status {
+        Some(AuthStatus::Valid) => " \u{1F511}".to_string(),
+        Some(AuthStatus::Invalid | AuthStatus::Missing) => " \u{1F512}".to_string(),
+        Some(AuthStatus::Revoked) => " \u{26D4}".to_string(),
+        Some(AuthStatus::NotApplicable) => " \u{2699}".to_string(),
+        Some(AuthStatus::Unchecked) => " ?".to_string(),
+        Some(AuthStatus::Unknown) => " \u{26A0}".to_string(),
+        None => match spin {
+            Some(frame) if !account.is_empty() => format!(" {}", frame),
+            _ => String::new(),
+        },
     }
 }
 
-fn draw_status_bar(frame: &mut Frame, app: &App, area: Rect) {
-    let is_input_mode = matches!(
-        app.input_mode,
-        InputMode::AddProfileName
-            | InputMode::AddProfileUserAccount
-            | InputMode::AddProfileUserProject
-            | InputMode::AddProfileAdcAccount
-            | InputMode::AddProfileAdcQuotaProject
-    );
+/// One line with what gcloud will really use: its active configuration with account and
+/// project, and whose ADC is installed. Parts that disagree with the active profile are
+/// shown in the error color with the drift marker.
+fn draw_context(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    let live = &app.live;
+    let label = |text: &str| Span::styled(text.to_string(), Style::default().fg(theme.muted));
+    let value = |text: String, drifted: bool| {
+        if drifted {
+            Span::styled(format!("{}{}", text, DRIFT), Style::default().fg(theme.error))
+        } else {
+            Span::styled(text, Style::default().fg(theme.text))
+        }
+    };
+    let active = app.active_profile.as_deref();
+
+    let mut spans = vec![label(" gcloud ")];
+    match &app.gcloud_active {
+        Some(config) => {
+            spans.push(value(config.clone(), active != Some(config.as_str())));
+            let identity: Vec<&str> = [live.account.as_str(), live.project.as_str()]
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .collect();
+            if !identity.is_empty() {
+                spans.push(Span::styled(
+                    format!(" ({})", identity.join(" \u{00B7} ")),
+                    Style::default().fg(theme.secondary),
+                ));
+            }
+        }
+        None => spans.push(value("no active configuration".to_string(), active.is_some())),
+    }
+    spans.push(label("   ADC "));
+    match (&live.adc, &live.adc_profile) {
+        (None, _) => spans.push(value("none".to_string(), false)),
+        (Some(_), Some(owner)) => spans.push(value(owner.clone(), active != Some(owner.as_str()))),
+        // Installed by something else: say what it is
+        (Some(description), None) => spans.push(value(description.clone(), active.is_some())),
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
 
-    let line = if is_input_mode {
-        let prompt = app.status_message.as_deref().unwrap_or("Input:");
+fn draw_status_bar(frame: &mut Frame, app: &App, area: Rect) {
+    let theme = &app.theme;
+    let line = if app.input_mode == InputMode::Rename {
+        let prompt = format!(
+            " Rename '{}' to: ",
+            app.profile_names.get(app.selected_row).map(String::as_str).unwrap_or("")
+        );
+        frame.set_cursor_position((
+            area.x + Line::from(prompt.as_str()).width() as u16 + app.rename_cursor as u16,
+            area.y,
+        ));
+        Line::from(vec![
+            Span::styled(prompt, Style::default().fg(theme.prompt)),
+            Span::styled(app.rename_buffer.clone(), Style::default().fg(theme.text)),
+        ])
+    } else if app.input_mode == InputMode::Tag {
+        let prompt = if app.marked.is_empty() {
+            format!(
+                " Tags for '{}' (tag adds, -tag removes): ",
+                app.profile_names.get(app.selected_row).map(String::as_str).unwrap_or("")
+            )
+        } else {
+            format!(" Tags for {} marked (tag adds, -tag removes): ", app.marked.len())
+        };
+        frame.set_cursor_position((
+            area.x + Line::from(prompt.as_str()).width() as u16 + app.tag_cursor as u16,
+            area.y,
+        ));
+        Line::from(vec![
+            Span::styled(prompt, Style::default().fg(theme.prompt)),
+            Span::styled(app.tag_buffer.clone(), Style::default().fg(theme.text)),
+        ])
+    } else if app.input_mode == InputMode::ConfirmGuarded {
         Line::from(vec![
             Span::styled(
-                format!(" {} ", prompt),
-                Style::default().fg(Color::Yellow),
+                format!(" Type '{}' to activate this guarded profile: ", app.profile_names[app.selected_row]),
+                Style::default().fg(theme.prompt),
             ),
+            Span::styled(app.guard_buffer.clone(), Style::default().fg(theme.text)),
+            Span::styled("_", Style::default().fg(theme.secondary)),
+        ])
+    } else if app.input_mode == InputMode::Filter {
+        Line::from(vec![
+            Span::styled(" / ", Style::default().fg(theme.prompt)),
+            Span::styled(app.filter_query.clone(), Style::default().fg(theme.text)),
+            Span::styled("_", Style::default().fg(theme.secondary)),
             Span::styled(
-                app.input_buffer.as_str().to_string(),
-                Style::default().fg(Color::White),
+                format!("  ({} of {})", app.view.len(), app.profiles.len()),
+                Style::default().fg(theme.muted),
             ),
-            Span::styled("_", Style::default().fg(Color::Gray)),
         ])
+    } else if app.input_mode == InputMode::Jump {
+        let mut spans = vec![
+            Span::styled(" jump: ", Style::default().fg(theme.prompt)),
+            Span::styled(app.jump_buffer.clone(), Style::default().fg(theme.text)),
+            Span::styled("_", Style::default().fg(theme.secondary)),
+        ];
+        if app.jump_missed {
+            spans.push(Span::styled("  (no match)", Style::default().fg(theme.muted)));
+        }
+        Line::from(spans)
     } else if let Some(ref msg) = app.status_message {
         Line::from(vec![
             Span::styled(
                 format!(" {}", msg),
-                Style::default().fg(Color::Green),
+                Style::default().fg(theme.status),
             ),
         ])
+    } else if let Some(drift) = app.selected_drift() {
+        Line::from(vec![
+            Span::styled(format!(" \u{21C4} {}", drift.describe()), Style::default().fg(theme.prompt)),
+            Span::styled("  (< take gcloud, > write gcloud)", Style::default().fg(theme.muted)),
+        ])
+    } else if let Some((reason, action)) = app.selected_auth_problem().and_then(|(status, _)| status.explain()) {
+        Line::from(vec![
+            Span::styled(format!(" \u{1F512} {}", reason), Style::default().fg(theme.prompt)),
+            Span::styled(format!("  ({})", action), Style::default().fg(theme.muted)),
+        ])
+    } else if !app.marked.is_empty() {
+        Line::from(vec![Span::styled(
+            format!(" {} marked  (Esc to clear)", app.marked.len()),
+            Style::default().fg(theme.muted),
+        )])
+    } else if !app.filter_query.is_empty() {
+        Line::from(vec![Span::styled(
+            format!(" filter: {}  (Esc to clear)", app.filter_query),
+            Style::default().fg(theme.muted),
+        )])
+    } else if app.auth_view != AuthView::All {
+        Line::from(vec![Span::styled(
+            format!(" {}  (h to cycle, Esc to clear)", app.auth_view.label()),
+            Style::default().fg(theme.muted),
+        )])
     } else {
         Line::default()
     };
 
     let bar = Paragraph::new(line);
     frame.render_widget(bar, area);
+
+    // Busy indicator, right-aligned over whatever the bar shows
+    if app.is_busy() {
+        let what = if let Some(direction) = app.sync_running {
+            match direction {
+                SyncDirection::Push => "pushing profiles".to_string(),
+                SyncDirection::Pull => "pulling profiles".to_string(),
+            }
+        } else if let Some(refresh) = &app.gke_refresh {
+            format!("refreshing kubectl contexts ({}/{})", refresh.done, refresh.total)
+        } else if app.auth_pending > 0 {
+            format!("checking credentials ({} left)", app.auth_pending)
+        } else if app.cluster_picker.as_ref().is_some_and(|p| p.loading) {
+            "listing GKE clusters".to_string()
+        } else if !app.numbers_pending.is_empty() {
+            "resolving project numbers".to_string()
+        } else if app.fetching_locations {
+            "listing regions and zones".to_string()
+        } else {
+            "fetching projects".to_string()
+        };
+        let busy = Paragraph::new(Line::from(Span::styled(
+            format!("{} {}\u{2026} ", spinner(app), what),
+            Style::default().fg(theme.muted),
+        )))
+        .alignment(Alignment::Right);
+        frame.render_widget(busy, area);
+    }
 }
 
-fn help_key(key: &str, desc: &str) -> Vec<Span<'static>> {
+fn help_key(theme: &Theme, key: &str, desc: &str) -> Vec<Span<'static>> {
     vec![
         Span::styled(
             key.to_string(),
-            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            Style::default().fg(theme.key).add_modifier(Modifier::BOLD),
         ),
-        Span::styled(format!("{}", desc), Style::default().fg(Color::DarkGray)),
+        Span::styled(desc.to_string(), Style::default().fg(theme.muted)),
     ]
 }
 
-fn title_prefix() -> Vec<Span<'static>> {
+fn title_prefix(theme: &Theme) -> Vec<Span<'static>> {
     vec![
         Span::styled(
             "gcloud-switch",
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.accent)
                 .add_modifier(Modifier::BOLD),
         ),
         Span::styled(
             format!(" v{}", env!("CARGO_PKG_VERSION")),
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(theme.muted),
         ),
         Span::raw("  "),
     ]
 }
 
 fn build_normal_help_spans(app: &App) -> Vec<Span<'static>> {
-    let mut s = title_prefix();
-    s.extend(help_key("\u{2191}\u{2193}", " row "));
-    s.extend(help_key("\u{2190}\u{2192}", " col "));
-    s.extend(help_key("\u{21b5}", " activate "));
-    s.extend(help_key("a", "uthenticate "));
-    s.extend(help_key("e", "dit "));
-    s.extend(help_key("n", "ew "));
-    s.extend(help_key("d", "el "));
-    s.extend(help_key("s", "ync"));
+    let theme = &app.theme;
+    let mut s = title_prefix(theme);
+    s.extend(help_key(theme, "\u{2191}\u{2193}", " row "));
+    s.extend(help_key(theme, "\u{2190}\u{2192}", " col "));
+    s.extend(help_key(theme, "\u{21b5}", " activate"));
+    s.push(Span::styled(
+        if app.stay_open { "(stay) " } else { "(quit) " },
+        Style::default().fg(theme.muted),
+    ));
+    s.extend(help_key(theme, "o", "pen/quit "));
+    s.extend(help_key(theme, "a", "uthenticate "));
+    s.extend(help_key(theme, "e", "dit "));
+    s.extend(help_key(theme, "n", "ew "));
+    s.extend(help_key(theme, "c", "lone "));
+    s.extend(help_key(theme, "y", "ank "));
+    s.extend(help_key(theme, "d", "el "));
+    s.extend(help_key(theme, "u", "ndo "));
+    s.extend(help_key(theme, "\u{2423}", " mark "));
+    s.extend(help_key(theme, "t", "ag "));
+    s.extend(help_key(theme, "s", "ync"));
     let sync_mode_label = match app.sync_mode {
         SyncMode::Strict => "(both)",
         SyncMode::Add => "(add)",
         SyncMode::Off => "(off)",
+        SyncMode::Mirror => "(mirror)",
     };
     s.push(Span::styled(
         format!("{} ", sync_mode_label),
-        Style::default().fg(Color::DarkGray),
+        Style::default().fg(theme.muted),
     ));
-    s.extend(help_key("i", "mport "));
-    s.extend(help_key("esc", " exit"));
+    s.extend(help_key(theme, "i", "mport "));
+    s.extend(help_key(theme, "p", "ull/"));
+    s.extend(help_key(theme, "P", "ush "));
+    s.extend(help_key(theme, "L", " local "));
+    s.extend(help_key(theme, "!", " confirm "));
+    s.extend(help_key(theme, "K", " lock "));
+    s.extend(help_key(theme, "v", "iew details "));
+    s.extend(help_key(theme, "l", "og "));
+    s.extend(help_key(theme, "F5", " reload "));
+    s.extend(help_key(theme, "/", " filter "));
+    s.extend(help_key(theme, "esc", " exit"));
     s
 }
 
@@ -356,85 +1457,148 @@ fn build_normal_help_width(app: &App) -> usize {
 }
 
 fn build_help_line(app: &App) -> Line<'static> {
+    let theme = &app.theme;
     let spans: Vec<Span> = match app.input_mode {
         InputMode::Normal => build_normal_help_spans(app),
-        InputMode::ConfirmDelete => {
-            let mut s = title_prefix();
-            s.extend(help_key("y", "es "));
-            s.extend(help_key("n", "/Esc cancel"));
+        InputMode::ConfirmDelete | InputMode::ConfirmActivate => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "y", "es "));
+            s.extend(help_key(theme, "n", "/Esc cancel"));
             s
         }
-        InputMode::EditAccount | InputMode::EditProject => {
-            let mut s = title_prefix();
-            s.extend(help_key("Tab", " next "));
-            s.extend(help_key("\u{2193}", " suggestions "));
-            s.extend(help_key("\u{23ce}", " save "));
-            s.extend(help_key("Esc", " cancel"));
+        InputMode::ConfirmGuarded => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{23ce}", " activate "));
+            s.extend(help_key(theme, "Esc", " cancel"));
+            s
+        }
+        InputMode::Form => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "Tab", "/"));
+            s.extend(help_key(theme, "\u{21e7}Tab", " field "));
+            s.extend(help_key(theme, "\u{2193}", " suggestions "));
+            s.extend(help_key(theme, "^A", " accounts "));
+            s.extend(help_key(theme, "\u{23ce}", " save "));
+            s.extend(help_key(theme, "Esc", " cancel"));
+            s
+        }
+        InputMode::Tag => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{23ce}", " apply "));
+            s.extend(help_key(theme, "Esc", " cancel"));
+            s
+        }
+        InputMode::Rename => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{23ce}", " rename "));
+            s.extend(help_key(theme, "Esc", " cancel"));
+            s
+        }
+        InputMode::Filter => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{2191}\u{2193}", " row "));
+            s.extend(help_key(theme, "\u{23ce}", " activate "));
+            s.extend(help_key(theme, "Esc", " clear"));
+            s
+        }
+        InputMode::Jump => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "a-z", " jump "));
+            s.extend(help_key(theme, "\u{23ce}", " activate "));
+            s.extend(help_key(theme, "Esc", " back"));
+            s
+        }
+        InputMode::AccountPicker => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{2191}\u{2193}", " select "));
+            s.extend(help_key(theme, "\u{23ce}", " pick "));
+            s.extend(help_key(theme, "Esc", " back"));
+            s
+        }
+        InputMode::ClusterPicker => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{2191}\u{2193}", " select "));
+            s.extend(help_key(theme, "\u{2423}", " toggle "));
+            s.extend(help_key(theme, "\u{23ce}", " save "));
+            s.extend(help_key(theme, "Esc", " cancel"));
+            s
+        }
+        InputMode::SyncConflict => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "l", "ocal "));
+            s.extend(help_key(theme, "r", "emote "));
+            s.extend(help_key(theme, "e", "dit "));
+            s.extend(help_key(theme, "Esc", " keep local for all"));
+            s
+        }
+        InputMode::DryRun => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{2191}\u{2193}", " scroll "));
+            s.extend(help_key(theme, "\u{21B5}", " activate "));
+            s.extend(help_key(theme, "Esc", " close"));
+            s
+        }
+        InputMode::Messages => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{2191}\u{2193}", " scroll "));
+            s.extend(help_key(theme, "Esc", " close"));
+            s
+        }
+        InputMode::Compare => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{2191}\u{2193}", " scroll "));
+            let only = app.compare.as_ref().is_some_and(|view| view.only_differences);
+            s.extend(help_key(theme, "d", if only { "ifferences only (all) " } else { "ifferences only " }));
+            s.extend(help_key(theme, "Esc", " close"));
             s
         }
-        _ => {
-            let mut s = title_prefix();
-            s.extend(help_key("\u{23ce}", "confirm"));
-            s.extend(help_key("Esc", " cancel"));
+        InputMode::ErrorDetail => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "\u{2191}\u{2193}", " scroll "));
+            s.extend(help_key(theme, "Esc", " close"));
+            s
+        }
+        InputMode::EditAccount | InputMode::EditProject => {
+            let mut s = title_prefix(theme);
+            s.extend(help_key(theme, "Tab", " next "));
+            s.extend(help_key(theme, "\u{2193}", " suggestions "));
+            if app.input_mode == InputMode::EditAccount {
+                s.extend(help_key(theme, "^A", " accounts "));
+            }
+            s.extend(help_key(theme, "\u{23ce}", " save "));
+            s.extend(help_key(theme, "Esc", " cancel"));
             s
         }
     };
     Line::from(spans)
 }
 
-fn draw_suggestions(frame: &mut Frame, app: &App, table_area: Rect) {
-    if app.suggestion_index.is_none() || app.suggestions.is_empty() {
+fn draw_suggestions(frame: &mut Frame, app: &mut App) {
+    if !dropdown_open(app) {
         return;
     }
+    // Just below the line being edited
+    let (x, y) = edit_origin(app);
+    let y = y + 1;
+    draw_dropdown(frame, app, x, y);
+}
 
-    let selected_idx = app.suggestion_index.unwrap_or(0);
-
-    // Replicate column width calculation to find dropdown x position
-    let header_labels: [(&str, &str); 3] = [
-        ("Profile", ""),
-        ("User Account", "Project"),
-        ("ADC Account", "Quota Project"),
-    ];
-    let mut col_max = [0usize; 3];
-    for (i, (line1, line2)) in header_labels.iter().enumerate() {
-        col_max[i] = col_max[i].max(line1.len()).max(line2.len());
-    }
-    for (name, profile) in app.profile_names.iter().zip(app.profiles.iter()) {
-        let profile_w = name.len() + 4;
-        col_max[0] = col_max[0].max(profile_w);
-        col_max[1] = col_max[1]
-            .max(profile.user_account.len())
-            .max(profile.user_project.len());
-        col_max[2] = col_max[2]
-            .max(profile.adc_account.len())
-            .max(profile.adc_quota_project.len());
-    }
-    let total: usize = col_max.iter().sum::<usize>().max(1);
-
-    let inner_x = table_area.x;
-    let inner_w = table_area.width;
-
-    // Column pixel widths (proportional)
-    let col_widths: Vec<u16> = col_max
-        .iter()
-        .map(|w| ((*w as u16) * inner_w / total as u16).max(1))
-        .collect();
-
-    // X position based on which column is being edited
-    let dropdown_x = match app.edit_col {
-        Column::User => inner_x + col_widths[0],
-        Column::Adc => inner_x + col_widths[0] + col_widths[1],
-        Column::Both => inner_x + col_widths[0],
-    };
+/// Whether the suggestion dropdown is shown: a highlighted suggestion, or the project
+/// picker (which stays up while projects are still being fetched).
+fn dropdown_open(app: &App) -> bool {
+    if app.suggestion_index.is_some() {
+        return !app.suggestions.is_empty();
+    }
+    app.picker_visible() && (!app.suggestions.is_empty() || app.fetching_suggestions())
+}
 
-    // Y position: header (2) + rows above * 2 + current row offset
-    let row_y_offset = if app.input_mode == InputMode::EditAccount {
-        1 // below the account line
-    } else {
-        2 // below the project line
-    };
-    let scroll_offset = app.table_state.offset();
-    let dropdown_y = table_area.y + 2 + (app.selected_row.saturating_sub(scroll_offset) as u16) * 2 + row_y_offset;
+/// Draw the suggestion dropdown with its top-left corner at (x, y), clamped to the screen.
+fn draw_dropdown(frame: &mut Frame, app: &mut App, x: u16, y: u16) {
+    let theme = app.theme;
+    if !dropdown_open(app) {
+        return;
+    }
+    let selected = app.suggestion_index;
 
     // Dropdown dimensions
     let max_item_width = app
@@ -443,13 +1607,13 @@ fn draw_suggestions(frame: &mut Frame, app: &App, table_area: Rect) {
         .map(|s| s.len())
         .max()
         .unwrap_or(20) as u16;
-    let dropdown_w = (max_item_width + 4).min(50).max(20);
+    let dropdown_w = (max_item_width + 4).clamp(20, 50);
     let dropdown_h = (app.suggestions.len() as u16 + 2).min(12); // +2 for borders
 
     // Clamp to screen bounds
     let frame_area = frame.area();
-    let dropdown_x = dropdown_x.min(frame_area.width.saturating_sub(dropdown_w));
-    let dropdown_y = dropdown_y.min(frame_area.height.saturating_sub(dropdown_h));
+    let dropdown_x = x.min(frame_area.width.saturating_sub(dropdown_w));
+    let dropdown_y = y.min(frame_area.height.saturating_sub(dropdown_h));
 
     let dropdown_area = Rect {
         x: dropdown_x,
@@ -466,34 +1630,41 @@ fn draw_suggestions(frame: &mut Frame, app: &App, table_area: Rect) {
         .iter()
         .enumerate()
         .map(|(i, suggestion)| {
-            let style = if i == selected_idx {
+            let style = if Some(i) == selected {
                 Style::default()
-                    .bg(Color::Indexed(24))
-                    .fg(Color::White)
+                    .bg(theme.suggestion_bg)
+                    .fg(theme.suggestion_fg)
                     .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::Gray)
+                Style::default().fg(theme.secondary)
             };
             ListItem::new(suggestion.as_str()).style(style)
         })
         .collect();
 
-    let list = List::new(items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan)),
-    );
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent));
+    if app.picker_visible() && app.fetching_suggestions() {
+        block = block.title(Span::styled(
+            format!(" {} fetching\u{2026} ", spinner(app)),
+            Style::default().fg(theme.muted),
+        ));
+    }
+    let list = List::new(items).block(block);
 
-    let mut list_state = ListState::default().with_selected(Some(selected_idx));
+    let mut list_state = ListState::default().with_selected(selected);
     frame.render_stateful_widget(list, dropdown_area, &mut list_state);
+    app.hit_areas.suggestions = Some(dropdown_area);
+    app.hit_areas.suggestion_offset = list_state.offset();
 
     // Scrollbar (only if items overflow the visible area)
     let visible_items = dropdown_area.height.saturating_sub(2) as usize; // minus borders
     if app.suggestions.len() > visible_items {
         let mut scrollbar_state = ScrollbarState::new(app.suggestions.len().saturating_sub(visible_items))
-            .position(selected_idx.saturating_sub(visible_items / 2).min(app.suggestions.len().saturating_sub(visible_items)));
+            .position(selected.unwrap_or(0).saturating_sub(visible_items / 2).min(app.suggestions.len().saturating_sub(visible_items)));
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
-            .style(Style::default().fg(Color::DarkGray));
+            .style(Style::default().fg(theme.muted));
         frame.render_stateful_widget(
             scrollbar,
             dropdown_area.inner(ratatui::layout::Margin { horizontal: 0, vertical: 1 }),
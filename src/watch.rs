@@ -0,0 +1,67 @@
+//! Watch profiles.toml and gcloud's configuration state for changes made outside the
+//! TUI: another terminal, a sync pull, a raw `gcloud config` command, or an ADC login.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use anyhow::{Context, Result};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use gcloud_switch::gcloud;
+
+pub struct Watcher {
+    // Dropping the watcher stops it, so it lives as long as the receiver
+    _inner: RecommendedWatcher,
+    rx: mpsc::Receiver<notify::Result<Event>>,
+    profiles_file: PathBuf,
+    active_config_file: PathBuf,
+    adc_file: PathBuf,
+    configurations_dir: PathBuf,
+}
+
+impl Watcher {
+    /// Watch `store_dir` (profiles.toml) and gcloud's config directory. Directories are
+    /// watched rather than files because editors and gcloud replace files on save.
+    pub fn new(store_dir: &Path) -> Result<Self> {
+        let gcloud_dir = gcloud::gcloud_config_dir()?;
+        let configurations_dir = gcloud_dir.join("configurations");
+        let (tx, rx) = mpsc::channel();
+        let mut inner = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+        for dir in [store_dir, &gcloud_dir, &configurations_dir] {
+            if dir.exists() {
+                inner
+                    .watch(dir, RecursiveMode::NonRecursive)
+                    .with_context(|| format!("Failed to watch {}", dir.display()))?;
+            }
+        }
+        Ok(Self {
+            _inner: inner,
+            rx,
+            profiles_file: store_dir.join("profiles.toml"),
+            active_config_file: gcloud_dir.join("active_config"),
+            adc_file: gcloud::live_adc_path()?,
+            configurations_dir,
+        })
+    }
+
+    /// Whether a watched file changed since the last call. Other files in the watched
+    /// directories (credential databases, logs) are ignored.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        while let Ok(event) = self.rx.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if event.kind.is_access() {
+                continue;
+            }
+            changed |= event.paths.iter().any(|path| {
+                path == &self.profiles_file
+                    || path == &self.active_config_file
+                    || path == &self.adc_file
+                    || path.parent() == Some(self.configurations_dir.as_path())
+            });
+        }
+        changed
+    }
+}
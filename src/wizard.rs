@@ -0,0 +1,238 @@
+//! `gcloud-switch add --interactive`: prompts for a new profile's fields on a plain line,
+//! for when the full TUI is too heavy (slow SSH links). Accounts complete from gcloud's
+//! logins and existing profiles, projects from existing profiles plus a background
+//! `gcloud projects list` for the chosen account. Without a terminal it reads plain lines.
+
+use std::collections::BTreeSet;
+use std::io::{self, IsTerminal, Write};
+use std::sync::mpsc;
+
+use anyhow::Result;
+use crossterm::{
+    cursor::MoveLeft,
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, Clear, ClearType},
+};
+
+use gcloud_switch::gcloud;
+use gcloud_switch::profile::{self, ProfilesFile};
+use gcloud_switch::runtime;
+
+/// What the wizard asks for. Values passed in are offered as defaults.
+#[derive(Debug, Default)]
+pub struct Answers {
+    pub name: String,
+    pub account: String,
+    pub project: String,
+    pub adc_account: String,
+    pub adc_quota_project: String,
+    pub tags: Vec<String>,
+}
+
+/// Walk through the fields, then confirm. Errors when cancelled (Esc, Ctrl+C) or declined.
+pub fn add(data: &ProfilesFile, defaults: Answers) -> Result<Answers> {
+    let mut accounts = BTreeSet::new();
+    let mut projects = BTreeSet::new();
+    for profile in data.profiles.values() {
+        accounts.extend([profile.user_account.clone(), profile.adc_account.clone()]);
+        projects.extend([profile.user_project.clone(), profile.adc_quota_project.clone()]);
+    }
+    accounts.extend(gcloud::list_authenticated_accounts().unwrap_or_default());
+    accounts.remove("");
+    projects.remove("");
+    let accounts: Vec<String> = accounts.into_iter().collect();
+
+    println!("New profile (Tab completes, Enter takes the [default], Esc cancels)");
+    let name = loop {
+        let name = prompt("Name", &defaults.name, &mut Vec::new)?;
+        if let Err(e) = profile::validate_name(&name) {
+            println!("  {}.", e);
+        } else if data.profiles.contains_key(&name) {
+            println!("  Profile '{}' already exists.", name);
+        } else {
+            break name;
+        }
+    };
+    let account = loop {
+        let account = prompt("Account", &defaults.account, &mut || accounts.clone())?;
+        if account.is_empty() {
+            println!("  An account is required.");
+        } else {
+            break account;
+        }
+    };
+
+    // Projects the account can see, fetched while the user types; merged in once they arrive
+    let (tx, rx) = mpsc::channel();
+    if !gcloud_switch::net::is_offline() {
+        let fetch_account = account.clone();
+        runtime::spawn(async move {
+            let _ = tx.send(gcloud::list_projects_for_account(&fetch_account).await.unwrap_or_default());
+        });
+    }
+    let mut project_candidates = || {
+        if let Ok(fetched) = rx.try_recv() {
+            projects.extend(fetched);
+        }
+        projects.iter().cloned().collect()
+    };
+    let project = prompt("Project", &defaults.project, &mut project_candidates)?;
+    let adc_account = prompt(
+        "ADC account",
+        or(&defaults.adc_account, &account),
+        &mut || accounts.clone(),
+    )?;
+    let adc_quota_project = prompt(
+        "ADC quota project",
+        or(&defaults.adc_quota_project, &project),
+        &mut project_candidates,
+    )?;
+    let tags_line = prompt("Tags (space-separated)", &defaults.tags.join(" "), &mut Vec::new)?;
+
+    let answers = Answers {
+        name,
+        account,
+        project,
+        adc_account,
+        adc_quota_project,
+        tags: tags_line.split_whitespace().map(str::to_string).collect(),
+    };
+    println!();
+    println!("  {}: user={}@{} adc={}@{}", answers.name, answers.account, answers.project, answers.adc_account, answers.adc_quota_project);
+    if !answers.tags.is_empty() {
+        println!("  tags: {}", answers.tags.join(", "));
+    }
+    let confirm = prompt("Add this profile? [Y/n]", "", &mut Vec::new)?;
+    if !confirm.is_empty() && !confirm.eq_ignore_ascii_case("y") && !confirm.eq_ignore_ascii_case("yes") {
+        anyhow::bail!("Profile not added");
+    }
+    Ok(answers)
+}
+
+fn or<'a>(value: &'a str, fallback: &'a str) -> &'a str {
+    if value.is_empty() {
+        fallback
+    } else {
+        value
+    }
+}
+
+/// Read one value. On a terminal the first candidate extending the input is shown dimmed
+/// after it; Tab (or →) accepts it and further Tabs cycle through the other matches.
+fn prompt(label: &str, default: &str, candidates: &mut dyn FnMut() -> Vec<String>) -> Result<String> {
+    let label = if default.is_empty() {
+        format!("{}: ", label)
+    } else {
+        format!("{} [{}]: ", label, default)
+    };
+    if !io::stdin().is_terminal() {
+        print!("{}", label);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            anyhow::bail!("Cancelled (end of input)");
+        }
+        let line = line.trim();
+        return Ok(if line.is_empty() { default } else { line }.to_string());
+    }
+
+    let _raw = RawMode::enable()?;
+    let mut input = String::new();
+    // The text Tab completed from, and which match it is showing
+    let mut cycle: Option<(String, usize)> = None;
+    let mut cancelled = false;
+    loop {
+        let typed = cycle.as_ref().map_or(input.as_str(), |(typed, _)| typed.as_str());
+        let matches: Vec<String> = if typed.is_empty() {
+            Vec::new()
+        } else {
+            candidates()
+                .into_iter()
+                .filter(|c| c.len() > typed.len() && c.to_lowercase().starts_with(&typed.to_lowercase()))
+                .collect()
+        };
+        let hint = match &cycle {
+            Some(_) => "",
+            None => matches.first().and_then(|m| m.get(input.len()..)).unwrap_or(""),
+        };
+        let mut out = io::stdout();
+        queue!(out, Print("\r"), Clear(ClearType::CurrentLine), Print(&label), Print(&input))?;
+        if !hint.is_empty() {
+            queue!(
+                out,
+                SetAttribute(Attribute::Dim),
+                Print(hint),
+                SetAttribute(Attribute::Reset),
+                MoveLeft(hint.chars().count() as u16)
+            )?;
+        }
+        out.flush()?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => break,
+            KeyCode::Esc => {
+                cancelled = true;
+                break;
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                cancelled = true;
+                break;
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                input.clear();
+                cycle = None;
+            }
+            KeyCode::Tab | KeyCode::Right if !matches.is_empty() => {
+                let next = match &cycle {
+                    Some((typed, i)) if key.code == KeyCode::Tab => Some((typed.clone(), (i + 1) % matches.len())),
+                    Some(_) => None,
+                    None => Some((input.clone(), 0)),
+                };
+                if let Some((typed, i)) = next {
+                    input = matches[i].clone();
+                    cycle = Some((typed, i));
+                }
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                cycle = None;
+            }
+            KeyCode::Char(c) => {
+                input.push(c);
+                cycle = None;
+            }
+            _ => {}
+        }
+    }
+    print!("\r\n");
+    io::stdout().flush()?;
+    if cancelled {
+        anyhow::bail!("Cancelled");
+    }
+    let input = input.trim();
+    Ok(if input.is_empty() { default } else { input }.to_string())
+}
+
+/// Raw mode for the duration of one prompt, restored on every exit path.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}